@@ -22,6 +22,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         let mut config = prost_build::Config::new();
         config
             .type_attribute(".", "#[derive(serde::Serialize, serde::Deserialize)]")
+            // Decoded as slices of the model buffer, so their file offsets
+            // can be recovered.
+            .bytes([".onnx.TensorProto.raw_data"])
             .out_dir(&proto_dir);
 
         config.compile_protos(&[&proto_path], &[&proto_dir])?;
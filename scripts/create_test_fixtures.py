@@ -2,6 +2,7 @@
 import struct
 import json
 import os
+import zipfile
 
 
 def create_empty_gguf(path):
@@ -43,6 +44,25 @@ def create_minimal_gguf(path):
         f.write(struct.pack("<Q", 0))
 
 
+def create_minimal_ggjt(path):
+    """Create a legacy llama.cpp GGJT v3 file holding the tensor of
+    tiny.gguf, with its data."""
+    with open(path, "wb") as f:
+        f.write(b"tjgg")  # 0x67676a74, little-endian
+        f.write(struct.pack("<I", 3))
+        # n_vocab, n_embd, n_mult, n_head, n_layer, n_rot, ftype
+        f.write(struct.pack("<7I", 2, 64, 256, 2, 1, 32, 0))
+        for token, score in [(b"<s>", 0.0), (b"\xe2\x96\x81the", -1.5)]:
+            f.write(struct.pack("<I", len(token)) + token + struct.pack("<f", score))
+
+        name = b"test.weight.0"
+        f.write(struct.pack("<3I", 2, len(name), 0))
+        f.write(struct.pack("<2I", 64, 128))
+        f.write(name)
+        f.write(b"\x00" * (-f.tell() % 32))
+        f.write(b"\x00" * 32768)
+
+
 def create_minimal_safetensors(path):
     tensors = {
         "test.weight.0": {
@@ -65,13 +85,161 @@ def create_minimal_safetensors(path):
         f.write(b"\x00" * 32768)
 
 
+def create_minimal_pytorch(path):
+    """Create a torch.save zip archive holding the same tensor as
+    tiny.safetensors, without needing torch installed."""
+
+    def unicode(s):
+        return b"X" + struct.pack("<I", len(s)) + s.encode("utf-8")
+
+    def int32(i):
+        return b"J" + struct.pack("<i", i)
+
+    def ints(values):
+        return b"(" + b"".join(int32(v) for v in values) + b"t"
+
+    # OrderedDict(test.weight.0=_rebuild_tensor_v2(storage, 0, size, stride,
+    # False, OrderedDict())), pickled with protocol 2
+    pickle = (
+        b"\x80\x02ccollections\nOrderedDict\n)R("
+        + unicode("test.weight.0")
+        + b"ctorch._utils\n_rebuild_tensor_v2\n(("
+        + unicode("storage")
+        + b"ctorch\nFloatStorage\n"
+        + unicode("0")
+        + unicode("cpu")
+        + int32(64 * 128)
+        + b"tQ"
+        + int32(0)
+        + ints([64, 128])
+        + ints([128, 1])
+        + b"\x89ccollections\nOrderedDict\n)RtRu."
+    )
+
+    files = [
+        ("tiny/data.pkl", pickle),
+        ("tiny/byteorder", b"little"),
+        ("tiny/data/0", b"\x00" * 32768),
+        ("tiny/version", b"3\n"),
+    ]
+    with zipfile.ZipFile(path, "w", zipfile.ZIP_STORED) as archive:
+        for name, data in files:
+            archive.writestr(zipfile.ZipInfo(name, (1980, 1, 1, 0, 0, 0)), data)
+
+
+def create_minimal_npz(path):
+    """Create an np.savez archive holding the same tensor as
+    tiny.safetensors, without needing numpy installed."""
+    header = "{'descr': '<f4', 'fortran_order': False, 'shape': (64, 128), }"
+    # np.save pads the header with spaces so the data is 64-byte aligned.
+    header += " " * (63 - (10 + len(header)) % 64) + "\n"
+    npy = (
+        b"\x93NUMPY\x01\x00"
+        + struct.pack("<H", len(header))
+        + header.encode("latin-1")
+        + b"\x00" * 32768
+    )
+    with zipfile.ZipFile(path, "w", zipfile.ZIP_STORED) as archive:
+        info = zipfile.ZipInfo("test.weight.0.npy", (1980, 1, 1, 0, 0, 0))
+        archive.writestr(info, npy)
+
+
+def create_minimal_tflite(path):
+    """Create a TFLite model with one fully connected layer whose weights
+    are the tensor in tiny.safetensors, without needing tensorflow."""
+    buf = bytearray(b"\x00\x00\x00\x00TFL3")
+
+    def align():
+        buf.extend(b"\x00" * (-len(buf) % 4))
+
+    def patch(at, target):
+        buf[at : at + 4] = struct.pack("<I", target - at)
+
+    # Fields are (index, kind, value) with kind one of "u8", "u32", "str",
+    # "ints", "bytes" or "tables"; children are written after their table.
+    def table(fields):
+        slots = max((i for i, _, _ in fields), default=-1) + 1
+        sizes = [1 if kind == "u8" else 4 for _, kind, _ in fields]
+        offsets = [0] * slots
+        at = 4
+        for (i, _, _), size in zip(fields, sizes):
+            offsets[i] = at
+            at += size
+        align()
+        vtable = len(buf)
+        buf.extend(struct.pack(f"<HH{slots}H", 4 + 2 * slots, at, *offsets))
+        align()
+        pos = len(buf)
+        buf.extend(struct.pack("<i", pos - vtable))
+        refs = []
+        for _, kind, value in fields:
+            if kind == "u8":
+                buf.append(value)
+            elif kind == "u32":
+                buf.extend(struct.pack("<I", value))
+            else:
+                refs.append((len(buf), kind, value))
+                buf.extend(b"\x00" * 4)
+        for at, kind, value in refs:
+            align()
+            patch(at, len(buf))
+            if kind == "str":
+                buf.extend(struct.pack("<I", len(value)) + value.encode() + b"\x00")
+            elif kind == "bytes":
+                buf.extend(struct.pack("<I", len(value)) + value)
+            elif kind == "ints":
+                buf.extend(struct.pack(f"<I{len(value)}i", len(value), *value))
+            else:
+                buf.extend(struct.pack("<I", len(value)))
+                slots_at = len(buf)
+                buf.extend(b"\x00" * 4 * len(value))
+                for i, child in enumerate(value):
+                    patch(slots_at + 4 * i, table(child))
+        return pos
+
+    def tensor(name, shape, buffer):
+        return [(0, "ints", shape), (2, "u32", buffer), (3, "str", name)]
+
+    subgraph = [
+        (0, "tables", [
+            tensor("input", [1, 128], 0),
+            tensor("test.weight.0", [64, 128], 1),
+            tensor("output", [1, 64], 0),
+        ]),
+        (1, "ints", [0]),
+        (2, "ints", [2]),
+        (3, "tables", [[(1, "ints", [0, 1]), (2, "ints", [2])]]),
+        (4, "str", "main"),
+    ]
+    root = table([
+        (0, "u32", 3),
+        # BuiltinOperator.FULLY_CONNECTED
+        (1, "tables", [[(0, "u8", 9), (2, "u32", 1)]]),
+        (2, "tables", [subgraph]),
+        (3, "str", "weight-inspect test fixture"),
+        (4, "tables", [
+            [],
+            [(0, "bytes", b"\x00" * 32768)],
+            [(0, "bytes", b"1.5.0\x00")],
+        ]),
+        (6, "tables", [[(0, "str", "min_runtime_version"), (1, "u32", 2)]]),
+    ])
+    buf[0:4] = struct.pack("<I", root)
+    with open(path, "wb") as f:
+        f.write(buf)
+
+
 os.makedirs("tests/fixtures", exist_ok=True)
 create_empty_gguf("tests/fixtures/empty.gguf")
 create_minimal_gguf("tests/fixtures/tiny.gguf")
+create_minimal_ggjt("tests/fixtures/tiny.ggjt")
 create_minimal_safetensors("tests/fixtures/tiny.safetensors")
+create_minimal_pytorch("tests/fixtures/tiny.pt")
+create_minimal_npz("tests/fixtures/tiny.npz")
+create_minimal_tflite("tests/fixtures/tiny.tflite")
 print("Created test fixtures")
 
-for name in ["empty.gguf", "tiny.gguf", "tiny.safetensors"]:
+for name in ["empty.gguf", "tiny.gguf", "tiny.ggjt", "tiny.safetensors", "tiny.pt", "tiny.npz", "tiny.tflite"]:
     path = f"tests/fixtures/{name}"
     size = os.path.getsize(path)
     print(f"{name}: {size} bytes")
@@ -0,0 +1,511 @@
+use crate::values::f16_to_f32;
+use thiserror::Error;
+
+/// Errors raised while dequantizing a byte buffer.
+#[derive(Error, Debug)]
+pub enum DequantError {
+    #[error("{len} bytes is not a whole number of {block_bytes}-byte blocks")]
+    PartialBlock { len: usize, block_bytes: usize },
+}
+
+/// GGUF block-quantized types that can be converted to `f32`.
+///
+/// Block layouts follow ggml (`ggml-common.h`); all multi-byte fields are
+/// little-endian and scales are stored as f16.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuantType {
+    Q4_0,
+    Q4_1,
+    Q5_0,
+    Q5_1,
+    Q8_0,
+    Q2K,
+    Q3K,
+    Q4K,
+    Q5K,
+    Q6K,
+}
+
+const QK_K: usize = 256;
+
+impl QuantType {
+    /// Map a GGUF dtype name (as produced by the GGUF parser) to a quant type.
+    pub fn from_dtype(dtype: &str) -> Option<Self> {
+        let ty = match dtype {
+            "q4_0" => QuantType::Q4_0,
+            "q4_1" => QuantType::Q4_1,
+            "q5_0" => QuantType::Q5_0,
+            "q5_1" => QuantType::Q5_1,
+            "q8_0" => QuantType::Q8_0,
+            "q2_k" => QuantType::Q2K,
+            "q3_k" => QuantType::Q3K,
+            "q4_k" => QuantType::Q4K,
+            "q5_k" => QuantType::Q5K,
+            "q6_k" => QuantType::Q6K,
+            _ => return None,
+        };
+        Some(ty)
+    }
+
+    /// Number of values in one block.
+    pub fn block_size(self) -> usize {
+        match self {
+            QuantType::Q4_0
+            | QuantType::Q4_1
+            | QuantType::Q5_0
+            | QuantType::Q5_1
+            | QuantType::Q8_0 => 32,
+            QuantType::Q2K | QuantType::Q3K | QuantType::Q4K | QuantType::Q5K | QuantType::Q6K => {
+                QK_K
+            }
+        }
+    }
+
+    /// Size of one block in bytes.
+    pub fn block_bytes(self) -> usize {
+        match self {
+            QuantType::Q4_0 => 18,
+            QuantType::Q4_1 => 20,
+            QuantType::Q5_0 => 22,
+            QuantType::Q5_1 => 24,
+            QuantType::Q8_0 => 34,
+            QuantType::Q2K => 84,
+            QuantType::Q3K => 110,
+            QuantType::Q4K => 144,
+            QuantType::Q5K => 176,
+            QuantType::Q6K => 210,
+        }
+    }
+}
+
+/// Dequantize a buffer holding a whole number of blocks.
+///
+/// # Example
+///
+/// ```
+/// use weight_inspect::dequant::{dequantize, QuantType};
+///
+/// // One q8_0 block: scale 0.5 (f16 0x3800) followed by 32 signed quants.
+/// let mut block = vec![0x00, 0x38];
+/// block.extend((0..32).map(|i| i as u8));
+/// let values = dequantize(QuantType::Q8_0, &block).unwrap();
+/// assert_eq!(values.len(), 32);
+/// assert_eq!(values[3], 1.5);
+/// ```
+pub fn dequantize(ty: QuantType, data: &[u8]) -> Result<Vec<f32>, DequantError> {
+    let block_bytes = ty.block_bytes();
+    if !data.len().is_multiple_of(block_bytes) {
+        return Err(DequantError::PartialBlock {
+            len: data.len(),
+            block_bytes,
+        });
+    }
+    let mut out = Vec::with_capacity(data.len() / block_bytes * ty.block_size());
+    for block in data.chunks_exact(block_bytes) {
+        dequantize_block(ty, block, &mut out);
+    }
+    Ok(out)
+}
+
+/// Append the values of one block to `out`.
+///
+/// `block` must be exactly [`QuantType::block_bytes`] long.
+pub fn dequantize_block(ty: QuantType, block: &[u8], out: &mut Vec<f32>) {
+    debug_assert_eq!(block.len(), ty.block_bytes());
+    match ty {
+        QuantType::Q4_0 => q4_0(block, out),
+        QuantType::Q4_1 => q4_1(block, out),
+        QuantType::Q5_0 => q5_0(block, out),
+        QuantType::Q5_1 => q5_1(block, out),
+        QuantType::Q8_0 => q8_0(block, out),
+        QuantType::Q2K => q2_k(block, out),
+        QuantType::Q3K => q3_k(block, out),
+        QuantType::Q4K => q4_k(block, out),
+        QuantType::Q5K => q5_k(block, out),
+        QuantType::Q6K => q6_k(block, out),
+    }
+}
+
+fn half(bytes: &[u8], at: usize) -> f32 {
+    f16_to_f32(u16::from_le_bytes([bytes[at], bytes[at + 1]]))
+}
+
+/// `d: f16, qs: [u8; 16]`; value = (nibble - 8) * d.
+fn q4_0(block: &[u8], out: &mut Vec<f32>) {
+    let d = half(block, 0);
+    let qs = &block[2..18];
+    out.extend(qs.iter().map(|&q| ((q & 0x0f) as i32 - 8) as f32 * d));
+    out.extend(qs.iter().map(|&q| ((q >> 4) as i32 - 8) as f32 * d));
+}
+
+/// `d: f16, m: f16, qs: [u8; 16]`; value = nibble * d + m.
+fn q4_1(block: &[u8], out: &mut Vec<f32>) {
+    let (d, m) = (half(block, 0), half(block, 2));
+    let qs = &block[4..20];
+    out.extend(qs.iter().map(|&q| (q & 0x0f) as f32 * d + m));
+    out.extend(qs.iter().map(|&q| (q >> 4) as f32 * d + m));
+}
+
+/// Low nibbles plus the fifth bit from `qh` for both halves of a 32-value block.
+fn five_bit_quants(qh: u32, qs: &[u8]) -> impl Iterator<Item = u8> + '_ {
+    let low = qs
+        .iter()
+        .enumerate()
+        .map(move |(j, &q)| (q & 0x0f) | ((((qh >> j) << 4) & 0x10) as u8));
+    let high = qs
+        .iter()
+        .enumerate()
+        .map(move |(j, &q)| (q >> 4) | (((qh >> (j + 12)) & 0x10) as u8));
+    low.chain(high)
+}
+
+/// `d: f16, qh: u32, qs: [u8; 16]`; value = (5-bit quant - 16) * d.
+fn q5_0(block: &[u8], out: &mut Vec<f32>) {
+    let d = half(block, 0);
+    let qh = u32::from_le_bytes(block[2..6].try_into().unwrap());
+    out.extend(five_bit_quants(qh, &block[6..22]).map(|q| (q as i32 - 16) as f32 * d));
+}
+
+/// `d: f16, m: f16, qh: u32, qs: [u8; 16]`; value = 5-bit quant * d + m.
+fn q5_1(block: &[u8], out: &mut Vec<f32>) {
+    let (d, m) = (half(block, 0), half(block, 2));
+    let qh = u32::from_le_bytes(block[4..8].try_into().unwrap());
+    out.extend(five_bit_quants(qh, &block[8..24]).map(|q| q as f32 * d + m));
+}
+
+/// `d: f16, qs: [i8; 32]`; value = q * d.
+fn q8_0(block: &[u8], out: &mut Vec<f32>) {
+    let d = half(block, 0);
+    out.extend(block[2..34].iter().map(|&q| q as i8 as f32 * d));
+}
+
+/// `scales: [u8; 16], qs: [u8; 64], d: f16, dmin: f16`.
+///
+/// Sixteen sub-blocks of 16 values, each with a 4-bit scale and 4-bit min.
+fn q2_k(block: &[u8], out: &mut Vec<f32>) {
+    let scales = &block[0..16];
+    let d = half(block, 80);
+    let dmin = half(block, 82);
+    let mut is = 0;
+    for qs in block[16..80].chunks_exact(32) {
+        for shift in [0, 2, 4, 6] {
+            for half_qs in [&qs[..16], &qs[16..]] {
+                let sc = scales[is];
+                is += 1;
+                let dl = d * (sc & 0x0f) as f32;
+                let ml = dmin * (sc >> 4) as f32;
+                out.extend(half_qs.iter().map(|&q| dl * ((q >> shift) & 3) as f32 - ml));
+            }
+        }
+    }
+}
+
+/// `hmask: [u8; 32], qs: [u8; 64], scales: [u8; 12], d: f16`.
+///
+/// Sixteen sub-blocks of 16 values with signed 6-bit scales; the high bit of
+/// each 3-bit quant comes from `hmask`.
+fn q3_k(block: &[u8], out: &mut Vec<f32>) {
+    let hmask = &block[0..32];
+    let d = half(block, 108);
+
+    // Unpack twelve bytes into sixteen 6-bit scales.
+    let raw = &block[96..108];
+    let mut scales = [0i8; 16];
+    for (i, scale) in scales.iter_mut().enumerate() {
+        let low = if i < 8 {
+            raw[i] & 0x0f
+        } else {
+            raw[i - 8] >> 4
+        };
+        let high = (raw[8 + i % 4] >> (2 * (i / 4))) & 3;
+        *scale = (low | (high << 4)) as i8 - 32;
+    }
+
+    let mut is = 0;
+    let mut m = 1u8;
+    for qs in block[32..96].chunks_exact(32) {
+        for shift in [0, 2, 4, 6] {
+            for offset in [0, 16] {
+                let dl = d * scales[is] as f32;
+                is += 1;
+                out.extend((offset..offset + 16).map(|l| {
+                    let q = ((qs[l] >> shift) & 3) as i32;
+                    let high = if hmask[l] & m != 0 { 0 } else { 4 };
+                    dl * (q - high) as f32
+                }));
+            }
+            m <<= 1;
+        }
+    }
+}
+
+/// Scale and min of sub-block `j` from the packed 12-byte k-quant scales.
+fn scale_min_k4(j: usize, q: &[u8]) -> (f32, f32) {
+    if j < 4 {
+        ((q[j] & 63) as f32, (q[j + 4] & 63) as f32)
+    } else {
+        let scale = (q[j + 4] & 0x0f) | ((q[j - 4] >> 6) << 4);
+        let min = (q[j + 4] >> 4) | ((q[j] >> 6) << 4);
+        (scale as f32, min as f32)
+    }
+}
+
+/// `d: f16, dmin: f16, scales: [u8; 12], qs: [u8; 128]`.
+///
+/// Eight sub-blocks of 32 values with 6-bit scales and mins.
+fn q4_k(block: &[u8], out: &mut Vec<f32>) {
+    let (d, dmin) = (half(block, 0), half(block, 2));
+    let scales = &block[4..16];
+    for (i, qs) in block[16..144].chunks_exact(32).enumerate() {
+        let (sc1, m1) = scale_min_k4(2 * i, scales);
+        let (sc2, m2) = scale_min_k4(2 * i + 1, scales);
+        out.extend(qs.iter().map(|&q| d * sc1 * (q & 0x0f) as f32 - dmin * m1));
+        out.extend(qs.iter().map(|&q| d * sc2 * (q >> 4) as f32 - dmin * m2));
+    }
+}
+
+/// `d: f16, dmin: f16, scales: [u8; 12], qh: [u8; 32], qs: [u8; 128]`.
+///
+/// Like q4_k with a fifth bit per value taken from `qh`.
+fn q5_k(block: &[u8], out: &mut Vec<f32>) {
+    let (d, dmin) = (half(block, 0), half(block, 2));
+    let scales = &block[4..16];
+    let qh = &block[16..48];
+    for (i, qs) in block[48..176].chunks_exact(32).enumerate() {
+        let (sc1, m1) = scale_min_k4(2 * i, scales);
+        let (sc2, m2) = scale_min_k4(2 * i + 1, scales);
+        let (u1, u2) = (1u8 << (2 * i), 2u8 << (2 * i));
+        out.extend(qs.iter().zip(qh).map(|(&q, &h)| {
+            let high = if h & u1 != 0 { 16 } else { 0 };
+            d * sc1 * ((q & 0x0f) + high) as f32 - dmin * m1
+        }));
+        out.extend(qs.iter().zip(qh).map(|(&q, &h)| {
+            let high = if h & u2 != 0 { 16 } else { 0 };
+            d * sc2 * ((q >> 4) + high) as f32 - dmin * m2
+        }));
+    }
+}
+
+/// `ql: [u8; 128], qh: [u8; 64], scales: [i8; 16], d: f16`.
+///
+/// Sixteen sub-blocks of 16 values; each 6-bit quant is split between the
+/// low nibble in `ql` and two high bits in `qh`.
+fn q6_k(block: &[u8], out: &mut Vec<f32>) {
+    let d = half(block, 208);
+    for n in 0..2 {
+        let ql = &block[64 * n..64 * n + 64];
+        let qh = &block[128 + 32 * n..128 + 32 * n + 32];
+        let sc = &block[192 + 8 * n..192 + 8 * n + 8];
+        let mut y = [0f32; 128];
+        for l in 0..32 {
+            let is = l / 16;
+            let q1 = ((ql[l] & 0x0f) | ((qh[l] & 3) << 4)) as i32 - 32;
+            let q2 = ((ql[l + 32] & 0x0f) | (((qh[l] >> 2) & 3) << 4)) as i32 - 32;
+            let q3 = ((ql[l] >> 4) | (((qh[l] >> 4) & 3) << 4)) as i32 - 32;
+            let q4 = ((ql[l + 32] >> 4) | (((qh[l] >> 6) & 3) << 4)) as i32 - 32;
+            y[l] = d * (sc[is] as i8) as f32 * q1 as f32;
+            y[l + 32] = d * (sc[is + 2] as i8) as f32 * q2 as f32;
+            y[l + 64] = d * (sc[is + 4] as i8) as f32 * q3 as f32;
+            y[l + 96] = d * (sc[is + 6] as i8) as f32 * q4 as f32;
+        }
+        out.extend_from_slice(&y);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ONE: [u8; 2] = [0x00, 0x3c]; // f16 1.0
+    const HALF: [u8; 2] = [0x00, 0x38]; // f16 0.5
+    const TWO: [u8; 2] = [0x00, 0x40]; // f16 2.0
+
+    fn dequant(ty: QuantType, block: &[u8]) -> Vec<f32> {
+        assert_eq!(block.len(), ty.block_bytes());
+        let values = dequantize(ty, block).unwrap();
+        assert_eq!(values.len(), ty.block_size());
+        values
+    }
+
+    #[test]
+    fn test_q4_0() {
+        let mut block = TWO.to_vec();
+        // Low nibble 9 (-> 1), high nibble 2 (-> -6).
+        block.extend([0x29; 16]);
+        block[2] = 0xf0; // first value 0 -> -8, value 16 is 15 -> 7
+        let values = dequant(QuantType::Q4_0, &block);
+        assert_eq!(values[0], -16.0);
+        assert_eq!(values[1], 2.0);
+        assert_eq!(values[16], 14.0);
+        assert_eq!(values[17], -12.0);
+    }
+
+    #[test]
+    fn test_q4_1() {
+        let mut block = HALF.to_vec();
+        block.extend(ONE); // min 1.0
+        block.extend([0x40; 16]);
+        let values = dequant(QuantType::Q4_1, &block);
+        assert_eq!(values[0], 1.0);
+        assert_eq!(values[31], 3.0);
+    }
+
+    #[test]
+    fn test_q5_0() {
+        let mut block = ONE.to_vec();
+        // Fifth bit set for value 0 (bit 0) and value 16 (bit 16).
+        block.extend(0x0001_0001u32.to_le_bytes());
+        block.extend([0x00; 16]);
+        let values = dequant(QuantType::Q5_0, &block);
+        assert_eq!(values[0], 0.0); // 16 - 16
+        assert_eq!(values[1], -16.0);
+        assert_eq!(values[16], 0.0);
+        assert_eq!(values[17], -16.0);
+    }
+
+    #[test]
+    fn test_q5_1() {
+        let mut block = TWO.to_vec();
+        block.extend(HALF);
+        block.extend(0x8000_0000u32.to_le_bytes()); // fifth bit of value 31
+        block.extend([0x13; 16]);
+        let values = dequant(QuantType::Q5_1, &block);
+        assert_eq!(values[0], 3.0 * 2.0 + 0.5);
+        assert_eq!(values[30], 2.5);
+        assert_eq!(values[31], 17.0 * 2.0 + 0.5);
+    }
+
+    #[test]
+    fn test_q8_0() {
+        let mut block = HALF.to_vec();
+        block.extend((0..32).map(|i: i8| (i - 16) as u8));
+        let values = dequant(QuantType::Q8_0, &block);
+        assert_eq!(values[0], -8.0);
+        assert_eq!(values[31], 7.5);
+    }
+
+    #[test]
+    fn test_q2_k() {
+        let mut block = vec![0u8; 84];
+        block[0] = 0x12; // sub-block 0: scale 2, min 1
+        block[1] = 0x03; // sub-block 1: scale 3, min 0
+        block[16] = 0b11; // value 0, shift 0 -> 3
+        block[32] = 0b01; // value 16, shift 0 -> 1
+        block[80..82].copy_from_slice(&ONE);
+        block[82..84].copy_from_slice(&HALF);
+        let values = dequant(QuantType::Q2K, &block);
+        assert_eq!(values[0], 2.0 * 3.0 - 0.5);
+        assert_eq!(values[1], -0.5);
+        assert_eq!(values[16], 3.0);
+        assert_eq!(values[255], 0.0);
+    }
+
+    #[test]
+    fn test_q3_k() {
+        let mut block = vec![0u8; 110];
+        block[0] = 0x01; // hmask: value 0 has the high bit, so no -4
+                         // Scale 0 = 33 (low nibble 1, high bits 2) -> 33 - 32 = 1.
+        block[96] = 0x01;
+        block[104] = 0x02;
+        block[32] = 0b10; // value 0 -> 2
+        block[108..110].copy_from_slice(&TWO);
+        let values = dequant(QuantType::Q3K, &block);
+        assert_eq!(values[0], 2.0 * 2.0);
+        assert_eq!(values[1], 2.0 * -4.0);
+        // Remaining scales are 0 - 32 = -32, and quants are 0 - 4.
+        assert_eq!(values[16], 2.0 * -32.0 * -4.0);
+    }
+
+    #[test]
+    fn test_q4_k() {
+        let mut block = vec![0u8; 144];
+        block[0..2].copy_from_slice(&ONE);
+        block[2..4].copy_from_slice(&HALF);
+        block[4] = 2; // sub-block 0 scale
+        block[8] = 4; // sub-block 0 min
+        block[5] = 3; // sub-block 1 scale
+        block[16] = 0x75; // value 0 -> 5, value 32 -> 7
+        let values = dequant(QuantType::Q4K, &block);
+        assert_eq!(values[0], 2.0 * 5.0 - 0.5 * 4.0);
+        assert_eq!(values[1], -2.0);
+        assert_eq!(values[32], 3.0 * 7.0);
+        assert_eq!(values[255], 0.0);
+    }
+
+    #[test]
+    fn test_scale_min_k4_high_sub_blocks() {
+        let mut scales = [0u8; 12];
+        scales[0] = 0b0100_0000; // high bits of scale 4
+        scales[4] = 0b1000_0000; // high bits of min 4
+        scales[8] = 0x35; // low nibbles of scale 4 (5) and min 4 (3)
+        assert_eq!(scale_min_k4(4, &scales), (5.0 + 16.0, 3.0 + 32.0));
+    }
+
+    #[test]
+    fn test_q5_k() {
+        let mut block = vec![0u8; 176];
+        block[0..2].copy_from_slice(&ONE);
+        block[4] = 1; // sub-block 0 scale
+        block[5] = 1; // sub-block 1 scale
+        block[16] = 0b11; // qh for value 0 (bit 0) and value 32 (bit 1)
+        block[48] = 0x21;
+        let values = dequant(QuantType::Q5K, &block);
+        assert_eq!(values[0], 17.0);
+        assert_eq!(values[32], 18.0);
+        assert_eq!(values[1], 0.0);
+    }
+
+    #[test]
+    fn test_q6_k() {
+        let mut block = vec![0u8; 210];
+        block[0] = 0x21; // values 0 and 64 (low nibbles 1 and 2)
+        block[32] = 0x50; // value 96 (low nibble 5)
+        block[128] = 0b10_00_00_10; // high bits: value 0 -> 2, value 96 -> 2
+        block[192] = 1; // scale for values 0..16
+        block[196] = 0xff; // scale -1 for values 64..80
+        block[198] = 3; // scale for values 96..112
+        block[208..210].copy_from_slice(&HALF);
+        let values = dequant(QuantType::Q6K, &block);
+        assert_eq!(values[0], 0.5 * ((1 | (2 << 4)) - 32) as f32);
+        assert_eq!(values[64], -0.5 * (2 - 32) as f32); // scale -1
+        assert_eq!(values[96], 0.5 * 3.0 * ((5 | (2 << 4)) - 32) as f32);
+        assert_eq!(values[128], 0.0);
+    }
+
+    #[test]
+    fn test_block_sizes_match_gguf() {
+        for (code, ty) in [
+            (2, QuantType::Q4_0),
+            (3, QuantType::Q4_1),
+            (6, QuantType::Q5_0),
+            (7, QuantType::Q5_1),
+            (8, QuantType::Q8_0),
+            (10, QuantType::Q2K),
+            (11, QuantType::Q3K),
+            (12, QuantType::Q4K),
+            (13, QuantType::Q5K),
+            (14, QuantType::Q6K),
+        ] {
+            let (block, bytes) = crate::gguf::ggml_type_size(code).unwrap();
+            assert_eq!(
+                (block as usize, bytes as usize),
+                (ty.block_size(), ty.block_bytes())
+            );
+            assert_eq!(
+                QuantType::from_dtype(&crate::gguf::gguf_dtype_str(code)),
+                Some(ty)
+            );
+        }
+    }
+
+    #[test]
+    fn test_partial_block() {
+        assert!(matches!(
+            dequantize(QuantType::Q8_0, &[0u8; 40]),
+            Err(DequantError::PartialBlock {
+                len: 40,
+                block_bytes: 34
+            })
+        ));
+    }
+}
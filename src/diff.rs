@@ -1,12 +1,17 @@
-use crate::types::{Artifact, CanonicalValue};
+use crate::graph::{self, GraphDiff};
+use crate::names::{self, NameMap};
+use crate::types::{Artifact, CanonicalValue, Format, Tensor};
+use crate::values::{self, ValueError, ValueReader};
 use serde::Serialize;
-use std::collections::BTreeSet;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{Read, Seek};
 
 /// Result of comparing two model artifacts structurally.
 ///
 /// Contains detailed information about differences in:
 /// - Metadata (added, removed, changed)
-/// - Tensors (added, removed, modified)
+/// - Tensors (added, removed, renamed, modified)
 /// - Structural identity (format, hash, counts)
 ///
 /// # Example
@@ -39,7 +44,24 @@ pub struct DiffResult {
     pub metadata_changed: Vec<MetadataChange>,
     pub tensors_added: Vec<String>,
     pub tensors_removed: Vec<String>,
+    /// Tensors that only changed name; they are not listed as added or removed.
+    pub tensors_renamed: Vec<TensorRename>,
+    /// Prefix rules that explain two or more of `tensors_renamed`.
+    pub bulk_renames: Vec<BulkRename>,
     pub tensor_changes: Vec<TensorChange>,
+    /// Numeric comparison of tensor values; only filled by [`diff_values`].
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub value_diffs: Vec<ValueDiff>,
+    /// Tensors whose values [`diff_values`] could not compare, with the
+    /// reason.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub values_skipped: BTreeMap<String, String>,
+    /// Differences between the two models' graphs, when both have one (ONNX).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub graph: Option<GraphDiff>,
+    /// Architecture of the name map used by [`diff_with_map`], if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name_map: Option<String>,
 }
 
 impl DiffResult {
@@ -80,13 +102,84 @@ pub struct TensorChange {
     pub byte_length_old: Option<u64>,
     /// New byte length (if different).
     pub byte_length_new: Option<u64>,
+    /// Original data digest (if both sides have digests and they differ).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub digest_old: Option<String>,
+    /// New data digest (if both sides have digests and they differ).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub digest_new: Option<String>,
+}
+
+/// A tensor that was removed under one name and added under another with the
+/// same dtype, shape and byte length (and data digest, when both are known).
+#[derive(Debug, Serialize)]
+pub struct TensorRename {
+    /// The name in the first artifact.
+    pub old_name: String,
+    /// The name in the second artifact.
+    pub new_name: String,
+    /// How likely the pair is the same tensor, from 0.5 to 1:
+    /// - 1.0 when both data digests are known and equal.
+    /// - 0.9 when the pair follows one of the [`BulkRename`] rules.
+    /// - Otherwise 0.5, plus up to 0.4 for the trailing name components the
+    ///   two names share.
+    pub confidence: f64,
+}
+
+/// A rename rule shared by several tensors, such as `transformer.` becoming
+/// `model.`.
+///
+/// `{n}` in a prefix stands for a numeric component (usually a layer index)
+/// that is kept by the rename, so `transformer.h.{n}.` → `model.layers.{n}.`
+/// covers every layer.
+#[derive(Debug, Serialize)]
+pub struct BulkRename {
+    pub old_prefix: String,
+    pub new_prefix: String,
+    /// Number of tensors renamed by this rule.
+    pub count: usize,
+}
+
+/// Numeric similarity of one tensor's values in two artifacts.
+#[derive(Debug, Serialize)]
+pub struct ValueDiff {
+    /// The tensor name.
+    pub name: String,
+    /// Number of values compared.
+    pub element_count: u64,
+    /// Largest absolute difference between corresponding values.
+    pub max_abs_diff: f64,
+    /// Mean absolute difference between corresponding values.
+    pub mean_abs_diff: f64,
+    /// Cosine similarity of the two tensors viewed as flat vectors.
+    pub cosine_similarity: f64,
+    /// `||b - a|| / ||a||`, the L2 error relative to the first tensor.
+    pub relative_l2_error: f64,
+}
+
+/// Result of [`diff_values`].
+#[derive(Debug, Default)]
+pub struct ValueDiffs {
+    /// One entry per compared tensor, in name order.
+    pub diffs: Vec<ValueDiff>,
+    /// Tensors present in both artifacts with the same shape that could not
+    /// be compared, with the reason.
+    pub skipped: BTreeMap<String, String>,
 }
 
 /// Compare two artifacts and return their structural differences.
 ///
 /// This function performs a deep comparison of:
 /// - Metadata keys and values
-/// - Tensor names, dtypes, shapes, and byte lengths
+/// - Tensor names, dtypes, shapes, and byte lengths (a GGUF shape equals the
+///   reversed shape of a tensor from another format)
+/// - Tensor data digests, when both artifacts have them (see
+///   [`hash::compute_tensor_digests`](crate::hash::compute_tensor_digests))
+/// - The graphs, when both artifacts have one (see [`graph::diff`])
+///
+/// Removed and added tensors with the same structure are paired up as
+/// [`TensorRename`]s. Bulk renames (a prefix that changed for many tensors)
+/// are found first; the remaining tensors are paired by confidence.
 ///
 /// # Example
 ///
@@ -99,6 +192,9 @@ pub struct TensorChange {
 ///     gguf_version: Some(3),
 ///     metadata: BTreeMap::new(),
 ///     tensors: BTreeMap::new(),
+///     graph: None,
+///     data_offset: None,
+///     findings: Vec::new(),
 /// };
 ///
 /// let mut artifact_b = Artifact {
@@ -106,6 +202,9 @@ pub struct TensorChange {
 ///     gguf_version: Some(3),
 ///     metadata: BTreeMap::new(),
 ///     tensors: BTreeMap::new(),
+///     graph: None,
+///     data_offset: None,
+///     findings: Vec::new(),
 /// };
 ///
 /// artifact_b.tensors.insert("new.weight".to_string(), Tensor {
@@ -113,6 +212,11 @@ pub struct TensorChange {
 ///     dtype: "f32".to_string(),
 ///     shape: vec![10, 10],
 ///     byte_length: 400,
+///     data_offset: None,
+///     digest: None,
+///     location: None,
+///     nnz: None,
+///     big_endian: false,
 /// });
 ///
 /// let result = diff::diff(&artifact_a, &artifact_b);
@@ -145,14 +249,36 @@ pub fn diff(a: &Artifact, b: &Artifact) -> DiffResult {
         }
     }
 
+    let transposed = dims_reversed(a, b);
+
     let tensor_names_a: BTreeSet<_> = a.tensors.keys().collect();
     let tensor_names_b: BTreeSet<_> = b.tensors.keys().collect();
 
-    for name in tensor_names_b.difference(&tensor_names_a) {
-        result.tensors_added.push((*name).clone());
+    let added: Vec<&str> = tensor_names_b
+        .difference(&tensor_names_a)
+        .map(|name| name.as_str())
+        .collect();
+    let removed: Vec<&str> = tensor_names_a
+        .difference(&tensor_names_b)
+        .map(|name| name.as_str())
+        .collect();
+    let (renames, bulk_renames) = detect_renames(a, b, &removed, &added, transposed);
+    let renamed_old: BTreeSet<_> = renames.iter().map(|r| r.old_name.as_str()).collect();
+    let renamed_new: BTreeSet<_> = renames.iter().map(|r| r.new_name.as_str()).collect();
+    for name in added {
+        if !renamed_new.contains(name) {
+            result.tensors_added.push(name.to_string());
+        }
     }
-    for name in tensor_names_a.difference(&tensor_names_b) {
-        result.tensors_removed.push((*name).clone());
+    for name in removed {
+        if !renamed_old.contains(name) {
+            result.tensors_removed.push(name.to_string());
+        }
+    }
+    result.tensors_renamed = renames;
+    result.bulk_renames = bulk_renames;
+    if let (Some(graph_a), Some(graph_b)) = (&a.graph, &b.graph) {
+        result.graph = Some(graph::diff(graph_a, graph_b));
     }
     for name in tensor_names_a.intersection(&tensor_names_b) {
         let old_tensor = a.tensors.get(*name).unwrap();
@@ -166,13 +292,15 @@ pub fn diff(a: &Artifact, b: &Artifact) -> DiffResult {
             shape_new: None,
             byte_length_old: None,
             byte_length_new: None,
+            digest_old: None,
+            digest_new: None,
         };
 
         if old_tensor.dtype != new_tensor.dtype {
             change.dtype_old = Some(old_tensor.dtype.clone());
             change.dtype_new = Some(new_tensor.dtype.clone());
         }
-        if old_tensor.shape != new_tensor.shape {
+        if !shapes_match(old_tensor, new_tensor, transposed) {
             change.shape_old = Some(old_tensor.shape.clone());
             change.shape_new = Some(new_tensor.shape.clone());
         }
@@ -180,10 +308,17 @@ pub fn diff(a: &Artifact, b: &Artifact) -> DiffResult {
             change.byte_length_old = Some(old_tensor.byte_length);
             change.byte_length_new = Some(new_tensor.byte_length);
         }
+        if let (Some(old_digest), Some(new_digest)) = (&old_tensor.digest, &new_tensor.digest) {
+            if old_digest != new_digest {
+                change.digest_old = Some(old_digest.clone());
+                change.digest_new = Some(new_digest.clone());
+            }
+        }
 
         if change.dtype_old.is_some()
             || change.shape_old.is_some()
             || change.byte_length_old.is_some()
+            || change.digest_old.is_some()
         {
             result.tensor_changes.push(change);
         }
@@ -192,6 +327,377 @@ pub fn diff(a: &Artifact, b: &Artifact) -> DiffResult {
     result
 }
 
+/// Whether one artifact lists tensor dimensions in the opposite order from
+/// the other: GGUF and legacy GGML list them innermost first, the other
+/// formats outermost first.
+fn dims_reversed(a: &Artifact, b: &Artifact) -> bool {
+    let ggml_order = |format: &Format| matches!(format, Format::GGUF | Format::GGML);
+    ggml_order(&a.format) != ggml_order(&b.format)
+}
+
+/// Whether two shapes are equal, allowing for GGUF's reversed dimension order
+/// when `transposed` is set.
+fn shapes_match(old: &Tensor, new: &Tensor, transposed: bool) -> bool {
+    old.shape == new.shape || (transposed && old.shape.iter().rev().eq(&new.shape))
+}
+
+/// Whether `new` could be `old` under another name.
+fn same_structure(old: &Tensor, new: &Tensor, transposed: bool) -> bool {
+    let digests_match = match (&old.digest, &new.digest) {
+        (Some(old_digest), Some(new_digest)) => old_digest == new_digest,
+        _ => true,
+    };
+    old.dtype == new.dtype
+        && old.byte_length == new.byte_length
+        && shapes_match(old, new, transposed)
+        && digests_match
+}
+
+fn is_index(part: &str) -> bool {
+    !part.is_empty() && part.bytes().all(|c| c.is_ascii_digit())
+}
+
+/// Number of trailing dot-separated components two names share.
+fn common_suffix(old: &[&str], new: &[&str]) -> usize {
+    old.iter()
+        .rev()
+        .zip(new.iter().rev())
+        .take_while(|(o, n)| o == n)
+        .count()
+}
+
+/// The prefix rule that turns `old` into `new`, if they share a suffix.
+///
+/// Numeric components are replaced by `{n}` when both prefixes contain the
+/// same numbers in the same order.
+fn rename_rule(old: &str, new: &str) -> Option<(String, String)> {
+    let old_parts: Vec<&str> = old.split('.').collect();
+    let new_parts: Vec<&str> = new.split('.').collect();
+    let common = common_suffix(&old_parts, &new_parts);
+    if common == 0 {
+        return None;
+    }
+    let old_prefix = &old_parts[..old_parts.len() - common];
+    let new_prefix = &new_parts[..new_parts.len() - common];
+    let old_indices: Vec<_> = old_prefix.iter().filter(|p| is_index(p)).collect();
+    let new_indices: Vec<_> = new_prefix.iter().filter(|p| is_index(p)).collect();
+    let generalize = !old_indices.is_empty() && old_indices == new_indices;
+    let pattern = |parts: &[&str]| -> String {
+        parts
+            .iter()
+            .map(|part| match generalize && is_index(part) {
+                true => "{n}.".to_string(),
+                false => format!("{}.", part),
+            })
+            .collect()
+    };
+    Some((pattern(old_prefix), pattern(new_prefix)))
+}
+
+/// Apply a rule from [`rename_rule`] to `name`.
+fn apply_rule((old_prefix, new_prefix): &(String, String), name: &str) -> Option<String> {
+    let pattern: Vec<&str> = old_prefix.split_terminator('.').collect();
+    let parts: Vec<&str> = name.split('.').collect();
+    if parts.len() <= pattern.len() {
+        return None;
+    }
+    let mut indices = Vec::new();
+    for (expected, part) in pattern.iter().zip(&parts) {
+        if *expected == "{n}" && is_index(part) {
+            indices.push(*part);
+        } else if expected != part {
+            return None;
+        }
+    }
+    let mut indices = indices.into_iter();
+    let mut renamed = Vec::new();
+    for part in new_prefix.split_terminator('.') {
+        renamed.push(if part == "{n}" { indices.next()? } else { part });
+    }
+    renamed.extend(&parts[pattern.len()..]);
+    Some(renamed.join("."))
+}
+
+/// Pair removed tensors with added tensors of the same structure.
+fn detect_renames(
+    a: &Artifact,
+    b: &Artifact,
+    removed: &[&str],
+    added: &[&str],
+    transposed: bool,
+) -> (Vec<TensorRename>, Vec<BulkRename>) {
+    let compatible =
+        |old: &str, new: &str| same_structure(&a.tensors[old], &b.tensors[new], transposed);
+    let digests_equal = |old: &str, new: &str| {
+        a.tensors[old].digest.is_some() && a.tensors[old].digest == b.tensors[new].digest
+    };
+    let mut unmatched_old: BTreeSet<&str> = removed.iter().copied().collect();
+    let mut unmatched_new: BTreeSet<&str> = added.iter().copied().collect();
+    let mut renames = Vec::new();
+    let mut bulk_renames = Vec::new();
+
+    // Count how many compatible pairs each prefix rule explains, then apply
+    // the rules that explain the most pairs first.
+    let mut pairs = Vec::new();
+    let mut support: BTreeMap<(String, String), usize> = BTreeMap::new();
+    for &old in removed {
+        for &new in added {
+            if compatible(old, new) {
+                if let Some(rule) = rename_rule(old, new) {
+                    *support.entry(rule).or_default() += 1;
+                }
+                pairs.push((old, new));
+            }
+        }
+    }
+    let mut rules: Vec<_> = support.into_iter().filter(|(_, n)| *n >= 2).collect();
+    rules.sort_by(|(rule_a, n_a), (rule_b, n_b)| n_b.cmp(n_a).then(rule_a.cmp(rule_b)));
+    for (rule, _) in rules {
+        let matched: Vec<(&str, String)> = unmatched_old
+            .iter()
+            .filter_map(|&old| {
+                let new = apply_rule(&rule, old)?;
+                (unmatched_new.contains(new.as_str()) && compatible(old, &new))
+                    .then_some((old, new))
+            })
+            .collect();
+        if matched.len() < 2 {
+            continue;
+        }
+        for (old, new) in &matched {
+            unmatched_old.remove(old);
+            unmatched_new.remove(new.as_str());
+            renames.push(TensorRename {
+                old_name: old.to_string(),
+                new_name: new.clone(),
+                confidence: if digests_equal(old, new) { 1.0 } else { 0.9 },
+            });
+        }
+        bulk_renames.push(BulkRename {
+            old_prefix: rule.0,
+            new_prefix: rule.1,
+            count: matched.len(),
+        });
+    }
+
+    // Pair what is left one by one, most confident first.
+    let mut scored: Vec<(f64, f64, &str, &str)> = pairs
+        .into_iter()
+        .filter(|(old, new)| unmatched_old.contains(old) && unmatched_new.contains(new))
+        .map(|(old, new)| {
+            let old_parts: Vec<&str> = old.split('.').collect();
+            let new_parts: Vec<&str> = new.split('.').collect();
+            let similarity = common_suffix(&old_parts, &new_parts) as f64
+                / old_parts.len().max(new_parts.len()) as f64;
+            let confidence = if digests_equal(old, new) {
+                1.0
+            } else {
+                0.5 + 0.4 * similarity
+            };
+            (confidence, similarity, old, new)
+        })
+        .collect();
+    scored.sort_by(|x, y| {
+        (y.0.total_cmp(&x.0))
+            .then(y.1.total_cmp(&x.1))
+            .then(x.2.cmp(y.2))
+            .then(x.3.cmp(y.3))
+    });
+    for (confidence, _, old, new) in scored {
+        if unmatched_old.contains(old) && unmatched_new.contains(new) {
+            unmatched_old.remove(old);
+            unmatched_new.remove(new);
+            renames.push(TensorRename {
+                old_name: old.to_string(),
+                new_name: new.to_string(),
+                confidence,
+            });
+        }
+    }
+
+    renames.sort_by(|x, y| x.old_name.cmp(&y.old_name));
+    (renames, bulk_renames)
+}
+
+/// Compare two artifacts after mapping Hugging Face tensor names to GGUF
+/// names.
+///
+/// Tensors of the non-GGUF artifact(s) are renamed with
+/// [`names::to_gguf_names`], so a checkpoint and its GGUF conversion line up.
+/// As in [`diff`], shapes that differ only by GGUF's reversed dimension order
+/// are not reported as changes.
+///
+/// # Example
+///
+/// ```
+/// use weight_inspect::{diff, names, types::{Artifact, Format, Tensor}};
+/// use std::collections::BTreeMap;
+///
+/// let tensor = |name: &str, shape: Vec<u64>| Tensor {
+///     name: name.to_string(),
+///     dtype: "f16".to_string(),
+///     shape,
+///     byte_length: 64,
+///     data_offset: None,
+///     digest: None,
+///     location: None,
+///     nnz: None,
+///     big_endian: false,
+/// };
+/// let mut hf = Artifact {
+///     format: Format::Safetensors,
+///     gguf_version: None,
+///     metadata: BTreeMap::new(),
+///     tensors: BTreeMap::new(),
+///     graph: None,
+///     data_offset: None,
+///     findings: Vec::new(),
+/// };
+/// let mut gguf = hf.clone();
+/// gguf.format = Format::GGUF;
+/// hf.tensors.insert(
+///     "model.layers.0.mlp.up_proj.weight".to_string(),
+///     tensor("model.layers.0.mlp.up_proj.weight", vec![8, 4]),
+/// );
+/// gguf.tensors.insert("blk.0.ffn_up.weight".to_string(), tensor("blk.0.ffn_up.weight", vec![4, 8]));
+///
+/// let result = diff::diff_with_map(&hf, &gguf, &names::LLAMA);
+/// assert!(!result.has_changes());
+/// ```
+pub fn diff_with_map(a: &Artifact, b: &Artifact, map: &NameMap) -> DiffResult {
+    let mut result = diff(&gguf_named(a, map), &gguf_named(b, map));
+    result.name_map = Some(map.architectures[0].to_string());
+    result
+}
+
+/// The artifact with its tensors renamed to GGUF names, unless it is GGUF.
+fn gguf_named<'a>(artifact: &'a Artifact, map: &NameMap) -> Cow<'a, Artifact> {
+    if artifact.format == Format::GGUF {
+        Cow::Borrowed(artifact)
+    } else {
+        Cow::Owned(names::to_gguf_names(artifact, map))
+    }
+}
+
+/// Compare the values of every tensor present in both artifacts.
+///
+/// Tensors are compared when they have the same name and shape and both
+/// dtypes can be decoded (see [`values::is_decodable`]); the dtypes do not
+/// have to match, so an f32 checkpoint can be compared with its f16
+/// conversion. As in [`diff`], shapes that differ only by GGUF's reversed
+/// dimension order match. With a name `map`, tensors are matched by their
+/// GGUF names as in [`diff_with_map`]. Tensors whose shapes differ, or that cannot be read
+/// on either side (see [`values::skip_reason`]), are listed in
+/// [`ValueDiffs::skipped`]. `reader_a` and `reader_b` must be the files the
+/// artifacts were parsed from.
+///
+/// Cosine similarity is 1 when both tensors are all zeros and 0 when only one
+/// is. The relative L2 error is infinite when `a` is all zeros and `b` is not.
+pub fn diff_values<RA: Read + Seek, RB: Read + Seek>(
+    a: &Artifact,
+    b: &Artifact,
+    map: Option<&NameMap>,
+    reader_a: &mut RA,
+    reader_b: &mut RB,
+) -> Result<ValueDiffs, ValueError> {
+    let (a, b) = match map {
+        Some(map) => (gguf_named(a, map), gguf_named(b, map)),
+        None => (Cow::Borrowed(a), Cow::Borrowed(b)),
+    };
+    let (a, b) = (a.as_ref(), b.as_ref());
+    let transposed = dims_reversed(a, b);
+    let mut result = ValueDiffs::default();
+    for (name, tensor_a) in &a.tensors {
+        let Some(tensor_b) = b.tensors.get(name) else {
+            continue;
+        };
+        if !shapes_match(tensor_a, tensor_b, transposed) {
+            result.skipped.insert(
+                name.clone(),
+                format!(
+                    "shape {:?} does not match {:?}",
+                    tensor_a.shape, tensor_b.shape
+                ),
+            );
+            continue;
+        }
+        match value_diff(name, tensor_a, tensor_b, reader_a, reader_b) {
+            Ok(diff) => result.diffs.push(diff),
+            Err((side, e)) => {
+                let reason = values::skip_reason(&e).ok_or(e)?;
+                result
+                    .skipped
+                    .insert(name.clone(), format!("{} in the {} file", reason, side));
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// Compare the values of one pair of tensors. Errors come with the side
+/// ("first" or "second") they were raised on.
+fn value_diff<RA: Read + Seek, RB: Read + Seek>(
+    name: &str,
+    tensor_a: &Tensor,
+    tensor_b: &Tensor,
+    reader_a: &mut RA,
+    reader_b: &mut RB,
+) -> Result<ValueDiff, (&'static str, ValueError)> {
+    const CHUNK: usize = 64 * 1024;
+
+    let mut values_a = ValueReader::new(tensor_a, reader_a).map_err(|e| ("first", e))?;
+    let mut values_b = ValueReader::new(tensor_b, reader_b).map_err(|e| ("second", e))?;
+    let (mut chunk_a, mut chunk_b) = (Vec::new(), Vec::new());
+    let mut count = 0u64;
+    let (mut max_abs, mut sum_abs) = (0f64, 0f64);
+    let (mut dot, mut norm_a, mut norm_b, mut norm_diff) = (0f64, 0f64, 0f64, 0f64);
+    loop {
+        let n_a = values_a
+            .read_chunk(CHUNK, &mut chunk_a)
+            .map_err(|e| ("first", e))?;
+        let n_b = values_b
+            .read_chunk(CHUNK, &mut chunk_b)
+            .map_err(|e| ("second", e))?;
+        let n = n_a.min(n_b);
+        if n == 0 {
+            break;
+        }
+        for (&x, &y) in chunk_a.iter().zip(&chunk_b) {
+            let d = y - x;
+            max_abs = max_abs.max(d.abs());
+            sum_abs += d.abs();
+            dot += x * y;
+            norm_a += x * x;
+            norm_b += y * y;
+            norm_diff += d * d;
+        }
+        count += n as u64;
+    }
+
+    let cosine_similarity = match (norm_a == 0.0, norm_b == 0.0) {
+        (true, true) => 1.0,
+        (true, false) | (false, true) => 0.0,
+        (false, false) => dot / (norm_a.sqrt() * norm_b.sqrt()),
+    };
+    let relative_l2_error = if norm_diff == 0.0 {
+        0.0
+    } else {
+        norm_diff.sqrt() / norm_a.sqrt()
+    };
+    Ok(ValueDiff {
+        name: name.to_string(),
+        element_count: count,
+        max_abs_diff: max_abs,
+        mean_abs_diff: if count == 0 {
+            0.0
+        } else {
+            sum_abs / count as f64
+        },
+        cosine_similarity,
+        relative_l2_error,
+    })
+}
+
 impl DiffResult {
     pub fn has_changes(&self) -> bool {
         !self.metadata_added.is_empty()
@@ -199,15 +705,19 @@ impl DiffResult {
             || !self.metadata_changed.is_empty()
             || !self.tensors_added.is_empty()
             || !self.tensors_removed.is_empty()
+            || !self.tensors_renamed.is_empty()
             || !self.tensor_changes.is_empty()
+            || self.graph.as_ref().is_some_and(GraphDiff::has_changes)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::diff;
+    use super::{diff, diff_values};
     use crate::types::{Artifact, CanonicalValue, Format, Tensor};
+    use crate::values::f32_artifact;
     use std::collections::BTreeMap;
+    use std::io::Cursor;
 
     fn create_test_artifact(
         format: Format,
@@ -231,6 +741,11 @@ mod tests {
                     dtype: "f32".to_string(),
                     shape: vec![10, 10],
                     byte_length: 400,
+                    data_offset: None,
+                    digest: None,
+                    location: None,
+                    nnz: None,
+                    big_endian: false,
                 },
             );
         }
@@ -240,6 +755,9 @@ mod tests {
             gguf_version: Some(3),
             metadata,
             tensors,
+            graph: None,
+            data_offset: None,
+            findings: Vec::new(),
         }
     }
 
@@ -326,6 +844,11 @@ mod tests {
                 dtype: "f32".to_string(),
                 shape: vec![10],
                 byte_length: 40,
+                data_offset: None,
+                digest: None,
+                location: None,
+                nnz: None,
+                big_endian: false,
             },
         );
 
@@ -360,6 +883,398 @@ mod tests {
         assert_eq!(result.tensor_changes[0].dtype_new, Some("f16".to_string()));
     }
 
+    #[test]
+    fn test_diff_tensor_data_changed() {
+        let mut a = create_test_artifact(Format::GGUF, 0, 2);
+        for tensor in a.tensors.values_mut() {
+            tensor.digest = Some("aa".repeat(32));
+        }
+        let mut b = a.clone();
+        b.tensors.get_mut("tensor_1").unwrap().digest = Some("bb".repeat(32));
+
+        let result = diff(&a, &b);
+
+        assert_eq!(result.tensor_changes.len(), 1);
+        assert_eq!(result.tensor_changes[0].name, "tensor_1");
+        assert_eq!(result.tensor_changes[0].digest_new, Some("bb".repeat(32)));
+        assert!(result.tensor_changes[0].dtype_old.is_none());
+    }
+
+    #[test]
+    fn test_diff_ignores_missing_digests() {
+        let a = create_test_artifact(Format::GGUF, 0, 1);
+        let mut b = a.clone();
+        b.tensors.get_mut("tensor_0").unwrap().digest = Some("bb".repeat(32));
+
+        assert!(diff(&a, &b).tensor_changes.is_empty());
+    }
+
+    #[test]
+    fn test_diff_values_metrics() {
+        let (a, data_a) = f32_artifact(&[("w", &[1.0, 2.0, 2.0]), ("same", &[1.0, -1.0])]);
+        let (b, data_b) = f32_artifact(&[("w", &[1.0, 2.0, 2.5]), ("same", &[1.0, -1.0])]);
+
+        let diffs = diff_values(
+            &a,
+            &b,
+            None,
+            &mut Cursor::new(data_a),
+            &mut Cursor::new(data_b),
+        )
+        .unwrap()
+        .diffs;
+
+        assert_eq!(diffs.len(), 2);
+        let same = &diffs[0];
+        assert_eq!(same.name, "same");
+        assert_eq!(same.max_abs_diff, 0.0);
+        assert_eq!(same.relative_l2_error, 0.0);
+        assert!((same.cosine_similarity - 1.0).abs() < 1e-12);
+
+        let w = &diffs[1];
+        assert_eq!(w.element_count, 3);
+        assert_eq!(w.max_abs_diff, 0.5);
+        assert!((w.mean_abs_diff - 0.5 / 3.0).abs() < 1e-12);
+        assert!((w.relative_l2_error - 0.5 / 3.0).abs() < 1e-12);
+        let expected_cosine = 10.0 / (3.0 * 11.25f64.sqrt());
+        assert!((w.cosine_similarity - expected_cosine).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_diff_values_across_dtypes() {
+        let (a, data_a) = f32_artifact(&[("w", &[1.0, -2.0])]);
+        let mut b = a.clone();
+        let tensor = b.tensors.get_mut("w").unwrap();
+        tensor.dtype = "f16".to_string();
+        tensor.byte_length = 4;
+        let data_b = vec![0x00, 0x3c, 0x00, 0xc0];
+
+        let diffs = diff_values(
+            &a,
+            &b,
+            None,
+            &mut Cursor::new(data_a),
+            &mut Cursor::new(data_b),
+        )
+        .unwrap()
+        .diffs;
+
+        assert_eq!(diffs.len(), 1);
+        assert_eq!(diffs[0].max_abs_diff, 0.0);
+    }
+
+    #[test]
+    fn test_diff_values_skips_shape_changes() {
+        let (a, data_a) = f32_artifact(&[("w", &[1.0, 2.0])]);
+        let (b, data_b) = f32_artifact(&[("w", &[1.0, 2.0, 3.0])]);
+
+        let result = diff_values(
+            &a,
+            &b,
+            None,
+            &mut Cursor::new(data_a),
+            &mut Cursor::new(data_b),
+        )
+        .unwrap();
+
+        assert!(result.diffs.is_empty());
+        assert_eq!(result.skipped["w"], "shape [2] does not match [3]");
+    }
+
+    #[test]
+    fn test_diff_values_across_dimension_orders() {
+        let (mut a, data) = f32_artifact(&[("w", &[1.0, 2.0, 3.0, 4.0, 5.0, 6.0])]);
+        a.tensors.get_mut("w").unwrap().shape = vec![2, 3];
+        let mut b = a.clone();
+        b.format = Format::GGUF;
+        b.tensors.get_mut("w").unwrap().shape = vec![3, 2];
+
+        let result = diff_values(
+            &a,
+            &b,
+            None,
+            &mut Cursor::new(&data),
+            &mut Cursor::new(&data),
+        )
+        .unwrap();
+
+        assert_eq!(result.diffs.len(), 1);
+        assert_eq!(result.diffs[0].element_count, 6);
+        assert!(result.skipped.is_empty());
+    }
+
+    #[test]
+    fn test_diff_values_with_name_map() {
+        let (a, data) = f32_artifact(&[("model.norm.weight", &[1.0, 2.0])]);
+        let (mut b, _) = f32_artifact(&[("output_norm.weight", &[1.0, 2.0])]);
+        b.format = Format::GGUF;
+
+        let result = diff_values(
+            &a,
+            &b,
+            Some(&crate::names::LLAMA),
+            &mut Cursor::new(&data),
+            &mut Cursor::new(&data),
+        )
+        .unwrap();
+
+        assert_eq!(result.diffs.len(), 1);
+        assert_eq!(result.diffs[0].name, "output_norm.weight");
+        assert_eq!(result.diffs[0].max_abs_diff, 0.0);
+    }
+
+    #[test]
+    fn test_diff_values_skips_truncated_tensors() {
+        let (a, data) = f32_artifact(&[("w", &[1.0, 2.0]), ("x", &[3.0, 4.0])]);
+        let truncated = data[..12].to_vec();
+
+        let result = diff_values(
+            &a,
+            &a,
+            None,
+            &mut Cursor::new(&data),
+            &mut Cursor::new(truncated),
+        )
+        .unwrap();
+
+        assert_eq!(result.diffs.len(), 1);
+        assert_eq!(result.diffs[0].name, "w");
+        assert_eq!(result.skipped["x"], "data is truncated in the second file");
+    }
+
+    #[test]
+    fn test_diff_values_skips_unreadable_tensors() {
+        let (a, data_a) = f32_artifact(&[("q", &[1.0]), ("w", &[1.0]), ("x", &[1.0])]);
+        let mut b = a.clone();
+        b.tensors.get_mut("q").unwrap().dtype = "string".to_string();
+        b.tensors.get_mut("w").unwrap().data_offset = None;
+
+        let result = diff_values(
+            &a,
+            &b,
+            None,
+            &mut Cursor::new(&data_a),
+            &mut Cursor::new(&data_a),
+        )
+        .unwrap();
+
+        assert_eq!(result.diffs.len(), 1);
+        assert_eq!(result.diffs[0].name, "x");
+        assert_eq!(
+            result.skipped["q"],
+            "dtype 'string' cannot be decoded in the second file"
+        );
+        assert_eq!(
+            result.skipped["w"],
+            "no recorded data offset in the second file"
+        );
+    }
+
+    #[test]
+    fn test_diff_gguf_shapes_are_transposed() {
+        let mut a = create_test_artifact(Format::Safetensors, 0, 1);
+        let mut b = a.clone();
+        a.tensors.get_mut("tensor_0").unwrap().shape = vec![4, 100];
+        b.format = Format::GGUF;
+        b.tensors.get_mut("tensor_0").unwrap().shape = vec![100, 4];
+
+        assert!(diff(&a, &b).tensor_changes.is_empty());
+
+        b.format = Format::Safetensors;
+        assert_eq!(diff(&a, &b).tensor_changes.len(), 1);
+    }
+
+    #[test]
+    fn test_diff_with_map() {
+        let tensor = |name: &str| Tensor {
+            name: name.to_string(),
+            dtype: "f32".to_string(),
+            shape: vec![10, 10],
+            byte_length: 400,
+            data_offset: None,
+            digest: None,
+            location: None,
+            nnz: None,
+            big_endian: false,
+        };
+        let mut hf = create_test_artifact(Format::Safetensors, 0, 0);
+        let mut gguf = create_test_artifact(Format::GGUF, 0, 0);
+        for name in [
+            "model.layers.0.self_attn.q_proj.weight",
+            "model.rotary.inv_freq",
+        ] {
+            hf.tensors.insert(name.to_string(), tensor(name));
+        }
+        gguf.tensors.insert(
+            "blk.0.attn_q.weight".to_string(),
+            tensor("blk.0.attn_q.weight"),
+        );
+
+        let result = super::diff_with_map(&hf, &gguf, &crate::names::LLAMA);
+
+        assert!(result.tensors_added.is_empty());
+        assert_eq!(result.tensors_removed, vec!["model.rotary.inv_freq"]);
+        assert_eq!(result.name_map.as_deref(), Some("llama"));
+    }
+
+    fn rename_artifact(names: &[&str]) -> Artifact {
+        let mut artifact = create_test_artifact(Format::Safetensors, 0, 0);
+        for (i, name) in names.iter().enumerate() {
+            artifact.tensors.insert(
+                name.to_string(),
+                Tensor {
+                    name: name.to_string(),
+                    dtype: "f32".to_string(),
+                    shape: vec![i as u64 % 2 + 1, 8],
+                    byte_length: (i as u64 % 2 + 1) * 32,
+                    data_offset: None,
+                    digest: None,
+                    location: None,
+                    nnz: None,
+                    big_endian: false,
+                },
+            );
+        }
+        artifact
+    }
+
+    #[test]
+    fn test_diff_detects_rename() {
+        let a = create_test_artifact(Format::GGUF, 0, 2);
+        let mut b = a.clone();
+        let tensor = b.tensors.remove("tensor_1").unwrap();
+        b.tensors.insert("renamed.tensor_1".to_string(), tensor);
+
+        let result = diff(&a, &b);
+
+        assert!(result.tensors_added.is_empty());
+        assert!(result.tensors_removed.is_empty());
+        assert_eq!(result.tensors_renamed.len(), 1);
+        assert_eq!(result.tensors_renamed[0].old_name, "tensor_1");
+        assert_eq!(result.tensors_renamed[0].new_name, "renamed.tensor_1");
+        assert!((result.tensors_renamed[0].confidence - 0.7).abs() < 1e-12);
+        assert!(result.has_changes());
+    }
+
+    #[test]
+    fn test_diff_rename_uses_digests() {
+        let mut a = create_test_artifact(Format::GGUF, 0, 2);
+        a.tensors.get_mut("tensor_0").unwrap().digest = Some("aa".repeat(32));
+        a.tensors.get_mut("tensor_1").unwrap().digest = Some("bb".repeat(32));
+        let mut b = create_test_artifact(Format::GGUF, 0, 0);
+        for (old, new) in [("tensor_0", "x"), ("tensor_1", "y")] {
+            let mut tensor = a.tensors[old].clone();
+            tensor.name = new.to_string();
+            b.tensors.insert(new.to_string(), tensor);
+        }
+        b.tensors.get_mut("x").unwrap().digest = Some("bb".repeat(32));
+        b.tensors.get_mut("y").unwrap().digest = Some("cc".repeat(32));
+
+        let result = diff(&a, &b);
+
+        assert_eq!(result.tensors_renamed.len(), 1);
+        assert_eq!(result.tensors_renamed[0].old_name, "tensor_1");
+        assert_eq!(result.tensors_renamed[0].new_name, "x");
+        assert_eq!(result.tensors_renamed[0].confidence, 1.0);
+        assert_eq!(result.tensors_removed, vec!["tensor_0"]);
+        assert_eq!(result.tensors_added, vec!["y"]);
+    }
+
+    #[test]
+    fn test_diff_rename_requires_same_structure() {
+        let a = create_test_artifact(Format::GGUF, 0, 1);
+        let mut b = a.clone();
+        let mut tensor = b.tensors.remove("tensor_0").unwrap();
+        tensor.dtype = "f16".to_string();
+        b.tensors.insert("other".to_string(), tensor);
+
+        let result = diff(&a, &b);
+
+        assert!(result.tensors_renamed.is_empty());
+        assert_eq!(result.tensors_added, vec!["other"]);
+    }
+
+    #[test]
+    fn test_diff_detects_bulk_prefix_rename() {
+        let a = rename_artifact(&[
+            "transformer.h.0.attn.weight",
+            "transformer.h.0.mlp.weight",
+            "transformer.h.1.attn.weight",
+            "transformer.h.1.mlp.weight",
+        ]);
+        let b = rename_artifact(&[
+            "model.h.0.attn.weight",
+            "model.h.0.mlp.weight",
+            "model.h.1.attn.weight",
+            "model.h.1.mlp.weight",
+        ]);
+
+        let result = diff(&a, &b);
+
+        assert_eq!(result.tensors_renamed.len(), 4);
+        assert!(result.tensors_added.is_empty());
+        assert!(result.tensors_removed.is_empty());
+        assert_eq!(result.bulk_renames.len(), 1);
+        assert_eq!(result.bulk_renames[0].old_prefix, "transformer.");
+        assert_eq!(result.bulk_renames[0].new_prefix, "model.");
+        assert_eq!(result.bulk_renames[0].count, 4);
+        let rename = &result.tensors_renamed[0];
+        assert_eq!(rename.old_name, "transformer.h.0.attn.weight");
+        assert_eq!(rename.new_name, "model.h.0.attn.weight");
+        assert_eq!(rename.confidence, 0.9);
+    }
+
+    #[test]
+    fn test_diff_bulk_rename_keeps_layer_index() {
+        let a = rename_artifact(&[
+            "transformer.h.0.attn.weight",
+            "transformer.h.0.ln.weight",
+            "transformer.h.1.attn.weight",
+            "transformer.h.1.ln.weight",
+        ]);
+        let b = rename_artifact(&[
+            "model.layers.0.self_attn.weight",
+            "model.layers.0.ln.weight",
+            "model.layers.1.self_attn.weight",
+            "model.layers.1.ln.weight",
+        ]);
+
+        let result = diff(&a, &b);
+
+        let rules: Vec<_> = result
+            .bulk_renames
+            .iter()
+            .map(|r| (r.old_prefix.as_str(), r.new_prefix.as_str(), r.count))
+            .collect();
+        assert_eq!(
+            rules,
+            vec![
+                ("transformer.h.", "model.layers.", 2),
+                ("transformer.h.{n}.attn.", "model.layers.{n}.self_attn.", 2),
+            ]
+        );
+        let renamed: Vec<_> = result
+            .tensors_renamed
+            .iter()
+            .map(|r| (r.old_name.as_str(), r.new_name.as_str()))
+            .collect();
+        assert_eq!(
+            renamed,
+            vec![
+                (
+                    "transformer.h.0.attn.weight",
+                    "model.layers.0.self_attn.weight"
+                ),
+                ("transformer.h.0.ln.weight", "model.layers.0.ln.weight"),
+                (
+                    "transformer.h.1.attn.weight",
+                    "model.layers.1.self_attn.weight"
+                ),
+                ("transformer.h.1.ln.weight", "model.layers.1.ln.weight"),
+            ]
+        );
+    }
+
     #[test]
     fn test_diff_has_changes() {
         let a = create_test_artifact(Format::GGUF, 3, 2);
@@ -425,6 +1340,11 @@ mod tests {
                 dtype: "f32".to_string(),
                 shape: vec![10],
                 byte_length: 40,
+                data_offset: None,
+                digest: None,
+                location: None,
+                nnz: None,
+                big_endian: false,
             },
         );
         a.tensors.insert(
@@ -434,6 +1354,11 @@ mod tests {
                 dtype: "f32".to_string(),
                 shape: vec![10],
                 byte_length: 40,
+                data_offset: None,
+                digest: None,
+                location: None,
+                nnz: None,
+                big_endian: false,
             },
         );
 
@@ -444,6 +1369,11 @@ mod tests {
                 dtype: "f32".to_string(),
                 shape: vec![10],
                 byte_length: 40,
+                data_offset: None,
+                digest: None,
+                location: None,
+                nnz: None,
+                big_endian: false,
             },
         );
         b.tensors.insert(
@@ -453,6 +1383,11 @@ mod tests {
                 dtype: "f32".to_string(),
                 shape: vec![10],
                 byte_length: 40,
+                data_offset: None,
+                digest: None,
+                location: None,
+                nnz: None,
+                big_endian: false,
             },
         );
 
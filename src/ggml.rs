@@ -0,0 +1,447 @@
+use crate::gguf::{ggml_type_size, gguf_dtype_str};
+use crate::types::{Artifact, CanonicalValue, Finding, Format, ParseLimits, Severity, Tensor};
+use std::collections::BTreeMap;
+use std::io::{Read, Seek, SeekFrom};
+use thiserror::Error;
+
+const GGML_MAGIC: u32 = 0x67676d6c;
+const GGMF_MAGIC: u32 = 0x67676d66;
+const GGJT_MAGIC: u32 = 0x67676a74;
+/// GGJT aligns each tensor's data so the file can be memory-mapped.
+const GGJT_ALIGNMENT: u64 = 32;
+const MAX_STRING_LEN: usize = 1_000_000;
+
+#[derive(Error, Debug)]
+pub enum GGMLParserError {
+    #[error("invalid magic number: expected 'ggml', 'ggmf' or 'ggjt'")]
+    InvalidMagic,
+    #[error("unsupported {magic} version: {version}")]
+    UnsupportedVersion { magic: &'static str, version: u32 },
+    #[error("string length {0} is too large")]
+    StringTooLong(usize),
+    #[error("vocabulary size {count} exceeds max_array_elements ({max}); needs at least {count}")]
+    VocabTooLarge { count: u64, max: usize },
+    #[error("tensor count {count} exceeds max_tensor_count ({max}); needs at least {count}")]
+    TensorCountTooLarge { count: u64, max: u64 },
+    #[error("tensor dimensions {dims} exceed max_dimensions ({max}); needs at least {dims}")]
+    DimensionsTooLarge { dims: u32, max: u32 },
+    #[error("tensor '{name}' has unsupported type {dtype}; its size and the tensors after it are unknown")]
+    UnsupportedType { name: String, dtype: u32 },
+    #[error("tensor '{name}' row length {row_length} is not a multiple of the {dtype} block size {block_size}")]
+    InvalidBlockAlignment {
+        name: String,
+        dtype: String,
+        row_length: u64,
+        block_size: u64,
+    },
+    #[error("tensor '{0}' shape too large (product overflow)")]
+    ShapeTooLargeOverflow(String),
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+}
+
+/// Whether `magic` starts a legacy `ggml`, `ggmf` or `ggjt` file.
+pub fn is_ggml(magic: &[u8]) -> bool {
+    magic.get(..4).is_some_and(|m| {
+        let magic = u32::from_le_bytes(m.try_into().unwrap());
+        matches!(magic, GGML_MAGIC | GGMF_MAGIC | GGJT_MAGIC)
+    })
+}
+
+/// Generation of the quantized block layouts. GGJT v2 changed the order of
+/// the 4- and 5-bit quants and v3 stored the q4_0, q4_1 and q8_0 scales as
+/// f16, as GGUF does.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Quants {
+    Ggjt1,
+    Ggjt2,
+    Ggjt3,
+}
+
+/// Map a legacy tensor type to a dtype name, block size and bytes per block.
+///
+/// Types whose layout matches GGUF get the GGUF name; older layouts are
+/// suffixed with the last file version that used them (`q4_0_ggjt2`), so
+/// they are never decoded as the GGUF type.
+fn legacy_type(dtype: u32, quants: Quants) -> Option<(String, u64, u64)> {
+    use Quants::*;
+    let (name, block_size, type_size) = match (dtype, quants) {
+        (2, Ggjt1) => ("q4_0_ggjt1", 32, 20),
+        (2, Ggjt2) => ("q4_0_ggjt2", 32, 20),
+        (3, Ggjt1) => ("q4_1_ggjt1", 32, 24),
+        (3, Ggjt2) => ("q4_1_ggjt2", 32, 24),
+        (4, Ggjt1) => ("q4_2", 16, 10),
+        (5, Ggjt1) => ("q4_3", 16, 12),
+        (6, Ggjt1) => ("q5_0_ggjt1", 32, 22),
+        (7, Ggjt1) => ("q5_1_ggjt1", 32, 24),
+        (8, Ggjt1 | Ggjt2) => ("q8_0_ggjt2", 32, 36),
+        (0..=3 | 6..=8 | 10..=15, _) => {
+            let (block_size, type_size) = ggml_type_size(dtype)?;
+            return Some((gguf_dtype_str(dtype), block_size, type_size));
+        }
+        _ => return None,
+    };
+    Some((name.to_string(), block_size, type_size))
+}
+
+/// Parse a legacy llama.cpp model file (`ggml`, `ggmf` or `ggjt`), the
+/// formats that preceded GGUF.
+///
+/// The hyperparameters are stored under the keys a GGUF conversion uses
+/// (`llama.embedding_length`, `llama.block_count`, `general.file_type`, ...)
+/// and the vocabulary as `tokenizer.ggml.tokens` and `tokenizer.ggml.scores`,
+/// so [`diff`](crate::diff) can compare the file with its conversion. The
+/// container is recorded as `ggml.magic`, and `n_mult`, which has no GGUF
+/// key, as `ggml.n_mult`. The version is `None` for unversioned `ggml`
+/// files.
+///
+/// Shapes are listed innermost dimension first, as in GGUF. Quantized types
+/// whose block layout changed before GGUF are named after the last file
+/// version that used them (`q4_0_ggjt2`).
+///
+/// Findings:
+/// - `duplicate_tensor`: a tensor name appears more than once.
+/// - `tensor_out_of_bounds`: the last tensor's data extends past the end of
+///   the file.
+///
+/// # Example
+///
+/// ```
+/// use weight_inspect::ggml;
+///
+/// let data = std::fs::read("tests/fixtures/tiny.ggjt").unwrap();
+/// let mut cursor = std::io::Cursor::new(data);
+/// let artifact = ggml::parse_ggml(&mut cursor).unwrap();
+/// assert_eq!(artifact.format, weight_inspect::types::Format::GGML);
+/// assert_eq!(artifact.gguf_version, Some(3));
+/// ```
+pub fn parse_ggml<R: Read + Seek>(reader: &mut R) -> Result<Artifact, GGMLParserError> {
+    parse_ggml_with_limits(reader, &ParseLimits::default())
+}
+
+/// Parse a legacy llama.cpp model file, enforcing the given safety limits.
+///
+/// The vocabulary is bounded by `max_array_elements`.
+pub fn parse_ggml_with_limits<R: Read + Seek>(
+    reader: &mut R,
+    limits: &ParseLimits,
+) -> Result<Artifact, GGMLParserError> {
+    let file_size = reader.seek(SeekFrom::End(0))?;
+    reader.seek(SeekFrom::Start(0))?;
+
+    let (magic, version, quants) = match read_u32(reader)? {
+        GGML_MAGIC => ("ggml", None, Quants::Ggjt1),
+        GGMF_MAGIC => match read_u32(reader)? {
+            1 => ("ggmf", Some(1), Quants::Ggjt1),
+            version => {
+                return Err(GGMLParserError::UnsupportedVersion {
+                    magic: "ggmf",
+                    version,
+                })
+            }
+        },
+        GGJT_MAGIC => match read_u32(reader)? {
+            1 => ("ggjt", Some(1), Quants::Ggjt1),
+            2 => ("ggjt", Some(2), Quants::Ggjt2),
+            3 => ("ggjt", Some(3), Quants::Ggjt3),
+            version => {
+                return Err(GGMLParserError::UnsupportedVersion {
+                    magic: "ggjt",
+                    version,
+                })
+            }
+        },
+        _ => return Err(GGMLParserError::InvalidMagic),
+    };
+
+    let mut metadata = BTreeMap::new();
+    let mut hparam = |key: &str, value: u32| {
+        metadata.insert(key.to_string(), CanonicalValue::Uint32(value as i64));
+    };
+    let n_vocab = read_u32(reader)?;
+    hparam("llama.embedding_length", read_u32(reader)?);
+    hparam("ggml.n_mult", read_u32(reader)?);
+    hparam("llama.attention.head_count", read_u32(reader)?);
+    hparam("llama.block_count", read_u32(reader)?);
+    hparam("llama.rope.dimension_count", read_u32(reader)?);
+    hparam("general.file_type", read_u32(reader)?);
+    metadata.insert(
+        "general.architecture".to_string(),
+        CanonicalValue::String("llama".to_string()),
+    );
+    metadata.insert(
+        "ggml.magic".to_string(),
+        CanonicalValue::String(magic.to_string()),
+    );
+
+    if n_vocab as usize > limits.max_array_elements {
+        return Err(GGMLParserError::VocabTooLarge {
+            count: n_vocab as u64,
+            max: limits.max_array_elements,
+        });
+    }
+    let mut tokens = Vec::with_capacity(n_vocab.min(4096) as usize);
+    let mut scores = Vec::with_capacity(n_vocab.min(4096) as usize);
+    for _ in 0..n_vocab {
+        let len = read_u32(reader)? as usize;
+        tokens.push(CanonicalValue::String(read_string(reader, len)?));
+        // Unversioned files have no token scores.
+        if version.is_some() {
+            scores.push(CanonicalValue::Float32(read_f32(reader)?.into()));
+        }
+    }
+    metadata.insert(
+        "tokenizer.ggml.tokens".to_string(),
+        CanonicalValue::TypedArray("string".to_string(), tokens),
+    );
+    if version.is_some() {
+        metadata.insert(
+            "tokenizer.ggml.scores".to_string(),
+            CanonicalValue::TypedArray("float32".to_string(), scores),
+        );
+    }
+
+    let mut tensors = BTreeMap::new();
+    let mut findings = Vec::new();
+    let mut position = reader.stream_position()?;
+    while position < file_size {
+        if tensors.len() as u64 >= limits.max_tensor_count {
+            return Err(GGMLParserError::TensorCountTooLarge {
+                count: tensors.len() as u64 + 1,
+                max: limits.max_tensor_count,
+            });
+        }
+        let n_dims = read_u32(reader)?;
+        let name_len = read_u32(reader)? as usize;
+        let dtype = read_u32(reader)?;
+        if n_dims > limits.max_dimensions {
+            return Err(GGMLParserError::DimensionsTooLarge {
+                dims: n_dims,
+                max: limits.max_dimensions,
+            });
+        }
+        let mut shape = Vec::with_capacity(n_dims as usize);
+        for _ in 0..n_dims {
+            shape.push(read_u32(reader)? as u64);
+        }
+        let name = read_string(reader, name_len)?;
+
+        let Some((dtype_name, block_size, type_size)) = legacy_type(dtype, quants) else {
+            return Err(GGMLParserError::UnsupportedType { name, dtype });
+        };
+        let byte_length = byte_length(&name, &shape, &dtype_name, block_size, type_size)?;
+
+        let mut data_offset = reader.stream_position()?;
+        if magic == "ggjt" {
+            data_offset = data_offset.div_ceil(GGJT_ALIGNMENT) * GGJT_ALIGNMENT;
+        }
+        let end = data_offset.saturating_add(byte_length);
+        if end > file_size {
+            findings.push(Finding::new(
+                Severity::Error,
+                "tensor_out_of_bounds",
+                Some(&name),
+                format!(
+                    "data ends at byte {} but the file is only {} bytes (truncated?)",
+                    end, file_size
+                ),
+            ));
+        }
+        if tensors.contains_key(&name) {
+            findings.push(Finding::new(
+                Severity::Error,
+                "duplicate_tensor",
+                Some(&name),
+                "tensor name appears more than once; the last entry is used".to_string(),
+            ));
+        }
+        tensors.insert(
+            name.clone(),
+            Tensor {
+                name,
+                dtype: dtype_name,
+                shape,
+                byte_length,
+                data_offset: Some(data_offset),
+                digest: None,
+                location: None,
+                nnz: None,
+                big_endian: false,
+            },
+        );
+        position = end;
+        reader.seek(SeekFrom::Start(end.min(file_size)))?;
+    }
+
+    Ok(Artifact {
+        format: Format::GGML,
+        gguf_version: version,
+        metadata,
+        tensors,
+        graph: None,
+        data_offset: None,
+        findings,
+    })
+}
+
+/// Number of data bytes of a tensor. Quantized rows (the first dimension)
+/// must be a whole number of blocks.
+fn byte_length(
+    name: &str,
+    shape: &[u64],
+    dtype: &str,
+    block_size: u64,
+    type_size: u64,
+) -> Result<u64, GGMLParserError> {
+    let overflow = || GGMLParserError::ShapeTooLargeOverflow(name.to_string());
+    let elements = shape
+        .iter()
+        .try_fold(1u64, |acc, &dim| acc.checked_mul(dim))
+        .ok_or_else(overflow)?;
+    let row_length = shape.first().copied().unwrap_or(1);
+    if row_length % block_size != 0 {
+        return Err(GGMLParserError::InvalidBlockAlignment {
+            name: name.to_string(),
+            dtype: dtype.to_string(),
+            row_length,
+            block_size,
+        });
+    }
+    (elements / block_size)
+        .checked_mul(type_size)
+        .ok_or_else(overflow)
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> Result<u32, GGMLParserError> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_f32<R: Read>(reader: &mut R) -> Result<f32, GGMLParserError> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(f32::from_le_bytes(buf))
+}
+
+/// Read `len` bytes as a string. Token and tensor names are raw bytes;
+/// invalid UTF-8 (e.g. single-byte tokens) is replaced.
+fn read_string<R: Read>(reader: &mut R, len: usize) -> Result<String, GGMLParserError> {
+    if len > MAX_STRING_LEN {
+        return Err(GGMLParserError::StringTooLong(len));
+    }
+    let mut buf = vec![0u8; len];
+    reader.read_exact(&mut buf)?;
+    Ok(String::from_utf8_lossy(&buf).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// A legacy file with a two-token vocabulary and the given tensors
+    /// (name, ggml shape, type, data length).
+    fn ggml_bytes(
+        magic: u32,
+        version: Option<u32>,
+        tensors: &[(&str, &[u32], u32, usize)],
+    ) -> Vec<u8> {
+        let mut buf = magic.to_le_bytes().to_vec();
+        if let Some(version) = version {
+            buf.extend(version.to_le_bytes());
+        }
+        for hparam in [2u32, 64, 256, 2, 1, 32, 1] {
+            buf.extend(hparam.to_le_bytes());
+        }
+        for token in ["<s>", "\u{2581}the"] {
+            buf.extend((token.len() as u32).to_le_bytes());
+            buf.extend(token.as_bytes());
+            if version.is_some() {
+                buf.extend((-1.5f32).to_le_bytes());
+            }
+        }
+        for (name, shape, dtype, len) in tensors {
+            buf.extend((shape.len() as u32).to_le_bytes());
+            buf.extend((name.len() as u32).to_le_bytes());
+            buf.extend(dtype.to_le_bytes());
+            for dim in *shape {
+                buf.extend(dim.to_le_bytes());
+            }
+            buf.extend(name.as_bytes());
+            if magic == GGJT_MAGIC {
+                buf.resize(buf.len().div_ceil(32) * 32, 0);
+            }
+            buf.extend(vec![0u8; *len]);
+        }
+        buf
+    }
+
+    #[test]
+    fn test_parse_ggjt() {
+        let data = ggml_bytes(
+            GGJT_MAGIC,
+            Some(3),
+            &[
+                ("tok_embeddings.weight", &[64, 2], 1, 256),
+                ("layers.0.attention.wq.weight", &[64, 64], 2, 64 * 2 * 18),
+            ],
+        );
+        let artifact = parse_ggml(&mut Cursor::new(data)).unwrap();
+
+        assert_eq!(artifact.gguf_version, Some(3));
+        assert_eq!(
+            artifact.metadata["llama.embedding_length"],
+            CanonicalValue::Uint32(64)
+        );
+        assert_eq!(
+            artifact.metadata["tokenizer.ggml.tokens"],
+            CanonicalValue::TypedArray(
+                "string".to_string(),
+                vec![
+                    CanonicalValue::String("<s>".to_string()),
+                    CanonicalValue::String("\u{2581}the".to_string()),
+                ]
+            )
+        );
+        let wq = &artifact.tensors["layers.0.attention.wq.weight"];
+        assert_eq!(wq.dtype, "q4_0");
+        assert_eq!(wq.shape, vec![64, 64]);
+        assert_eq!(wq.byte_length, 64 * 2 * 18);
+        assert_eq!(wq.data_offset.unwrap() % 32, 0);
+        assert!(artifact.findings.is_empty());
+    }
+
+    #[test]
+    fn test_old_quant_layouts_are_renamed() {
+        let data = ggml_bytes(GGML_MAGIC, None, &[("w", &[32, 2], 2, 40)]);
+        let artifact = parse_ggml(&mut Cursor::new(data)).unwrap();
+        assert_eq!(artifact.gguf_version, None);
+        assert_eq!(artifact.tensors["w"].dtype, "q4_0_ggjt1");
+        assert!(!artifact.metadata.contains_key("tokenizer.ggml.scores"));
+
+        let data = ggml_bytes(GGJT_MAGIC, Some(2), &[("w", &[32], 8, 36)]);
+        let artifact = parse_ggml(&mut Cursor::new(data)).unwrap();
+        assert_eq!(artifact.tensors["w"].dtype, "q8_0_ggjt2");
+        assert_eq!(artifact.tensors["w"].byte_length, 36);
+    }
+
+    #[test]
+    fn test_truncated_and_invalid_files() {
+        let mut data = ggml_bytes(GGMF_MAGIC, Some(1), &[("w", &[16], 0, 64)]);
+        data.truncate(data.len() - 10);
+        let artifact = parse_ggml(&mut Cursor::new(data)).unwrap();
+        assert_eq!(artifact.findings[0].code, "tensor_out_of_bounds");
+
+        let data = ggml_bytes(GGJT_MAGIC, Some(4), &[]);
+        assert!(matches!(
+            parse_ggml(&mut Cursor::new(data)),
+            Err(GGMLParserError::UnsupportedVersion { version: 4, .. })
+        ));
+        let data = ggml_bytes(GGJT_MAGIC, Some(3), &[("w", &[32], 4, 20)]);
+        assert!(matches!(
+            parse_ggml(&mut Cursor::new(data)),
+            Err(GGMLParserError::UnsupportedType { dtype: 4, .. })
+        ));
+    }
+}
@@ -1,8 +1,14 @@
-use crate::types::{Artifact, CanonicalValue, Format, Tensor};
+use crate::types::{Artifact, CanonicalValue, Finding, Format, ParseLimits, Severity, Tensor};
 use std::collections::BTreeMap;
 use std::io::{Read, Seek};
 use thiserror::Error;
 
+mod split;
+
+pub use split::{
+    is_split, parse_split, parse_split_with_limits, split_paths, SplitError, SPLIT_KEYS,
+};
+
 #[derive(Error, Debug)]
 pub enum GGUFParserError {
     #[error("unable to parse GGUF header")]
@@ -11,28 +17,37 @@ pub enum GGUFParserError {
     InvalidMagic,
     #[error("unsupported GGUF version: {0}")]
     UnsupportedVersion(u32),
-    #[error("array element count exceeds maximum ({max}): {count}")]
+    #[error(
+        "array element count {count} exceeds max_array_elements ({max}); needs at least {count}"
+    )]
     ArrayTooLarge { count: u64, max: usize },
+    #[error("array nesting exceeds maximum depth ({max})")]
+    ArrayNestingTooDeep { max: usize },
     #[error("shape dimension exceeds maximum: {0}")]
     ShapeTooLarge(u64),
-    #[error("tensor count exceeds maximum ({max}): {count}")]
+    #[error("tensor count {count} exceeds max_tensor_count ({max}); needs at least {count}")]
     TensorCountTooLarge { count: u64, max: u64 },
-    #[error("metadata count exceeds maximum ({max}): {count}")]
+    #[error("metadata count {count} exceeds max_metadata_count ({max}); needs at least {count}")]
     MetadataCountTooLarge { count: u64, max: u64 },
-    #[error("tensor dimensions exceed maximum ({max}): {dims}")]
+    #[error("tensor dimensions {dims} exceed max_dimensions ({max}); needs at least {dims}")]
     DimensionsTooLarge { dims: u32, max: u32 },
     #[error("tensor shape too large (product overflow)")]
     ShapeTooLargeOverflow,
+    #[error("tensor '{name}' row length {row_length} is not a multiple of the {dtype} block size {block_size}")]
+    InvalidBlockAlignment {
+        name: String,
+        dtype: String,
+        row_length: u64,
+        block_size: u64,
+    },
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
 }
 
-const MAX_ARRAY_ELEMENTS: usize = 100_000;
+const MAX_ARRAY_DEPTH: usize = 16;
 
 const GGUF_MAGIC: u32 = 0x46554747;
-const MAX_TENSOR_COUNT: u64 = 100_000;
-const MAX_METADATA_COUNT: u64 = 10_000;
-const MAX_DIMENSIONS: u32 = 32;
+const DEFAULT_ALIGNMENT: u64 = 32;
 
 /// Parse a GGUF model file.
 ///
@@ -47,6 +62,25 @@ const MAX_DIMENSIONS: u32 = 32;
 /// assert_eq!(artifact.format, weight_inspect::types::Format::GGUF);
 /// ```
 pub fn parse_gguf<R: Read + Seek>(reader: &mut R) -> Result<Artifact, GGUFParserError> {
+    parse_gguf_with_limits(reader, &ParseLimits::default())
+}
+
+/// Parse a GGUF model file, enforcing the given safety limits.
+///
+/// # Example
+///
+/// ```
+/// use weight_inspect::{gguf, types::ParseLimits};
+///
+/// let data = std::fs::read("tests/fixtures/tiny.gguf").unwrap();
+/// let mut cursor = std::io::Cursor::new(data);
+/// let limits = ParseLimits { max_tensor_count: 0, ..ParseLimits::default() };
+/// assert!(gguf::parse_gguf_with_limits(&mut cursor, &limits).is_err());
+/// ```
+pub fn parse_gguf_with_limits<R: Read + Seek>(
+    reader: &mut R,
+    limits: &ParseLimits,
+) -> Result<Artifact, GGUFParserError> {
     let magic = read_u32(reader)?;
     if magic != GGUF_MAGIC {
         return Err(GGUFParserError::InvalidMagic);
@@ -58,34 +92,44 @@ pub fn parse_gguf<R: Read + Seek>(reader: &mut R) -> Result<Artifact, GGUFParser
     }
 
     let tensor_count = read_u64(reader)?;
-    if tensor_count > MAX_TENSOR_COUNT {
+    if tensor_count > limits.max_tensor_count {
         return Err(GGUFParserError::TensorCountTooLarge {
             count: tensor_count,
-            max: MAX_TENSOR_COUNT,
+            max: limits.max_tensor_count,
         });
     }
     let metadata_kv_count = read_u64(reader)?;
-    if metadata_kv_count > MAX_METADATA_COUNT {
+    if metadata_kv_count > limits.max_metadata_count {
         return Err(GGUFParserError::MetadataCountTooLarge {
             count: metadata_kv_count,
-            max: MAX_METADATA_COUNT,
+            max: limits.max_metadata_count,
         });
     }
 
+    let mut findings = Vec::new();
     let mut metadata = BTreeMap::new();
     for _ in 0..metadata_kv_count {
-        let (key, value) = read_kv(reader, version)?;
+        let (key, value) = read_kv(reader, version, limits)?;
+        if metadata.contains_key(&key) {
+            findings.push(Finding::new(
+                Severity::Warning,
+                "duplicate_metadata_key",
+                Some(&key),
+                "key appears more than once; the last value is used".to_string(),
+            ));
+        }
         metadata.insert(key, value);
     }
 
     let mut tensors = BTreeMap::new();
+    let mut relative_offsets = Vec::new();
     for _ in 0..tensor_count {
         let name = read_string(reader)?;
         let n_dims = read_u32(reader)?;
-        if n_dims > MAX_DIMENSIONS {
+        if n_dims > limits.max_dimensions {
             return Err(GGUFParserError::DimensionsTooLarge {
                 dims: n_dims,
-                max: MAX_DIMENSIONS,
+                max: limits.max_dimensions,
             });
         }
         let mut shape = Vec::new();
@@ -93,9 +137,18 @@ pub fn parse_gguf<R: Read + Seek>(reader: &mut R) -> Result<Artifact, GGUFParser
             shape.push(read_u64(reader)?);
         }
         let dtype = read_u32(reader)?;
-        let _offset = read_u64(reader)?;
-        let byte_length = compute_byte_length(&shape, dtype);
+        let offset = read_u64(reader)?;
+        let byte_length = compute_byte_length(&name, &shape, dtype)?;
+        relative_offsets.push((name.clone(), offset));
 
+        if tensors.contains_key(&name) {
+            findings.push(Finding::new(
+                Severity::Error,
+                "duplicate_tensor",
+                Some(&name),
+                "tensor name appears more than once; the last entry is used".to_string(),
+            ));
+        }
         tensors.insert(
             name.clone(),
             Tensor {
@@ -103,18 +156,45 @@ pub fn parse_gguf<R: Read + Seek>(reader: &mut R) -> Result<Artifact, GGUFParser
                 dtype: gguf_dtype_str(dtype),
                 shape,
                 byte_length,
+                data_offset: None,
+                digest: None,
+                location: None,
+                nnz: None,
+                big_endian: false,
             },
         );
     }
 
+    // Tensor offsets are relative to the data section, which starts at the
+    // first multiple of `general.alignment` after the tensor infos.
+    let header_end = reader.stream_position()?;
+    let align = alignment(&metadata);
+    let data_offset = header_end.div_ceil(align) * align;
+    for (name, offset) in relative_offsets {
+        if let Some(tensor) = tensors.get_mut(&name) {
+            tensor.data_offset = data_offset.checked_add(offset);
+        }
+    }
+
     Ok(Artifact {
         format: Format::GGUF,
         gguf_version: Some(version as i64),
         metadata,
         tensors,
+        graph: None,
+        data_offset: Some(data_offset),
+        findings,
     })
 }
 
+/// Data alignment declared by `general.alignment`, or the GGUF default of 32.
+pub fn alignment(metadata: &BTreeMap<String, CanonicalValue>) -> u64 {
+    match metadata.get("general.alignment") {
+        Some(CanonicalValue::Uint32(a)) | Some(CanonicalValue::Uint64(a)) if *a > 0 => *a as u64,
+        _ => DEFAULT_ALIGNMENT,
+    }
+}
+
 fn read_u32<R: Read>(reader: &mut R) -> Result<u32, GGUFParserError> {
     let mut buf = [0u8; 4];
     reader.read_exact(&mut buf)?;
@@ -143,9 +223,25 @@ fn read_string<R: Read>(reader: &mut R) -> Result<String, GGUFParserError> {
 fn read_kv<R: Read>(
     reader: &mut R,
     _version: u32,
+    limits: &ParseLimits,
 ) -> Result<(String, CanonicalValue), GGUFParserError> {
     let key = read_string(reader)?;
     let value_type = read_u32(reader)?;
+    let value = read_value(reader, value_type, 0, limits)?;
+    Ok((key, value))
+}
+
+/// Read a single metadata value of the given GGUF value type.
+///
+/// Arrays are decoded recursively into [`CanonicalValue::TypedArray`], so
+/// arrays of any element type (including arrays of arrays) keep their element
+/// type, even when empty.
+fn read_value<R: Read>(
+    reader: &mut R,
+    value_type: u32,
+    depth: usize,
+    limits: &ParseLimits,
+) -> Result<CanonicalValue, GGUFParserError> {
     let value = match value_type {
         0 => CanonicalValue::Uint8(read_u8(reader)? as i64),
         1 => CanonicalValue::Int8(read_i8(reader)? as i64),
@@ -157,38 +253,55 @@ fn read_kv<R: Read>(
         7 => CanonicalValue::Bool(read_bool(reader)?),
         8 => CanonicalValue::String(read_string(reader)?),
         9 => {
+            if depth >= MAX_ARRAY_DEPTH {
+                return Err(GGUFParserError::ArrayNestingTooDeep {
+                    max: MAX_ARRAY_DEPTH,
+                });
+            }
             let element_type = read_u32(reader)?;
+            let type_name = value_type_name(element_type).ok_or(GGUFParserError::InvalidHeader)?;
             let n = read_u64(reader)? as usize;
-            if n > MAX_ARRAY_ELEMENTS {
+            if n > limits.max_array_elements {
                 return Err(GGUFParserError::ArrayTooLarge {
                     count: n as u64,
-                    max: MAX_ARRAY_ELEMENTS,
+                    max: limits.max_array_elements,
                 });
             }
-            let mut arr = Vec::with_capacity(n);
+            // `n` is untrusted: grow past a small reservation as values
+            // actually arrive.
+            let mut arr = Vec::with_capacity(n.min(4096));
             for _ in 0..n {
-                let val = match element_type {
-                    0 => CanonicalValue::Uint8(read_u8(reader)? as i64),
-                    1 => CanonicalValue::Int8(read_i8(reader)? as i64),
-                    2 => CanonicalValue::Uint16(read_u16(reader)? as i64),
-                    3 => CanonicalValue::Int16(read_i16(reader)? as i64),
-                    4 => CanonicalValue::Uint32(read_u32(reader)? as i64),
-                    5 => CanonicalValue::Int32(read_i32(reader)? as i64),
-                    6 => CanonicalValue::Float32(read_f32(reader)?.into()),
-                    7 => CanonicalValue::Bool(read_bool(reader)?),
-                    8 => CanonicalValue::String(read_string(reader)?),
-                    _ => return Err(GGUFParserError::InvalidHeader),
-                };
-                arr.push(val);
+                arr.push(read_value(reader, element_type, depth + 1, limits)?);
             }
-            CanonicalValue::Array(arr)
+            CanonicalValue::TypedArray(type_name.to_string(), arr)
         }
         10 => CanonicalValue::Uint64(read_u64(reader)? as i64),
         11 => CanonicalValue::Int64(read_i64(reader)?),
         12 => CanonicalValue::Float(read_f64(reader)?),
         _ => return Err(GGUFParserError::InvalidHeader),
     };
-    Ok((key, value))
+    Ok(value)
+}
+
+/// The GGUF spec's name for a metadata value type.
+fn value_type_name(value_type: u32) -> Option<&'static str> {
+    let name = match value_type {
+        0 => "uint8",
+        1 => "int8",
+        2 => "uint16",
+        3 => "int16",
+        4 => "uint32",
+        5 => "int32",
+        6 => "float32",
+        7 => "bool",
+        8 => "string",
+        9 => "array",
+        10 => "uint64",
+        11 => "int64",
+        12 => "float64",
+        _ => return None,
+    };
+    Some(name)
 }
 
 fn read_u8<R: Read>(reader: &mut R) -> Result<u8, GGUFParserError> {
@@ -244,7 +357,7 @@ fn read_bool<R: Read>(reader: &mut R) -> Result<bool, GGUFParserError> {
     Ok(val != 0)
 }
 
-fn gguf_dtype_str(dtype: u32) -> String {
+pub(crate) fn gguf_dtype_str(dtype: u32) -> String {
     match dtype {
         0 => "f32".to_string(),
         1 => "f16".to_string(),
@@ -282,23 +395,82 @@ fn gguf_dtype_str(dtype: u32) -> String {
     }
 }
 
-fn compute_byte_length(shape: &[u64], dtype: u32) -> u64 {
+/// Block size (elements per block) and bytes per block for a ggml type.
+///
+/// Non-quantized types have a block size of 1. Returns `None` for type codes
+/// that `gguf_dtype_str` does not know about.
+pub(crate) fn ggml_type_size(dtype: u32) -> Option<(u64, u64)> {
+    let info = match dtype {
+        0 => (1, 4),      // f32
+        1 => (1, 2),      // f16
+        2 => (32, 18),    // q4_0
+        3 => (32, 20),    // q4_1
+        6 => (32, 22),    // q5_0
+        7 => (32, 24),    // q5_1
+        8 => (32, 34),    // q8_0
+        9 => (32, 36),    // q8_1
+        10 => (256, 84),  // q2_k
+        11 => (256, 110), // q3_k
+        12 => (256, 144), // q4_k
+        13 => (256, 176), // q5_k
+        14 => (256, 210), // q6_k
+        15 => (256, 292), // q8_k
+        16 => (256, 66),  // iq2_xxs
+        17 => (256, 74),  // iq2_xs
+        18 => (256, 98),  // iq3_xxs
+        19 => (256, 50),  // iq1_s
+        20 => (32, 18),   // iq4_nl
+        21 => (256, 110), // iq3_s
+        22 => (256, 82),  // iq2_s
+        23 => (256, 136), // iq4_xs
+        24 => (1, 1),     // i8
+        25 => (1, 2),     // i16
+        26 => (1, 4),     // i32
+        27 => (1, 8),     // i64
+        28 => (1, 8),     // f64
+        29 => (256, 56),  // iq1_m
+        30 => (1, 2),     // bf16
+        34 => (256, 54),  // tq1_0
+        35 => (256, 66),  // tq2_0
+        39 => (32, 17),   // mxfp4
+        _ => return None,
+    };
+    Some(info)
+}
+
+/// Compute the number of data bytes a tensor occupies.
+///
+/// Quantized types are stored in fixed-size blocks along the first (row)
+/// dimension, so the row length must be a multiple of the block size.
+/// Unknown dtypes yield 0.
+fn compute_byte_length(name: &str, shape: &[u64], dtype: u32) -> Result<u64, GGUFParserError> {
     let mut elements: u64 = 1;
     for &dim in shape {
-        elements = elements.checked_mul(dim).unwrap_or(0);
+        elements = elements
+            .checked_mul(dim)
+            .ok_or(GGUFParserError::ShapeTooLargeOverflow)?;
     }
-    match dtype {
-        0 | 26 => elements * 4,      // f32, i32
-        1 | 25 | 30 => elements * 2, // f16, i16, bf16
-        24 => elements,              // i8 (1 byte)
-        27 | 28 => elements * 8,     // i64, f64
-        _ => 0,                      // Quantized types - byte size unknown, return 0
+    let Some((block_size, type_size)) = ggml_type_size(dtype) else {
+        return Ok(0);
+    };
+    let row_length = shape.first().copied().unwrap_or(1);
+    if row_length % block_size != 0 {
+        return Err(GGUFParserError::InvalidBlockAlignment {
+            name: name.to_string(),
+            dtype: gguf_dtype_str(dtype),
+            row_length,
+            block_size,
+        });
     }
+    (elements / block_size)
+        .checked_mul(type_size)
+        .ok_or(GGUFParserError::ShapeTooLargeOverflow)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::io::Cursor;
 
     #[test]
     fn test_gguf_dtype_str() {
@@ -309,12 +481,244 @@ mod tests {
         assert_eq!(gguf_dtype_str(28), "f64");
     }
 
+    fn kv_bytes(key: &str, value_type: u32, payload: &[u8]) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&(key.len() as u64).to_le_bytes());
+        buf.extend_from_slice(key.as_bytes());
+        buf.extend_from_slice(&value_type.to_le_bytes());
+        buf.extend_from_slice(payload);
+        buf
+    }
+
+    fn array_payload(element_type: u32, elements: &[&[u8]]) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&element_type.to_le_bytes());
+        buf.extend_from_slice(&(elements.len() as u64).to_le_bytes());
+        for element in elements {
+            buf.extend_from_slice(element);
+        }
+        buf
+    }
+
+    fn gguf_bytes(kvs: &[Vec<u8>], tensors: &[(&str, &[u64], u32, u64)]) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(b"GGUF");
+        buf.extend_from_slice(&3u32.to_le_bytes());
+        buf.extend_from_slice(&(tensors.len() as u64).to_le_bytes());
+        buf.extend_from_slice(&(kvs.len() as u64).to_le_bytes());
+        for kv in kvs {
+            buf.extend_from_slice(kv);
+        }
+        for (name, shape, dtype, offset) in tensors {
+            buf.extend_from_slice(&(name.len() as u64).to_le_bytes());
+            buf.extend_from_slice(name.as_bytes());
+            buf.extend_from_slice(&(shape.len() as u32).to_le_bytes());
+            for dim in *shape {
+                buf.extend_from_slice(&dim.to_le_bytes());
+            }
+            buf.extend_from_slice(&dtype.to_le_bytes());
+            buf.extend_from_slice(&offset.to_le_bytes());
+        }
+        buf
+    }
+
+    #[test]
+    fn test_tensor_data_offsets() {
+        let data = gguf_bytes(&[], &[("a", &[4], 0, 0), ("b", &[8], 0, 32)]);
+        let header_len = data.len() as u64;
+
+        let artifact = parse_gguf(&mut Cursor::new(data)).unwrap();
+
+        let data_start = header_len.div_ceil(32) * 32;
+        assert_eq!(artifact.data_offset, Some(data_start));
+        assert_eq!(artifact.tensors["a"].data_offset, Some(data_start));
+        assert_eq!(artifact.tensors["b"].data_offset, Some(data_start + 32));
+    }
+
+    #[test]
+    fn test_duplicate_tensor_names_are_reported() {
+        let data = gguf_bytes(&[], &[("a", &[4], 0, 0), ("a", &[4], 0, 32)]);
+
+        let artifact = parse_gguf(&mut Cursor::new(data)).unwrap();
+
+        assert_eq!(artifact.tensors.len(), 1);
+        assert_eq!(artifact.findings.len(), 1);
+        assert_eq!(artifact.findings[0].code, "duplicate_tensor");
+    }
+
+    #[test]
+    fn test_read_kv_64bit_arrays() {
+        let cases: Vec<(u32, Vec<u8>, CanonicalValue)> = vec![
+            (
+                10,
+                u64::MAX.to_le_bytes().to_vec(),
+                CanonicalValue::Uint64(u64::MAX as i64),
+            ),
+            (
+                11,
+                (-7i64).to_le_bytes().to_vec(),
+                CanonicalValue::Int64(-7),
+            ),
+            (
+                12,
+                2.5f64.to_le_bytes().to_vec(),
+                CanonicalValue::Float(2.5),
+            ),
+        ];
+        for (element_type, element, expected) in cases {
+            let payload = array_payload(element_type, &[&element, &element]);
+            let data = kv_bytes("k", 9, &payload);
+            let (key, value) = read_kv(&mut Cursor::new(data), 3, &ParseLimits::default()).unwrap();
+            assert_eq!(key, "k");
+            assert_eq!(
+                value,
+                CanonicalValue::TypedArray(
+                    value_type_name(element_type).unwrap().to_string(),
+                    vec![expected.clone(), expected]
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn test_read_kv_nested_arrays() {
+        let inner_a = array_payload(4, &[&1u32.to_le_bytes(), &2u32.to_le_bytes()]);
+        let inner_b = array_payload(4, &[&3u32.to_le_bytes()]);
+        let payload = array_payload(9, &[&inner_a, &inner_b]);
+        let data = kv_bytes("nested", 9, &payload);
+
+        let (_, value) = read_kv(&mut Cursor::new(data), 3, &ParseLimits::default()).unwrap();
+
+        assert_eq!(
+            value,
+            CanonicalValue::TypedArray(
+                "array".to_string(),
+                vec![
+                    CanonicalValue::TypedArray(
+                        "uint32".to_string(),
+                        vec![CanonicalValue::Uint32(1), CanonicalValue::Uint32(2)]
+                    ),
+                    CanonicalValue::TypedArray(
+                        "uint32".to_string(),
+                        vec![CanonicalValue::Uint32(3)]
+                    ),
+                ]
+            )
+        );
+    }
+
+    #[test]
+    fn test_read_kv_empty_arrays_keep_element_type() {
+        let read = |element_type| {
+            let data = kv_bytes("k", 9, &array_payload(element_type, &[]));
+            read_kv(&mut Cursor::new(data), 3, &ParseLimits::default())
+                .unwrap()
+                .1
+        };
+
+        let ints = read(4);
+        let strings = read(8);
+        assert_eq!(
+            ints,
+            CanonicalValue::TypedArray("uint32".to_string(), vec![])
+        );
+        assert_ne!(ints, strings);
+    }
+
+    #[test]
+    fn test_read_kv_array_nesting_limit() {
+        let mut payload = array_payload(4, &[]);
+        for _ in 0..MAX_ARRAY_DEPTH {
+            payload = array_payload(9, &[&payload]);
+        }
+        let data = kv_bytes("deep", 9, &payload);
+
+        let result = read_kv(&mut Cursor::new(data), 3, &ParseLimits::default());
+        assert!(matches!(
+            result,
+            Err(GGUFParserError::ArrayNestingTooDeep { .. })
+        ));
+    }
+
+    #[test]
+    fn test_array_limit_is_configurable() {
+        let elements: Vec<[u8; 1]> = (0..200u8).map(|i| [i]).collect();
+        let refs: Vec<&[u8]> = elements.iter().map(|e| e.as_slice()).collect();
+        let data = kv_bytes("tokens", 9, &array_payload(0, &refs));
+
+        let tight = ParseLimits {
+            max_array_elements: 100,
+            ..ParseLimits::default()
+        };
+        let result = read_kv(&mut Cursor::new(data.clone()), 3, &tight);
+        assert!(matches!(
+            result,
+            Err(GGUFParserError::ArrayTooLarge {
+                count: 200,
+                max: 100
+            })
+        ));
+
+        let (_, value) = read_kv(&mut Cursor::new(data), 3, &ParseLimits::default()).unwrap();
+        assert!(matches!(value, CanonicalValue::TypedArray(_, arr) if arr.len() == 200));
+    }
+
+    #[test]
+    fn test_default_limits_fit_large_vocabularies() {
+        assert!(ParseLimits::default().max_array_elements >= 152_064);
+    }
+
     #[test]
     fn test_compute_byte_length() {
         let shape = vec![2, 3, 4];
-        assert_eq!(compute_byte_length(&shape, 0), 2 * 3 * 4 * 4); // f32
-        assert_eq!(compute_byte_length(&shape, 1), 2 * 3 * 4 * 2); // f16
-        assert_eq!(compute_byte_length(&shape, 24), 2 * 3 * 4 * 1); // i8
-        assert_eq!(compute_byte_length(&shape, 27), 2 * 3 * 4 * 8); // i64
+        let len = |dtype| compute_byte_length("t", &shape, dtype).unwrap();
+        assert_eq!(len(0), 2 * 3 * 4 * 4); // f32
+        assert_eq!(len(1), 2 * 3 * 4 * 2); // f16
+        assert_eq!(len(24), 2 * 3 * 4); // i8
+        assert_eq!(len(27), 2 * 3 * 4 * 8); // i64
+    }
+
+    #[test]
+    fn test_compute_byte_length_quantized() {
+        let shape = vec![4096, 32];
+        let len = |dtype| compute_byte_length("t", &shape, dtype).unwrap();
+        assert_eq!(len(2), 4096 * 32 / 32 * 18); // q4_0
+        assert_eq!(len(8), 4096 * 32 / 32 * 34); // q8_0
+        assert_eq!(len(12), 4096 * 32 / 256 * 144); // q4_k
+        assert_eq!(len(14), 4096 * 32 / 256 * 210); // q6_k
+        assert_eq!(len(16), 4096 * 32 / 256 * 66); // iq2_xxs
+        assert_eq!(len(34), 4096 * 32 / 256 * 54); // tq1_0
+        assert_eq!(len(39), 4096 * 32 / 32 * 17); // mxfp4
+    }
+
+    #[test]
+    fn test_every_known_dtype_has_size() {
+        for dtype in 0..64 {
+            if !gguf_dtype_str(dtype).starts_with("unknown_") {
+                assert!(
+                    ggml_type_size(dtype).is_some(),
+                    "missing size for {}",
+                    dtype
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_compute_byte_length_misaligned_row() {
+        let result = compute_byte_length("t", &[100, 4], 12); // q4_k, block size 256
+        assert!(matches!(
+            result,
+            Err(GGUFParserError::InvalidBlockAlignment {
+                row_length: 100,
+                block_size: 256,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_compute_byte_length_unknown_dtype() {
+        assert_eq!(compute_byte_length("t", &[32], 1000).unwrap(), 0);
     }
 }
@@ -0,0 +1,577 @@
+use super::{parse_gguf_with_limits, GGUFParserError};
+use crate::types::{Artifact, CanonicalValue, Finding, Format, ParseLimits, Severity};
+use crate::validate;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Metadata keys written by `gguf-split` that describe how a model is split
+/// rather than the model itself.
+pub const SPLIT_KEYS: [&str; 3] = ["split.no", "split.count", "split.tensors.count"];
+
+#[derive(Error, Debug)]
+pub enum SplitError {
+    #[error("failed to read '{path}': {source}")]
+    Io { path: String, source: io::Error },
+    #[error("failed to parse split '{path}': {source}")]
+    Shard {
+        path: String,
+        source: GGUFParserError,
+    },
+}
+
+/// The `{prefix}-{no}-of-{count}.gguf` parts of a split file name, with `no`
+/// counted from 1 as in the file name.
+fn split_name(path: &Path) -> Option<(String, u32, u32)> {
+    let stem = path.file_name()?.to_str()?.strip_suffix(".gguf")?;
+    let (rest, count) = stem.rsplit_once("-of-")?;
+    let (prefix, no) = rest.rsplit_once('-')?;
+    let number = |s: &str| {
+        (!s.is_empty() && s.bytes().all(|c| c.is_ascii_digit()))
+            .then(|| s.parse().ok())
+            .flatten()
+    };
+    let (no, count) = (number(no)?, number(count)?);
+    (!prefix.is_empty() && (1..=count).contains(&no)).then(|| (prefix.to_string(), no, count))
+}
+
+/// Whether `path` is named like a shard of a split GGUF, e.g.
+/// `model-00001-of-00003.gguf`.
+pub fn is_split(path: &Path) -> bool {
+    split_name(path).is_some_and(|(_, _, count)| count > 1)
+}
+
+/// File name of shard `no` (from 1) of the split that `path` belongs to,
+/// zero-padded like `path`'s own name.
+fn sibling_name(path: &Path, no: u64) -> Option<String> {
+    split_name(path)?;
+    let (rest, count) = path.file_name()?.to_str()?.rsplit_once("-of-")?;
+    let (prefix, own_no) = rest.rsplit_once('-')?;
+    let width = own_no.len();
+    Some(format!("{prefix}-{no:0width$}-of-{count}"))
+}
+
+/// The shard files of the split GGUF that `path` belongs to, in the order
+/// [`parse_split`] concatenates them. Shards that do not exist are left out.
+///
+/// Siblings are found by listing `path`'s directory rather than trying every
+/// number up to the count in the name, which may be arbitrarily large.
+pub fn split_paths(path: &Path) -> Vec<PathBuf> {
+    if split_name(path).is_none() {
+        return vec![path.to_path_buf()];
+    }
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return vec![path.to_path_buf()];
+    };
+    let mut shards: Vec<(u32, PathBuf)> = entries
+        .filter_map(|entry| {
+            let name = entry.ok()?.file_name().into_string().ok()?;
+            let (_, no, _) = split_name(Path::new(&name))?;
+            (sibling_name(path, u64::from(no)).as_deref() == Some(name.as_str()))
+                .then(|| (no, path.with_file_name(&name)))
+        })
+        .filter(|(_, shard)| shard.is_file())
+        .collect();
+    shards.sort();
+    shards.into_iter().map(|(_, shard)| shard).collect()
+}
+
+fn metadata_u64(artifact: &Artifact, key: &str) -> Option<u64> {
+    match artifact.metadata.get(key)? {
+        CanonicalValue::Uint8(n)
+        | CanonicalValue::Uint16(n)
+        | CanonicalValue::Uint32(n)
+        | CanonicalValue::Uint64(n)
+        | CanonicalValue::Int16(n)
+        | CanonicalValue::Int32(n)
+        | CanonicalValue::Int64(n)
+        | CanonicalValue::Int(n) => u64::try_from(*n).ok(),
+        _ => None,
+    }
+}
+
+/// Parse a split GGUF model (as written by llama.cpp's `gguf-split`) into a
+/// single artifact.
+///
+/// See [`parse_split_with_limits`].
+pub fn parse_split<P: AsRef<Path>>(paths: &[P]) -> Result<Artifact, SplitError> {
+    parse_split_with_limits(paths, &ParseLimits::default())
+}
+
+/// Parse a split GGUF model into a single artifact, enforcing the given
+/// safety limits on every shard.
+///
+/// `paths` is either one shard, whose siblings are found by their
+/// `-NNNNN-of-NNNNN.gguf` names, or every shard in order. Tensors and
+/// metadata are merged and the [`SPLIT_KEYS`] dropped, so the result matches
+/// the unsplit model. Tensor data offsets point into the shards laid end to
+/// end, which is what a [`ShardReader`](crate::shards::ShardReader) opened on
+/// [`split_paths`] (or the given paths) reads.
+///
+/// Problems found while merging are recorded in
+/// [`Artifact::findings`]:
+/// - `split_missing_shard`: a shard numbered below `split.count` is missing,
+///   with one finding per run of missing shards.
+/// - `split_numbering`: a shard's `split.no` does not match its file name or
+///   appears twice.
+/// - `split_count_mismatch`: shards disagree on `split.count`, or it differs
+///   from the count in the file names.
+/// - `split_tensor_count_mismatch`: the shards' tensors do not add up to
+///   `split.tensors.count`.
+/// - `duplicate_tensor`: a tensor is stored in more than one shard.
+/// - `split_metadata_conflict`: shards disagree on a metadata value.
+///
+/// Each shard's own parser and layout findings are included too, prefixed
+/// with the shard's file name.
+///
+/// # Example
+///
+/// ```
+/// use weight_inspect::gguf;
+///
+/// let artifact = gguf::parse_split(&["tests/fixtures/tiny.gguf"]).unwrap();
+/// assert_eq!(artifact.format, weight_inspect::types::Format::GGUF);
+/// ```
+pub fn parse_split_with_limits<P: AsRef<Path>>(
+    paths: &[P],
+    limits: &ParseLimits,
+) -> Result<Artifact, SplitError> {
+    let shard_paths = match paths {
+        [path] => split_paths(path.as_ref()),
+        _ => paths.iter().map(|p| p.as_ref().to_path_buf()).collect(),
+    };
+    // A single given shard must exist; its siblings are checked below.
+    if let [path] = paths {
+        let path = path.as_ref();
+        std::fs::metadata(path).map_err(|source| SplitError::Io {
+            path: path.display().to_string(),
+            source,
+        })?;
+    }
+
+    let mut merged = Artifact {
+        format: Format::GGUF,
+        gguf_version: None,
+        metadata: BTreeMap::new(),
+        tensors: BTreeMap::new(),
+        graph: None,
+        data_offset: None,
+        findings: Vec::new(),
+    };
+    let mut location: BTreeMap<String, String> = BTreeMap::new();
+    let mut split_count: Option<u64> = None;
+    let mut tensors_count: Option<u64> = None;
+    let mut seen = BTreeSet::new();
+    let mut stored = 0u64;
+    let mut base = 0u64;
+    for shard_path in &shard_paths {
+        let file_name = shard_path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let io_error = |source| SplitError::Io {
+            path: shard_path.display().to_string(),
+            source,
+        };
+        let file = match File::open(shard_path) {
+            Ok(file) => file,
+            // Removed since the directory was listed; reported as a
+            // missing split number below.
+            Err(e) if e.kind() == io::ErrorKind::NotFound && paths.len() == 1 => continue,
+            Err(e) => return Err(io_error(e)),
+        };
+        let size = file.metadata().map_err(io_error)?.len();
+        let shard =
+            parse_gguf_with_limits(&mut BufReader::new(file), limits).map_err(|source| {
+                SplitError::Shard {
+                    path: shard_path.display().to_string(),
+                    source,
+                }
+            })?;
+
+        let layout = validate::check_layout(&shard, size);
+        for mut finding in shard.findings.iter().cloned().chain(layout) {
+            finding.message = format!("{}: {}", file_name, finding.message);
+            merged.findings.push(finding);
+        }
+
+        let count = metadata_u64(&shard, "split.count").unwrap_or(1);
+        match split_count {
+            None => split_count = Some(count),
+            Some(expected) if expected != count => merged.findings.push(Finding::new(
+                Severity::Error,
+                "split_count_mismatch",
+                Some("split.count"),
+                format!(
+                    "'{}' says the model has {} shards but an earlier shard says {}",
+                    file_name, count, expected
+                ),
+            )),
+            Some(_) => {}
+        }
+        let no = metadata_u64(&shard, "split.no").unwrap_or(0);
+        if !seen.insert(no) {
+            merged.findings.push(Finding::new(
+                Severity::Error,
+                "split_numbering",
+                Some("split.no"),
+                format!("'{}' is shard {} again", file_name, no + 1),
+            ));
+        }
+        if let Some((_, name_no, _)) = split_name(shard_path) {
+            if u64::from(name_no) != no + 1 {
+                merged.findings.push(Finding::new(
+                    Severity::Error,
+                    "split_numbering",
+                    Some("split.no"),
+                    format!(
+                        "'{}' is named as shard {} but its split.no says shard {}",
+                        file_name,
+                        name_no,
+                        no + 1
+                    ),
+                ));
+            }
+        }
+        if tensors_count.is_none() {
+            tensors_count = metadata_u64(&shard, "split.tensors.count");
+        }
+        stored += shard.tensors.len() as u64;
+
+        merged.gguf_version = merged.gguf_version.or(shard.gguf_version);
+        for (key, value) in shard.metadata {
+            if SPLIT_KEYS.contains(&key.as_str()) {
+                continue;
+            }
+            match merged.metadata.get(&key) {
+                Some(existing) if *existing != value => merged.findings.push(Finding::new(
+                    Severity::Warning,
+                    "split_metadata_conflict",
+                    Some(&key),
+                    format!(
+                        "'{}' has {} but an earlier shard has {}; keeping the first",
+                        file_name, value, existing
+                    ),
+                )),
+                Some(_) => {}
+                None => {
+                    merged.metadata.insert(key, value);
+                }
+            }
+        }
+        for (name, mut tensor) in shard.tensors {
+            if let Some(first) = location.get(&name) {
+                merged.findings.push(Finding::new(
+                    Severity::Error,
+                    "duplicate_tensor",
+                    Some(&name),
+                    format!(
+                        "stored in both '{}' and '{}'; only the first is used",
+                        first, file_name
+                    ),
+                ));
+                continue;
+            }
+            tensor.data_offset = tensor.data_offset.and_then(|o| o.checked_add(base));
+            location.insert(name.clone(), file_name.clone());
+            merged.tensors.insert(name, tensor);
+        }
+        base += size;
+    }
+
+    let named_count = match paths {
+        [path] => split_name(path.as_ref()).map_or(1, |(_, _, count)| u64::from(count)),
+        _ => paths.len() as u64,
+    };
+    let mut split_count = split_count.unwrap_or(named_count);
+    // Shards found by name: the names say how many to expect, and a
+    // different split.count is reported rather than trusted.
+    if paths.len() == 1 && split_count != named_count {
+        merged.findings.push(Finding::new(
+            Severity::Error,
+            "split_count_mismatch",
+            Some("split.count"),
+            format!(
+                "split.count says the model has {} shards but the file names say {}",
+                split_count, named_count
+            ),
+        ));
+        split_count = named_count;
+    }
+    // One finding per run of missing shards, so a huge split.count cannot
+    // flood the findings.
+    let mut next = 0u64;
+    for end in seen
+        .iter()
+        .copied()
+        .filter(|&no| no < split_count)
+        .chain([split_count])
+    {
+        if end > next {
+            let subject = match paths {
+                [path] => sibling_name(path.as_ref(), next + 1),
+                _ => None,
+            };
+            let message = if end - next == 1 {
+                format!("shard {} of {} is missing", end, split_count)
+            } else {
+                format!(
+                    "shards {} to {} of {} are missing",
+                    next + 1,
+                    end,
+                    split_count
+                )
+            };
+            merged.findings.push(Finding::new(
+                Severity::Error,
+                "split_missing_shard",
+                subject.as_deref(),
+                message,
+            ));
+        }
+        next = end.saturating_add(1);
+    }
+    if let Some(tensors_count) = tensors_count {
+        if tensors_count != stored {
+            merged.findings.push(Finding::new(
+                Severity::Error,
+                "split_tensor_count_mismatch",
+                Some("split.tensors.count"),
+                format!(
+                    "the model has {} tensors but the shards store {}",
+                    tensors_count, stored
+                ),
+            ));
+        }
+    }
+
+    Ok(merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash;
+    use crate::shards::ShardReader;
+
+    /// A GGUF file with the given u16/i32 split keys and one-byte i8 tensors
+    /// holding the first byte of their name.
+    fn gguf_bytes(split: Option<(u16, u16, i32)>, tensors: &[&str]) -> Vec<u8> {
+        let mut kv: Vec<(&str, u32, Vec<u8>)> = vec![(
+            "general.architecture",
+            8,
+            [&5u64.to_le_bytes()[..], b"llama"].concat(),
+        )];
+        if let Some((no, count, total)) = split {
+            kv.push(("split.no", 2, no.to_le_bytes().to_vec()));
+            kv.push(("split.count", 2, count.to_le_bytes().to_vec()));
+            kv.push(("split.tensors.count", 5, total.to_le_bytes().to_vec()));
+        }
+        let mut bytes = b"GGUF".to_vec();
+        bytes.extend(3u32.to_le_bytes());
+        bytes.extend((tensors.len() as u64).to_le_bytes());
+        bytes.extend((kv.len() as u64).to_le_bytes());
+        for (key, ty, value) in &kv {
+            bytes.extend((key.len() as u64).to_le_bytes());
+            bytes.extend(key.as_bytes());
+            bytes.extend(ty.to_le_bytes());
+            bytes.extend(value);
+        }
+        for (i, name) in tensors.iter().enumerate() {
+            bytes.extend((name.len() as u64).to_le_bytes());
+            bytes.extend(name.as_bytes());
+            bytes.extend(1u32.to_le_bytes());
+            bytes.extend(1u64.to_le_bytes());
+            bytes.extend(24u32.to_le_bytes()); // i8
+            bytes.extend((i as u64 * 32).to_le_bytes());
+        }
+        bytes.resize(bytes.len().div_ceil(32) * 32, 0);
+        for name in tensors {
+            bytes.push(name.as_bytes()[0]);
+            bytes.resize(bytes.len().div_ceil(32) * 32, 0);
+        }
+        bytes
+    }
+
+    fn write_split(dir: &Path, shards: &[&[&str]]) -> Vec<PathBuf> {
+        let total: usize = shards.iter().map(|s| s.len()).sum();
+        let count = shards.len();
+        shards
+            .iter()
+            .enumerate()
+            .map(|(i, tensors)| {
+                let path = dir.join(format!("model-{:05}-of-{:05}.gguf", i + 1, count));
+                let split = Some((i as u16, count as u16, total as i32));
+                std::fs::write(&path, gguf_bytes(split, tensors)).unwrap();
+                path
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_split_name() {
+        assert_eq!(
+            split_name(Path::new("dir/model-00002-of-00003.gguf")),
+            Some(("model".to_string(), 2, 3))
+        );
+        assert!(is_split(Path::new("a-b-00001-of-00002.gguf")));
+        assert!(!is_split(Path::new("model-00001-of-00001.gguf")));
+        assert!(!is_split(Path::new("model-00004-of-00003.gguf")));
+        assert!(!is_split(Path::new("model.gguf")));
+    }
+
+    #[test]
+    fn test_merged_split_matches_unsplit_model() {
+        let dir = tempfile::tempdir().unwrap();
+        let shards = write_split(dir.path(), &[&["a", "b"], &["c"]]);
+        let unsplit = gguf_bytes(None, &["a", "b", "c"]);
+        let mut cursor = io::Cursor::new(unsplit);
+        let expected = parse_gguf_with_limits(&mut cursor, &ParseLimits::default()).unwrap();
+
+        for given in [&shards[1..], &shards[..]] {
+            let merged = parse_split(given).unwrap();
+            assert!(merged.findings.is_empty(), "{:?}", merged.findings);
+            assert_eq!(merged.tensors.len(), 3);
+            assert!(!merged.metadata.contains_key("split.count"));
+            assert_eq!(
+                hash::compute_structural_hash(&merged).unwrap(),
+                hash::compute_structural_hash(&expected).unwrap()
+            );
+
+            let mut reader = ShardReader::open(&split_paths(&shards[0])).unwrap();
+            assert_eq!(
+                hash::compute_content_hash(&merged, &mut reader).unwrap(),
+                hash::compute_content_hash(&expected, &mut cursor).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_missing_shard_is_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let shards = write_split(dir.path(), &[&["a"], &["b"], &["c"]]);
+        std::fs::remove_file(&shards[1]).unwrap();
+
+        let merged = parse_split(&shards[..1]).unwrap();
+        let codes: Vec<_> = merged.findings.iter().map(|f| f.code).collect();
+
+        assert_eq!(
+            codes,
+            vec!["split_missing_shard", "split_tensor_count_mismatch"]
+        );
+        assert_eq!(
+            merged.findings[0].subject.as_deref(),
+            Some("model-00002-of-00003.gguf")
+        );
+        assert_eq!(split_paths(&shards[0]).len(), 2);
+    }
+
+    #[test]
+    fn test_missing_shards_are_grouped() {
+        let dir = tempfile::tempdir().unwrap();
+        let shards = write_split(dir.path(), &[&["a"], &["b"], &["c"], &["d"]]);
+        std::fs::remove_file(&shards[1]).unwrap();
+        std::fs::remove_file(&shards[2]).unwrap();
+
+        let merged = parse_split(&shards[..1]).unwrap();
+
+        assert_eq!(merged.findings[0].code, "split_missing_shard");
+        assert_eq!(merged.findings[0].message, "shards 2 to 3 of 4 are missing");
+        assert_eq!(merged.findings[1].code, "split_tensor_count_mismatch");
+    }
+
+    #[test]
+    fn test_split_count_is_checked_against_names() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("model-00001-of-00002.gguf");
+        std::fs::write(&path, gguf_bytes(Some((0, u16::MAX, 1)), &["a"])).unwrap();
+
+        let merged = parse_split(&[&path]).unwrap();
+        let messages: Vec<_> = merged
+            .findings
+            .iter()
+            .map(|f| (f.code, f.message.as_str()))
+            .collect();
+
+        assert_eq!(
+            messages,
+            vec![
+                (
+                    "split_count_mismatch",
+                    "split.count says the model has 65535 shards but the file names say 2"
+                ),
+                ("split_missing_shard", "shard 2 of 2 is missing"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_huge_count_in_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("model-00001-of-99999999.gguf");
+        std::fs::write(&path, gguf_bytes(Some((0, 2, 1)), &["a"])).unwrap();
+
+        let merged = parse_split(&[&path]).unwrap();
+        let messages: Vec<_> = merged
+            .findings
+            .iter()
+            .map(|f| (f.code, f.message.as_str()))
+            .collect();
+
+        assert_eq!(
+            messages,
+            vec![
+                (
+                    "split_count_mismatch",
+                    "split.count says the model has 2 shards but the file names say 99999999"
+                ),
+                (
+                    "split_missing_shard",
+                    "shards 2 to 99999999 of 99999999 are missing"
+                ),
+            ]
+        );
+        assert_eq!(
+            merged.findings[1].subject.as_deref(),
+            Some("model-00002-of-99999999.gguf")
+        );
+        assert_eq!(split_paths(&path), vec![path]);
+    }
+
+    #[test]
+    fn test_split_numbering_is_checked() {
+        let dir = tempfile::tempdir().unwrap();
+        let shards = write_split(dir.path(), &[&["a"], &["b"]]);
+        std::fs::write(&shards[1], gguf_bytes(Some((0, 3, 2)), &["b"])).unwrap();
+
+        let merged = parse_split(&shards[..1]).unwrap();
+        let codes: Vec<_> = merged.findings.iter().map(|f| f.code).collect();
+
+        assert_eq!(
+            codes,
+            vec![
+                "split_count_mismatch",
+                "split_numbering",
+                "split_numbering",
+                "split_missing_shard",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tensor_in_two_shards() {
+        let dir = tempfile::tempdir().unwrap();
+        let shards = write_split(dir.path(), &[&["a"], &["a"]]);
+
+        let merged = parse_split(&shards[..1]).unwrap();
+
+        assert_eq!(merged.findings.len(), 1);
+        assert_eq!(merged.findings[0].code, "duplicate_tensor");
+    }
+}
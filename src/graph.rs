@@ -0,0 +1,580 @@
+use crate::types::Tensor;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// A computation graph, as stored in an ONNX model.
+///
+/// Nodes keep the order of the file, which ONNX requires to be topological.
+/// Weights are not repeated here: they are the artifact's tensors.
+///
+/// # Example
+///
+/// ```
+/// # #[cfg(feature = "onnx")]
+/// # {
+/// use weight_inspect::onnx;
+///
+/// let artifact = onnx::parse_onnx_file("tests/fixtures/mnist.onnx").unwrap();
+/// let graph = artifact.graph.unwrap();
+/// assert!(graph.nodes.iter().any(|node| node.op_type == "Conv"));
+/// assert_eq!(graph.inputs[0].name, "Input3");
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct Graph {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub name: String,
+    /// Operator set version by domain, with `ai.onnx` for the default
+    /// domain. Only the model's main graph and functions have them.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub opset_imports: BTreeMap<String, i64>,
+    pub inputs: Vec<ValueInfo>,
+    pub outputs: Vec<ValueInfo>,
+    pub nodes: Vec<Node>,
+    /// Types recorded for intermediate values, usually by shape inference.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub value_info: Vec<ValueInfo>,
+    /// Model-local functions, keyed by `domain::name`. Only the model's main
+    /// graph has them; a function's inputs and outputs have no type.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub functions: BTreeMap<String, Graph>,
+}
+
+/// One operation of a [`Graph`].
+///
+/// Inputs and outputs name the values the node reads and writes; an empty
+/// input name marks an omitted optional input.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Node {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub name: String,
+    pub op_type: String,
+    /// Operator set domain; empty for the default `ai.onnx` domain.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub domain: String,
+    pub inputs: Vec<String>,
+    pub outputs: Vec<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub attributes: BTreeMap<String, Attribute>,
+}
+
+/// The type of a graph input, output or intermediate value.
+///
+/// For tensors `elem_type` is the dtype (`float32`) and `shape` has one
+/// entry per axis. Other values get a type expression such as
+/// `seq(tensor(float32))` and no shape. Both are `None` when the model does
+/// not record them.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ValueInfo {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub elem_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shape: Option<Vec<Dim>>,
+}
+
+/// One axis of a [`ValueInfo`] shape.
+///
+/// Serialized as a number, a string, or `null`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum Dim {
+    /// A static size.
+    Fixed(u64),
+    /// A named dynamic size, such as `batch`.
+    Symbolic(String),
+    /// A dynamic size with no name.
+    Unknown,
+}
+
+/// A typed node attribute, serialized as `{"<type>": value}`.
+///
+/// Tensors are described by dtype and shape only, never by their values.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Attribute {
+    Float(f32),
+    Int(i64),
+    String(String),
+    Tensor(Tensor),
+    Graph(Box<Graph>),
+    /// A type expression, as for [`ValueInfo::elem_type`].
+    Type(String),
+    Floats(Vec<f32>),
+    Ints(Vec<i64>),
+    Strings(Vec<String>),
+    Tensors(Vec<Tensor>),
+    Graphs(Vec<Graph>),
+    Types(Vec<String>),
+    /// A sparse tensor, described like a sparse initializer (see
+    /// [`Tensor::nnz`]).
+    SparseTensor(Tensor),
+    SparseTensors(Vec<Tensor>),
+}
+
+/// Differences between two graphs.
+///
+/// Nodes are matched by name, or by their first output when they have no
+/// name. ONNX does not require node names to be unique, so the second and
+/// later nodes sharing one are matched as `name#2`, `name#3`, ... in file
+/// order. A matched node whose inputs changed was rewired.
+#[derive(Debug, Default, Serialize, PartialEq)]
+pub struct GraphDiff {
+    pub opset_changes: Vec<OpsetChange>,
+    pub inputs_added: Vec<String>,
+    pub inputs_removed: Vec<String>,
+    pub input_changes: Vec<ValueChange>,
+    pub outputs_added: Vec<String>,
+    pub outputs_removed: Vec<String>,
+    pub output_changes: Vec<ValueChange>,
+    pub nodes_added: Vec<String>,
+    pub nodes_removed: Vec<String>,
+    pub node_changes: Vec<NodeChange>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub functions_added: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub functions_removed: Vec<String>,
+    /// Differences in the functions present in both models, by key.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub function_changes: BTreeMap<String, GraphDiff>,
+}
+
+/// An operator set whose version changed, or that was imported or dropped.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct OpsetChange {
+    pub domain: String,
+    /// The version in the first graph, if imported.
+    pub old_version: Option<i64>,
+    /// The version in the second graph, if imported.
+    pub new_version: Option<i64>,
+}
+
+/// A graph input or output whose type changed.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct ValueChange {
+    pub name: String,
+    /// Original element type (if different).
+    pub elem_type_old: Option<String>,
+    /// New element type (if different).
+    pub elem_type_new: Option<String>,
+    /// Original shape (if different), including which axes are dynamic.
+    pub shape_old: Option<Vec<Dim>>,
+    /// New shape (if different).
+    pub shape_new: Option<Vec<Dim>>,
+}
+
+/// A node present in both graphs that changed.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct NodeChange {
+    /// The node's name, or its first output when it has none, with `#N`
+    /// appended to repeated names (see [`GraphDiff`]).
+    pub name: String,
+    /// Original op type, as `domain::op_type` outside the default domain
+    /// (if different).
+    pub op_type_old: Option<String>,
+    /// New op type (if different).
+    pub op_type_new: Option<String>,
+    /// Original inputs (if different).
+    pub inputs_old: Option<Vec<String>>,
+    /// New inputs (if different).
+    pub inputs_new: Option<Vec<String>>,
+    /// Original outputs (if different).
+    pub outputs_old: Option<Vec<String>>,
+    /// New outputs (if different).
+    pub outputs_new: Option<Vec<String>>,
+    pub attribute_changes: Vec<AttributeChange>,
+    /// Differences in the graphs held by `graph` attributes, such as the
+    /// branches of an `If`, keyed by attribute name.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub subgraph_changes: BTreeMap<String, GraphDiff>,
+}
+
+/// An attribute that was added, removed or changed.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct AttributeChange {
+    pub name: String,
+    /// The value in the first graph; `None` when the attribute was added.
+    pub old_value: Option<Attribute>,
+    /// The value in the second graph; `None` when the attribute was removed.
+    pub new_value: Option<Attribute>,
+}
+
+impl GraphDiff {
+    /// Whether the two graphs differ.
+    pub fn has_changes(&self) -> bool {
+        *self != GraphDiff::default()
+    }
+}
+
+impl Node {
+    /// The name the node is matched by in a [`GraphDiff`]: its name, or its
+    /// first output when it has none.
+    pub fn key(&self) -> &str {
+        match (self.name.as_str(), self.outputs.first()) {
+            ("", Some(output)) => output,
+            (name, _) => name,
+        }
+    }
+
+    /// The operator set domain, with `ai.onnx` for the default domain.
+    pub fn opset_domain(&self) -> &str {
+        match self.domain.as_str() {
+            "" => "ai.onnx",
+            domain => domain,
+        }
+    }
+
+    fn qualified_op_type(&self) -> String {
+        match self.opset_domain() {
+            "ai.onnx" => self.op_type.clone(),
+            domain => format!("{}::{}", domain, self.op_type),
+        }
+    }
+}
+
+/// Compare two graphs.
+///
+/// # Example
+///
+/// ```
+/// use weight_inspect::graph::{self, Graph, Node};
+///
+/// let relu = Node {
+///     name: "act".to_string(),
+///     op_type: "Relu".to_string(),
+///     domain: String::new(),
+///     inputs: vec!["x".to_string()],
+///     outputs: vec!["y".to_string()],
+///     attributes: Default::default(),
+/// };
+/// let gelu = Node {
+///     op_type: "Gelu".to_string(),
+///     ..relu.clone()
+/// };
+/// let a = Graph { nodes: vec![relu], ..Default::default() };
+/// let b = Graph { nodes: vec![gelu], ..Default::default() };
+///
+/// let result = graph::diff(&a, &b);
+/// assert_eq!(result.node_changes[0].op_type_new.as_deref(), Some("Gelu"));
+/// ```
+pub fn diff(a: &Graph, b: &Graph) -> GraphDiff {
+    let mut result = GraphDiff::default();
+
+    let domains: BTreeSet<_> = a
+        .opset_imports
+        .keys()
+        .chain(b.opset_imports.keys())
+        .collect();
+    for domain in domains {
+        let old_version = a.opset_imports.get(domain).copied();
+        let new_version = b.opset_imports.get(domain).copied();
+        if old_version != new_version {
+            result.opset_changes.push(OpsetChange {
+                domain: domain.clone(),
+                old_version,
+                new_version,
+            });
+        }
+    }
+
+    (
+        result.inputs_added,
+        result.inputs_removed,
+        result.input_changes,
+    ) = diff_values(&a.inputs, &b.inputs);
+    (
+        result.outputs_added,
+        result.outputs_removed,
+        result.output_changes,
+    ) = diff_values(&a.outputs, &b.outputs);
+
+    let nodes_a = keyed_nodes(&a.nodes);
+    let nodes_b = keyed_nodes(&b.nodes);
+    result.nodes_added = added(&nodes_a, &nodes_b);
+    result.nodes_removed = added(&nodes_b, &nodes_a);
+    for (key, old) in &nodes_a {
+        let Some(new) = nodes_b.get(key) else {
+            continue;
+        };
+        let change = diff_node(key, old, new);
+        if change.op_type_old.is_some()
+            || change.inputs_old.is_some()
+            || change.outputs_old.is_some()
+            || !change.attribute_changes.is_empty()
+            || !change.subgraph_changes.is_empty()
+        {
+            result.node_changes.push(change);
+        }
+    }
+
+    let functions_a: BTreeMap<_, _> = a.functions.iter().map(|(k, f)| (k.as_str(), f)).collect();
+    let functions_b: BTreeMap<_, _> = b.functions.iter().map(|(k, f)| (k.as_str(), f)).collect();
+    result.functions_added = added(&functions_a, &functions_b);
+    result.functions_removed = added(&functions_b, &functions_a);
+    for (key, old) in &functions_a {
+        if let Some(new) = functions_b.get(key) {
+            let diff = diff(old, new);
+            if diff.has_changes() {
+                result.function_changes.insert(key.to_string(), diff);
+            }
+        }
+    }
+
+    result
+}
+
+/// Nodes by [`Node::key`], with `#2`, `#3`, ... appended to keys that
+/// repeat.
+fn keyed_nodes(nodes: &[Node]) -> BTreeMap<String, &Node> {
+    let mut keyed = BTreeMap::new();
+    let mut seen: BTreeMap<&str, usize> = BTreeMap::new();
+    for node in nodes {
+        let count = seen.entry(node.key()).or_default();
+        *count += 1;
+        let mut key = match *count {
+            1 => node.key().to_string(),
+            n => format!("{}#{}", node.key(), n),
+        };
+        // A node may itself be named like `name#2`.
+        while keyed.contains_key(&key) {
+            *count += 1;
+            key = format!("{}#{}", node.key(), *count);
+        }
+        keyed.insert(key, node);
+    }
+    keyed
+}
+
+/// Names in `b` but not in `a`, in sorted order.
+fn added<K: Ord + ToString, T>(a: &BTreeMap<K, T>, b: &BTreeMap<K, T>) -> Vec<String> {
+    b.keys()
+        .filter(|key| !a.contains_key(*key))
+        .map(|key| key.to_string())
+        .collect()
+}
+
+fn diff_values(a: &[ValueInfo], b: &[ValueInfo]) -> (Vec<String>, Vec<String>, Vec<ValueChange>) {
+    let values_a: BTreeMap<_, _> = a.iter().map(|v| (v.name.as_str(), v)).collect();
+    let values_b: BTreeMap<_, _> = b.iter().map(|v| (v.name.as_str(), v)).collect();
+    let mut changes = Vec::new();
+    for (name, old) in &values_a {
+        let Some(new) = values_b.get(name) else {
+            continue;
+        };
+        if old.elem_type != new.elem_type || old.shape != new.shape {
+            let (elem_type_old, elem_type_new) = changed(&old.elem_type, &new.elem_type);
+            let (shape_old, shape_new) = changed(&old.shape, &new.shape);
+            changes.push(ValueChange {
+                name: name.to_string(),
+                elem_type_old: elem_type_old.flatten(),
+                elem_type_new: elem_type_new.flatten(),
+                shape_old: shape_old.flatten(),
+                shape_new: shape_new.flatten(),
+            });
+        }
+    }
+    (
+        added(&values_a, &values_b),
+        added(&values_b, &values_a),
+        changes,
+    )
+}
+
+/// Both values when they differ, otherwise `None`s.
+fn changed<T: PartialEq + Clone>(old: &T, new: &T) -> (Option<T>, Option<T>) {
+    if old == new {
+        (None, None)
+    } else {
+        (Some(old.clone()), Some(new.clone()))
+    }
+}
+
+fn diff_node(key: &str, old: &Node, new: &Node) -> NodeChange {
+    let (op_type_old, op_type_new) = changed(&old.qualified_op_type(), &new.qualified_op_type());
+    let (inputs_old, inputs_new) = changed(&old.inputs, &new.inputs);
+    let (outputs_old, outputs_new) = changed(&old.outputs, &new.outputs);
+
+    let mut attribute_changes = Vec::new();
+    let mut subgraph_changes = BTreeMap::new();
+    let names: BTreeSet<_> = old.attributes.keys().chain(new.attributes.keys()).collect();
+    for name in names {
+        match (old.attributes.get(name), new.attributes.get(name)) {
+            (Some(Attribute::Graph(a)), Some(Attribute::Graph(b))) => {
+                let diff = diff(a, b);
+                if diff.has_changes() {
+                    subgraph_changes.insert(name.clone(), diff);
+                }
+            }
+            (old_value, new_value) if old_value != new_value => {
+                attribute_changes.push(AttributeChange {
+                    name: name.clone(),
+                    old_value: old_value.cloned(),
+                    new_value: new_value.cloned(),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    NodeChange {
+        name: key.to_string(),
+        op_type_old,
+        op_type_new,
+        inputs_old,
+        inputs_new,
+        outputs_old,
+        outputs_new,
+        attribute_changes,
+        subgraph_changes,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_shape() {
+        let value = ValueInfo {
+            name: "x".to_string(),
+            elem_type: Some("float32".to_string()),
+            shape: Some(vec![
+                Dim::Symbolic("batch".to_string()),
+                Dim::Fixed(3),
+                Dim::Unknown,
+            ]),
+        };
+        let json = serde_json::to_string(&value).unwrap();
+        assert_eq!(
+            json,
+            r#"{"name":"x","elem_type":"float32","shape":["batch",3,null]}"#
+        );
+        assert_eq!(serde_json::from_str::<ValueInfo>(&json).unwrap(), value);
+
+        let attribute = Attribute::Ints(vec![1, 1]);
+        assert_eq!(
+            serde_json::to_string(&attribute).unwrap(),
+            r#"{"ints":[1,1]}"#
+        );
+    }
+
+    fn node(name: &str, op_type: &str, inputs: &[&str]) -> Node {
+        Node {
+            name: name.to_string(),
+            op_type: op_type.to_string(),
+            domain: String::new(),
+            inputs: inputs.iter().map(|s| s.to_string()).collect(),
+            outputs: vec![format!("{}_out", name)],
+            attributes: BTreeMap::new(),
+        }
+    }
+
+    fn value(name: &str, shape: Vec<Dim>) -> ValueInfo {
+        ValueInfo {
+            name: name.to_string(),
+            elem_type: Some("float32".to_string()),
+            shape: Some(shape),
+        }
+    }
+
+    #[test]
+    fn test_diff() {
+        let mut gemm = node("fc", "Gemm", &["x", "w"]);
+        gemm.attributes
+            .insert("alpha".to_string(), Attribute::Float(1.0));
+        let mut branch = node("if", "If", &["cond"]);
+        branch.attributes.insert(
+            "then_branch".to_string(),
+            Attribute::Graph(Box::new(Graph {
+                nodes: vec![node("", "Relu", &["fc_out"])],
+                ..Default::default()
+            })),
+        );
+        let a = Graph {
+            opset_imports: BTreeMap::from([("ai.onnx".to_string(), 13)]),
+            inputs: vec![value("x", vec![Dim::Fixed(1), Dim::Fixed(8)])],
+            nodes: vec![
+                gemm.clone(),
+                node("act", "Relu", &["fc_out"]),
+                branch.clone(),
+            ],
+            ..Default::default()
+        };
+
+        let mut b = a.clone();
+        b.opset_imports.insert("ai.onnx".to_string(), 17);
+        b.opset_imports.insert("com.microsoft".to_string(), 1);
+        b.inputs[0].shape = Some(vec![Dim::Symbolic("batch".to_string()), Dim::Fixed(8)]);
+        gemm.inputs[1] = "w2".to_string();
+        gemm.attributes
+            .insert("alpha".to_string(), Attribute::Float(0.5));
+        let mut gelu = node("act", "Gelu", &["fc_out"]);
+        gelu.domain = "com.microsoft".to_string();
+        let Attribute::Graph(then_branch) = branch.attributes.get_mut("then_branch").unwrap()
+        else {
+            unreachable!()
+        };
+        then_branch.nodes[0].op_type = "Sigmoid".to_string();
+        b.nodes = vec![gemm, gelu, branch, node("out", "Identity", &["act_out"])];
+
+        let result = diff(&a, &b);
+        assert!(result.has_changes());
+        let opsets: Vec<_> = result
+            .opset_changes
+            .iter()
+            .map(|c| (c.domain.as_str(), c.old_version, c.new_version))
+            .collect();
+        assert_eq!(
+            opsets,
+            [
+                ("ai.onnx", Some(13), Some(17)),
+                ("com.microsoft", None, Some(1))
+            ]
+        );
+        assert_eq!(
+            result.input_changes[0].shape_new.as_ref().unwrap()[0],
+            Dim::Symbolic("batch".to_string())
+        );
+        assert_eq!(result.nodes_added, ["out"]);
+
+        let [act, fc, branch] = &result.node_changes[..] else {
+            panic!("expected three changed nodes");
+        };
+        assert_eq!(act.op_type_new.as_deref(), Some("com.microsoft::Gelu"));
+        assert_eq!(fc.inputs_new.as_ref().unwrap()[1], "w2");
+        assert_eq!(
+            fc.attribute_changes[0].new_value,
+            Some(Attribute::Float(0.5))
+        );
+        let then_diff = &branch.subgraph_changes["then_branch"];
+        assert_eq!(then_diff.node_changes[0].name, "_out");
+        assert_eq!(
+            then_diff.node_changes[0].op_type_new.as_deref(),
+            Some("Sigmoid")
+        );
+
+        assert!(!diff(&a, &a).has_changes());
+    }
+
+    #[test]
+    fn test_diff_nodes_sharing_a_name() {
+        let a = Graph {
+            nodes: vec![
+                node("act", "Relu", &["x"]),
+                node("act", "Relu", &["y"]),
+                node("act", "Relu", &["z"]),
+            ],
+            ..Default::default()
+        };
+        let mut b = a.clone();
+        b.nodes[1].op_type = "Gelu".to_string();
+        b.nodes.pop();
+
+        let result = diff(&a, &b);
+
+        assert_eq!(result.node_changes.len(), 1);
+        assert_eq!(result.node_changes[0].name, "act#2");
+        assert_eq!(result.node_changes[0].op_type_new.as_deref(), Some("Gelu"));
+        assert_eq!(result.nodes_removed, ["act#3"]);
+    }
+}
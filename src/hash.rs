@@ -1,10 +1,22 @@
-use crate::types::Artifact;
+use crate::gguf::SPLIT_KEYS;
+use crate::types::{Artifact, Format, Tensor};
 use sha2::{Digest, Sha256};
+use std::io::{Read, Seek, SeekFrom};
+use thiserror::Error;
+
+/// Errors raised while hashing tensor data.
+#[derive(Error, Debug)]
+pub enum ContentHashError {
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+}
 
 /// Compute a deterministic structural hash for an artifact.
 ///
 /// The hash is based on the canonical JSON representation of the artifact,
-/// making it independent of file layout and ordering.
+/// making it independent of file layout and ordering. Layout-only fields such
+/// as data offsets, and the `split.*` keys of a split GGUF shard, are cleared
+/// before hashing.
 ///
 /// # Example
 ///
@@ -19,17 +31,219 @@ use sha2::{Digest, Sha256};
 /// assert!(hash.len() == 64); // SHA256 hex = 64 chars
 /// ```
 pub fn compute_structural_hash(artifact: &Artifact) -> Result<String, serde_json::Error> {
-    let canonical = serde_json::to_string(artifact)?;
+    let canonical = serde_json::to_string(&structural_view(artifact))?;
     let mut hasher = Sha256::new();
     hasher.update(canonical.as_bytes());
     let result = hasher.finalize();
     Ok(hex::encode(result))
 }
 
+/// Compute a content hash over every tensor's data bytes.
+///
+/// Tensors are visited in canonical (sorted) name order and each one
+/// contributes its name, its byte length and its data bytes, so the hash does
+/// not depend on the physical order of tensors in the file. Metadata is not
+/// included. Tensors without a data offset, whose data is not in `reader`
+/// (compressed or non-contiguous storage), are left out entirely; the hash
+/// then matches that of the same artifact without them. `reader` must be the
+/// file the artifact was parsed from.
+///
+/// # Example
+///
+/// ```
+/// use weight_inspect::{hash, safetensors};
+///
+/// let data = std::fs::read("tests/fixtures/tiny.safetensors").unwrap();
+/// let mut cursor = std::io::Cursor::new(data);
+/// let artifact = safetensors::parse_safetensors(&mut cursor).unwrap();
+/// let content_hash = hash::compute_content_hash(&artifact, &mut cursor).unwrap();
+/// assert_eq!(content_hash.len(), 64);
+/// ```
+pub fn compute_content_hash<R: Read + Seek>(
+    artifact: &Artifact,
+    reader: &mut R,
+) -> Result<String, ContentHashError> {
+    let mut hasher = Sha256::new();
+    for tensor in artifact.tensors.values() {
+        let Some(offset) = tensor.data_offset else {
+            continue;
+        };
+        hash_header(&mut hasher, tensor);
+        reader.seek(SeekFrom::Start(offset))?;
+        stream_into(reader, tensor.byte_length, &mut [&mut hasher])?;
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Compute the content hash and the tensor digests in one read of the data.
+///
+/// Returns the same content hash as [`compute_content_hash`] and the same
+/// Merkle root as [`compute_tensor_digests`], and fills in the digests the
+/// same way.
+///
+/// # Example
+///
+/// ```
+/// use weight_inspect::{hash, safetensors};
+///
+/// let data = std::fs::read("tests/fixtures/tiny.safetensors").unwrap();
+/// let mut cursor = std::io::Cursor::new(data);
+/// let mut artifact = safetensors::parse_safetensors(&mut cursor).unwrap();
+/// let (content_hash, root) =
+///     hash::compute_content_hash_and_digests(&mut artifact, &mut cursor).unwrap();
+/// assert_eq!(content_hash, hash::compute_content_hash(&artifact, &mut cursor).unwrap());
+/// assert_eq!(Some(root), hash::merkle_root(&artifact));
+/// ```
+pub fn compute_content_hash_and_digests<R: Read + Seek>(
+    artifact: &mut Artifact,
+    reader: &mut R,
+) -> Result<(String, String), ContentHashError> {
+    let mut content = Sha256::new();
+    for tensor in artifact.tensors.values_mut() {
+        let Some(offset) = tensor.data_offset else {
+            continue;
+        };
+        hash_header(&mut content, tensor);
+        let mut digest = Sha256::new();
+        reader.seek(SeekFrom::Start(offset))?;
+        stream_into(reader, tensor.byte_length, &mut [&mut content, &mut digest])?;
+        tensor.digest = Some(hex::encode(digest.finalize()));
+    }
+    let root = merkle_root(artifact).unwrap_or_else(|| hex::encode(Sha256::digest([])));
+    Ok((hex::encode(content.finalize()), root))
+}
+
+/// Feed the name and byte length that precede a tensor's data in the
+/// content hash.
+fn hash_header(hasher: &mut Sha256, tensor: &Tensor) {
+    hasher.update((tensor.name.len() as u64).to_le_bytes());
+    hasher.update(tensor.name.as_bytes());
+    hasher.update(tensor.byte_length.to_le_bytes());
+}
+
+/// Hash each tensor's data bytes and store the digest on the tensor.
+///
+/// Fills [`Tensor::digest`](crate::types::Tensor::digest) for every tensor
+/// with a data offset and returns the Merkle root over them (see
+/// [`merkle_root`]). Tensors without a data offset, whose data is not in
+/// `reader`, keep `digest: None`. `reader` must be the file the artifact was
+/// parsed from.
+///
+/// # Example
+///
+/// ```
+/// use weight_inspect::{hash, safetensors};
+///
+/// let data = std::fs::read("tests/fixtures/tiny.safetensors").unwrap();
+/// let mut cursor = std::io::Cursor::new(data);
+/// let mut artifact = safetensors::parse_safetensors(&mut cursor).unwrap();
+/// let root = hash::compute_tensor_digests(&mut artifact, &mut cursor).unwrap();
+/// assert!(artifact.tensors.values().all(|t| t.digest.is_some()));
+/// assert_eq!(root.len(), 64);
+/// ```
+pub fn compute_tensor_digests<R: Read + Seek>(
+    artifact: &mut Artifact,
+    reader: &mut R,
+) -> Result<String, ContentHashError> {
+    for tensor in artifact.tensors.values_mut() {
+        let Some(offset) = tensor.data_offset else {
+            continue;
+        };
+        let mut hasher = Sha256::new();
+        reader.seek(SeekFrom::Start(offset))?;
+        stream_into(reader, tensor.byte_length, &mut [&mut hasher])?;
+        tensor.digest = Some(hex::encode(hasher.finalize()));
+    }
+    Ok(merkle_root(artifact).unwrap_or_else(|| hex::encode(Sha256::digest([]))))
+}
+
+/// Combine per-tensor digests into a single Merkle root.
+///
+/// Leaves are `SHA-256(0x00 || name_len || name || digest)` in sorted name
+/// order; each parent is `SHA-256(0x01 || left || right)`, and an unpaired
+/// node is carried up unchanged. Tensors without a digest are left out, and
+/// an artifact without digested tensors has the root `SHA-256("")`. Returns
+/// `None` if the artifact has tensors but none of them has a digest.
+pub fn merkle_root(artifact: &Artifact) -> Option<String> {
+    let mut level = Vec::with_capacity(artifact.tensors.len());
+    for tensor in artifact.tensors.values() {
+        let Some(digest) = &tensor.digest else {
+            continue;
+        };
+        let digest = hex::decode(digest).ok()?;
+        let mut hasher = Sha256::new();
+        hasher.update([0x00]);
+        hasher.update((tensor.name.len() as u64).to_le_bytes());
+        hasher.update(tensor.name.as_bytes());
+        hasher.update(&digest);
+        level.push(hasher.finalize().to_vec());
+    }
+    if level.is_empty() {
+        return artifact
+            .tensors
+            .is_empty()
+            .then(|| hex::encode(Sha256::digest([])));
+    }
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| match pair {
+                [left, right] => {
+                    let mut hasher = Sha256::new();
+                    hasher.update([0x01]);
+                    hasher.update(left);
+                    hasher.update(right);
+                    hasher.finalize().to_vec()
+                }
+                [single] => single.clone(),
+                _ => unreachable!(),
+            })
+            .collect();
+    }
+    Some(hex::encode(&level[0]))
+}
+
+/// Feed exactly `len` bytes from `reader` into every hasher.
+fn stream_into<R: Read>(
+    reader: &mut R,
+    len: u64,
+    hashers: &mut [&mut Sha256],
+) -> std::io::Result<()> {
+    let mut buf = vec![0u8; 64 * 1024];
+    let mut remaining = len;
+    while remaining > 0 {
+        let chunk = remaining.min(buf.len() as u64) as usize;
+        reader.read_exact(&mut buf[..chunk])?;
+        for hasher in hashers.iter_mut() {
+            hasher.update(&buf[..chunk]);
+        }
+        remaining -= chunk as u64;
+    }
+    Ok(())
+}
+
+/// Copy of the artifact with every layout and content field cleared.
+fn structural_view(artifact: &Artifact) -> Artifact {
+    let mut view = artifact.clone();
+    view.data_offset = None;
+    if view.format == Format::GGUF {
+        for key in SPLIT_KEYS {
+            view.metadata.remove(key);
+        }
+    }
+    for tensor in view.tensors.values_mut() {
+        tensor.data_offset = None;
+        tensor.digest = None;
+        tensor.location = None;
+        tensor.big_endian = false;
+    }
+    view
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::types::{Format, Tensor};
+    use crate::types::CanonicalValue;
     use std::collections::BTreeMap;
 
     #[test]
@@ -39,6 +253,9 @@ mod tests {
             gguf_version: Some(3),
             metadata: BTreeMap::new(),
             tensors: BTreeMap::new(),
+            graph: None,
+            data_offset: None,
+            findings: Vec::new(),
         };
         artifact1.metadata.insert(
             "test".to_string(),
@@ -52,6 +269,9 @@ mod tests {
             gguf_version: Some(3),
             metadata: BTreeMap::new(),
             tensors: BTreeMap::new(),
+            graph: None,
+            data_offset: None,
+            findings: Vec::new(),
         };
         artifact2.metadata.insert(
             "test".to_string(),
@@ -70,6 +290,9 @@ mod tests {
             gguf_version: Some(3),
             metadata: BTreeMap::new(),
             tensors: BTreeMap::new(),
+            graph: None,
+            data_offset: None,
+            findings: Vec::new(),
         };
         artifact1.metadata.insert(
             "test".to_string(),
@@ -81,6 +304,9 @@ mod tests {
             gguf_version: Some(3),
             metadata: BTreeMap::new(),
             tensors: BTreeMap::new(),
+            graph: None,
+            data_offset: None,
+            findings: Vec::new(),
         };
         artifact2.metadata.insert(
             "test".to_string(),
@@ -96,6 +322,202 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_hash_ignores_data_offsets() {
+        let tensor = Tensor {
+            name: "tensor1".to_string(),
+            dtype: "f32".to_string(),
+            shape: vec![10],
+            byte_length: 40,
+            data_offset: None,
+            digest: None,
+            location: None,
+            nnz: None,
+            big_endian: false,
+        };
+        let mut artifact1 = Artifact {
+            format: Format::GGUF,
+            gguf_version: Some(3),
+            metadata: BTreeMap::new(),
+            tensors: BTreeMap::new(),
+            graph: None,
+            data_offset: None,
+            findings: Vec::new(),
+        };
+        artifact1
+            .tensors
+            .insert("tensor1".to_string(), tensor.clone());
+
+        let mut artifact2 = artifact1.clone();
+        artifact2.data_offset = Some(96);
+        artifact2.tensors.get_mut("tensor1").unwrap().data_offset = Some(128);
+
+        assert_eq!(
+            compute_structural_hash(&artifact1).unwrap(),
+            compute_structural_hash(&artifact2).unwrap(),
+            "file layout should not affect hash"
+        );
+    }
+
+    #[test]
+    fn test_hash_ignores_split_keys() {
+        let artifact = Artifact {
+            format: Format::GGUF,
+            gguf_version: Some(3),
+            metadata: BTreeMap::new(),
+            tensors: BTreeMap::new(),
+            graph: None,
+            data_offset: None,
+            findings: Vec::new(),
+        };
+        let mut shard = artifact.clone();
+        shard
+            .metadata
+            .insert("split.count".to_string(), CanonicalValue::Uint16(3));
+        shard
+            .metadata
+            .insert("split.no".to_string(), CanonicalValue::Uint16(0));
+
+        assert_eq!(
+            compute_structural_hash(&artifact).unwrap(),
+            compute_structural_hash(&shard).unwrap()
+        );
+    }
+
+    fn two_tensor_file(first: (&str, &[u8]), second: (&str, &[u8])) -> (Artifact, Vec<u8>) {
+        let mut artifact = Artifact {
+            format: Format::Safetensors,
+            gguf_version: None,
+            metadata: BTreeMap::new(),
+            tensors: BTreeMap::new(),
+            graph: None,
+            data_offset: Some(0),
+            findings: Vec::new(),
+        };
+        let mut data = Vec::new();
+        for (name, bytes) in [first, second] {
+            artifact.tensors.insert(
+                name.to_string(),
+                Tensor {
+                    name: name.to_string(),
+                    dtype: "u8".to_string(),
+                    shape: vec![bytes.len() as u64],
+                    byte_length: bytes.len() as u64,
+                    data_offset: Some(data.len() as u64),
+                    digest: None,
+                    location: None,
+                    nnz: None,
+                    big_endian: false,
+                },
+            );
+            data.extend_from_slice(bytes);
+        }
+        (artifact, data)
+    }
+
+    #[test]
+    fn test_content_hash_ignores_physical_order() {
+        let (a, data_a) = two_tensor_file(("x", b"abcd"), ("y", b"efgh"));
+        let (b, data_b) = two_tensor_file(("y", b"efgh"), ("x", b"abcd"));
+
+        let hash_a = compute_content_hash(&a, &mut std::io::Cursor::new(data_a)).unwrap();
+        let hash_b = compute_content_hash(&b, &mut std::io::Cursor::new(data_b)).unwrap();
+
+        assert_eq!(hash_a, hash_b);
+    }
+
+    #[test]
+    fn test_content_hash_sees_weight_changes() {
+        let (a, data_a) = two_tensor_file(("x", b"abcd"), ("y", b"efgh"));
+        let (b, data_b) = two_tensor_file(("x", b"abcd"), ("y", b"efgX"));
+
+        let hash_a = compute_content_hash(&a, &mut std::io::Cursor::new(data_a)).unwrap();
+        let hash_b = compute_content_hash(&b, &mut std::io::Cursor::new(data_b)).unwrap();
+
+        assert_ne!(hash_a, hash_b);
+        assert_eq!(
+            compute_structural_hash(&a).unwrap(),
+            compute_structural_hash(&b).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_content_hash_skips_tensors_without_offset() {
+        let (mut a, data_a) = two_tensor_file(("x", b"abcd"), ("y", b"efgh"));
+        let (mut x_only, data_x) = two_tensor_file(("x", b"abcd"), ("y", b""));
+        x_only.tensors.remove("y");
+        a.tensors.get_mut("y").unwrap().data_offset = None;
+
+        let hash = compute_content_hash(&a, &mut std::io::Cursor::new(data_a.clone())).unwrap();
+        assert_eq!(
+            hash,
+            compute_content_hash(&x_only, &mut std::io::Cursor::new(data_x)).unwrap()
+        );
+
+        let (both, root) =
+            compute_content_hash_and_digests(&mut a, &mut std::io::Cursor::new(data_a)).unwrap();
+        assert_eq!(both, hash);
+        assert_eq!(Some(root), merkle_root(&a));
+        assert_eq!(a.tensors["y"].digest, None);
+    }
+
+    #[test]
+    fn test_content_hash_truncated_data() {
+        let (a, mut data) = two_tensor_file(("x", b"abcd"), ("y", b"efgh"));
+        data.truncate(6);
+
+        let result = compute_content_hash(&a, &mut std::io::Cursor::new(data));
+        assert!(matches!(result, Err(ContentHashError::IoError(_))));
+    }
+
+    #[test]
+    fn test_tensor_digests_and_merkle_root() {
+        let (mut a, data_a) = two_tensor_file(("x", b"abcd"), ("y", b"efgh"));
+        let (mut b, data_b) = two_tensor_file(("y", b"efgh"), ("x", b"abcd"));
+        let (mut c, data_c) = two_tensor_file(("x", b"abcd"), ("y", b"efgX"));
+
+        let root_a = compute_tensor_digests(&mut a, &mut std::io::Cursor::new(data_a)).unwrap();
+        let root_b = compute_tensor_digests(&mut b, &mut std::io::Cursor::new(data_b)).unwrap();
+        let root_c = compute_tensor_digests(&mut c, &mut std::io::Cursor::new(data_c)).unwrap();
+
+        assert_eq!(root_a, root_b);
+        assert_ne!(root_a, root_c);
+        assert_eq!(a.tensors["x"].digest, c.tensors["x"].digest);
+        assert_ne!(a.tensors["y"].digest, c.tensors["y"].digest);
+        assert_eq!(
+            a.tensors["x"].digest.as_deref(),
+            Some(hex::encode(Sha256::digest(b"abcd")).as_str())
+        );
+        assert_eq!(
+            compute_structural_hash(&a).unwrap(),
+            compute_structural_hash(&c).unwrap(),
+            "digests should not affect the structural hash"
+        );
+    }
+
+    #[test]
+    fn test_tensors_without_offset_are_not_digested() {
+        let (mut a, data_a) = two_tensor_file(("x", b"abcd"), ("y", b"efgh"));
+        let (mut x_only, data_x) = two_tensor_file(("x", b"abcd"), ("y", b""));
+        x_only.tensors.remove("y");
+        a.tensors.get_mut("y").unwrap().data_offset = None;
+
+        let root = compute_tensor_digests(&mut a, &mut std::io::Cursor::new(data_a)).unwrap();
+
+        assert!(a.tensors["x"].digest.is_some());
+        assert_eq!(a.tensors["y"].digest, None);
+        assert_eq!(
+            root,
+            compute_tensor_digests(&mut x_only, &mut std::io::Cursor::new(data_x)).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_merkle_root_requires_digests() {
+        let (a, _) = two_tensor_file(("x", b"abcd"), ("y", b"efgh"));
+        assert_eq!(merkle_root(&a), None);
+    }
+
     #[test]
     fn test_hash_format_affects_hash() {
         let artifact1 = Artifact {
@@ -103,6 +525,9 @@ mod tests {
             gguf_version: Some(3),
             metadata: BTreeMap::new(),
             tensors: BTreeMap::new(),
+            graph: None,
+            data_offset: None,
+            findings: Vec::new(),
         };
 
         let artifact2 = Artifact {
@@ -110,6 +535,9 @@ mod tests {
             gguf_version: None,
             metadata: BTreeMap::new(),
             tensors: BTreeMap::new(),
+            graph: None,
+            data_offset: None,
+            findings: Vec::new(),
         };
 
         let hash1 = compute_structural_hash(&artifact1).unwrap();
@@ -128,6 +556,9 @@ mod tests {
             gguf_version: Some(3),
             metadata: BTreeMap::new(),
             tensors: BTreeMap::new(),
+            graph: None,
+            data_offset: None,
+            findings: Vec::new(),
         };
         artifact1.tensors.insert(
             "tensor1".to_string(),
@@ -136,6 +567,11 @@ mod tests {
                 dtype: "f32".to_string(),
                 shape: vec![10],
                 byte_length: 40,
+                data_offset: None,
+                digest: None,
+                location: None,
+                nnz: None,
+                big_endian: false,
             },
         );
 
@@ -144,6 +580,9 @@ mod tests {
             gguf_version: Some(3),
             metadata: BTreeMap::new(),
             tensors: BTreeMap::new(),
+            graph: None,
+            data_offset: None,
+            findings: Vec::new(),
         };
         artifact2.tensors.insert(
             "tensor1".to_string(),
@@ -152,6 +591,11 @@ mod tests {
                 dtype: "f32".to_string(),
                 shape: vec![10],
                 byte_length: 40,
+                data_offset: None,
+                digest: None,
+                location: None,
+                nnz: None,
+                big_endian: false,
             },
         );
         artifact2.tensors.insert(
@@ -161,6 +605,11 @@ mod tests {
                 dtype: "f32".to_string(),
                 shape: vec![10],
                 byte_length: 40,
+                data_offset: None,
+                digest: None,
+                location: None,
+                nnz: None,
+                big_endian: false,
             },
         );
 
@@ -177,7 +626,7 @@ mod tests {
 #[cfg(test)]
 mod property_tests {
     use super::*;
-    use crate::types::{CanonicalValue, Format, Tensor};
+    use crate::types::CanonicalValue;
     use proptest::prelude::*;
     use std::collections::BTreeMap;
 
@@ -195,12 +644,18 @@ mod property_tests {
                 gguf_version: Some(3),
                 metadata: BTreeMap::new(),
                 tensors: BTreeMap::new(),
+                graph: None,
+                data_offset: None,
+                findings: Vec::new(),
             };
             let mut artifact2 = Artifact {
                 format: Format::GGUF,
                 gguf_version: Some(3),
                 metadata: BTreeMap::new(),
                 tensors: BTreeMap::new(),
+                graph: None,
+                data_offset: None,
+                findings: Vec::new(),
             };
 
             for (i, key) in keys.iter().enumerate() {
@@ -236,12 +691,18 @@ mod property_tests {
                 gguf_version: Some(3),
                 metadata: BTreeMap::new(),
                 tensors: BTreeMap::new(),
+                graph: None,
+                data_offset: None,
+                findings: Vec::new(),
             };
             let mut artifact2 = Artifact {
                 format: Format::GGUF,
                 gguf_version: Some(3),
                 metadata: BTreeMap::new(),
                 tensors: BTreeMap::new(),
+                graph: None,
+                data_offset: None,
+                findings: Vec::new(),
             };
 
             for name in names.iter() {
@@ -252,6 +713,11 @@ mod property_tests {
                         dtype: "f32".to_string(),
                         shape: vec![10, 10],
                         byte_length: 400,
+                        data_offset: None,
+                        digest: None,
+                        location: None,
+                        nnz: None,
+                        big_endian: false,
                     },
                 );
             }
@@ -265,6 +731,11 @@ mod property_tests {
                         dtype: "f32".to_string(),
                         shape: vec![10, 10],
                         byte_length: 400,
+                        data_offset: None,
+                        digest: None,
+                        location: None,
+                        nnz: None,
+                        big_endian: false,
                     },
                 );
             }
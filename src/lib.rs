@@ -1,7 +1,22 @@
+pub mod dequant;
 pub mod diff;
+pub mod ggml;
 pub mod gguf;
+pub mod graph;
 pub mod hash;
+pub mod names;
+pub mod numpy;
 #[cfg(feature = "onnx")]
 pub mod onnx;
+pub mod pickle;
+pub mod pytorch;
+pub mod quant;
 pub mod safetensors;
+pub mod scan;
+pub mod shards;
+pub mod stats;
+pub mod tflite;
 pub mod types;
+pub mod validate;
+pub mod values;
+pub mod zip;
@@ -1,21 +1,38 @@
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
 use serde::Serialize;
 use std::fs::File;
 use std::io::{BufReader, Read, Seek, SeekFrom};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use thiserror::Error;
 
 use weight_inspect::diff;
-use weight_inspect::gguf::parse_gguf;
+use weight_inspect::ggml::{self, parse_ggml_with_limits, GGMLParserError};
 use weight_inspect::gguf::GGUFParserError;
-use weight_inspect::hash::compute_structural_hash;
-#[cfg(feature = "onnx")]
-use weight_inspect::onnx::parse_onnx;
+use weight_inspect::gguf::{self, parse_gguf_with_limits, SplitError};
+use weight_inspect::graph::{Attribute, Dim, Graph, GraphDiff, ValueInfo};
+use weight_inspect::hash::{
+    compute_content_hash_and_digests, compute_structural_hash, compute_tensor_digests,
+    ContentHashError,
+};
+use weight_inspect::names::{self, NameMap};
+use weight_inspect::numpy::{self, parse_numpy_with_limits, NumpyParserError};
 #[cfg(feature = "onnx")]
 use weight_inspect::onnx::OnnxParserError;
-use weight_inspect::safetensors::parse_safetensors;
+#[cfg(feature = "onnx")]
+use weight_inspect::onnx::{self, parse_onnx_file_with_limits};
+use weight_inspect::pytorch::{parse_pytorch_with_limits, PyTorchParserError};
+use weight_inspect::quant::{quant_report, QuantError, QuantReport};
 use weight_inspect::safetensors::SafetensorsParserError;
-use weight_inspect::types::{Artifact, CanonicalValue};
+use weight_inspect::safetensors::{self, parse_safetensors_with_limits, ShardedError};
+use weight_inspect::scan::{scan_with_limits, ScanError, ScanReport, Stream};
+use weight_inspect::shards::ShardReader;
+use weight_inspect::stats::{compute_stats, StatsReport, TensorStats};
+use weight_inspect::tflite::{self, parse_tflite_with_limits, TFLiteParserError};
+#[cfg(feature = "onnx")]
+use weight_inspect::types::Format;
+use weight_inspect::types::{Artifact, CanonicalValue, Finding, ParseLimits, Severity};
+use weight_inspect::validate;
+use weight_inspect::values::ValueError;
 
 #[derive(Error, Debug)]
 pub enum AppError {
@@ -34,19 +51,66 @@ pub enum AppError {
         path: String,
         source: GGUFParserError,
     },
+    #[error("failed to parse legacy GGML file '{path}': {source}")]
+    GGMLParse {
+        path: String,
+        source: GGMLParserError,
+    },
     #[error("failed to parse safetensors file '{path}': {source}")]
     SafetensorsParse {
         path: String,
         source: SafetensorsParserError,
     },
+    #[error("failed to parse PyTorch checkpoint '{path}': {source}")]
+    PyTorchParse {
+        path: String,
+        source: PyTorchParserError,
+    },
+    #[error("failed to scan '{path}': {source}")]
+    Scan { path: String, source: ScanError },
+    #[error("failed to parse NumPy file '{path}': {source}")]
+    NumpyParse {
+        path: String,
+        source: NumpyParserError,
+    },
+    #[error("failed to parse TFLite model '{path}': {source}")]
+    TFLiteParse {
+        path: String,
+        source: TFLiteParserError,
+    },
+    #[error("failed to read sharded checkpoint '{path}': {source}")]
+    Sharded { path: String, source: ShardedError },
+    #[error("failed to read split GGUF '{path}': {source}")]
+    GGUFSplit { path: String, source: SplitError },
     #[cfg(feature = "onnx")]
     #[error("failed to parse ONNX file '{path}': {source}")]
     OnnxParse {
         path: String,
         source: OnnxParserError,
     },
+    #[error("failed to hash tensor data in '{path}': {source}")]
+    ContentHash {
+        path: String,
+        source: ContentHashError,
+    },
+    #[error("failed to read tensor values in '{path}': {source}")]
+    Values { path: String, source: ValueError },
+    #[error("failed to compare tensor values of '{path_a}' and '{path_b}': {source}")]
+    ValueDiff {
+        path_a: String,
+        path_b: String,
+        source: ValueError,
+    },
+    #[error("failed to compare '{quantized}' with reference '{reference}': {source}")]
+    QuantReport {
+        reference: String,
+        quantized: String,
+        source: ValueError,
+    },
     #[error("ONNX support not enabled: rebuild with --features onnx")]
     OnnxNotSupported { path: String },
+    #[error("unknown name map '{map}': must be 'none', 'auto' or a supported architecture")]
+    InvalidNameMap { map: String },
     #[error("invalid format '{format}': must be 'text' or 'md'")]
     InvalidFormat { format: String },
     #[error("JSON error: {0}")]
@@ -66,6 +130,45 @@ impl From<serde_json::Error> for AppError {
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+    #[command(flatten)]
+    limits: LimitArgs,
+}
+
+/// Parser safety limits; unset flags keep the library defaults.
+#[derive(Args)]
+struct LimitArgs {
+    /// Maximum number of elements in a metadata array
+    #[arg(long, global = true)]
+    max_array_elements: Option<usize>,
+    /// Maximum number of tensors in a file
+    #[arg(long, global = true)]
+    max_tensor_count: Option<u64>,
+    /// Maximum number of metadata entries in a file
+    #[arg(long, global = true)]
+    max_metadata_count: Option<u64>,
+    /// Maximum number of dimensions per tensor
+    #[arg(long, global = true)]
+    max_dimensions: Option<u32>,
+    /// Maximum safetensors header size in bytes
+    #[arg(long, global = true)]
+    max_header_size: Option<usize>,
+}
+
+impl LimitArgs {
+    fn to_limits(&self) -> ParseLimits {
+        let defaults = ParseLimits::default();
+        ParseLimits {
+            max_array_elements: self
+                .max_array_elements
+                .unwrap_or(defaults.max_array_elements),
+            max_tensor_count: self.max_tensor_count.unwrap_or(defaults.max_tensor_count),
+            max_metadata_count: self
+                .max_metadata_count
+                .unwrap_or(defaults.max_metadata_count),
+            max_dimensions: self.max_dimensions.unwrap_or(defaults.max_dimensions),
+            max_header_size: self.max_header_size.unwrap_or(defaults.max_header_size),
+        }
+    }
 }
 
 #[derive(Subcommand)]
@@ -84,12 +187,25 @@ enum Commands {
         only_changes: bool,
         #[arg(long, default_value = "false")]
         verbose: bool,
+        /// Also compare tensor data digests (reads both files in full)
+        #[arg(long, default_value = "false")]
+        content: bool,
+        /// Decode tensors with matching shapes and report numeric differences
+        #[arg(long, default_value = "false")]
+        values: bool,
+        /// Map Hugging Face tensor names to GGUF names: 'none', 'auto' or an
+        /// architecture (llama, mistral, qwen2, gemma, gemma2, phi2, phi3)
+        #[arg(long, default_value = "none")]
+        map: String,
     },
     /// Show stable structural fingerprint
     Id {
         file: String,
         #[arg(long, default_value = "false")]
         json: bool,
+        /// Also hash the tensor data bytes (reads the whole file)
+        #[arg(long, default_value = "false")]
+        content: bool,
     },
     /// Show full model structure details
     Inspect {
@@ -100,27 +216,82 @@ enum Commands {
         html: bool,
         #[arg(long, default_value = "false")]
         verbose: bool,
+        /// Read tensor data and report per-tensor numeric statistics
+        #[arg(long, default_value = "false")]
+        stats: bool,
     },
     /// One-line summary for scripts and CI
     Summary { file: String },
+    /// Check a model file for integrity problems
+    Validate {
+        file: String,
+        #[arg(long, default_value = "false")]
+        json: bool,
+    },
+    /// Scan the pickle streams of a checkpoint for dangerous imports
+    Scan {
+        file: String,
+        #[arg(long, default_value = "false")]
+        json: bool,
+        /// Also fail on warnings (imports outside the allowlist)
+        #[arg(long, default_value = "false")]
+        strict: bool,
+        /// Print the disassembly of every pickle stream
+        #[arg(long, default_value = "false")]
+        verbose: bool,
+    },
+    /// Measure quantization error of a GGUF file against its reference checkpoint
+    QuantReport {
+        /// Reference checkpoint (e.g. f16/bf16 safetensors)
+        reference: String,
+        /// Quantized GGUF built from the reference
+        quantized: String,
+        #[arg(long, default_value = "false")]
+        json: bool,
+        /// Number of tensors and layers to list in text output
+        #[arg(long, default_value = "20")]
+        top: usize,
+    },
 }
 
 /// Detect the format of a model file and parse it into an Artifact.
 ///
-/// Checks file extension first, then magic bytes for GGUF/safetensors.
-fn detect_format(path: &Path) -> Result<Artifact, AppError> {
+/// A directory or `*.safetensors.index.json` is read as a sharded
+/// safetensors checkpoint and a `*-NNNNN-of-NNNNN.gguf` file as a split GGUF.
+/// Otherwise checks file extension first, then magic bytes for
+/// GGUF/safetensors. Legacy llama.cpp files are recognized by their `ggml`,
+/// `ggmf` or `ggjt` magic. Files with the `TFL3` identifier or a `.tflite`
+/// extension are read as TFLite models, `.npy`/`.npz` files as NumPy arrays,
+/// and other zip archives and `.pt`/`.pth`/`.bin`/`.ckpt` files as PyTorch checkpoints.
+fn detect_format(path: &Path, limits: &ParseLimits) -> Result<Artifact, AppError> {
+    if safetensors::is_sharded(path) {
+        return safetensors::parse_sharded_with_limits(path, limits).map_err(|e| {
+            AppError::Sharded {
+                path: path.display().to_string(),
+                source: e,
+            }
+        });
+    }
+    if gguf::is_split(path) {
+        return gguf::parse_split_with_limits(&[path], limits).map_err(|e| AppError::GGUFSplit {
+            path: path.display().to_string(),
+            source: e,
+        });
+    }
+
     // Check for .onnx extension first (before magic byte detection)
     if path.extension().is_some_and(|e| e == "onnx") {
         #[cfg(feature = "onnx")]
         {
-            let file = File::open(path).map_err(|e| AppError::FileOpen {
-                path: path.display().to_string(),
-                source: e,
-            })?;
-            let mut reader = BufReader::new(file);
-            return parse_onnx(&mut reader).map_err(|e| AppError::OnnxParse {
-                path: path.display().to_string(),
-                source: e,
+            return parse_onnx_file_with_limits(path, limits).map_err(|e| match e {
+                OnnxParserError::IoError(source) => AppError::FileOpen {
+                    path: path.display().to_string(),
+                    source,
+                },
+                source => AppError::OnnxParse {
+                    path: path.display().to_string(),
+                    source,
+                },
             });
         }
         #[cfg(not(feature = "onnx"))]
@@ -136,22 +307,28 @@ fn detect_format(path: &Path) -> Result<Artifact, AppError> {
         source: e,
     })?;
     let mut reader = BufReader::new(&file);
-    let mut magic = [0u8; 4];
+    let mut magic = Vec::with_capacity(8);
     reader
-        .read_exact(&mut magic)
+        .by_ref()
+        .take(8)
+        .read_to_end(&mut magic)
+        .and_then(|n| match n {
+            0..4 => Err(std::io::ErrorKind::UnexpectedEof.into()),
+            _ => Ok(()),
+        })
         .map_err(|e| AppError::FileRead {
             path: path.display().to_string(),
             source: e,
         })?;
 
-    if &magic == b"GGUF" {
+    if magic.starts_with(b"GGUF") {
         file.seek(SeekFrom::Start(0))
             .map_err(|e| AppError::FileRead {
                 path: path.display().to_string(),
                 source: e,
             })?;
         let mut reader = BufReader::new(file);
-        return parse_gguf(&mut reader).map_err(|e| AppError::GGUFParse {
+        return parse_gguf_with_limits(&mut reader, limits).map_err(|e| AppError::GGUFParse {
             path: path.display().to_string(),
             source: e,
         });
@@ -163,12 +340,192 @@ fn detect_format(path: &Path) -> Result<Artifact, AppError> {
             source: e,
         })?;
     let mut reader = BufReader::new(file);
-    parse_safetensors(&mut reader).map_err(|e| AppError::SafetensorsParse {
+    if ggml::is_ggml(&magic) {
+        return parse_ggml_with_limits(&mut reader, limits).map_err(|e| AppError::GGMLParse {
+            path: path.display().to_string(),
+            source: e,
+        });
+    }
+    if tflite::is_tflite(&magic) || path.extension().is_some_and(|e| e == "tflite") {
+        return parse_tflite_with_limits(&mut reader, limits).map_err(|e| AppError::TFLiteParse {
+            path: path.display().to_string(),
+            source: e,
+        });
+    }
+    let numpy_extension = path.extension().is_some_and(|e| e == "npy" || e == "npz");
+    if numpy::is_npy(&magic) || numpy_extension {
+        return parse_numpy_with_limits(&mut reader, limits).map_err(|e| AppError::NumpyParse {
+            path: path.display().to_string(),
+            source: e,
+        });
+    }
+    let torch_extension = path
+        .extension()
+        .is_some_and(|e| e == "pt" || e == "pth" || e == "bin" || e == "ckpt");
+    if magic.starts_with(b"PK\x03\x04") || torch_extension {
+        return parse_pytorch_with_limits(&mut reader, limits).map_err(|e| {
+            AppError::PyTorchParse {
+                path: path.display().to_string(),
+                source: e,
+            }
+        });
+    }
+    parse_safetensors_with_limits(&mut reader, limits).map_err(|e| AppError::SafetensorsParse {
         path: path.display().to_string(),
         source: e,
     })
 }
 
+/// Integrity findings for a parsed model file, sharded checkpoint or split
+/// GGUF.
+fn integrity_findings(path: &Path, artifact: &Artifact) -> Result<Vec<Finding>, AppError> {
+    if safetensors::is_sharded(path) || gguf::is_split(path) {
+        // The layout of each shard was already checked while merging.
+        let mut findings = artifact.findings.clone();
+        findings.extend(validate::check_tensors(artifact));
+        return Ok(findings);
+    }
+    let file_size = std::fs::metadata(path)
+        .map(|m| m.len())
+        .map_err(|e| AppError::FileRead {
+            path: path.display().to_string(),
+            source: e,
+        })?;
+    Ok(validate::validate(artifact, file_size))
+}
+
+trait ReadSeek: Read + Seek {}
+
+impl<T: Read + Seek> ReadSeek for T {}
+
+/// Open the tensor data of a model file, or of every shard of a sharded
+/// checkpoint or split GGUF, or of an ONNX model and its external data,
+/// matching the offsets `detect_format` produced for `artifact`.
+fn open_data(
+    path: &Path,
+    artifact: &Artifact,
+    limits: &ParseLimits,
+) -> Result<Box<dyn ReadSeek>, AppError> {
+    let shards = if safetensors::is_sharded(path) {
+        Some(
+            safetensors::shard_paths(path, limits).map_err(|e| AppError::Sharded {
+                path: path.display().to_string(),
+                source: e,
+            })?,
+        )
+    } else if gguf::is_split(path) {
+        Some(gguf::split_paths(path))
+    } else {
+        onnx_data_paths(path, artifact)
+    };
+    if let Some(shards) = shards {
+        let reader = ShardReader::open(&shards).map_err(|e| AppError::FileOpen {
+            path: path.display().to_string(),
+            source: e,
+        })?;
+        return Ok(Box::new(reader));
+    }
+    let file = File::open(path).map_err(|e| AppError::FileOpen {
+        path: path.display().to_string(),
+        source: e,
+    })?;
+    Ok(Box::new(BufReader::new(file)))
+}
+
+/// The model file followed by its external data files, for ONNX models.
+#[cfg(feature = "onnx")]
+fn onnx_data_paths(path: &Path, artifact: &Artifact) -> Option<Vec<PathBuf>> {
+    (artifact.format == Format::Onnx).then(|| onnx::data_paths(artifact, path))
+}
+
+#[cfg(not(feature = "onnx"))]
+fn onnx_data_paths(_path: &Path, _artifact: &Artifact) -> Option<Vec<PathBuf>> {
+    None
+}
+
+/// Fill in per-tensor digests and return the content hash and Merkle root,
+/// reading the data once.
+fn content_hashes(
+    path: &Path,
+    artifact: &mut Artifact,
+    limits: &ParseLimits,
+) -> Result<(String, String), AppError> {
+    let mut reader = open_data(path, artifact, limits)?;
+    compute_content_hash_and_digests(artifact, &mut reader).map_err(|e| AppError::ContentHash {
+        path: path.display().to_string(),
+        source: e,
+    })
+}
+
+/// Fill in per-tensor digests from the file and return their Merkle root.
+fn tensor_digests(
+    path: &Path,
+    artifact: &mut Artifact,
+    limits: &ParseLimits,
+) -> Result<String, AppError> {
+    let mut reader = open_data(path, artifact, limits)?;
+    compute_tensor_digests(artifact, &mut reader).map_err(|e| AppError::ContentHash {
+        path: path.display().to_string(),
+        source: e,
+    })
+}
+
+fn tensor_stats(
+    path: &Path,
+    artifact: &Artifact,
+    limits: &ParseLimits,
+) -> Result<StatsReport, AppError> {
+    let mut reader = open_data(path, artifact, limits)?;
+    compute_stats(artifact, &mut reader).map_err(|e| AppError::Values {
+        path: path.display().to_string(),
+        source: e,
+    })
+}
+
+fn value_diffs(
+    path_a: &Path,
+    path_b: &Path,
+    artifact_a: &Artifact,
+    artifact_b: &Artifact,
+    name_map: Option<&NameMap>,
+    limits: &ParseLimits,
+) -> Result<diff::ValueDiffs, AppError> {
+    let mut reader_a = open_data(path_a, artifact_a, limits)?;
+    let mut reader_b = open_data(path_b, artifact_b, limits)?;
+    diff::diff_values(
+        artifact_a,
+        artifact_b,
+        name_map,
+        &mut reader_a,
+        &mut reader_b,
+    )
+    .map_err(|e| AppError::ValueDiff {
+        path_a: path_a.display().to_string(),
+        path_b: path_b.display().to_string(),
+        source: e,
+    })
+}
+
+fn format_finding(finding: &Finding) -> String {
+    let subject = finding
+        .subject
+        .as_ref()
+        .map(|s| format!(" '{}'", s))
+        .unwrap_or_default();
+    format!(
+        "{}: {}{}: {}",
+        finding.severity, finding.code, subject, finding.message
+    )
+}
+
+/// Print integrity problems (truncation, overlaps, ...) to stderr.
+fn report_findings(path: &Path, artifact: &Artifact) -> Result<(), AppError> {
+    for finding in integrity_findings(path, artifact)? {
+        eprintln!("{}", format_finding(&finding));
+    }
+    Ok(())
+}
+
 fn print_diff(result: &diff::DiffResult, json: bool) -> Result<(), AppError> {
     if json {
         println!(
@@ -205,6 +562,7 @@ fn print_diff(result: &diff::DiffResult, json: bool) -> Result<(), AppError> {
 
     if !result.tensors_added.is_empty()
         || !result.tensors_removed.is_empty()
+        || !result.tensors_renamed.is_empty()
         || !result.tensor_changes.is_empty()
     {
         println!("\nTensors:");
@@ -214,6 +572,12 @@ fn print_diff(result: &diff::DiffResult, json: bool) -> Result<(), AppError> {
         for name in &result.tensors_removed {
             println!("  - {}", name);
         }
+        for rename in &result.tensors_renamed {
+            println!(
+                "  > {} -> {} (confidence {:.2})",
+                rename.old_name, rename.new_name, rename.confidence
+            );
+        }
         for change in &result.tensor_changes {
             println!("  ~ {}:", change.name);
             if let (Some(old), Some(new)) = (&change.dtype_old, &change.dtype_new) {
@@ -225,6 +589,9 @@ fn print_diff(result: &diff::DiffResult, json: bool) -> Result<(), AppError> {
             if let (Some(old), Some(new)) = (&change.byte_length_old, &change.byte_length_new) {
                 println!("      bytes: {} -> {}", old, new);
             }
+            if let (Some(old), Some(new)) = (&change.digest_old, &change.digest_new) {
+                println!("      data: {} -> {}", &old[..12], &new[..12]);
+            }
         }
     }
 
@@ -234,8 +601,16 @@ fn print_diff(result: &diff::DiffResult, json: bool) -> Result<(), AppError> {
     Ok(())
 }
 
-fn main() -> Result<(), AppError> {
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run() -> Result<(), AppError> {
     let cli = Cli::parse();
+    let limits = cli.limits.to_limits();
 
     match cli.command {
         Commands::Diff {
@@ -246,15 +621,45 @@ fn main() -> Result<(), AppError> {
             fail_on_diff,
             only_changes,
             verbose,
+            content,
+            values,
+            map,
         } => {
-            let artifact_a = detect_format(Path::new(&file_a))?;
-            let artifact_b = detect_format(Path::new(&file_b))?;
+            let mut artifact_a = detect_format(Path::new(&file_a), &limits)?;
+            let mut artifact_b = detect_format(Path::new(&file_b), &limits)?;
+            if content {
+                tensor_digests(Path::new(&file_a), &mut artifact_a, &limits)?;
+                tensor_digests(Path::new(&file_b), &mut artifact_b, &limits)?;
+            }
 
             let hash_a = compute_structural_hash(&artifact_a)?;
             let hash_b = compute_structural_hash(&artifact_b)?;
 
-            let mut result = diff::diff(&artifact_a, &artifact_b);
+            let name_map = match map.as_str() {
+                "none" => None,
+                "auto" => Some(names::detect(&artifact_a, &artifact_b)),
+                arch => match NameMap::for_architecture(arch) {
+                    Some(name_map) => Some(name_map),
+                    None => return Err(AppError::InvalidNameMap { map }),
+                },
+            };
+            let mut result = match name_map {
+                Some(name_map) => diff::diff_with_map(&artifact_a, &artifact_b, name_map),
+                None => diff::diff(&artifact_a, &artifact_b),
+            };
             result.hash_equal = hash_a == hash_b;
+            if values {
+                let values = value_diffs(
+                    Path::new(&file_a),
+                    Path::new(&file_b),
+                    &artifact_a,
+                    &artifact_b,
+                    name_map,
+                    &limits,
+                )?;
+                result.value_diffs = values.diffs;
+                result.values_skipped = values.skipped;
+            }
 
             if fail_on_diff && result.has_changes() {
                 std::process::exit(1);
@@ -266,9 +671,32 @@ fn main() -> Result<(), AppError> {
 
             print_diff_extended(&result, json, &format, only_changes, verbose)?;
         }
-        Commands::Id { file, json } => {
-            let artifact = detect_format(Path::new(&file))?;
+        Commands::Id {
+            file,
+            json,
+            content,
+        } => {
+            let mut artifact = detect_format(Path::new(&file), &limits)?;
             let hash = compute_structural_hash(&artifact)?;
+            let (content_hash, merkle_root) = if content {
+                let (content_hash, merkle_root) =
+                    content_hashes(Path::new(&file), &mut artifact, &limits)?;
+                (Some(content_hash), Some(merkle_root))
+            } else {
+                (None, None)
+            };
+            // Tensors whose data is not at a known offset are left out of
+            // the content hash and the Merkle root.
+            let unhashed_tensors: Vec<String> = if content {
+                artifact
+                    .tensors
+                    .values()
+                    .filter(|t| t.data_offset.is_none())
+                    .map(|t| t.name.clone())
+                    .collect()
+            } else {
+                Vec::new()
+            };
 
             if json {
                 #[derive(Serialize)]
@@ -276,6 +704,12 @@ fn main() -> Result<(), AppError> {
                     schema: u32,
                     format: String,
                     structural_hash: String,
+                    #[serde(skip_serializing_if = "Option::is_none")]
+                    content_hash: Option<String>,
+                    #[serde(skip_serializing_if = "Vec::is_empty")]
+                    unhashed_tensors: Vec<String>,
+                    #[serde(skip_serializing_if = "Option::is_none")]
+                    merkle_root: Option<String>,
                     tensor_count: usize,
                     metadata_count: usize,
                 }
@@ -283,6 +717,9 @@ fn main() -> Result<(), AppError> {
                     schema: 1,
                     format: format!("{:?}", artifact.format).to_lowercase(),
                     structural_hash: hash,
+                    content_hash,
+                    unhashed_tensors,
+                    merkle_root,
                     tensor_count: artifact.tensors.len(),
                     metadata_count: artifact.metadata.len(),
                 };
@@ -300,10 +737,27 @@ fn main() -> Result<(), AppError> {
                     &hash[..hash.len().min(8)]
                 );
                 println!("Stable:    yes (machine independent)");
-                println!("Includes:  header, tensor names, shapes, dtypes");
-                println!("Excludes:  raw weight bytes");
+                if content {
+                    println!("Includes:  header, tensor names, shapes, dtypes; weight bytes in content_hash");
+                } else {
+                    println!("Includes:  header, tensor names, shapes, dtypes");
+                    println!("Excludes:  raw weight bytes");
+                }
                 println!("\nformat: {:?}", artifact.format);
                 println!("structural_hash: {}", hash);
+                if let Some(content_hash) = content_hash {
+                    println!("content_hash: {}", content_hash);
+                }
+                if !unhashed_tensors.is_empty() {
+                    println!(
+                        "unhashed_tensors: {} (no data offset): {}",
+                        unhashed_tensors.len(),
+                        unhashed_tensors.join(", ")
+                    );
+                }
+                if let Some(merkle_root) = merkle_root {
+                    println!("merkle_root: {}", merkle_root);
+                }
                 println!("tensor_count: {}", artifact.tensors.len());
                 println!("metadata_count: {}", artifact.metadata.len());
             }
@@ -313,9 +767,16 @@ fn main() -> Result<(), AppError> {
             json,
             html,
             verbose,
+            stats,
         } => {
-            let artifact = detect_format(Path::new(&file))?;
+            let artifact = detect_format(Path::new(&file), &limits)?;
             let hash = compute_structural_hash(&artifact)?;
+            report_findings(Path::new(&file), &artifact)?;
+            let stats = if stats {
+                Some(tensor_stats(Path::new(&file), &artifact, &limits)?)
+            } else {
+                None
+            };
 
             if json {
                 let chat_template = artifact
@@ -331,7 +792,7 @@ fn main() -> Result<(), AppError> {
                     });
 
                 #[derive(Serialize)]
-                struct InspectOutput {
+                struct InspectOutput<'a> {
                     schema: u32,
                     format: String,
                     gguf_version: Option<i64>,
@@ -340,6 +801,10 @@ fn main() -> Result<(), AppError> {
                     structural_hash: String,
                     #[serde(skip_serializing_if = "Option::is_none")]
                     chat_template: Option<String>,
+                    #[serde(skip_serializing_if = "Option::is_none")]
+                    stats: Option<StatsReport>,
+                    #[serde(skip_serializing_if = "Option::is_none")]
+                    graph: Option<&'a Graph>,
                 }
                 let output = InspectOutput {
                     schema: 1,
@@ -349,6 +814,8 @@ fn main() -> Result<(), AppError> {
                     metadata_count: artifact.metadata.len(),
                     structural_hash: hash,
                     chat_template,
+                    stats,
+                    graph: artifact.graph.as_ref(),
                 };
                 println!(
                     "{}",
@@ -358,10 +825,168 @@ fn main() -> Result<(), AppError> {
                 print_inspect_html(&artifact, &hash);
             } else {
                 print_inspect(&artifact, &hash, verbose);
+                if let Some(stats) = &stats {
+                    print_stats(stats);
+                }
+            }
+        }
+        Commands::Validate { file, json } => {
+            let path = Path::new(&file);
+            let (format, findings) = match detect_format(path, &limits) {
+                Ok(artifact) => (
+                    Some(format!("{:?}", artifact.format).to_lowercase()),
+                    integrity_findings(path, &artifact)?,
+                ),
+                Err(e @ (AppError::FileOpen { .. } | AppError::OnnxNotSupported { .. })) => {
+                    return Err(e)
+                }
+                Err(e) => (
+                    None,
+                    vec![Finding {
+                        severity: Severity::Error,
+                        code: "parse_error",
+                        subject: None,
+                        message: e.to_string(),
+                    }],
+                ),
+            };
+            let error_count = findings
+                .iter()
+                .filter(|f| f.severity == Severity::Error)
+                .count();
+            let warning_count = findings
+                .iter()
+                .filter(|f| f.severity == Severity::Warning)
+                .count();
+
+            if json {
+                #[derive(Serialize)]
+                struct ValidateOutput {
+                    schema: u32,
+                    file: String,
+                    format: Option<String>,
+                    valid: bool,
+                    error_count: usize,
+                    warning_count: usize,
+                    findings: Vec<Finding>,
+                }
+                let output = ValidateOutput {
+                    schema: 1,
+                    file: file.clone(),
+                    format,
+                    valid: error_count == 0,
+                    error_count,
+                    warning_count,
+                    findings,
+                };
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&output).map_err(AppError::Json)?
+                );
+            } else {
+                println!(
+                    "{} {} ({} errors, {} warnings)",
+                    if error_count == 0 { "VALID" } else { "INVALID" },
+                    file,
+                    error_count,
+                    warning_count
+                );
+                for finding in &findings {
+                    println!("  {}", format_finding(finding));
+                }
+            }
+
+            if error_count > 0 {
+                std::process::exit(1);
+            }
+        }
+        Commands::Scan {
+            file,
+            json,
+            strict,
+            verbose,
+        } => {
+            let path = Path::new(&file);
+            let reader = File::open(path).map_err(|e| AppError::FileOpen {
+                path: file.clone(),
+                source: e,
+            })?;
+            let report = scan_with_limits(&mut BufReader::new(reader), &limits).map_err(|e| {
+                AppError::Scan {
+                    path: file.clone(),
+                    source: e,
+                }
+            })?;
+            let error_count = report.count(Severity::Error);
+            let warning_count = report.count(Severity::Warning);
+            let safe = error_count == 0 && !(strict && warning_count > 0);
+
+            if json {
+                #[derive(Serialize)]
+                struct ScanOutput<'a> {
+                    schema: u32,
+                    file: String,
+                    safe: bool,
+                    error_count: usize,
+                    warning_count: usize,
+                    #[serde(flatten)]
+                    report: &'a ScanReport,
+                }
+                let output = ScanOutput {
+                    schema: 1,
+                    file: file.clone(),
+                    safe,
+                    error_count,
+                    warning_count,
+                    report: &report,
+                };
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&output).map_err(AppError::Json)?
+                );
+            } else {
+                print_scan(&file, &report, safe, verbose);
+            }
+
+            if !safe {
+                std::process::exit(1);
+            }
+        }
+        Commands::QuantReport {
+            reference,
+            quantized,
+            json,
+            top,
+        } => {
+            let reference_path = Path::new(&reference);
+            let quantized_path = Path::new(&quantized);
+            let reference_artifact = detect_format(reference_path, &limits)?;
+            let quantized_artifact = detect_format(quantized_path, &limits)?;
+            let mut reference_reader = open_data(reference_path, &reference_artifact, &limits)?;
+            let mut quantized_reader = open_data(quantized_path, &quantized_artifact, &limits)?;
+            let report = quant_report(
+                &reference_artifact,
+                &quantized_artifact,
+                &mut reference_reader,
+                &mut quantized_reader,
+            )
+            .map_err(|e| AppError::QuantReport {
+                reference: reference.clone(),
+                quantized: quantized.clone(),
+                source: e,
+            })?;
+
+            if json {
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&report).map_err(AppError::Json)?
+                );
+            } else {
+                print_quant_report(&report, top);
             }
         }
         Commands::Summary { file } => {
-            let artifact = detect_format(Path::new(&file))?;
+            let artifact = detect_format(Path::new(&file), &limits)?;
             let hash = compute_structural_hash(&artifact)?;
 
             let version_str = artifact
@@ -438,6 +1063,30 @@ fn print_inspect(artifact: &Artifact, hash: &str, verbose: bool) {
         }
     }
 
+    if let (true, Some(graph)) = (verbose, &artifact.graph) {
+        println!("\nGraph");
+        println!("─────");
+        println!("Nodes:   {}", graph.nodes.len());
+        if !graph.opset_imports.is_empty() {
+            let opsets: Vec<String> = graph
+                .opset_imports
+                .iter()
+                .map(|(domain, version)| format!("{} {}", domain, version))
+                .collect();
+            println!("Opsets:  {}", opsets.join(", "));
+        }
+        if !graph.functions.is_empty() {
+            let functions: Vec<&str> = graph.functions.keys().map(String::as_str).collect();
+            println!("Functions: {}", functions.join(", "));
+        }
+        for input in &graph.inputs {
+            println!("Input:   {}", format_value_info(input));
+        }
+        for output in &graph.outputs {
+            println!("Output:  {}", format_value_info(output));
+        }
+    }
+
     println!("\nStructural ID");
     println!("─────────────");
     println!("Hash: {}", hash);
@@ -449,11 +1098,7 @@ fn print_inspect(artifact: &Artifact, hash: &str, verbose: bool) {
         println!("{:<40} {:<8} {:<20} -----", "", "", "");
         for (name, tensor) in artifact.tensors.iter().take(10) {
             let shape_str = format!("{:?}", tensor.shape);
-            let display_name = if name.len() > 40 {
-                format!("{}...", &name[..37])
-            } else {
-                name.clone()
-            };
+            let display_name = truncate_name(name, 40);
             println!(
                 "{:<40} {:<8} {:<20} {}",
                 display_name, tensor.dtype, shape_str, tensor.byte_length
@@ -462,6 +1107,169 @@ fn print_inspect(artifact: &Artifact, hash: &str, verbose: bool) {
     }
 }
 
+fn format_dims(shape: &[Dim]) -> String {
+    shape
+        .iter()
+        .map(|dim| match dim {
+            Dim::Fixed(n) => n.to_string(),
+            Dim::Symbolic(name) => name.clone(),
+            Dim::Unknown => "?".to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn format_value_info(value: &ValueInfo) -> String {
+    let dims = value.shape.as_deref().map(format_dims);
+    format!(
+        "{} {} [{}]",
+        value.name,
+        value.elem_type.as_deref().unwrap_or("?"),
+        dims.as_deref().unwrap_or("?")
+    )
+}
+
+fn format_stat(value: Option<f64>) -> String {
+    value.map_or_else(|| "-".to_string(), |v| format!("{:.4e}", v))
+}
+
+fn print_scan(file: &str, report: &ScanReport, safe: bool, verbose: bool) {
+    println!(
+        "{} {} ({} streams, {} errors, {} warnings)",
+        if safe { "SAFE" } else { "UNSAFE" },
+        file,
+        report.streams.len(),
+        report.count(Severity::Error),
+        report.count(Severity::Warning)
+    );
+    for stream in &report.streams {
+        print_stream(stream, verbose);
+    }
+    for finding in &report.findings {
+        println!("  {}", format_finding(finding));
+    }
+}
+
+fn print_stream(stream: &Stream, verbose: bool) {
+    let mut source = stream.entry.as_deref().unwrap_or("pickle").to_string();
+    if stream.offset > 0 {
+        source.push_str(&format!(" at byte {}", stream.offset));
+    }
+    println!(
+        "  {}: {} bytes, {} opcodes, {} imports",
+        source,
+        stream.length,
+        stream.opcodes,
+        stream.imports.len()
+    );
+    for import in &stream.imports {
+        let global = match (&import.module, &import.name) {
+            (Some(module), Some(name)) => format!("{}.{}", module, name),
+            _ => "<unresolved>".to_string(),
+        };
+        println!(
+            "    {:>8}  {:<7}  {}",
+            import.position,
+            import.severity.to_string(),
+            global
+        );
+    }
+    if verbose {
+        for instruction in &stream.instructions {
+            println!("    {}", instruction);
+        }
+    }
+}
+
+/// `name`, cut to at most `width` characters with a trailing `...`.
+fn truncate_name(name: &str, width: usize) -> String {
+    if name.chars().count() > width {
+        let kept: String = name.chars().take(width - 3).collect();
+        format!("{}...", kept)
+    } else {
+        name.to_string()
+    }
+}
+
+fn print_stats_row(name: &str, stats: &TensorStats) {
+    let display_name = truncate_name(name, 40);
+    println!(
+        "{:<40} {:>11} {:>11} {:>11} {:>11} {:>10} {:>6} {:>6}",
+        display_name,
+        format_stat(stats.min),
+        format_stat(stats.max),
+        format_stat(stats.mean),
+        format_stat(stats.std),
+        stats.zeros,
+        stats.nan_count,
+        stats.inf_count
+    );
+}
+
+fn print_stats(report: &StatsReport) {
+    println!("\nStatistics");
+    println!("──────────");
+    println!(
+        "{:<40} {:>11} {:>11} {:>11} {:>11} {:>10} {:>6} {:>6}",
+        "Name", "Min", "Max", "Mean", "Std", "Zeros", "NaN", "Inf"
+    );
+    for (name, stats) in &report.tensors {
+        print_stats_row(name, stats);
+    }
+    print_stats_row("(all tensors)", &report.summary);
+    for (name, reason) in &report.skipped {
+        println!("Skipped {}: {}", name, reason);
+    }
+    if !report.non_finite_tensors.is_empty() {
+        println!(
+            "\nWARNING: NaN or Inf values in: {}",
+            report.non_finite_tensors.join(", ")
+        );
+    }
+}
+
+fn print_quant_rows(rows: &[QuantError], top: usize) {
+    println!(
+        "{:<40} {:<8} {:>12} {:>12} {:>9}",
+        "Name", "Dtype", "RMSE", "Max error", "SNR (dB)"
+    );
+    for row in rows.iter().take(top) {
+        let display_name = truncate_name(&row.name, 40);
+        println!(
+            "{:<40} {:<8} {:>12.4e} {:>12.4e} {:>9.2}",
+            display_name,
+            row.dtype.as_deref().unwrap_or(""),
+            row.rmse,
+            row.max_error,
+            row.snr_db
+        );
+    }
+    if rows.len() > top {
+        println!("... and {} more", rows.len() - top);
+    }
+}
+
+fn print_quant_report(report: &QuantReport, top: usize) {
+    println!("Worst tensors");
+    println!("─────────────");
+    print_quant_rows(&report.tensors, top);
+
+    println!("\nWorst layers");
+    println!("────────────");
+    print_quant_rows(&report.layers, top);
+
+    if !report.unmatched.is_empty() {
+        println!(
+            "\n{} tensor(s) without a reference: {}",
+            report.unmatched.len(),
+            report.unmatched.join(", ")
+        );
+    }
+    for (name, reason) in &report.skipped {
+        println!("skipped {}: {}", name, reason);
+    }
+}
+
 fn print_inspect_html(artifact: &Artifact, hash: &str) {
     println!("<!DOCTYPE html>");
     println!("<html><head><title>Model Inspection</title>");
@@ -549,11 +1357,20 @@ fn print_diff_extended(
     };
     println!("{}", status);
     println!("{}", "-".repeat(20));
+    if let Some(name_map) = &result.name_map {
+        println!("Name map:         {}", name_map);
+    }
 
     if result.has_changes() {
         println!("Added tensors:    {}", result.tensors_added.len());
         println!("Removed tensors: {}", result.tensors_removed.len());
+        println!("Renamed tensors:  {}", result.tensors_renamed.len());
         println!("Modified tensors: {}", result.tensor_changes.len());
+        if let Some(graph) = &result.graph {
+            println!("Added nodes:      {}", graph.nodes_added.len());
+            println!("Removed nodes:    {}", graph.nodes_removed.len());
+            println!("Modified nodes:   {}", graph.node_changes.len());
+        }
     } else {
         println!("No structural differences found.");
     }
@@ -567,10 +1384,50 @@ fn print_diff_extended(
         }
     );
 
+    if !result.value_diffs.is_empty() || !result.values_skipped.is_empty() {
+        println!("\nValue differences");
+        println!("─────────────────");
+        for value_diff in &result.value_diffs {
+            println!(
+                "  {}: max_abs={:.6e} mean_abs={:.6e} cosine={:.6} rel_l2={:.6e}",
+                value_diff.name,
+                value_diff.max_abs_diff,
+                value_diff.mean_abs_diff,
+                value_diff.cosine_similarity,
+                value_diff.relative_l2_error
+            );
+        }
+        for (name, reason) in &result.values_skipped {
+            println!("  {}: skipped, {}", name, reason);
+        }
+    }
+
     if !only_changes && !result.has_changes() {
         return Ok(());
     }
 
+    if !result.bulk_renames.is_empty() {
+        println!("\nBulk renames");
+        println!("────────────");
+        for bulk in &result.bulk_renames {
+            println!(
+                "  {:?} -> {:?} ({} tensors)",
+                bulk.old_prefix, bulk.new_prefix, bulk.count
+            );
+        }
+    }
+
+    if !result.tensors_renamed.is_empty() && verbose {
+        println!("\nRenamed tensors");
+        println!("───────────────");
+        for rename in &result.tensors_renamed {
+            println!(
+                "  {} -> {} (confidence {:.2})",
+                rename.old_name, rename.new_name, rename.confidence
+            );
+        }
+    }
+
     if !result.tensor_changes.is_empty() && verbose {
         println!("\nTensor changes");
         println!("─────────────");
@@ -582,6 +1439,17 @@ fn print_diff_extended(
             if let (Some(old), Some(new)) = (&change.shape_old, &change.shape_new) {
                 println!("    shape: {:?} -> {:?}", old, new);
             }
+            if change.digest_old.is_some() {
+                println!("    data: changed");
+            }
+        }
+    }
+
+    if let Some(graph) = result.graph.as_ref().filter(|g| g.has_changes() && verbose) {
+        println!("\nGraph changes");
+        println!("─────────────");
+        for line in graph_diff_lines(graph) {
+            println!("  {}", line);
         }
     }
 
@@ -602,13 +1470,45 @@ fn print_diff_markdown(result: &diff::DiffResult, only_changes: bool) -> Result<
     println!("|-------------|-------|");
     println!("| Added tensors | {} |", result.tensors_added.len());
     println!("| Removed tensors | {} |", result.tensors_removed.len());
+    println!("| Renamed tensors | {} |", result.tensors_renamed.len());
     println!("| Modified tensors | {} |", result.tensor_changes.len());
+    if let Some(graph) = &result.graph {
+        println!("| Added nodes | {} |", graph.nodes_added.len());
+        println!("| Removed nodes | {} |", graph.nodes_removed.len());
+        println!("| Modified nodes | {} |", graph.node_changes.len());
+    }
     println!();
 
+    if !result.value_diffs.is_empty() {
+        println!("### Value differences");
+        println!("| Tensor | Max abs | Mean abs | Cosine | Rel. L2 |");
+        println!("|--------|---------|----------|--------|---------|");
+        for value_diff in &result.value_diffs {
+            println!(
+                "| {} | {:.6e} | {:.6e} | {:.6} | {:.6e} |",
+                value_diff.name,
+                value_diff.max_abs_diff,
+                value_diff.mean_abs_diff,
+                value_diff.cosine_similarity,
+                value_diff.relative_l2_error
+            );
+        }
+        println!();
+    }
+    if !result.values_skipped.is_empty() {
+        println!("Values not compared:");
+        for (name, reason) in &result.values_skipped {
+            println!("- `{}`: {}", name, reason);
+        }
+        println!();
+    }
+
     if !only_changes
         && result.tensors_added.is_empty()
         && result.tensors_removed.is_empty()
+        && result.tensors_renamed.is_empty()
         && result.tensor_changes.is_empty()
+        && !result.graph.as_ref().is_some_and(GraphDiff::has_changes)
     {
         return Ok(());
     }
@@ -631,6 +1531,24 @@ fn print_diff_markdown(result: &diff::DiffResult, only_changes: bool) -> Result<
         println!("```");
     }
 
+    if !result.tensors_renamed.is_empty() {
+        println!("### Renamed tensors");
+        for bulk in &result.bulk_renames {
+            println!(
+                "- `{}` → `{}` ({} tensors)",
+                bulk.old_prefix, bulk.new_prefix, bulk.count
+            );
+        }
+        println!("```");
+        for rename in &result.tensors_renamed {
+            println!(
+                "> {} → {} ({:.2})",
+                rename.old_name, rename.new_name, rename.confidence
+            );
+        }
+        println!("```");
+    }
+
     if !result.tensor_changes.is_empty() {
         println!("### Modified tensors");
         println!("```");
@@ -642,9 +1560,135 @@ fn print_diff_markdown(result: &diff::DiffResult, only_changes: bool) -> Result<
             if let (Some(old), Some(new)) = (&change.shape_old, &change.shape_new) {
                 println!("  shape: {:?} → {:?}", old, new);
             }
+            if change.digest_old.is_some() {
+                println!("  data: changed");
+            }
+        }
+        println!("```");
+    }
+
+    if let Some(graph) = result.graph.as_ref().filter(|g| g.has_changes()) {
+        println!("### Graph changes");
+        println!("```");
+        for line in graph_diff_lines(graph) {
+            println!("{}", line);
         }
         println!("```");
     }
 
     Ok(())
 }
+
+/// One line per graph change, with the changes inside subgraphs indented
+/// under their node.
+fn graph_diff_lines(diff: &GraphDiff) -> Vec<String> {
+    let version = |v: Option<i64>| v.map_or("none".to_string(), |v| v.to_string());
+    let value_type = |elem_type: &Option<String>, shape: &Option<Vec<Dim>>| {
+        format!(
+            "{} [{}]",
+            elem_type.as_deref().unwrap_or("?"),
+            shape
+                .as_ref()
+                .map_or("?".to_string(), |shape| format_dims(shape))
+        )
+    };
+    let attribute = |value: &Option<Attribute>| {
+        value.as_ref().map_or("none".to_string(), |value| {
+            serde_json::to_string(value).unwrap_or_default()
+        })
+    };
+
+    let mut lines = Vec::new();
+    for change in &diff.opset_changes {
+        lines.push(format!(
+            "~ opset {}: {} → {}",
+            change.domain,
+            version(change.old_version),
+            version(change.new_version)
+        ));
+    }
+    for (kind, added, removed, changes) in [
+        (
+            "input",
+            &diff.inputs_added,
+            &diff.inputs_removed,
+            &diff.input_changes,
+        ),
+        (
+            "output",
+            &diff.outputs_added,
+            &diff.outputs_removed,
+            &diff.output_changes,
+        ),
+    ] {
+        lines.extend(added.iter().map(|name| format!("+ {} {}", kind, name)));
+        lines.extend(removed.iter().map(|name| format!("- {} {}", kind, name)));
+        for change in changes {
+            lines.push(format!("~ {} {}", kind, change.name));
+            if change.elem_type_old.is_some() || change.shape_old.is_some() {
+                lines.push(format!(
+                    "  type: {} → {}",
+                    value_type(&change.elem_type_old, &change.shape_old),
+                    value_type(&change.elem_type_new, &change.shape_new)
+                ));
+            }
+        }
+    }
+    lines.extend(
+        diff.nodes_added
+            .iter()
+            .map(|name| format!("+ node {}", name)),
+    );
+    lines.extend(
+        diff.nodes_removed
+            .iter()
+            .map(|name| format!("- node {}", name)),
+    );
+    for change in &diff.node_changes {
+        lines.push(format!("~ node {}", change.name));
+        if let (Some(old), Some(new)) = (&change.op_type_old, &change.op_type_new) {
+            lines.push(format!("  op_type: {} → {}", old, new));
+        }
+        if let (Some(old), Some(new)) = (&change.inputs_old, &change.inputs_new) {
+            lines.push(format!("  inputs: {:?} → {:?}", old, new));
+        }
+        if let (Some(old), Some(new)) = (&change.outputs_old, &change.outputs_new) {
+            lines.push(format!("  outputs: {:?} → {:?}", old, new));
+        }
+        for attribute_change in &change.attribute_changes {
+            lines.push(format!(
+                "  {}: {} → {}",
+                attribute_change.name,
+                attribute(&attribute_change.old_value),
+                attribute(&attribute_change.new_value)
+            ));
+        }
+        for (name, subgraph) in &change.subgraph_changes {
+            lines.push(format!("  {}:", name));
+            lines.extend(
+                graph_diff_lines(subgraph)
+                    .into_iter()
+                    .map(|line| format!("    {}", line)),
+            );
+        }
+    }
+    lines.extend(
+        diff.functions_added
+            .iter()
+            .map(|key| format!("+ function {}", key)),
+    );
+    lines.extend(
+        diff.functions_removed
+            .iter()
+            .map(|key| format!("- function {}", key)),
+    );
+    for (key, function) in &diff.function_changes {
+        lines.push(format!("~ function {}", key));
+        lines.extend(
+            graph_diff_lines(function)
+                .into_iter()
+                .map(|line| format!("  {}", line)),
+        );
+    }
+    lines
+}
@@ -0,0 +1,438 @@
+use crate::types::{Artifact, CanonicalValue, Format};
+
+/// Tensor-name mapping between Hugging Face (transformers) checkpoints and
+/// GGUF files for one model architecture.
+///
+/// Names are split into a module path and a suffix (`weight`, `bias`, ...);
+/// only the module path is mapped. Block tensors use
+/// `model.layers.N.<module>` on the Hugging Face side and `blk.N.<module>` in
+/// GGUF.
+#[derive(Debug)]
+pub struct NameMap {
+    /// GGUF `general.architecture` values this map applies to.
+    pub architectures: &'static [&'static str],
+    global: &'static [(&'static str, &'static str)],
+    block: &'static [(&'static str, &'static str)],
+}
+
+const LLAMA_GLOBAL: &[(&str, &str)] = &[
+    ("model.embed_tokens", "token_embd"),
+    ("model.norm", "output_norm"),
+    ("lm_head", "output"),
+];
+
+const LLAMA_BLOCK: &[(&str, &str)] = &[
+    ("self_attn.q_proj", "attn_q"),
+    ("self_attn.k_proj", "attn_k"),
+    ("self_attn.v_proj", "attn_v"),
+    ("self_attn.o_proj", "attn_output"),
+    ("mlp.gate_proj", "ffn_gate"),
+    ("mlp.up_proj", "ffn_up"),
+    ("mlp.down_proj", "ffn_down"),
+    ("input_layernorm", "attn_norm"),
+    ("post_attention_layernorm", "ffn_norm"),
+];
+
+/// Mistral checkpoints are converted with the `llama` architecture; qwen2
+/// and gemma use the same layout.
+pub static LLAMA: NameMap = NameMap {
+    architectures: &["llama", "mistral", "qwen2", "gemma"],
+    global: LLAMA_GLOBAL,
+    block: LLAMA_BLOCK,
+};
+
+/// Gemma 2 adds norms around the attention and feed-forward blocks.
+pub static GEMMA2: NameMap = NameMap {
+    architectures: &["gemma2"],
+    global: LLAMA_GLOBAL,
+    block: &[
+        ("self_attn.q_proj", "attn_q"),
+        ("self_attn.k_proj", "attn_k"),
+        ("self_attn.v_proj", "attn_v"),
+        ("self_attn.o_proj", "attn_output"),
+        ("mlp.gate_proj", "ffn_gate"),
+        ("mlp.up_proj", "ffn_up"),
+        ("mlp.down_proj", "ffn_down"),
+        ("input_layernorm", "attn_norm"),
+        ("post_attention_layernorm", "post_attention_norm"),
+        ("pre_feedforward_layernorm", "ffn_norm"),
+        ("post_feedforward_layernorm", "post_ffw_norm"),
+    ],
+};
+
+/// Phi-2 (`PhiForCausalLM`).
+pub static PHI2: NameMap = NameMap {
+    architectures: &["phi2", "phi"],
+    global: &[
+        ("model.embed_tokens", "token_embd"),
+        ("model.final_layernorm", "output_norm"),
+        ("lm_head", "output"),
+    ],
+    block: &[
+        ("self_attn.q_proj", "attn_q"),
+        ("self_attn.k_proj", "attn_k"),
+        ("self_attn.v_proj", "attn_v"),
+        ("self_attn.dense", "attn_output"),
+        ("mlp.fc1", "ffn_up"),
+        ("mlp.fc2", "ffn_down"),
+        ("input_layernorm", "attn_norm"),
+    ],
+};
+
+/// Phi-3, which fuses the attention and gate/up projections.
+pub static PHI3: NameMap = NameMap {
+    architectures: &["phi3"],
+    global: LLAMA_GLOBAL,
+    block: &[
+        ("self_attn.qkv_proj", "attn_qkv"),
+        ("self_attn.o_proj", "attn_output"),
+        ("mlp.gate_up_proj", "ffn_up"),
+        ("mlp.down_proj", "ffn_down"),
+        ("input_layernorm", "attn_norm"),
+        ("post_attention_layernorm", "ffn_norm"),
+    ],
+};
+
+/// Legacy llama.cpp files (`ggml`, `ggjt`) kept the tensor names of the
+/// original LLaMA checkpoints.
+const LEGACY_GLOBAL: &[(&str, &str)] = &[
+    ("tok_embeddings", "token_embd"),
+    ("norm", "output_norm"),
+    ("output", "output"),
+];
+
+const LEGACY_BLOCK: &[(&str, &str)] = &[
+    ("attention.wq", "attn_q"),
+    ("attention.wk", "attn_k"),
+    ("attention.wv", "attn_v"),
+    ("attention.wo", "attn_output"),
+    ("feed_forward.w1", "ffn_gate"),
+    ("feed_forward.w2", "ffn_down"),
+    ("feed_forward.w3", "ffn_up"),
+    ("attention_norm", "attn_norm"),
+    ("ffn_norm", "ffn_norm"),
+];
+
+/// Every built-in name map.
+pub static NAME_MAPS: &[&NameMap] = &[&LLAMA, &GEMMA2, &PHI2, &PHI3];
+
+impl NameMap {
+    /// Look up a built-in map by architecture name (e.g. `qwen2`).
+    pub fn for_architecture(arch: &str) -> Option<&'static NameMap> {
+        NAME_MAPS
+            .iter()
+            .copied()
+            .find(|map| map.architectures.contains(&arch))
+    }
+
+    /// Map a Hugging Face tensor name to its GGUF name.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use weight_inspect::names::LLAMA;
+    ///
+    /// assert_eq!(
+    ///     LLAMA.hf_to_gguf("model.layers.3.self_attn.q_proj.weight").as_deref(),
+    ///     Some("blk.3.attn_q.weight")
+    /// );
+    /// ```
+    pub fn hf_to_gguf(&self, name: &str) -> Option<String> {
+        translate(
+            name,
+            "model.layers.",
+            "blk.",
+            self.global,
+            self.block,
+            false,
+        )
+    }
+
+    /// Map a GGUF tensor name to its Hugging Face name.
+    pub fn gguf_to_hf(&self, name: &str) -> Option<String> {
+        translate(name, "blk.", "model.layers.", self.global, self.block, true)
+    }
+}
+
+type Table = [(&'static str, &'static str)];
+
+fn translate(
+    name: &str,
+    from_block: &str,
+    to_block: &str,
+    global: &Table,
+    block: &Table,
+    reverse: bool,
+) -> Option<String> {
+    let lookup = |table: &Table, key: &str| {
+        table.iter().find_map(|&(hf, gguf)| {
+            let (from, to) = if reverse { (gguf, hf) } else { (hf, gguf) };
+            (from == key).then_some(to)
+        })
+    };
+    let (stem, suffix) = name.rsplit_once('.')?;
+    if let Some(rest) = stem.strip_prefix(from_block) {
+        let (layer, module) = rest.split_once('.')?;
+        layer.parse::<u32>().ok()?;
+        let mapped = lookup(block, module)?;
+        return Some(format!("{}{}.{}.{}", to_block, layer, mapped, suffix));
+    }
+    let mapped = lookup(global, stem)?;
+    Some(format!("{}.{}", mapped, suffix))
+}
+
+/// Map a Hugging Face tensor name to its GGUF name using the llama layout.
+///
+/// Covers llama, mistral, qwen2 and gemma checkpoints. Returns `None` for
+/// names without a known GGUF equivalent.
+///
+/// # Example
+///
+/// ```
+/// use weight_inspect::names::hf_to_gguf;
+///
+/// assert_eq!(
+///     hf_to_gguf("model.layers.3.self_attn.q_proj.weight").as_deref(),
+///     Some("blk.3.attn_q.weight")
+/// );
+/// assert_eq!(hf_to_gguf("lm_head.weight").as_deref(), Some("output.weight"));
+/// ```
+pub fn hf_to_gguf(name: &str) -> Option<String> {
+    LLAMA.hf_to_gguf(name)
+}
+
+/// Map a tensor name of a legacy llama.cpp file to its GGUF name.
+///
+/// # Example
+///
+/// ```
+/// use weight_inspect::names::legacy_to_gguf;
+///
+/// assert_eq!(
+///     legacy_to_gguf("layers.3.feed_forward.w1.weight").as_deref(),
+///     Some("blk.3.ffn_gate.weight")
+/// );
+/// assert_eq!(legacy_to_gguf("norm.weight").as_deref(), Some("output_norm.weight"));
+/// ```
+pub fn legacy_to_gguf(name: &str) -> Option<String> {
+    translate(name, "layers.", "blk.", LEGACY_GLOBAL, LEGACY_BLOCK, false)
+}
+
+/// Pick the name map for comparing two artifacts.
+///
+/// Uses `general.architecture` from whichever artifact is GGUF or legacy
+/// GGML, then falls back to recognizing module names in the tensors
+/// (`mlp.fc1` for phi-2, `qkv_proj` for phi-3, `pre_feedforward_layernorm`
+/// for gemma 2) and finally to the llama layout.
+pub fn detect(a: &Artifact, b: &Artifact) -> &'static NameMap {
+    for artifact in [a, b] {
+        if !matches!(artifact.format, Format::GGUF | Format::GGML) {
+            continue;
+        }
+        if let Some(CanonicalValue::String(arch)) = artifact.metadata.get("general.architecture") {
+            if let Some(map) = NameMap::for_architecture(arch) {
+                return map;
+            }
+        }
+    }
+    let has = |module: &str| {
+        [a, b]
+            .iter()
+            .any(|artifact| artifact.tensors.keys().any(|name| name.contains(module)))
+    };
+    if has(".mlp.fc1.") {
+        &PHI2
+    } else if has(".qkv_proj.") {
+        &PHI3
+    } else if has(".pre_feedforward_layernorm.") {
+        &GEMMA2
+    } else {
+        &LLAMA
+    }
+}
+
+/// Rename Hugging Face tensors in `artifact` to their GGUF names. Tensors of
+/// legacy GGML files are renamed with [`legacy_to_gguf`] instead of `map`.
+///
+/// Tensors without a mapping keep their name. If two tensors map to the same
+/// name, the one whose name already matched is kept.
+pub fn to_gguf_names(artifact: &Artifact, map: &NameMap) -> Artifact {
+    let mut mapped = artifact.clone();
+    mapped.tensors.clear();
+    for (name, tensor) in &artifact.tensors {
+        let gguf_name = match artifact.format {
+            Format::GGML => legacy_to_gguf(name),
+            _ => map.hf_to_gguf(name),
+        };
+        match gguf_name {
+            Some(gguf_name) if !artifact.tensors.contains_key(&gguf_name) => {
+                let mut tensor = tensor.clone();
+                tensor.name = gguf_name.clone();
+                mapped.tensors.insert(gguf_name, tensor);
+            }
+            _ => {
+                mapped.tensors.insert(name.clone(), tensor.clone());
+            }
+        }
+    }
+    mapped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Tensor;
+    use std::collections::BTreeMap;
+
+    fn artifact(format: Format, arch: Option<&str>, names: &[&str]) -> Artifact {
+        let mut metadata = BTreeMap::new();
+        if let Some(arch) = arch {
+            metadata.insert(
+                "general.architecture".to_string(),
+                CanonicalValue::String(arch.to_string()),
+            );
+        }
+        let tensors = names
+            .iter()
+            .map(|name| {
+                (
+                    name.to_string(),
+                    Tensor {
+                        name: name.to_string(),
+                        dtype: "f32".to_string(),
+                        shape: vec![1],
+                        byte_length: 4,
+                        data_offset: None,
+                        digest: None,
+                        location: None,
+                        nnz: None,
+                        big_endian: false,
+                    },
+                )
+            })
+            .collect();
+        Artifact {
+            format,
+            gguf_version: None,
+            metadata,
+            tensors,
+            graph: None,
+            data_offset: None,
+            findings: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_block_tensors() {
+        assert_eq!(
+            hf_to_gguf("model.layers.12.mlp.down_proj.weight").as_deref(),
+            Some("blk.12.ffn_down.weight")
+        );
+        assert_eq!(
+            hf_to_gguf("model.layers.0.self_attn.k_proj.bias").as_deref(),
+            Some("blk.0.attn_k.bias")
+        );
+        assert_eq!(
+            hf_to_gguf("model.layers.0.post_attention_layernorm.weight").as_deref(),
+            Some("blk.0.ffn_norm.weight")
+        );
+    }
+
+    #[test]
+    fn test_unknown_names() {
+        assert_eq!(hf_to_gguf("model.layers.x.mlp.up_proj.weight"), None);
+        assert_eq!(hf_to_gguf("model.layers.0.rotary_emb.inv_freq"), None);
+        assert_eq!(hf_to_gguf("weight"), None);
+    }
+
+    #[test]
+    fn test_round_trip_every_map() {
+        for map in NAME_MAPS {
+            for &(hf, _) in map.block {
+                let name = format!("model.layers.7.{}.weight", hf);
+                let gguf = map.hf_to_gguf(&name).unwrap();
+                assert!(gguf.starts_with("blk.7."));
+                assert_eq!(map.gguf_to_hf(&gguf), Some(name));
+            }
+            for &(hf, _) in map.global {
+                let name = format!("{}.weight", hf);
+                let gguf = map.hf_to_gguf(&name).unwrap();
+                assert_eq!(map.gguf_to_hf(&gguf), Some(name));
+            }
+        }
+    }
+
+    #[test]
+    fn test_architecture_specific_names() {
+        assert_eq!(
+            PHI2.hf_to_gguf("model.layers.1.mlp.fc1.bias").as_deref(),
+            Some("blk.1.ffn_up.bias")
+        );
+        assert_eq!(
+            PHI3.hf_to_gguf("model.layers.1.self_attn.qkv_proj.weight")
+                .as_deref(),
+            Some("blk.1.attn_qkv.weight")
+        );
+        assert_eq!(
+            GEMMA2
+                .hf_to_gguf("model.layers.1.post_attention_layernorm.weight")
+                .as_deref(),
+            Some("blk.1.post_attention_norm.weight")
+        );
+    }
+
+    #[test]
+    fn test_detect_prefers_gguf_architecture() {
+        let hf = artifact(
+            Format::Safetensors,
+            None,
+            &["model.layers.0.mlp.fc1.weight"],
+        );
+        let gguf = artifact(Format::GGUF, Some("phi3"), &[]);
+        assert_eq!(detect(&hf, &gguf).architectures[0], "phi3");
+        assert_eq!(detect(&hf, &hf).architectures[0], "phi2");
+        let plain = artifact(Format::Safetensors, None, &["model.norm.weight"]);
+        assert_eq!(detect(&plain, &plain).architectures[0], "llama");
+    }
+
+    #[test]
+    fn test_to_gguf_names() {
+        let hf = artifact(
+            Format::Safetensors,
+            None,
+            &["model.embed_tokens.weight", "custom.weight"],
+        );
+        let mapped = to_gguf_names(&hf, &LLAMA);
+        let names: Vec<&str> = mapped.tensors.keys().map(|k| k.as_str()).collect();
+        assert_eq!(names, vec!["custom.weight", "token_embd.weight"]);
+        assert_eq!(
+            mapped.tensors["token_embd.weight"].name,
+            "token_embd.weight"
+        );
+    }
+
+    #[test]
+    fn test_legacy_names() {
+        let legacy = artifact(
+            Format::GGML,
+            Some("llama"),
+            &[
+                "layers.0.attention.wq.weight",
+                "layers.0.feed_forward.w2.weight",
+                "tok_embeddings.weight",
+                "model.embed_tokens.weight",
+            ],
+        );
+        let mapped = to_gguf_names(&legacy, &LLAMA);
+        let names: Vec<&str> = mapped.tensors.keys().map(|k| k.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "blk.0.attn_q.weight",
+                "blk.0.ffn_down.weight",
+                "model.embed_tokens.weight",
+                "token_embd.weight",
+            ]
+        );
+    }
+}
@@ -0,0 +1,764 @@
+use crate::types::{Artifact, Finding, Format, ParseLimits, Severity, Tensor};
+use crate::zip::{self, ZipError, STORED};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{Read, Seek, SeekFrom};
+use thiserror::Error;
+
+const MAGIC: &[u8] = b"\x93NUMPY";
+
+#[derive(Error, Debug)]
+pub enum NumpyParserError {
+    #[error("invalid magic bytes: not a .npy file or .npz archive")]
+    InvalidMagic,
+    #[error("unsupported .npy format version {major}.{minor}")]
+    UnsupportedVersion { major: u8, minor: u8 },
+    #[error(
+        "header size {size} bytes exceeds max_header_size ({max} bytes); needs at least {size}"
+    )]
+    HeaderTooLarge { size: usize, max: usize },
+    #[error("invalid header for array '{name}': {reason}")]
+    InvalidHeader { name: String, reason: String },
+    #[error("tensor count {count} exceeds max_tensor_count ({max}); needs at least {count}")]
+    TensorCountTooLarge { count: u64, max: u64 },
+    #[error(
+        "tensor '{name}' dimensions {dims} exceed max_dimensions ({max}); needs at least {dims}"
+    )]
+    DimensionsTooLarge { name: String, dims: u32, max: u32 },
+    #[error("invalid archive: {0}")]
+    Zip(#[from] ZipError),
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+}
+
+/// Name given to the array of a `.npy` file, as `np.savez` names
+/// positional arrays.
+pub const NPY_ARRAY_NAME: &str = "arr_0";
+
+/// Whether `magic` starts a `.npy` file. The first four bytes are enough.
+pub fn is_npy(magic: &[u8]) -> bool {
+    let n = magic.len().min(MAGIC.len());
+    n >= 4 && magic[..n] == MAGIC[..n]
+}
+
+/// Parse a NumPy `.npy` file or `.npz` archive.
+///
+/// A `.npy` file holds one array, named [`NPY_ARRAY_NAME`]. In an `.npz`
+/// archive (`np.savez`, `np.savez_compressed`, MLX and JAX exports) each
+/// `<name>.npy` entry is an array named `<name>`. Dtypes are normalized to
+/// the names used for safetensors (`<f4` is `f32`, `|b1` is `bool`, ...).
+///
+/// Findings:
+/// - `array_size_mismatch`: the data does not match the shape and dtype.
+///   Arrays whose data is short have no data offset.
+/// - `compressed_array`: the `.npz` entry is deflated (`savez_compressed`);
+///   its data cannot be read in place and it has no data offset, so content
+///   hashes, digests and value statistics leave it out.
+/// - `fortran_order`: the data is column-major; it has no data offset.
+/// - `big_endian`: the data is big-endian.
+/// - `object_array`: the data is a pickle of Python objects, which
+///   `np.load` only reads with `allow_pickle=True`.
+///
+/// # Example
+///
+/// ```
+/// use weight_inspect::numpy;
+///
+/// let data = std::fs::read("tests/fixtures/tiny.npz").unwrap();
+/// let mut cursor = std::io::Cursor::new(data);
+/// let artifact = numpy::parse_numpy(&mut cursor).unwrap();
+/// assert_eq!(artifact.format, weight_inspect::types::Format::Numpy);
+/// assert_eq!(artifact.tensors["test.weight.0"].shape, vec![64, 128]);
+/// ```
+pub fn parse_numpy<R: Read + Seek>(reader: &mut R) -> Result<Artifact, NumpyParserError> {
+    parse_numpy_with_limits(reader, &ParseLimits::default())
+}
+
+/// Parse a NumPy `.npy` file or `.npz` archive, enforcing the given safety
+/// limits.
+///
+/// `max_header_size` bounds each array header and the zip central directory.
+pub fn parse_numpy_with_limits<R: Read + Seek>(
+    reader: &mut R,
+    limits: &ParseLimits,
+) -> Result<Artifact, NumpyParserError> {
+    let mut magic = [0u8; 6];
+    let n = reader.read(&mut magic)?;
+    reader.seek(SeekFrom::Start(0))?;
+    let mut arrays = Arrays {
+        tensors: BTreeMap::new(),
+        findings: Vec::new(),
+    };
+
+    if zip::is_zip(&magic[..n]) {
+        let entries = zip::read_entries(reader, limits.max_header_size)?;
+        let count = entries.iter().filter(|e| e.name.ends_with(".npy")).count() as u64;
+        if count > limits.max_tensor_count {
+            return Err(NumpyParserError::TensorCountTooLarge {
+                count,
+                max: limits.max_tensor_count,
+            });
+        }
+        let mut seen = BTreeSet::new();
+        for entry in &entries {
+            let Some(name) = entry.name.strip_suffix(".npy") else {
+                continue;
+            };
+            if !seen.insert(name) {
+                arrays.findings.push(Finding::new(
+                    Severity::Error,
+                    "duplicate_tensor",
+                    Some(name),
+                    "the archive has more than one entry for this array; only one is used"
+                        .to_string(),
+                ));
+                continue;
+            }
+            let header = read_header(&mut zip::entry_reader(reader, entry)?, name, limits)?;
+            let offset = (entry.method == STORED).then_some(entry.data_offset);
+            if entry.method != STORED {
+                arrays.findings.push(Finding::new(
+                    Severity::Warning,
+                    "compressed_array",
+                    Some(name),
+                    format!(
+                        "entry is compressed (method {}); its data cannot be read in place \
+                         and is left out of content hashes and value statistics",
+                        entry.method
+                    ),
+                ));
+            }
+            arrays.add(name, header, offset, entry.size)?;
+        }
+    } else if is_npy(&magic[..n]) {
+        let file_size = reader.seek(SeekFrom::End(0))?;
+        reader.seek(SeekFrom::Start(0))?;
+        let header = read_header(reader, NPY_ARRAY_NAME, limits)?;
+        arrays.add(NPY_ARRAY_NAME, header, Some(0), file_size)?;
+    } else {
+        return Err(NumpyParserError::InvalidMagic);
+    }
+
+    Ok(Artifact {
+        format: Format::Numpy,
+        gguf_version: None,
+        metadata: BTreeMap::new(),
+        tensors: arrays.tensors,
+        graph: None,
+        data_offset: None,
+        findings: arrays.findings,
+    })
+}
+
+/// The element type of an array, from its `descr`.
+#[derive(Debug, Clone, PartialEq)]
+enum Descr {
+    /// A numeric type with a crate dtype name.
+    Known {
+        dtype: &'static str,
+        size: u64,
+        big_endian: bool,
+    },
+    /// Pickled Python objects (`|O`).
+    Object,
+    /// Strings, raw bytes, datetimes and structured types. `size` is the
+    /// item size, if the descr gives one.
+    Other { dtype: String, size: Option<u64> },
+}
+
+/// A parsed `.npy` header.
+#[derive(Debug)]
+struct Header {
+    descr: Descr,
+    fortran_order: bool,
+    shape: Vec<u64>,
+    /// Bytes from the start of the file to the array data.
+    data_start: u64,
+}
+
+/// Read the header at the start of a `.npy` stream.
+fn read_header<R: Read>(
+    reader: &mut R,
+    name: &str,
+    limits: &ParseLimits,
+) -> Result<Header, NumpyParserError> {
+    let invalid = |reason: &str| NumpyParserError::InvalidHeader {
+        name: name.to_string(),
+        reason: reason.to_string(),
+    };
+    let mut preamble = [0u8; 8];
+    reader.read_exact(&mut preamble)?;
+    if !is_npy(&preamble) {
+        return Err(invalid("missing \\x93NUMPY magic"));
+    }
+    let (major, minor) = (preamble[6], preamble[7]);
+    let (len, prefix) = match major {
+        1 => {
+            let mut len = [0u8; 2];
+            reader.read_exact(&mut len)?;
+            (u16::from_le_bytes(len) as usize, 10)
+        }
+        2 | 3 => {
+            let mut len = [0u8; 4];
+            reader.read_exact(&mut len)?;
+            (u32::from_le_bytes(len) as usize, 12)
+        }
+        _ => return Err(NumpyParserError::UnsupportedVersion { major, minor }),
+    };
+    if len > limits.max_header_size {
+        return Err(NumpyParserError::HeaderTooLarge {
+            size: len,
+            max: limits.max_header_size,
+        });
+    }
+    let mut text = vec![0u8; len];
+    reader.read_exact(&mut text)?;
+    // Versions 1 and 2 are latin-1, version 3 is UTF-8.
+    let text: String = if major == 3 {
+        String::from_utf8(text).map_err(|_| invalid("header is not UTF-8"))?
+    } else {
+        text.iter().map(|&b| b as char).collect()
+    };
+
+    let Literal::Dict(fields) = parse_literal(&text).map_err(|e| invalid(&e))? else {
+        return Err(invalid("header is not a dict"));
+    };
+    let field = |key: &str| {
+        fields
+            .iter()
+            .find(|(k, _)| *k == Literal::Str(key.to_string()))
+            .map(|(_, v)| v)
+            .ok_or_else(|| invalid(&format!("missing '{}'", key)))
+    };
+    let descr = match field("descr")? {
+        Literal::Str(descr) => parse_descr(descr),
+        Literal::Seq(_) => Descr::Other {
+            dtype: "unknown_structured".to_string(),
+            size: None,
+        },
+        _ => return Err(invalid("'descr' is not a string or a list")),
+    };
+    let Literal::Bool(fortran_order) = field("fortran_order")? else {
+        return Err(invalid("'fortran_order' is not a bool"));
+    };
+    let Literal::Seq(dims) = field("shape")? else {
+        return Err(invalid("'shape' is not a tuple"));
+    };
+    if dims.len() > limits.max_dimensions as usize {
+        return Err(NumpyParserError::DimensionsTooLarge {
+            name: name.to_string(),
+            dims: dims.len() as u32,
+            max: limits.max_dimensions,
+        });
+    }
+    let shape = dims
+        .iter()
+        .map(|d| match d {
+            Literal::Int(i) => Ok(*i),
+            _ => Err(invalid("'shape' holds something other than integers")),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Header {
+        descr,
+        fortran_order: *fortran_order,
+        shape,
+        data_start: (prefix + len) as u64,
+    })
+}
+
+/// Map an array-protocol type string such as `<f4` to a dtype.
+fn parse_descr(descr: &str) -> Descr {
+    let (order, rest) = match descr.chars().next() {
+        Some(c @ ('<' | '>' | '|' | '=')) => (c, &descr[1..]),
+        _ => ('=', descr),
+    };
+    let (kind, digits) = rest.split_at(rest.chars().next().map_or(0, char::len_utf8));
+    let size: Option<u64> = digits.parse().ok();
+    let dtype = match (kind, size) {
+        ("f", Some(2)) => "f16",
+        ("f", Some(4)) => "f32",
+        ("f", Some(8)) => "f64",
+        ("i", Some(1)) => "i8",
+        ("i", Some(2)) => "i16",
+        ("i", Some(4)) => "i32",
+        ("i", Some(8)) => "i64",
+        ("u", Some(1)) => "u8",
+        ("u", Some(2)) => "u16",
+        ("u", Some(4)) => "u32",
+        ("u", Some(8)) => "u64",
+        ("b", Some(1)) => "bool",
+        ("c", Some(8)) => "complex64",
+        ("c", Some(16)) => "complex128",
+        ("O", _) => return Descr::Object,
+        _ => {
+            // Unicode strings are stored as UCS-4, so `<U10` takes 40 bytes.
+            let size = match kind {
+                "U" => size.and_then(|n| n.checked_mul(4)),
+                _ => size,
+            };
+            return Descr::Other {
+                dtype: format!("unknown_{}", rest),
+                size,
+            };
+        }
+    };
+    let size = size.unwrap_or(1);
+    Descr::Known {
+        dtype,
+        size,
+        big_endian: order == '>' && size > 1,
+    }
+}
+
+/// Collects the arrays of a file into tensors and findings.
+struct Arrays {
+    tensors: BTreeMap<String, Tensor>,
+    findings: Vec<Finding>,
+}
+
+impl Arrays {
+    /// Add the array whose `.npy` stream starts at `start` (or cannot be read
+    /// in place, if `None`) and is `stream_size` bytes long.
+    fn add(
+        &mut self,
+        name: &str,
+        header: Header,
+        start: Option<u64>,
+        stream_size: u64,
+    ) -> Result<(), NumpyParserError> {
+        let available = stream_size.saturating_sub(header.data_start);
+        let mut data_offset = start.map(|s| s + header.data_start);
+        let elements = header
+            .shape
+            .iter()
+            .try_fold(1u64, |acc, &d| acc.checked_mul(d))
+            .ok_or_else(|| NumpyParserError::InvalidHeader {
+                name: name.to_string(),
+                reason: format!("shape {:?} overflows", header.shape),
+            })?;
+        let (dtype, item_size, big_endian) = match &header.descr {
+            Descr::Known {
+                dtype,
+                size,
+                big_endian,
+            } => {
+                if *big_endian {
+                    self.finding(
+                        Severity::Warning,
+                        "big_endian",
+                        name,
+                        "data is big-endian; values are byte-swapped when decoded".to_string(),
+                    );
+                }
+                (dtype.to_string(), Some(*size), *big_endian)
+            }
+            Descr::Object => {
+                self.finding(
+                    Severity::Warning,
+                    "object_array",
+                    name,
+                    "data is a pickle of Python objects; np.load needs allow_pickle=True \
+                     and would unpickle it"
+                        .to_string(),
+                );
+                ("object".to_string(), None, false)
+            }
+            Descr::Other { dtype, size } => (dtype.clone(), *size, false),
+        };
+
+        let expected = item_size.and_then(|size| size.checked_mul(elements));
+        let byte_length = match expected {
+            Some(expected) if expected != available => {
+                self.finding(
+                    Severity::Error,
+                    "array_size_mismatch",
+                    name,
+                    format!(
+                        "shape {:?} of {} needs {} bytes of data but {} are present",
+                        header.shape, dtype, expected, available
+                    ),
+                );
+                if expected > available {
+                    data_offset = None;
+                }
+                expected
+            }
+            Some(expected) => expected,
+            None => available,
+        };
+        let multi_dim = header.shape.iter().filter(|&&d| d > 1).count() > 1;
+        if header.fortran_order && multi_dim && data_offset.is_some() {
+            self.finding(
+                Severity::Warning,
+                "fortran_order",
+                name,
+                "data is column-major; its bytes are not in row-major order".to_string(),
+            );
+            data_offset = None;
+        }
+
+        self.tensors.insert(
+            name.to_string(),
+            Tensor {
+                name: name.to_string(),
+                dtype,
+                shape: header.shape,
+                byte_length,
+                data_offset,
+                digest: None,
+                location: None,
+                nnz: None,
+                big_endian,
+            },
+        );
+        Ok(())
+    }
+
+    fn finding(&mut self, severity: Severity, code: &'static str, name: &str, message: String) {
+        self.findings
+            .push(Finding::new(severity, code, Some(name), message));
+    }
+}
+
+/// A Python literal in a `.npy` header.
+#[derive(Debug, Clone, PartialEq)]
+enum Literal {
+    Str(String),
+    Int(u64),
+    Bool(bool),
+    None,
+    /// A tuple or list.
+    Seq(Vec<Literal>),
+    Dict(Vec<(Literal, Literal)>),
+}
+
+/// Parse the `repr` of a dict of strings, booleans, integers and tuples, as
+/// written by `np.save`.
+fn parse_literal(text: &str) -> Result<Literal, String> {
+    let mut parser = LiteralParser {
+        text: text.as_bytes(),
+        pos: 0,
+        depth: 0,
+    };
+    let value = parser.value()?;
+    parser.skip_space();
+    if parser.pos != parser.text.len() {
+        return Err(format!("unexpected text at byte {}", parser.pos));
+    }
+    Ok(value)
+}
+
+/// Literals nested deeper than this are rejected.
+const MAX_LITERAL_DEPTH: usize = 32;
+
+struct LiteralParser<'a> {
+    text: &'a [u8],
+    pos: usize,
+    depth: usize,
+}
+
+impl LiteralParser<'_> {
+    fn skip_space(&mut self) {
+        while self.text.get(self.pos).is_some_and(u8::is_ascii_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_space();
+        self.text.get(self.pos).copied()
+    }
+
+    fn value(&mut self) -> Result<Literal, String> {
+        match self.peek() {
+            Some(b'{') => self.items(b'}').map(|items| {
+                let mut iter = items.into_iter();
+                let mut pairs = Vec::new();
+                while let (Some(k), Some(v)) = (iter.next(), iter.next()) {
+                    pairs.push((k, v));
+                }
+                Literal::Dict(pairs)
+            }),
+            Some(b'(') => self.items(b')').map(Literal::Seq),
+            Some(b'[') => self.items(b']').map(Literal::Seq),
+            Some(quote @ (b'\'' | b'"')) => self.string(quote),
+            Some(b'0'..=b'9') => {
+                let start = self.pos;
+                while self.text.get(self.pos).is_some_and(u8::is_ascii_digit) {
+                    self.pos += 1;
+                }
+                let digits = std::str::from_utf8(&self.text[start..self.pos]).unwrap();
+                let value = digits
+                    .parse()
+                    .map_err(|_| format!("integer {} is too large", digits))?;
+                // Python 2 longs, e.g. `(3L, 4L)`.
+                if self.text.get(self.pos) == Some(&b'L') {
+                    self.pos += 1;
+                }
+                Ok(Literal::Int(value))
+            }
+            _ => {
+                for (word, value) in [
+                    ("True", Literal::Bool(true)),
+                    ("False", Literal::Bool(false)),
+                    ("None", Literal::None),
+                ] {
+                    if self.text[self.pos..].starts_with(word.as_bytes()) {
+                        self.pos += word.len();
+                        return Ok(value);
+                    }
+                }
+                Err(format!("unexpected text at byte {}", self.pos))
+            }
+        }
+    }
+
+    /// Comma-separated values up to `close`; dict keys and values alternate.
+    fn items(&mut self, close: u8) -> Result<Vec<Literal>, String> {
+        self.depth += 1;
+        if self.depth > MAX_LITERAL_DEPTH {
+            return Err("nested too deeply".to_string());
+        }
+        self.pos += 1;
+        let mut items = Vec::new();
+        loop {
+            if self.peek() == Some(close) {
+                self.pos += 1;
+                break;
+            }
+            items.push(self.value()?);
+            if close == b'}' {
+                if self.peek() != Some(b':') {
+                    return Err(format!("expected ':' at byte {}", self.pos));
+                }
+                self.pos += 1;
+                items.push(self.value()?);
+            }
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(c) if c == close => {}
+                _ => return Err(format!("expected ',' at byte {}", self.pos)),
+            }
+        }
+        self.depth -= 1;
+        Ok(items)
+    }
+
+    fn string(&mut self, quote: u8) -> Result<Literal, String> {
+        self.pos += 1;
+        let mut out = Vec::new();
+        loop {
+            match self.text.get(self.pos) {
+                None => return Err("unterminated string".to_string()),
+                Some(&b'\\') => {
+                    let escaped = *self.text.get(self.pos + 1).ok_or("unterminated string")?;
+                    out.push(escaped);
+                    self.pos += 2;
+                }
+                Some(&c) if c == quote => {
+                    self.pos += 1;
+                    return Ok(Literal::Str(String::from_utf8_lossy(&out).into_owned()));
+                }
+                Some(&c) => {
+                    out.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// A version 1 `.npy` file as `np.save` writes it.
+    fn npy(descr: &str, fortran_order: bool, shape: &str, data: &[u8]) -> Vec<u8> {
+        let mut header = format!(
+            "{{'descr': '{}', 'fortran_order': {}, 'shape': {}, }}",
+            descr,
+            if fortran_order { "True" } else { "False" },
+            shape
+        );
+        while (10 + header.len() + 1) % 64 != 0 {
+            header.push(' ');
+        }
+        header.push('\n');
+        let mut out = b"\x93NUMPY\x01\x00".to_vec();
+        out.extend((header.len() as u16).to_le_bytes());
+        out.extend(header.as_bytes());
+        out.extend(data);
+        out
+    }
+
+    fn parse(bytes: Vec<u8>) -> Artifact {
+        parse_numpy(&mut Cursor::new(bytes)).unwrap()
+    }
+
+    fn codes(artifact: &Artifact) -> Vec<&str> {
+        artifact.findings.iter().map(|f| f.code).collect()
+    }
+
+    #[test]
+    fn test_parse_npy() {
+        let artifact = parse(npy("<f4", false, "(2, 3)", &[0; 24]));
+
+        let tensor = &artifact.tensors[NPY_ARRAY_NAME];
+        assert_eq!(tensor.dtype, "f32");
+        assert_eq!(tensor.shape, vec![2, 3]);
+        assert_eq!(tensor.byte_length, 24);
+        assert_eq!(tensor.data_offset, Some(128));
+        assert!(artifact.findings.is_empty());
+    }
+
+    #[test]
+    fn test_parse_npz() {
+        let bytes = zip::stored_archive(&[
+            ("embed.weight.npy", &npy("<f2", false, "(4,)", &[0; 8])),
+            ("mask.npy", &npy("|b1", false, "()", &[1])),
+            ("notes.txt", b"not an array"),
+        ]);
+        let artifact = parse(bytes.clone());
+
+        assert_eq!(artifact.tensors.len(), 2);
+        let embed = &artifact.tensors["embed.weight"];
+        assert_eq!(embed.dtype, "f16");
+        let offset = embed.data_offset.unwrap() as usize;
+        assert_eq!(&bytes[offset - 1..offset + 8], b"\n\0\0\0\0\0\0\0\0");
+        let mask = &artifact.tensors["mask"];
+        assert_eq!((mask.dtype.as_str(), mask.byte_length), ("bool", 1));
+        assert!(mask.shape.is_empty());
+    }
+
+    #[test]
+    fn test_compressed_npz() {
+        use flate2::write::DeflateEncoder;
+        use flate2::Compression;
+        use std::io::Write;
+
+        let array = npy("<f4", false, "(4,)", &[0; 16]);
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&array).unwrap();
+        let compressed = encoder.finish().unwrap();
+        let mut bytes = zip::stored_archive(&[
+            ("packed.npy", &compressed),
+            ("plain.npy", &npy("<f4", false, "(1,)", &[0; 4])),
+        ]);
+        // Mark the first entry deflated, with its real size, in both the
+        // local and the central header.
+        let central = bytes.windows(4).position(|w| w == b"PK\x01\x02").unwrap();
+        for (method, size) in [(8, 22), (central + 10, central + 24)] {
+            bytes[method..method + 2].copy_from_slice(&zip::DEFLATED.to_le_bytes());
+            bytes[size..size + 4].copy_from_slice(&(array.len() as u32).to_le_bytes());
+        }
+        let artifact = parse(bytes.clone());
+
+        assert_eq!(codes(&artifact), ["compressed_array"]);
+        assert_eq!(artifact.tensors["packed"].shape, vec![4]);
+        assert_eq!(artifact.tensors["packed"].data_offset, None);
+
+        let mut cursor = Cursor::new(bytes);
+        let report = crate::stats::compute_stats(&artifact, &mut cursor).unwrap();
+        assert_eq!(report.skipped["packed"], "no recorded data offset");
+        assert!(report.tensors.contains_key("plain"));
+        assert!(crate::hash::compute_content_hash(&artifact, &mut cursor).is_ok());
+    }
+
+    #[test]
+    fn test_big_endian_stats() {
+        let data: Vec<u8> = [1.0f32, -3.0, 2.0]
+            .iter()
+            .flat_map(|v| v.to_be_bytes())
+            .collect();
+        let bytes = npy(">f4", false, "(3,)", &data);
+        let artifact = parse(bytes.clone());
+
+        assert_eq!(codes(&artifact), ["big_endian"]);
+        let report = crate::stats::compute_stats(&artifact, &mut Cursor::new(bytes)).unwrap();
+        let stats = &report.tensors[NPY_ARRAY_NAME];
+        assert_eq!(stats.min, Some(-3.0));
+        assert_eq!(stats.max, Some(2.0));
+        assert_eq!(stats.mean, Some(0.0));
+    }
+
+    #[test]
+    fn test_descr() {
+        assert_eq!(
+            parse_descr(">i8"),
+            Descr::Known {
+                dtype: "i64",
+                size: 8,
+                big_endian: true
+            }
+        );
+        assert_eq!(
+            parse_descr("|u1"),
+            Descr::Known {
+                dtype: "u8",
+                size: 1,
+                big_endian: false
+            }
+        );
+        assert_eq!(parse_descr("|O"), Descr::Object);
+        assert_eq!(
+            parse_descr("<U10"),
+            Descr::Other {
+                dtype: "unknown_U10".to_string(),
+                size: Some(40)
+            }
+        );
+    }
+
+    #[test]
+    fn test_findings() {
+        let short = parse(npy("<f8", false, "(2, 2)", &[0; 16]));
+        assert_eq!(codes(&short), ["array_size_mismatch"]);
+        assert_eq!(short.tensors[NPY_ARRAY_NAME].data_offset, None);
+        assert_eq!(short.tensors[NPY_ARRAY_NAME].byte_length, 32);
+
+        let fortran = parse(npy(">f4", true, "(2, 2)", &[0; 16]));
+        assert_eq!(codes(&fortran), ["big_endian", "fortran_order"]);
+        assert_eq!(fortran.tensors[NPY_ARRAY_NAME].data_offset, None);
+
+        // One-dimensional arrays are the same in either order.
+        let vector = parse(npy("<f4", true, "(1, 4)", &[0; 16]));
+        assert!(vector.findings.is_empty());
+
+        let objects = parse(npy("|O", false, "(1,)", b"\x80\x02N."));
+        assert_eq!(codes(&objects), ["object_array"]);
+        assert_eq!(objects.tensors[NPY_ARRAY_NAME].byte_length, 4);
+    }
+
+    #[test]
+    fn test_header_errors() {
+        let mut bytes = npy("<f4", false, "(1,)", &[0; 4]);
+        bytes[6] = 4;
+        assert!(matches!(
+            parse_numpy(&mut Cursor::new(bytes)),
+            Err(NumpyParserError::UnsupportedVersion { major: 4, .. })
+        ));
+        assert!(matches!(
+            parse_numpy(&mut Cursor::new(npy("<f4", false, "(-1,)", &[]))),
+            Err(NumpyParserError::InvalidHeader { .. })
+        ));
+        assert!(matches!(
+            parse_numpy(&mut Cursor::new(b"GGUF\x03\x00\x00\x00".to_vec())),
+            Err(NumpyParserError::InvalidMagic)
+        ));
+        assert_eq!(
+            parse_literal("{'shape': (3L, 4L), 'a': [None]}"),
+            Ok(Literal::Dict(vec![
+                (
+                    Literal::Str("shape".to_string()),
+                    Literal::Seq(vec![Literal::Int(3), Literal::Int(4)])
+                ),
+                (
+                    Literal::Str("a".to_string()),
+                    Literal::Seq(vec![Literal::None])
+                ),
+            ]))
+        );
+        assert!(parse_literal(&"(".repeat(100)).is_err());
+    }
+}
@@ -0,0 +1,240 @@
+use super::{parse_onnx_with_limits, OnnxParserError};
+use crate::types::{Artifact, Finding, ParseLimits, Severity};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Component, Path, PathBuf};
+
+/// Parse an ONNX model file and check the external data files its
+/// initializers refer to (see [`check_external_data`]).
+///
+/// Initializers whose external data is in bounds get a data offset into the
+/// files of [`data_paths`] laid end to end, which is what a
+/// [`ShardReader`](crate::shards::ShardReader) opened on them reads.
+///
+/// # Example
+///
+/// ```
+/// use weight_inspect::onnx;
+///
+/// let artifact = onnx::parse_onnx_file("tests/fixtures/mnist.onnx").unwrap();
+/// assert!(artifact.tensors.values().all(|t| t.location.is_none()));
+/// ```
+pub fn parse_onnx_file<P: AsRef<Path>>(path: P) -> Result<Artifact, OnnxParserError> {
+    parse_onnx_file_with_limits(path, &ParseLimits::default())
+}
+
+/// Parse an ONNX model file and check its external data, enforcing the given
+/// safety limits.
+pub fn parse_onnx_file_with_limits<P: AsRef<Path>>(
+    path: P,
+    limits: &ParseLimits,
+) -> Result<Artifact, OnnxParserError> {
+    let path = path.as_ref();
+    let mut artifact = parse_onnx_with_limits(&mut BufReader::new(File::open(path)?), limits)?;
+    let model_dir = path.parent().unwrap_or(Path::new(""));
+    let findings = check_external_data(&artifact, model_dir);
+    artifact.findings.extend(findings);
+
+    let mut starts = BTreeMap::new();
+    let mut start = std::fs::metadata(path)?.len();
+    for (location, size) in external_files(&artifact, model_dir) {
+        starts.insert(location, (start, size));
+        start += size;
+    }
+    for tensor in artifact.tensors.values_mut() {
+        let Some(location) = &tensor.location else {
+            continue;
+        };
+        if let Some(&(start, size)) = starts.get(&location.path) {
+            if location.offset.saturating_add(tensor.byte_length) <= size {
+                tensor.data_offset = Some(start + location.offset);
+            }
+        }
+    }
+    Ok(artifact)
+}
+
+/// The model file followed by the external data files its initializers
+/// refer to, in the order whose concatenation the data offsets of
+/// [`parse_onnx_file`] point into. Files that are missing or outside the
+/// model's directory are left out.
+///
+/// # Example
+///
+/// ```
+/// use std::path::Path;
+/// use weight_inspect::onnx;
+///
+/// let path = "tests/fixtures/mnist.onnx";
+/// let artifact = onnx::parse_onnx_file(path).unwrap();
+/// assert_eq!(onnx::data_paths(&artifact, path), [Path::new(path)]);
+/// ```
+pub fn data_paths<P: AsRef<Path>>(artifact: &Artifact, path: P) -> Vec<PathBuf> {
+    let path = path.as_ref();
+    let model_dir = path.parent().unwrap_or(Path::new(""));
+    let mut paths = vec![path.to_path_buf()];
+    paths.extend(
+        external_files(artifact, model_dir)
+            .into_keys()
+            .map(|location| model_dir.join(location)),
+    );
+    paths
+}
+
+/// The readable external data files of an artifact, by location, with
+/// their sizes.
+fn external_files(artifact: &Artifact, model_dir: &Path) -> BTreeMap<String, u64> {
+    artifact
+        .tensors
+        .values()
+        .filter_map(|t| t.location.as_ref())
+        .filter(|location| inside(&location.path))
+        .filter_map(|location| {
+            let metadata = std::fs::metadata(model_dir.join(&location.path)).ok()?;
+            metadata
+                .is_file()
+                .then(|| (location.path.clone(), metadata.len()))
+        })
+        .collect()
+}
+
+/// Whether a location stays within the model's directory.
+fn inside(location: &str) -> bool {
+    Path::new(location)
+        .components()
+        .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+}
+
+/// Check that the external data of every initializer exists in `model_dir`,
+/// the directory of the model file.
+///
+/// Findings:
+/// - `external_data_outside_model`: the location is absolute or leaves the
+///   model's directory; the file is not opened.
+/// - `missing_external_data`: the file does not exist or cannot be read.
+/// - `external_data_out_of_bounds`: the data extends past the end of the
+///   file.
+pub fn check_external_data(artifact: &Artifact, model_dir: &Path) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let mut sizes: BTreeMap<&str, Option<u64>> = BTreeMap::new();
+    for tensor in artifact.tensors.values() {
+        let Some(location) = &tensor.location else {
+            continue;
+        };
+        if !inside(&location.path) {
+            findings.push(Finding::new(
+                Severity::Error,
+                "external_data_outside_model",
+                Some(&tensor.name),
+                format!(
+                    "location '{}' is outside the model's directory; it was not read",
+                    location.path
+                ),
+            ));
+            continue;
+        }
+        let size = *sizes.entry(&location.path).or_insert_with(|| {
+            std::fs::metadata(model_dir.join(&location.path))
+                .ok()
+                .filter(|m| m.is_file())
+                .map(|m| m.len())
+        });
+        let Some(size) = size else {
+            findings.push(Finding::new(
+                Severity::Error,
+                "missing_external_data",
+                Some(&tensor.name),
+                format!("data file '{}' does not exist", location.path),
+            ));
+            continue;
+        };
+        let end = location.offset.saturating_add(tensor.byte_length);
+        if end > size {
+            findings.push(Finding::new(
+                Severity::Error,
+                "external_data_out_of_bounds",
+                Some(&tensor.name),
+                format!(
+                    "data ends at byte {} but '{}' is only {} bytes (truncated?)",
+                    end, location.path, size
+                ),
+            ));
+        }
+    }
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::onnx_proto::{
+        tensor_proto::DataLocation, GraphProto, ModelProto, StringStringEntryProto, TensorProto,
+    };
+    use super::*;
+    use prost::Message;
+
+    fn external(name: &str, location: &str, offset: u64) -> TensorProto {
+        let entry = |key: &str, value: String| StringStringEntryProto {
+            key: Some(key.to_string()),
+            value: Some(value),
+        };
+        TensorProto {
+            name: Some(name.to_string()),
+            dims: vec![4, 4],
+            data_type: Some(1),
+            data_location: Some(DataLocation::External as i32),
+            external_data: vec![
+                entry("location", location.to_string()),
+                entry("offset", offset.to_string()),
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_external_data_files_are_checked() {
+        let dir = tempfile::tempdir().unwrap();
+        let model = ModelProto {
+            ir_version: Some(8),
+            graph: Some(GraphProto {
+                initializer: vec![
+                    external("a", "model.onnx.data", 0),
+                    external("b", "model.onnx.data", 64),
+                    external("c", "weights/c.bin", 0),
+                    external("d", "../secret", 0),
+                ],
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let path = dir.path().join("model.onnx");
+        std::fs::write(&path, model.encode_to_vec()).unwrap();
+        std::fs::write(dir.path().join("model.onnx.data"), [0u8; 100]).unwrap();
+
+        let artifact = parse_onnx_file(&path).unwrap();
+
+        let a = &artifact.tensors["a"];
+        assert_eq!(a.byte_length, 64);
+        assert_eq!(a.location.as_ref().unwrap().path, "model.onnx.data");
+        let model_size = std::fs::metadata(&path).unwrap().len();
+        assert_eq!(a.data_offset, Some(model_size));
+        assert_eq!(artifact.tensors["b"].data_offset, None);
+        assert_eq!(
+            data_paths(&artifact, &path),
+            [path.clone(), dir.path().join("model.onnx.data")]
+        );
+        let codes: Vec<_> = artifact
+            .findings
+            .iter()
+            .map(|f| (f.subject.as_deref().unwrap(), f.code))
+            .collect();
+        assert_eq!(
+            codes,
+            [
+                ("b", "external_data_out_of_bounds"),
+                ("c", "missing_external_data"),
+                ("d", "external_data_outside_model"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,300 @@
+use super::onnx_proto::{
+    attribute_proto::AttributeType,
+    tensor_shape_proto::{dimension, Dimension},
+    type_proto, AttributeProto, FunctionProto, GraphProto, NodeProto, OperatorSetIdProto,
+    TypeProto, ValueInfoProto,
+};
+use super::{onnx_dtype_str, sparse_tensor, tensor};
+use crate::graph::{Attribute, Dim, Graph, Node, ValueInfo};
+use std::collections::BTreeMap;
+
+/// Convert a graph, including the graphs nested in its node attributes.
+pub(super) fn graph(proto: &GraphProto) -> Graph {
+    Graph {
+        name: proto.name.clone().unwrap_or_default(),
+        opset_imports: BTreeMap::new(),
+        inputs: proto.input.iter().map(value_info).collect(),
+        outputs: proto.output.iter().map(value_info).collect(),
+        nodes: proto.node.iter().map(node).collect(),
+        value_info: proto.value_info.iter().map(value_info).collect(),
+        functions: BTreeMap::new(),
+    }
+}
+
+/// Convert a model-local function, returning it with its
+/// [`function_key`].
+pub(super) fn function(proto: &FunctionProto) -> (String, Graph) {
+    let name = proto.name.clone().unwrap_or_default();
+    let key = function_key(proto);
+    let untyped = |names: &[String]| {
+        names
+            .iter()
+            .map(|name| ValueInfo {
+                name: name.clone(),
+                elem_type: None,
+                shape: None,
+            })
+            .collect()
+    };
+    let function = Graph {
+        name,
+        opset_imports: opset_imports(&proto.opset_import),
+        inputs: untyped(&proto.input),
+        outputs: untyped(&proto.output),
+        nodes: proto.node.iter().map(node).collect(),
+        value_info: proto.value_info.iter().map(value_info).collect(),
+        functions: BTreeMap::new(),
+    };
+    (key, function)
+}
+
+/// The key of a function: `domain::name`, or `domain::name:overload`.
+pub(super) fn function_key(proto: &FunctionProto) -> String {
+    let key = format!(
+        "{}::{}",
+        proto.domain.as_deref().unwrap_or_default(),
+        proto.name.as_deref().unwrap_or_default()
+    );
+    match proto.overload.as_deref() {
+        Some(overload) if !overload.is_empty() => format!("{}:{}", key, overload),
+        _ => key,
+    }
+}
+
+/// Operator set versions by domain, with `ai.onnx` for the default domain.
+pub(super) fn opset_imports(imports: &[OperatorSetIdProto]) -> BTreeMap<String, i64> {
+    imports
+        .iter()
+        .filter_map(|op| {
+            let domain = match op.domain.as_deref() {
+                None | Some("") => "ai.onnx",
+                Some(domain) => domain,
+            };
+            Some((domain.to_string(), op.version?))
+        })
+        .collect()
+}
+
+fn node(proto: &NodeProto) -> Node {
+    Node {
+        name: proto.name.clone().unwrap_or_default(),
+        op_type: proto.op_type.clone().unwrap_or_default(),
+        domain: proto.domain.clone().unwrap_or_default(),
+        inputs: proto.input.clone(),
+        outputs: proto.output.clone(),
+        attributes: proto
+            .attribute
+            .iter()
+            .filter_map(|a| Some((a.name.clone()?, attribute(a)?)))
+            .collect(),
+    }
+}
+
+/// Convert an attribute, or `None` for a kind this crate does not describe.
+fn attribute(proto: &AttributeProto) -> Option<Attribute> {
+    let string = |s: &[u8]| String::from_utf8_lossy(s).into_owned();
+    // Attribute tensors are described, not located: drop external data.
+    // Attribute tensors that are also artifact tensors (function constants)
+    // report their findings there.
+    let describe = |t| {
+        let mut tensor = tensor(t, &mut Vec::new());
+        tensor.location = None;
+        tensor
+    };
+    let describe_sparse = |t| sparse_tensor(t, &mut Vec::new());
+    Some(match proto.r#type() {
+        AttributeType::Float => Attribute::Float(proto.f?),
+        AttributeType::Int => Attribute::Int(proto.i?),
+        AttributeType::String => Attribute::String(string(proto.s.as_deref()?)),
+        AttributeType::Tensor => Attribute::Tensor(describe(proto.t.as_ref()?)),
+        AttributeType::Graph => Attribute::Graph(Box::new(graph(proto.g.as_ref()?))),
+        AttributeType::TypeProto => Attribute::Type(type_str(proto.tp.as_ref()?)),
+        AttributeType::Floats => Attribute::Floats(proto.floats.clone()),
+        AttributeType::Ints => Attribute::Ints(proto.ints.clone()),
+        AttributeType::Strings => {
+            Attribute::Strings(proto.strings.iter().map(|s| string(s)).collect())
+        }
+        AttributeType::Tensors => Attribute::Tensors(proto.tensors.iter().map(describe).collect()),
+        AttributeType::Graphs => Attribute::Graphs(proto.graphs.iter().map(graph).collect()),
+        AttributeType::TypeProtos => {
+            Attribute::Types(proto.type_protos.iter().map(type_str).collect())
+        }
+        AttributeType::SparseTensor => {
+            Attribute::SparseTensor(describe_sparse(proto.sparse_tensor.as_ref()?)?)
+        }
+        AttributeType::SparseTensors => Attribute::SparseTensors(
+            proto
+                .sparse_tensors
+                .iter()
+                .filter_map(describe_sparse)
+                .collect(),
+        ),
+        AttributeType::Undefined => return None,
+    })
+}
+
+fn value_info(proto: &ValueInfoProto) -> ValueInfo {
+    let (elem_type, shape) = match proto.r#type.as_ref().and_then(|t| t.value.as_ref()) {
+        Some(type_proto::Value::TensorType(t)) => (
+            t.elem_type.map(onnx_dtype_str),
+            t.shape.as_ref().map(|s| s.dim.iter().map(dim).collect()),
+        ),
+        Some(_) => (proto.r#type.as_ref().map(type_str), None),
+        None => (None, None),
+    };
+    ValueInfo {
+        name: proto.name.clone().unwrap_or_default(),
+        elem_type,
+        shape,
+    }
+}
+
+fn dim(proto: &Dimension) -> Dim {
+    match &proto.value {
+        Some(dimension::Value::DimValue(v)) if *v >= 0 => Dim::Fixed(*v as u64),
+        Some(dimension::Value::DimParam(p)) if !p.is_empty() => Dim::Symbolic(p.clone()),
+        _ => Dim::Unknown,
+    }
+}
+
+/// Format a type as an expression such as `tensor(float32)` or
+/// `map(int64,seq(tensor(float32)))`.
+fn type_str(proto: &TypeProto) -> String {
+    let elem = |t: &Option<i32>| t.map_or("?".to_string(), onnx_dtype_str);
+    let inner = |t: &Option<Box<TypeProto>>| t.as_deref().map_or("?".to_string(), type_str);
+    match &proto.value {
+        Some(type_proto::Value::TensorType(t)) => format!("tensor({})", elem(&t.elem_type)),
+        Some(type_proto::Value::SparseTensorType(t)) => {
+            format!("sparse_tensor({})", elem(&t.elem_type))
+        }
+        Some(type_proto::Value::SequenceType(s)) => format!("seq({})", inner(&s.elem_type)),
+        Some(type_proto::Value::MapType(m)) => {
+            format!("map({},{})", elem(&m.key_type), inner(&m.value_type))
+        }
+        Some(type_proto::Value::OptionalType(o)) => format!("optional({})", inner(&o.elem_type)),
+        None => "?".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::onnx_proto::TensorShapeProto;
+    use super::*;
+
+    fn dims(values: &[dimension::Value]) -> TensorShapeProto {
+        TensorShapeProto {
+            dim: values
+                .iter()
+                .map(|v| Dimension {
+                    value: Some(v.clone()),
+                    ..Default::default()
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_value_info_and_attributes() {
+        let input = ValueInfoProto {
+            name: Some("x".to_string()),
+            r#type: Some(TypeProto {
+                value: Some(type_proto::Value::TensorType(type_proto::Tensor {
+                    elem_type: Some(1),
+                    shape: Some(dims(&[
+                        dimension::Value::DimParam("batch".to_string()),
+                        dimension::Value::DimValue(3),
+                    ])),
+                })),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert_eq!(
+            value_info(&input),
+            ValueInfo {
+                name: "x".to_string(),
+                elem_type: Some("float32".to_string()),
+                shape: Some(vec![Dim::Symbolic("batch".to_string()), Dim::Fixed(3)]),
+            }
+        );
+
+        let sequence = TypeProto {
+            value: Some(type_proto::Value::SequenceType(Box::new(
+                type_proto::Sequence {
+                    elem_type: input.r#type.clone().map(Box::new),
+                },
+            ))),
+            ..Default::default()
+        };
+        assert_eq!(type_str(&sequence), "seq(tensor(float32))");
+
+        let proto = NodeProto {
+            op_type: Some("If".to_string()),
+            input: vec!["cond".to_string()],
+            output: vec!["y".to_string()],
+            attribute: vec![
+                AttributeProto {
+                    name: Some("then_branch".to_string()),
+                    r#type: Some(AttributeType::Graph as i32),
+                    g: Some(GraphProto {
+                        node: vec![NodeProto {
+                            op_type: Some("Relu".to_string()),
+                            ..Default::default()
+                        }],
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                },
+                AttributeProto {
+                    name: Some("perm".to_string()),
+                    r#type: Some(AttributeType::Ints as i32),
+                    ints: vec![0, 2, 1],
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        let node = node(&proto);
+        assert_eq!(node.attributes["perm"], Attribute::Ints(vec![0, 2, 1]));
+        let Attribute::Graph(branch) = &node.attributes["then_branch"] else {
+            panic!("expected a graph attribute");
+        };
+        assert_eq!(branch.nodes[0].op_type, "Relu");
+    }
+
+    #[test]
+    fn test_function() {
+        let proto = FunctionProto {
+            name: Some("Act".to_string()),
+            domain: Some("custom".to_string()),
+            input: vec!["x".to_string()],
+            output: vec!["y".to_string()],
+            opset_import: vec![
+                OperatorSetIdProto {
+                    domain: Some(String::new()),
+                    version: Some(17),
+                },
+                OperatorSetIdProto {
+                    domain: Some("com.microsoft".to_string()),
+                    version: Some(1),
+                },
+            ],
+            node: vec![NodeProto {
+                op_type: Some("Relu".to_string()),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let (key, function) = function(&proto);
+        assert_eq!(key, "custom::Act");
+        assert_eq!(
+            function.opset_imports,
+            BTreeMap::from([
+                ("ai.onnx".to_string(), 17),
+                ("com.microsoft".to_string(), 1)
+            ])
+        );
+        assert_eq!(function.inputs[0].name, "x");
+        assert_eq!(function.nodes[0].op_type, "Relu");
+    }
+}
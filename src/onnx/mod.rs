@@ -1,22 +1,34 @@
 #![cfg(feature = "onnx")]
 
-use crate::types::{Artifact, CanonicalValue, Format, Tensor};
+use crate::types::{
+    Artifact, CanonicalValue, DataLocation, Finding, Format, ParseLimits, Severity, Tensor,
+};
+use bytes::Bytes;
 use prost::Message;
 use std::collections::BTreeMap;
 use std::io::{Read, Seek};
 use thiserror::Error;
 
+mod external;
+mod graph;
 mod onnx_proto {
     include!(concat!(env!("OUT_DIR"), "/onnx-proto/onnx.rs"));
 }
 
-use onnx_proto::ModelProto;
+pub use external::{check_external_data, data_paths, parse_onnx_file, parse_onnx_file_with_limits};
+use onnx_proto::{tensor_proto, GraphProto, ModelProto, NodeProto, SparseTensorProto, TensorProto};
 
 /// Error types for ONNX parsing.
 #[derive(Error, Debug)]
 pub enum OnnxParserError {
     #[error("failed to parse ONNX: {0}")]
     ParseError(String),
+    #[error("tensor count {count} exceeds max_tensor_count ({max}); needs at least {count}")]
+    TensorCountTooLarge { count: u64, max: u64 },
+    #[error(
+        "tensor '{name}' dimensions {dims} exceed max_dimensions ({max}); needs at least {dims}"
+    )]
+    DimensionsTooLarge { name: String, dims: u32, max: u32 },
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
 }
@@ -25,6 +37,38 @@ pub enum OnnxParserError {
 ///
 /// Requires the `onnx` feature to be enabled.
 ///
+/// The initializers become the artifact's tensors and the rest of the model
+/// graph its [`graph`](Artifact::graph): nodes with their typed attributes,
+/// and the types and shapes of inputs, outputs and `value_info`.
+///
+/// Tensors outside the main graph get hierarchical names:
+/// - Initializers of the graphs held by node attributes (the bodies of
+///   `If`, `Loop` and `Scan`) are named `<node>/<attribute>/<name>`, where
+///   `<node>` is the node's name or, without one, its first output.
+/// - Tensor attributes of the nodes of those bodies and of model-local
+///   functions, such as the value of a `Constant`, are named
+///   `<node>/<attribute>/<body node>/<attribute>` and
+///   `<domain>::<function>/<node>/<attribute>`. Functions have no
+///   initializers. Tensor attributes of the main graph's own nodes are left
+///   out: exporters store the main graph's weights as initializers.
+/// - Sparse initializers keep their name; their shape is the dense shape and
+///   [`nnz`](Tensor::nnz) the number of stored values.
+///
+/// Dense tensors stored in `raw_data` get the file offset of that data.
+/// Tensors stored in the typed fields (`float_data`, ...) and sparse
+/// initializers have no data offset.
+///
+/// Initializers stored as external data get a [`DataLocation`]; their byte
+/// length is the declared `length`, if any. The external files are not
+/// opened here, since only the model's path says where they are: use
+/// [`parse_onnx_file`] to check them and give them a data offset as well.
+///
+/// Findings:
+/// - `invalid_external_data`: an external data entry has no `location`, or
+///   an `offset` or `length` that is not a number.
+/// - `invalid_sparse_tensor`: a sparse initializer has no values. The subject
+///   is its indices tensor's name, or `sparse_initializer[<index>]`.
+///
 /// # Example
 ///
 /// ```
@@ -34,16 +78,29 @@ pub enum OnnxParserError {
 /// let mut cursor = std::io::Cursor::new(data);
 /// let artifact = onnx::parse_onnx(&mut cursor).unwrap();
 /// assert_eq!(artifact.format, weight_inspect::types::Format::Onnx);
+/// assert!(!artifact.graph.unwrap().nodes.is_empty());
 /// ```
 pub fn parse_onnx<R: Read + Seek>(reader: &mut R) -> Result<Artifact, OnnxParserError> {
+    parse_onnx_with_limits(reader, &ParseLimits::default())
+}
+
+/// Parse an ONNX model file, enforcing the given safety limits.
+pub fn parse_onnx_with_limits<R: Read + Seek>(
+    reader: &mut R,
+    limits: &ParseLimits,
+) -> Result<Artifact, OnnxParserError> {
     let mut bytes = Vec::new();
     reader.read_to_end(&mut bytes)?;
+    // Decoding from `Bytes` leaves each `raw_data` a slice of `bytes`.
+    let bytes = Bytes::from(bytes);
 
-    let model =
-        ModelProto::decode(&*bytes).map_err(|e| OnnxParserError::ParseError(e.to_string()))?;
+    let model = ModelProto::decode(bytes.clone())
+        .map_err(|e| OnnxParserError::ParseError(e.to_string()))?;
 
     let mut metadata = BTreeMap::new();
     let mut tensors = BTreeMap::new();
+    let mut findings = Vec::new();
+    let mut onnx_graph = None;
 
     if let Some(ir_version) = model.ir_version {
         metadata.insert("ir_version".to_string(), CanonicalValue::Int(ir_version));
@@ -71,76 +128,37 @@ pub fn parse_onnx<R: Read + Seek>(reader: &mut R) -> Result<Artifact, OnnxParser
         );
     }
 
-    if !model.opset_import.is_empty() {
-        let versions: Vec<String> = model
-            .opset_import
-            .iter()
-            .filter_map(|op| op.version.map(|v| v.to_string()))
-            .collect();
-        metadata.insert(
-            "opset_imports".to_string(),
-            CanonicalValue::String(format!("{:?}", versions)),
-        );
-    }
-
     if let Some(graph) = model.graph {
+        if graph.initializer.len() as u64 > limits.max_tensor_count {
+            return Err(OnnxParserError::TensorCountTooLarge {
+                count: graph.initializer.len() as u64,
+                max: limits.max_tensor_count,
+            });
+        }
+
         if !graph.node.is_empty() {
-            let mut op_types: Vec<String> = graph
-                .node
-                .iter()
-                .filter_map(|n| n.op_type.clone())
-                .collect();
-            op_types.sort();
-            metadata.insert(
-                "node_types".to_string(),
-                CanonicalValue::String(format!("{:?}", op_types)),
-            );
             metadata.insert(
                 "node_count".to_string(),
                 CanonicalValue::Int(graph.node.len() as i64),
             );
         }
 
-        for init in &graph.initializer {
-            let name = init.name.clone().unwrap_or_default();
-            let dims: Vec<u64> = init.dims.iter().map(|&x| x as u64).collect();
-            let dtype = onnx_dtype_str(init.data_type());
-            let mut element_count: u64 = 1;
-            for &dim in &dims {
-                element_count = element_count.checked_mul(dim).unwrap_or(0);
-            }
-            let byte_length: u64 = element_count
-                .checked_mul(dtype_size(init.data_type()) as u64)
-                .unwrap_or(0);
-
-            tensors.insert(
-                name.clone(),
-                Tensor {
-                    name,
-                    dtype,
-                    shape: dims,
-                    byte_length,
-                },
-            );
+        let mut initializers = Initializers {
+            model: &bytes,
+            tensors: &mut tensors,
+            findings: &mut findings,
+            limits,
+        };
+        initializers.add_graph(&graph, "", false)?;
+        for function in &model.functions {
+            let prefix = format!("{}/", graph::function_key(function));
+            initializers.add_nodes(&function.node, &prefix, true)?;
         }
 
-        if !graph.input.is_empty() {
-            let input_names: Vec<String> =
-                graph.input.iter().filter_map(|i| i.name.clone()).collect();
-            metadata.insert(
-                "input_names".to_string(),
-                CanonicalValue::String(format!("{:?}", input_names)),
-            );
-        }
-
-        if !graph.output.is_empty() {
-            let output_names: Vec<String> =
-                graph.output.iter().filter_map(|o| o.name.clone()).collect();
-            metadata.insert(
-                "output_names".to_string(),
-                CanonicalValue::String(format!("{:?}", output_names)),
-            );
-        }
+        let mut main_graph = graph::graph(&graph);
+        main_graph.opset_imports = graph::opset_imports(&model.opset_import);
+        main_graph.functions = model.functions.iter().map(graph::function).collect();
+        onnx_graph = Some(main_graph);
     }
 
     let ir_version = metadata
@@ -156,6 +174,247 @@ pub fn parse_onnx<R: Read + Seek>(reader: &mut R) -> Result<Artifact, OnnxParser
         gguf_version: Some(ir_version),
         metadata,
         tensors,
+        graph: onnx_graph,
+        data_offset: None,
+        findings,
+    })
+}
+
+/// Collects the tensors of a model: the initializers of its graph and of
+/// the graphs nested in node attributes, and the constants of those nested
+/// graphs and of its functions.
+struct Initializers<'a> {
+    /// The model file, which `raw_data` fields are slices of.
+    model: &'a [u8],
+    tensors: &'a mut BTreeMap<String, Tensor>,
+    findings: &'a mut Vec<Finding>,
+    limits: &'a ParseLimits,
+}
+
+impl Initializers<'_> {
+    /// Add a graph's dense and sparse initializers, named `prefix` followed
+    /// by the initializer name, and those of its nested graphs. With
+    /// `constants`, also add the tensors held by the graph's node attributes.
+    fn add_graph(
+        &mut self,
+        graph: &GraphProto,
+        prefix: &str,
+        constants: bool,
+    ) -> Result<(), OnnxParserError> {
+        for init in &graph.initializer {
+            self.check_dims(init.name.as_deref(), init.dims.len())?;
+            let tensor = self.dense(init);
+            self.insert(format!("{}{}", prefix, tensor.name), tensor)?;
+        }
+        for (index, sparse) in graph.sparse_initializer.iter().enumerate() {
+            let name = sparse.values.as_ref().and_then(|v| v.name.as_deref());
+            self.check_dims(name, sparse.dims.len())?;
+            match sparse_tensor(sparse, self.findings) {
+                Some(tensor) => self.insert(format!("{}{}", prefix, tensor.name), tensor)?,
+                None => {
+                    // Without values the indices name is the only name left.
+                    let subject = match sparse.indices.as_ref().and_then(|i| i.name.as_deref()) {
+                        Some(indices) => format!("{}{}", prefix, indices),
+                        None => format!("{}sparse_initializer[{}]", prefix, index),
+                    };
+                    self.findings.push(Finding::new(
+                        Severity::Error,
+                        "invalid_sparse_tensor",
+                        Some(&subject),
+                        "sparse initializer has no values; it was skipped".to_string(),
+                    ))
+                }
+            }
+        }
+        self.add_nodes(&graph.node, prefix, constants)
+    }
+
+    /// Add the initializers and constants of the graphs held by node
+    /// attributes, under `prefix` + `<node>/<attribute>/`, and with
+    /// `constants` the tensors held by the attributes themselves, as
+    /// `prefix` + `<node>/<attribute>`.
+    fn add_nodes(
+        &mut self,
+        nodes: &[NodeProto],
+        prefix: &str,
+        constants: bool,
+    ) -> Result<(), OnnxParserError> {
+        for node in nodes {
+            let key = match (node.name.as_deref(), node.output.first()) {
+                (None | Some(""), Some(output)) => output.as_str(),
+                (name, _) => name.unwrap_or_default(),
+            };
+            for attribute in &node.attribute {
+                let scope = format!(
+                    "{}{}/{}",
+                    prefix,
+                    key,
+                    attribute.name.as_deref().unwrap_or_default()
+                );
+                if let Some(body) = &attribute.g {
+                    self.add_graph(body, &format!("{}/", scope), true)?;
+                }
+                for (i, body) in attribute.graphs.iter().enumerate() {
+                    self.add_graph(body, &format!("{}/{}/", scope, i), true)?;
+                }
+                if !constants {
+                    continue;
+                }
+                if let Some(t) = &attribute.t {
+                    self.check_dims(Some(&scope), t.dims.len())?;
+                    let tensor = self.dense(t);
+                    self.insert(scope.clone(), tensor)?;
+                }
+                for (i, t) in attribute.tensors.iter().enumerate() {
+                    self.check_dims(Some(&scope), t.dims.len())?;
+                    let tensor = self.dense(t);
+                    self.insert(format!("{}/{}", scope, i), tensor)?;
+                }
+                let sparse = attribute.sparse_tensor.iter().map(|t| (scope.clone(), t));
+                let sparse_list = (attribute.sparse_tensors.iter().enumerate())
+                    .map(|(i, t)| (format!("{}/{}", scope, i), t));
+                for (name, t) in sparse.chain(sparse_list) {
+                    self.check_dims(Some(&name), t.dims.len())?;
+                    if let Some(tensor) = sparse_tensor(t, self.findings) {
+                        self.insert(name, tensor)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Describe a dense tensor, with the file offset of its `raw_data` when
+    /// that holds exactly the tensor's bytes.
+    fn dense(&mut self, init: &TensorProto) -> Tensor {
+        let mut tensor = tensor(init, self.findings);
+        tensor.data_offset = init
+            .raw_data
+            .as_deref()
+            .filter(|raw| !raw.is_empty() && raw.len() as u64 == tensor.byte_length)
+            .and_then(|raw| {
+                let offset = (raw.as_ptr() as usize).checked_sub(self.model.as_ptr() as usize)?;
+                (offset + raw.len() <= self.model.len()).then_some(offset as u64)
+            });
+        tensor
+    }
+
+    fn check_dims(&self, name: Option<&str>, dims: usize) -> Result<(), OnnxParserError> {
+        if dims > self.limits.max_dimensions as usize {
+            return Err(OnnxParserError::DimensionsTooLarge {
+                name: name.unwrap_or_default().to_string(),
+                dims: dims as u32,
+                max: self.limits.max_dimensions,
+            });
+        }
+        Ok(())
+    }
+
+    fn insert(&mut self, name: String, mut tensor: Tensor) -> Result<(), OnnxParserError> {
+        if self.tensors.contains_key(&name) {
+            self.findings.push(Finding::new(
+                Severity::Error,
+                "duplicate_tensor",
+                Some(&name),
+                "initializer name appears more than once; the last entry is used".to_string(),
+            ));
+        }
+        tensor.name = name.clone();
+        self.tensors.insert(name, tensor);
+        if self.tensors.len() as u64 > self.limits.max_tensor_count {
+            return Err(OnnxParserError::TensorCountTooLarge {
+                count: self.tensors.len() as u64,
+                max: self.limits.max_tensor_count,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Describe a sparse tensor by the dtype and count of its stored values and
+/// its dense shape, or `None` when it has no values.
+///
+/// Its byte length covers the stored values and indices. Sparse tensors
+/// stored as external data have no location.
+fn sparse_tensor(sparse: &SparseTensorProto, findings: &mut Vec<Finding>) -> Option<Tensor> {
+    let mut tensor = tensor(sparse.values.as_ref()?, findings);
+    let indices = sparse
+        .indices
+        .as_ref()
+        .map_or(0, |indices| self::tensor(indices, findings).byte_length);
+    tensor.nnz = Some(tensor.shape.first().copied().unwrap_or(0));
+    tensor.shape = sparse.dims.iter().map(|&x| x as u64).collect();
+    tensor.byte_length = tensor.byte_length.saturating_add(indices);
+    tensor.location = None;
+    Some(tensor)
+}
+
+/// Describe a tensor by its dtype, shape and byte length, and where its
+/// data is stored when that is outside the model.
+fn tensor(init: &TensorProto, findings: &mut Vec<Finding>) -> Tensor {
+    let name = init.name.clone().unwrap_or_default();
+    let shape: Vec<u64> = init.dims.iter().map(|&x| x as u64).collect();
+    let mut element_count: u64 = 1;
+    for &dim in &shape {
+        element_count = element_count.checked_mul(dim).unwrap_or(0);
+    }
+    let mut byte_length: u64 = element_count
+        .checked_mul(dtype_size(init.data_type()) as u64)
+        .unwrap_or(0);
+    let mut location = None;
+    if init.data_location == Some(tensor_proto::DataLocation::External as i32) {
+        match external_location(init) {
+            Ok(external) => {
+                byte_length = external.length.unwrap_or(byte_length);
+                location = Some(external);
+            }
+            Err(reason) => findings.push(Finding::new(
+                Severity::Error,
+                "invalid_external_data",
+                Some(&name),
+                reason,
+            )),
+        }
+    }
+    Tensor {
+        name,
+        dtype: onnx_dtype_str(init.data_type()),
+        shape,
+        byte_length,
+        data_offset: None,
+        digest: None,
+        location,
+        nnz: None,
+        big_endian: false,
+    }
+}
+
+/// Read the `location`, `offset` and `length` entries of an initializer
+/// stored as external data.
+fn external_location(init: &TensorProto) -> Result<DataLocation, String> {
+    let mut path = None;
+    let mut offset = 0;
+    let mut length = None;
+    for entry in &init.external_data {
+        let key = entry.key.as_deref().unwrap_or_default();
+        let value = entry.value.as_deref().unwrap_or_default();
+        let number = || {
+            value
+                .parse::<u64>()
+                .map_err(|_| format!("external data {} '{}' is not a number", key, value))
+        };
+        match key {
+            "location" => path = Some(value.to_string()),
+            "offset" => offset = number()?,
+            "length" => length = Some(number()?),
+            _ => {}
+        }
+    }
+    let path = path.ok_or("stored as external data but has no location")?;
+    Ok(DataLocation {
+        path,
+        offset,
+        length,
     })
 }
 
@@ -214,6 +473,162 @@ mod tests {
         assert_eq!(onnx_dtype_str(10), "float16");
     }
 
+    #[test]
+    fn test_external_location() {
+        let entry = |key: &str, value: &str| onnx_proto::StringStringEntryProto {
+            key: Some(key.to_string()),
+            value: Some(value.to_string()),
+        };
+        let mut init = TensorProto {
+            external_data: vec![
+                entry("location", "model.onnx.data"),
+                entry("offset", "4096"),
+                entry("length", "64"),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(
+            external_location(&init),
+            Ok(DataLocation {
+                path: "model.onnx.data".to_string(),
+                offset: 4096,
+                length: Some(64),
+            })
+        );
+
+        init.external_data[1] = entry("offset", "-1");
+        assert!(external_location(&init).unwrap_err().contains("offset"));
+        init.external_data.remove(0);
+        assert!(external_location(&init).is_err());
+    }
+
+    #[test]
+    fn test_raw_data_offset() {
+        let raw: Vec<u8> = (1..=16).collect();
+        let model = ModelProto {
+            graph: Some(GraphProto {
+                initializer: vec![
+                    TensorProto {
+                        name: Some("raw".to_string()),
+                        dims: vec![4],
+                        data_type: Some(1),
+                        raw_data: Some(raw.clone().into()),
+                        ..Default::default()
+                    },
+                    TensorProto {
+                        name: Some("typed".to_string()),
+                        dims: vec![2],
+                        data_type: Some(1),
+                        float_data: vec![1.0, 2.0],
+                        ..Default::default()
+                    },
+                ],
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let bytes = model.encode_to_vec();
+        let artifact = parse_onnx(&mut std::io::Cursor::new(bytes.clone())).unwrap();
+
+        let offset = artifact.tensors["raw"].data_offset.unwrap() as usize;
+        assert_eq!(bytes[offset..offset + 16], raw);
+        assert_eq!(artifact.tensors["typed"].data_offset, None);
+    }
+
+    #[test]
+    fn test_nested_tensors() {
+        use onnx_proto::{attribute_proto::AttributeType, AttributeProto, FunctionProto};
+
+        let dense = |name: &str, dims: Vec<i64>| TensorProto {
+            name: Some(name.to_string()),
+            dims,
+            data_type: Some(1),
+            ..Default::default()
+        };
+        let attribute = |name: &str, kind: AttributeType| AttributeProto {
+            name: Some(name.to_string()),
+            r#type: Some(kind as i32),
+            ..Default::default()
+        };
+        let constant = |output: &str| NodeProto {
+            output: vec![output.to_string()],
+            op_type: Some("Constant".to_string()),
+            attribute: vec![AttributeProto {
+                t: Some(dense("", vec![1])),
+                ..attribute("value", AttributeType::Tensor)
+            }],
+            ..Default::default()
+        };
+        let branch = GraphProto {
+            initializer: vec![dense("w", vec![4])],
+            node: vec![constant("c")],
+            ..Default::default()
+        };
+        let model = ModelProto {
+            graph: Some(GraphProto {
+                initializer: vec![dense("w", vec![2, 2])],
+                sparse_initializer: vec![
+                    SparseTensorProto {
+                        values: Some(dense("emb", vec![3])),
+                        indices: Some(TensorProto {
+                            dims: vec![3],
+                            data_type: Some(7),
+                            ..Default::default()
+                        }),
+                        dims: vec![100, 8],
+                    },
+                    SparseTensorProto {
+                        dims: vec![4],
+                        ..Default::default()
+                    },
+                ],
+                node: vec![
+                    NodeProto {
+                        name: Some("if".to_string()),
+                        op_type: Some("If".to_string()),
+                        attribute: vec![AttributeProto {
+                            g: Some(branch),
+                            ..attribute("then_branch", AttributeType::Graph)
+                        }],
+                        ..Default::default()
+                    },
+                    constant("main_constant"),
+                ],
+                ..Default::default()
+            }),
+            functions: vec![FunctionProto {
+                name: Some("Scale".to_string()),
+                domain: Some("custom".to_string()),
+                node: vec![constant("k")],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let artifact = parse_onnx(&mut std::io::Cursor::new(model.encode_to_vec())).unwrap();
+
+        let names: Vec<_> = artifact.tensors.keys().map(String::as_str).collect();
+        assert_eq!(
+            names,
+            [
+                "custom::Scale/k/value",
+                "emb",
+                "if/then_branch/c/value",
+                "if/then_branch/w",
+                "w"
+            ]
+        );
+        let emb = &artifact.tensors["emb"];
+        assert_eq!(emb.shape, [100, 8]);
+        assert_eq!(emb.nnz, Some(3));
+        assert_eq!(emb.byte_length, 3 * 4 + 3 * 8);
+        assert_eq!(artifact.tensors["if/then_branch/w"].shape, [4]);
+        let [finding] = &artifact.findings[..] else {
+            panic!("expected one finding: {:?}", artifact.findings);
+        };
+        assert_eq!(finding.code, "invalid_sparse_tensor");
+        assert_eq!(finding.subject.as_deref(), Some("sparse_initializer[1]"));
+    }
+
     #[test]
     fn test_dtype_size() {
         assert_eq!(dtype_size(1), 4);
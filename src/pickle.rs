@@ -0,0 +1,1089 @@
+use std::collections::HashMap;
+use std::fmt;
+use thiserror::Error;
+
+/// Maximum nesting of containers and objects in a pickle.
+const MAX_DEPTH: usize = 64;
+/// Maximum number of values built, counting each copy taken from the memo.
+const MAX_VALUES: usize = 1 << 24;
+
+#[derive(Error, Debug)]
+pub enum PickleError {
+    #[error("unexpected end of pickle data")]
+    UnexpectedEof,
+    #[error("unsupported pickle opcode 0x{opcode:02x} at byte {position}")]
+    UnsupportedOpcode { opcode: u8, position: usize },
+    #[error("malformed pickle at byte {position}: {reason}")]
+    Malformed { position: usize, reason: String },
+    #[error("pickle nesting exceeds maximum depth ({max})")]
+    TooDeep { max: usize },
+    #[error("pickle builds more than {max} values")]
+    TooLarge { max: usize },
+}
+
+/// A value rebuilt from a pickle without running any of its code.
+///
+/// Calls (`REDUCE`, `NEWOBJ`, `OBJ`, `INST`) are never made; they become an
+/// [`Value::Object`] recording what would have been called with what. The
+/// only exception is `collections.OrderedDict` and `builtins.dict`, which
+/// become a plain [`Value::Dict`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    None,
+    Bool(bool),
+    Int(i64),
+    /// An integer too large for `i64`, as little-endian two's complement.
+    BigInt(Vec<u8>),
+    Float(f64),
+    String(String),
+    Bytes(Vec<u8>),
+    Tuple(Vec<Value>),
+    List(Vec<Value>),
+    Dict(Vec<(Value, Value)>),
+    Set(Vec<Value>),
+    /// A global imported by `GLOBAL`, `STACK_GLOBAL` or `INST`.
+    Global {
+        module: String,
+        name: String,
+    },
+    /// The would-be result of calling `callable` with `args`, with the
+    /// state passed to `BUILD` and any items set on it.
+    Object {
+        callable: Box<Value>,
+        args: Vec<Value>,
+        state: Option<Box<Value>>,
+        items: Vec<(Value, Value)>,
+    },
+    /// A persistent id, resolved by the loader rather than by pickle.
+    Persistent(Box<Value>),
+}
+
+impl Value {
+    /// The string, if this is one.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// The integer, if this is one that fits in `i64`.
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            Value::Int(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    /// The `(module, name)` of a global.
+    pub fn as_global(&self) -> Option<(&str, &str)> {
+        match self {
+            Value::Global { module, name } => Some((module, name)),
+            _ => None,
+        }
+    }
+
+    /// Items of a tuple or list.
+    pub fn as_seq(&self) -> Option<&[Value]> {
+        match self {
+            Value::Tuple(items) | Value::List(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    /// Number of values this one is made of, itself included.
+    fn size(&self) -> usize {
+        1 + match self {
+            Value::Tuple(items) | Value::List(items) | Value::Set(items) => {
+                items.iter().map(Value::size).sum()
+            }
+            Value::Dict(pairs) => pairs.iter().map(|(k, v)| k.size() + v.size()).sum(),
+            Value::Object {
+                callable,
+                args,
+                state,
+                items,
+            } => {
+                callable.size()
+                    + args.iter().map(Value::size).sum::<usize>()
+                    + state.as_ref().map_or(0, |s| s.size())
+                    + items
+                        .iter()
+                        .map(|(k, v)| k.size() + v.size())
+                        .sum::<usize>()
+            }
+            Value::Persistent(id) => id.size(),
+            _ => 0,
+        }
+    }
+
+    /// Nesting depth; 0 for values that contain no others.
+    fn depth(&self) -> usize {
+        let max = |values: &mut dyn Iterator<Item = &Value>| {
+            values.map(|v| v.depth() + 1).max().unwrap_or(0)
+        };
+        match self {
+            Value::Tuple(items) | Value::List(items) | Value::Set(items) => max(&mut items.iter()),
+            Value::Dict(pairs) => max(&mut pairs.iter().flat_map(|(k, v)| [k, v])),
+            Value::Object {
+                callable,
+                args,
+                state,
+                items,
+            } => max(&mut std::iter::once(callable.as_ref())
+                .chain(args)
+                .chain(state.as_deref())
+                .chain(items.iter().flat_map(|(k, v)| [k, v]))),
+            Value::Persistent(id) => id.depth() + 1,
+            _ => 0,
+        }
+    }
+}
+
+/// A global a pickle imports, and the byte offset of the opcode doing so.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GlobalRef {
+    pub module: String,
+    pub name: String,
+    pub position: usize,
+}
+
+/// A loaded pickle: the value it builds and every global it imports.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pickle {
+    pub value: Value,
+    pub globals: Vec<GlobalRef>,
+}
+
+/// Rebuild the value a pickle describes, without importing or calling
+/// anything.
+///
+/// Every pickle protocol (0 to 5) is understood except out-of-band buffers.
+/// Globals are recorded in [`Pickle::globals`] and otherwise kept as
+/// [`Value::Global`], so the caller decides which ones to trust.
+///
+/// # Example
+///
+/// ```
+/// use weight_inspect::pickle::{self, Value};
+///
+/// // pickle.dumps({"a": 1}, protocol=2)
+/// let data = b"\x80\x02}q\x00X\x01\x00\x00\x00aq\x01K\x01s.";
+/// let loaded = pickle::load(data).unwrap();
+/// assert_eq!(
+///     loaded.value,
+///     Value::Dict(vec![(Value::String("a".into()), Value::Int(1))])
+/// );
+/// assert!(loaded.globals.is_empty());
+/// ```
+pub fn load(data: &[u8]) -> Result<Pickle, PickleError> {
+    let mut machine = Machine::new(data);
+    let value = machine.run()?;
+    Ok(Pickle {
+        value,
+        globals: machine.globals,
+    })
+}
+
+/// Every global a pickle imports, as far as it can be loaded.
+///
+/// Unlike [`load`], a malformed or oversized pickle is not an error: the
+/// globals imported before loading failed are returned along with the
+/// error, so nothing a pickle imports up to that point goes unreported.
+pub fn globals(data: &[u8]) -> (Vec<GlobalRef>, Option<PickleError>) {
+    let mut machine = Machine::new(data);
+    let error = machine.run().err();
+    (machine.globals, error)
+}
+
+/// The argument of an [`Instruction`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Arg {
+    None,
+    Int(i64),
+    /// Little-endian two's complement bytes of `LONG1`/`LONG4`.
+    Long(Vec<u8>),
+    Float(f64),
+    /// A string, or the unparsed line of a text opcode such as `INT`.
+    Text(String),
+    Bytes(Vec<u8>),
+    /// The module and name of `GLOBAL` and `INST`.
+    Global {
+        module: String,
+        name: String,
+    },
+}
+
+impl fmt::Display for Arg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Arg::None => Ok(()),
+            Arg::Int(i) => write!(f, "{}", i),
+            Arg::Long(bytes) => match long(bytes.clone()) {
+                Value::Int(i) => write!(f, "{}", i),
+                _ => write!(f, "<{}-byte integer>", bytes.len()),
+            },
+            Arg::Float(v) => write!(f, "{}", v),
+            Arg::Text(s) if s.chars().count() > 64 => {
+                write!(f, "{:?}...", s.chars().take(64).collect::<String>())
+            }
+            Arg::Text(s) => write!(f, "{:?}", s),
+            Arg::Bytes(bytes) => write!(f, "<{} bytes>", bytes.len()),
+            Arg::Global { module, name } => write!(f, "{} {}", module, name),
+        }
+    }
+}
+
+/// One opcode of a pickle stream and its argument, decoded but not run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Instruction {
+    /// Byte offset of the opcode.
+    pub position: usize,
+    pub opcode: u8,
+    pub arg: Arg,
+}
+
+impl Instruction {
+    /// The opcode's name, as printed by Python's `pickletools.dis`.
+    pub fn name(&self) -> &'static str {
+        opcode_name(self.opcode).unwrap_or("UNKNOWN")
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.arg {
+            Arg::None => write!(f, "{:>8}: {}", self.position, self.name()),
+            _ => write!(f, "{:>8}: {:<16} {}", self.position, self.name(), self.arg),
+        }
+    }
+}
+
+/// The decoded opcodes of a pickle stream.
+#[derive(Debug)]
+pub struct Disassembly {
+    /// Instructions up to and including `STOP`.
+    pub instructions: Vec<Instruction>,
+    /// Number of bytes decoded: the length of the stream if it ended with
+    /// `STOP`, otherwise the offset decoding failed at.
+    pub length: usize,
+    /// Why decoding stopped before reaching `STOP`.
+    pub error: Option<PickleError>,
+}
+
+/// Decode the opcodes of a pickle stream up to its `STOP`, without
+/// running them.
+///
+/// Bytes after `STOP` are left alone; they may hold another stream.
+///
+/// # Example
+///
+/// ```
+/// use weight_inspect::pickle;
+///
+/// let listing = pickle::disassemble(b"cposix\nsystem\n(S'ls'\ntR.trailing");
+/// let names: Vec<_> = listing.instructions.iter().map(|i| i.name()).collect();
+/// assert_eq!(names, ["GLOBAL", "MARK", "STRING", "TUPLE", "REDUCE", "STOP"]);
+/// assert_eq!(listing.length, 24);
+/// assert!(listing.error.is_none());
+/// ```
+pub fn disassemble(data: &[u8]) -> Disassembly {
+    let mut instructions = Vec::new();
+    let mut pos = 0;
+    loop {
+        match decode(data, pos) {
+            Ok((instruction, next)) => {
+                let stop = instruction.opcode == b'.';
+                instructions.push(instruction);
+                pos = next;
+                if stop {
+                    return Disassembly {
+                        instructions,
+                        length: pos,
+                        error: None,
+                    };
+                }
+            }
+            Err(error) => {
+                return Disassembly {
+                    instructions,
+                    length: pos,
+                    error: Some(error),
+                }
+            }
+        }
+    }
+}
+
+fn opcode_name(opcode: u8) -> Option<&'static str> {
+    let name = match opcode {
+        b'(' => "MARK",
+        b'.' => "STOP",
+        b'0' => "POP",
+        b'1' => "POP_MARK",
+        b'2' => "DUP",
+        b'F' => "FLOAT",
+        b'I' => "INT",
+        b'J' => "BININT",
+        b'K' => "BININT1",
+        b'L' => "LONG",
+        b'M' => "BININT2",
+        b'N' => "NONE",
+        b'P' => "PERSID",
+        b'Q' => "BINPERSID",
+        b'R' => "REDUCE",
+        b'S' => "STRING",
+        b'T' => "BINSTRING",
+        b'U' => "SHORT_BINSTRING",
+        b'V' => "UNICODE",
+        b'X' => "BINUNICODE",
+        b'a' => "APPEND",
+        b'b' => "BUILD",
+        b'c' => "GLOBAL",
+        b'd' => "DICT",
+        b'}' => "EMPTY_DICT",
+        b'e' => "APPENDS",
+        b'g' => "GET",
+        b'h' => "BINGET",
+        b'i' => "INST",
+        b'j' => "LONG_BINGET",
+        b'l' => "LIST",
+        b']' => "EMPTY_LIST",
+        b'o' => "OBJ",
+        b'p' => "PUT",
+        b'q' => "BINPUT",
+        b'r' => "LONG_BINPUT",
+        b's' => "SETITEM",
+        b't' => "TUPLE",
+        b')' => "EMPTY_TUPLE",
+        b'u' => "SETITEMS",
+        b'G' => "BINFLOAT",
+        0x80 => "PROTO",
+        0x81 => "NEWOBJ",
+        0x82 => "EXT1",
+        0x83 => "EXT2",
+        0x84 => "EXT4",
+        0x85 => "TUPLE1",
+        0x86 => "TUPLE2",
+        0x87 => "TUPLE3",
+        0x88 => "NEWTRUE",
+        0x89 => "NEWFALSE",
+        0x8a => "LONG1",
+        0x8b => "LONG4",
+        b'B' => "BINBYTES",
+        b'C' => "SHORT_BINBYTES",
+        0x8c => "SHORT_BINUNICODE",
+        0x8d => "BINUNICODE8",
+        0x8e => "BINBYTES8",
+        0x8f => "EMPTY_SET",
+        0x90 => "ADDITEMS",
+        0x91 => "FROZENSET",
+        0x92 => "NEWOBJ_EX",
+        0x93 => "STACK_GLOBAL",
+        0x94 => "MEMOIZE",
+        0x95 => "FRAME",
+        0x96 => "BYTEARRAY8",
+        0x97 => "NEXT_BUFFER",
+        0x98 => "READONLY_BUFFER",
+        _ => return None,
+    };
+    Some(name)
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, n: u64) -> Result<&[u8], PickleError> {
+        let end = usize::try_from(n)
+            .ok()
+            .and_then(|n| self.pos.checked_add(n))
+            .filter(|&end| end <= self.data.len())
+            .ok_or(PickleError::UnexpectedEof)?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, PickleError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, PickleError> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, PickleError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, PickleError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn text(&mut self, n: u64) -> Result<Arg, PickleError> {
+        Ok(Arg::Text(
+            String::from_utf8_lossy(self.take(n)?).into_owned(),
+        ))
+    }
+
+    fn bytes(&mut self, n: u64) -> Result<Vec<u8>, PickleError> {
+        Ok(self.take(n)?.to_vec())
+    }
+
+    /// A newline-terminated argument of the text opcodes.
+    fn line(&mut self) -> Result<String, PickleError> {
+        let rest = &self.data[self.pos..];
+        let end = rest
+            .iter()
+            .position(|&b| b == b'\n')
+            .ok_or(PickleError::UnexpectedEof)?;
+        let line = String::from_utf8_lossy(&rest[..end]).into_owned();
+        self.pos += end + 1;
+        Ok(line)
+    }
+}
+
+/// Decode the instruction at `position`, returning it and the offset of
+/// the next one.
+fn decode(data: &[u8], position: usize) -> Result<(Instruction, usize), PickleError> {
+    let mut r = Reader {
+        data,
+        pos: position,
+    };
+    let opcode = r.u8()?;
+    let arg = match opcode {
+        // PROTO, BININT1, BINPUT, BINGET, EXT1
+        0x80 | b'K' | b'q' | b'h' | 0x82 => Arg::Int(r.u8()? as i64),
+        // BININT2, EXT2
+        b'M' | 0x83 => Arg::Int(r.u16()? as i64),
+        b'J' => Arg::Int(r.u32()? as i32 as i64),
+        // LONG_BINPUT, LONG_BINGET, EXT4
+        b'r' | b'j' | 0x84 => Arg::Int(r.u32()? as i64),
+        // FRAME
+        0x95 => Arg::Int(r.u64()? as i64),
+        // PUT, GET
+        b'p' | b'g' => {
+            let line = r.line()?;
+            Arg::Int(line.trim().parse().map_err(|_| PickleError::Malformed {
+                position,
+                reason: "invalid memo index".to_string(),
+            })?)
+        }
+        // INT, LONG, FLOAT, STRING, UNICODE, PERSID
+        b'I' | b'L' | b'F' | b'S' | b'V' | b'P' => Arg::Text(r.line()?),
+        b'G' => Arg::Float(f64::from_be_bytes(r.take(8)?.try_into().unwrap())),
+        0x8a => {
+            let n = r.u8()? as u64;
+            Arg::Long(r.bytes(n)?)
+        }
+        0x8b => {
+            let n = r.u32()? as u64;
+            Arg::Long(r.bytes(n)?)
+        }
+        // SHORT_BINSTRING, BINSTRING, SHORT_BINUNICODE, BINUNICODE, BINUNICODE8
+        b'U' | 0x8c => {
+            let n = r.u8()? as u64;
+            r.text(n)?
+        }
+        b'T' | b'X' => {
+            let n = r.u32()? as u64;
+            r.text(n)?
+        }
+        0x8d => {
+            let n = r.u64()?;
+            r.text(n)?
+        }
+        // SHORT_BINBYTES, BINBYTES, BINBYTES8, BYTEARRAY8
+        b'C' => {
+            let n = r.u8()? as u64;
+            Arg::Bytes(r.bytes(n)?)
+        }
+        b'B' => {
+            let n = r.u32()? as u64;
+            Arg::Bytes(r.bytes(n)?)
+        }
+        0x8e | 0x96 => {
+            let n = r.u64()?;
+            Arg::Bytes(r.bytes(n)?)
+        }
+        // GLOBAL, INST
+        b'c' | b'i' => {
+            let module = r.line()?;
+            let name = r.line()?;
+            Arg::Global { module, name }
+        }
+        _ if opcode_name(opcode).is_some() => Arg::None,
+        _ => return Err(PickleError::UnsupportedOpcode { opcode, position }),
+    };
+    Ok((
+        Instruction {
+            position,
+            opcode,
+            arg,
+        },
+        r.pos,
+    ))
+}
+
+/// A memoized value. Pickles memoize containers before filling them, so an
+/// entry refers to its stack slot until the value is popped.
+enum Memo {
+    Stack(usize),
+    Value(Value),
+}
+
+struct Machine<'a> {
+    data: &'a [u8],
+    /// Offset of the instruction being run.
+    pos: usize,
+    stack: Vec<Value>,
+    /// Stack lengths at each `MARK`.
+    marks: Vec<usize>,
+    memo: HashMap<u64, Memo>,
+    /// Memo entries still on the stack, as `(position, key)` by position.
+    on_stack: Vec<(usize, u64)>,
+    globals: Vec<GlobalRef>,
+    values: usize,
+}
+
+impl<'a> Machine<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Machine {
+            data,
+            pos: 0,
+            stack: Vec::new(),
+            marks: Vec::new(),
+            memo: HashMap::new(),
+            on_stack: Vec::new(),
+            globals: Vec::new(),
+            values: 0,
+        }
+    }
+
+    fn malformed(&self, reason: &str) -> PickleError {
+        PickleError::Malformed {
+            position: self.pos,
+            reason: reason.to_string(),
+        }
+    }
+
+    fn push(&mut self, value: Value) -> Result<(), PickleError> {
+        self.count(&value)?;
+        self.stack.push(value);
+        Ok(())
+    }
+
+    /// Account for `value` in the value budget.
+    fn count(&mut self, value: &Value) -> Result<(), PickleError> {
+        self.values = self.values.saturating_add(value.size());
+        if self.values > MAX_VALUES {
+            return Err(PickleError::TooLarge { max: MAX_VALUES });
+        }
+        Ok(())
+    }
+
+    /// Check that `values` can be nested one level deeper.
+    fn nest(&self, values: &[&Value]) -> Result<(), PickleError> {
+        if values.iter().any(|v| v.depth() + 1 > MAX_DEPTH) {
+            return Err(PickleError::TooDeep { max: MAX_DEPTH });
+        }
+        Ok(())
+    }
+
+    /// Copy memoized values at `from` and above off the stack before they
+    /// are popped.
+    fn settle(&mut self, from: usize) -> Result<(), PickleError> {
+        while let Some(&(pos, key)) = self.on_stack.last() {
+            if pos < from {
+                break;
+            }
+            self.on_stack.pop();
+            if let Some(Memo::Stack(at)) = self.memo.get(&key) {
+                if *at == pos {
+                    let value = self.stack[pos].clone();
+                    self.count(&value)?;
+                    self.memo.insert(key, Memo::Value(value));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Result<Value, PickleError> {
+        let len = self.stack.len();
+        if len == 0 || self.marks.last() == Some(&len) {
+            return Err(self.malformed("stack underflow"));
+        }
+        self.settle(len - 1)?;
+        self.stack
+            .pop()
+            .ok_or_else(|| self.malformed("stack underflow"))
+    }
+
+    fn pop_mark(&mut self) -> Result<Vec<Value>, PickleError> {
+        let mark = self
+            .marks
+            .pop()
+            .ok_or_else(|| self.malformed("no mark on the stack"))?;
+        self.settle(mark)?;
+        let items = self.stack.split_off(mark);
+        self.nest(&items.iter().collect::<Vec<_>>())?;
+        Ok(items)
+    }
+
+    fn top(&mut self) -> Result<&mut Value, PickleError> {
+        if self.marks.last() == Some(&self.stack.len()) {
+            return Err(self.malformed("stack is empty above the mark"));
+        }
+        let pos = self.pos;
+        self.stack.last_mut().ok_or(PickleError::Malformed {
+            position: pos,
+            reason: "stack underflow".to_string(),
+        })
+    }
+
+    fn tuple(&mut self, n: usize) -> Result<(), PickleError> {
+        let mut items = Vec::with_capacity(n);
+        for _ in 0..n {
+            items.push(self.pop()?);
+        }
+        items.reverse();
+        self.nest(&items.iter().collect::<Vec<_>>())?;
+        self.push(Value::Tuple(items))
+    }
+
+    fn global(&mut self, module: String, name: String, position: usize) -> Result<(), PickleError> {
+        self.globals.push(GlobalRef {
+            module: module.clone(),
+            name: name.clone(),
+            position,
+        });
+        self.push(Value::Global { module, name })
+    }
+
+    /// Record a call to `callable` without making it.
+    fn call(&mut self, callable: Value, args: Value) -> Result<(), PickleError> {
+        self.nest(&[&callable, &args])?;
+        let args = match args {
+            Value::Tuple(items) => items,
+            other => vec![other],
+        };
+        let value = match (callable.as_global(), args.as_slice()) {
+            (Some(("collections", "OrderedDict") | ("builtins", "dict")), []) => {
+                Value::Dict(Vec::new())
+            }
+            _ => Value::Object {
+                callable: Box::new(callable),
+                args,
+                state: None,
+                items: Vec::new(),
+            },
+        };
+        self.push(value)
+    }
+
+    fn set_items(&mut self, pairs: Vec<Value>) -> Result<(), PickleError> {
+        if !pairs.len().is_multiple_of(2) {
+            return Err(self.malformed("odd number of items for SETITEMS"));
+        }
+        let mut iter = pairs.into_iter();
+        let mut pairs = Vec::new();
+        while let (Some(k), Some(v)) = (iter.next(), iter.next()) {
+            pairs.push((k, v));
+        }
+        match self.top()? {
+            Value::Dict(items) | Value::Object { items, .. } => items.extend(pairs),
+            _ => return Err(self.malformed("SETITEMS on a value that is not a dict")),
+        }
+        Ok(())
+    }
+
+    fn append(&mut self, values: Vec<Value>) -> Result<(), PickleError> {
+        match self.top()? {
+            Value::List(items) => items.extend(values),
+            // Items appended to list subclasses are not kept.
+            Value::Object { .. } => {}
+            _ => return Err(self.malformed("APPENDS on a value that is not a list")),
+        }
+        Ok(())
+    }
+
+    fn memo_get(&mut self, index: u64) -> Result<(), PickleError> {
+        let value = match self.memo.get(&index) {
+            Some(Memo::Stack(pos)) => self.stack[*pos].clone(),
+            Some(Memo::Value(value)) => value.clone(),
+            None => return Err(self.malformed("memo key not found")),
+        };
+        self.push(value)
+    }
+
+    fn memo_put(&mut self, index: u64) -> Result<(), PickleError> {
+        self.top()?;
+        let pos = self.stack.len() - 1;
+        self.memo.insert(index, Memo::Stack(pos));
+        self.on_stack.push((pos, index));
+        Ok(())
+    }
+
+    fn run(&mut self) -> Result<Value, PickleError> {
+        let mut next = 0;
+        loop {
+            let (instruction, after) = decode(self.data, next)?;
+            self.pos = instruction.position;
+            next = after;
+            if let Some(value) = self.step(instruction)? {
+                return Ok(value);
+            }
+        }
+    }
+
+    /// Run one instruction, returning the pickled value at `STOP`.
+    fn step(&mut self, instruction: Instruction) -> Result<Option<Value>, PickleError> {
+        let Instruction {
+            position,
+            opcode,
+            arg,
+        } = instruction;
+        match (opcode, arg) {
+            // PROTO, FRAME
+            (0x80 | 0x95, _) => {}
+            // STOP
+            (b'.', _) => {
+                if self.marks.last() == Some(&self.stack.len()) {
+                    return Err(self.malformed("stack is empty above the mark"));
+                }
+                let value = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| self.malformed("stack underflow"))?;
+                return Ok(Some(value));
+            }
+            // MARK, POP, POP_MARK, DUP
+            (b'(', _) => self.marks.push(self.stack.len()),
+            (b'0', _) => {
+                if self.marks.last() == Some(&self.stack.len()) {
+                    self.marks.pop();
+                } else {
+                    self.pop()?;
+                }
+            }
+            (b'1', _) => {
+                self.pop_mark()?;
+            }
+            (b'2', _) => {
+                let value = self.top()?.clone();
+                self.push(value)?;
+            }
+
+            // NONE, NEWTRUE, NEWFALSE
+            (b'N', _) => self.push(Value::None)?,
+            (0x88, _) => self.push(Value::Bool(true))?,
+            (0x89, _) => self.push(Value::Bool(false))?,
+
+            // BININT, BININT1, BININT2, INT, LONG, LONG1, LONG4
+            (b'J' | b'K' | b'M', Arg::Int(v)) => self.push(Value::Int(v))?,
+            (b'I', Arg::Text(text)) => {
+                let value = match text.as_str() {
+                    "00" => Value::Bool(false),
+                    "01" => Value::Bool(true),
+                    text => Value::Int(
+                        text.parse()
+                            .map_err(|_| self.malformed("invalid INT argument"))?,
+                    ),
+                };
+                self.push(value)?;
+            }
+            (b'L', Arg::Text(text)) => {
+                let v = text
+                    .trim_end_matches('L')
+                    .parse()
+                    .map_err(|_| self.malformed("invalid or oversized LONG argument"))?;
+                self.push(Value::Int(v))?;
+            }
+            (0x8a | 0x8b, Arg::Long(bytes)) => self.push(long(bytes))?,
+
+            // BINFLOAT, FLOAT
+            (b'G', Arg::Float(v)) => self.push(Value::Float(v))?,
+            (b'F', Arg::Text(text)) => {
+                let v = text
+                    .trim()
+                    .parse()
+                    .map_err(|_| self.malformed("invalid FLOAT argument"))?;
+                self.push(Value::Float(v))?;
+            }
+
+            // STRING
+            (b'S', Arg::Text(line)) => {
+                let quoted = line.trim_end();
+                let text = quoted
+                    .strip_prefix('\'')
+                    .and_then(|s| s.strip_suffix('\''))
+                    .or_else(|| quoted.strip_prefix('"').and_then(|s| s.strip_suffix('"')))
+                    .ok_or_else(|| self.malformed("unquoted STRING argument"))?;
+                self.push(Value::String(text.to_string()))?;
+            }
+            // UNICODE, SHORT_BINSTRING, BINSTRING, SHORT_BINUNICODE, BINUNICODE, BINUNICODE8
+            (b'V' | b'U' | b'T' | 0x8c | b'X' | 0x8d, Arg::Text(text)) => {
+                self.push(Value::String(text))?
+            }
+            // SHORT_BINBYTES, BINBYTES, BINBYTES8, BYTEARRAY8
+            (b'C' | b'B' | 0x8e | 0x96, Arg::Bytes(bytes)) => self.push(Value::Bytes(bytes))?,
+
+            // EMPTY_TUPLE, TUPLE1, TUPLE2, TUPLE3, TUPLE
+            (b')', _) => self.tuple(0)?,
+            (0x85, _) => self.tuple(1)?,
+            (0x86, _) => self.tuple(2)?,
+            (0x87, _) => self.tuple(3)?,
+            (b't', _) => {
+                let items = self.pop_mark()?;
+                self.push(Value::Tuple(items))?;
+            }
+            // EMPTY_LIST, LIST, APPEND, APPENDS
+            (b']', _) => self.push(Value::List(Vec::new()))?,
+            (b'l', _) => {
+                let items = self.pop_mark()?;
+                self.push(Value::List(items))?;
+            }
+            (b'a', _) => {
+                let value = self.pop()?;
+                self.nest(&[&value])?;
+                self.append(vec![value])?;
+            }
+            (b'e', _) => {
+                let items = self.pop_mark()?;
+                self.append(items)?;
+            }
+            // EMPTY_DICT, DICT, SETITEM, SETITEMS
+            (b'}', _) => self.push(Value::Dict(Vec::new()))?,
+            (b'd', _) => {
+                let items = self.pop_mark()?;
+                self.push(Value::Dict(Vec::new()))?;
+                self.set_items(items)?;
+            }
+            (b's', _) => {
+                let value = self.pop()?;
+                let key = self.pop()?;
+                self.nest(&[&key, &value])?;
+                self.set_items(vec![key, value])?;
+            }
+            (b'u', _) => {
+                let items = self.pop_mark()?;
+                self.set_items(items)?;
+            }
+            // EMPTY_SET, ADDITEMS, FROZENSET
+            (0x8f, _) => self.push(Value::Set(Vec::new()))?,
+            (0x90, _) => {
+                let items = self.pop_mark()?;
+                match self.top()? {
+                    Value::Set(set) => set.extend(items),
+                    _ => return Err(self.malformed("ADDITEMS on a value that is not a set")),
+                }
+            }
+            (0x91, _) => {
+                let items = self.pop_mark()?;
+                self.push(Value::Set(items))?;
+            }
+
+            // BINPUT, LONG_BINPUT, PUT, MEMOIZE
+            (b'q' | b'r' | b'p', Arg::Int(index)) => self.memo_put(index as u64)?,
+            (0x94, _) => {
+                let index = self.memo.len() as u64;
+                self.memo_put(index)?;
+            }
+            // BINGET, LONG_BINGET, GET
+            (b'h' | b'j' | b'g', Arg::Int(index)) => self.memo_get(index as u64)?,
+
+            // GLOBAL, STACK_GLOBAL
+            (b'c', Arg::Global { module, name }) => self.global(module, name, position)?,
+            (0x93, _) => {
+                let name = self.pop()?;
+                let module = self.pop()?;
+                let (Value::String(module), Value::String(name)) = (module, name) else {
+                    return Err(self.malformed("STACK_GLOBAL needs two strings"));
+                };
+                self.global(module, name, position)?;
+            }
+            // EXT1, EXT2, EXT4: globals from the copyreg extension registry
+            (0x82..=0x84, Arg::Int(code)) => self.global(
+                "copyreg".to_string(),
+                format!("_extension_registry[{}]", code),
+                position,
+            )?,
+
+            // REDUCE, NEWOBJ, NEWOBJ_EX, OBJ, INST, BUILD
+            (b'R' | 0x81, _) => {
+                let args = self.pop()?;
+                let callable = self.pop()?;
+                self.call(callable, args)?;
+            }
+            (0x92, _) => {
+                self.pop()?; // keyword arguments
+                let args = self.pop()?;
+                let callable = self.pop()?;
+                self.call(callable, args)?;
+            }
+            (b'o', _) => {
+                let mut items = self.pop_mark()?;
+                if items.is_empty() {
+                    return Err(self.malformed("OBJ without a class"));
+                }
+                let callable = items.remove(0);
+                self.call(callable, Value::Tuple(items))?;
+            }
+            (b'i', Arg::Global { module, name }) => {
+                let args = self.pop_mark()?;
+                self.global(module, name, position)?;
+                let callable = self.pop()?;
+                self.call(callable, Value::Tuple(args))?;
+            }
+            (b'b', _) => {
+                let state = self.pop()?;
+                self.nest(&[&state])?;
+                // State set on dicts (e.g. attributes of an OrderedDict
+                // subclass) is not kept.
+                if let Value::Object { state: slot, .. } = self.top()? {
+                    *slot = Some(Box::new(state));
+                }
+            }
+
+            // PERSID, BINPERSID
+            (b'P', Arg::Text(id)) => {
+                self.push(Value::Persistent(Box::new(Value::String(id))))?;
+            }
+            (b'Q', _) => {
+                let id = self.pop()?;
+                self.nest(&[&id])?;
+                self.push(Value::Persistent(Box::new(id)))?;
+            }
+
+            _ => return Err(PickleError::UnsupportedOpcode { opcode, position }),
+        }
+        Ok(None)
+    }
+}
+
+/// Decode a little-endian two's complement integer of `LONG1`/`LONG4`.
+fn long(bytes: Vec<u8>) -> Value {
+    if bytes.is_empty() {
+        return Value::Int(0);
+    }
+    if bytes.len() > 8 {
+        return Value::BigInt(bytes);
+    }
+    let fill = if bytes[bytes.len() - 1] & 0x80 != 0 {
+        0xff
+    } else {
+        0
+    };
+    let mut buf = [fill; 8];
+    buf[..bytes.len()].copy_from_slice(&bytes);
+    Value::Int(i64::from_le_bytes(buf))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn string(s: &str) -> Value {
+        Value::String(s.to_string())
+    }
+
+    #[test]
+    fn test_protocol_0_text_opcodes() {
+        // pickle.dumps(("a", 1, 2.5, True, None), protocol=0)
+        let data = b"(Va\np0\nI1\nF2.5\nI01\nNtp1\n.";
+        let loaded = load(data).unwrap();
+        assert_eq!(
+            loaded.value,
+            Value::Tuple(vec![
+                string("a"),
+                Value::Int(1),
+                Value::Float(2.5),
+                Value::Bool(true),
+                Value::None
+            ])
+        );
+    }
+
+    #[test]
+    fn test_memo_and_long() {
+        // x = [1 << 40, -2]; pickle.dumps((x, x), protocol=2)
+        let data =
+            b"\x80\x02]q\x00(\x8a\x06\x00\x00\x00\x00\x00\x01J\xfe\xff\xff\xffeh\x00\x86q\x01.";
+        let loaded = load(data).unwrap();
+        let list = Value::List(vec![Value::Int(1 << 40), Value::Int(-2)]);
+        assert_eq!(loaded.value, Value::Tuple(vec![list.clone(), list]));
+    }
+
+    #[test]
+    fn test_calls_are_recorded_not_made() {
+        // pickle.dumps(os.system("echo hi")) as written by a malicious __reduce__
+        let data = b"\x80\x02cposix\nsystem\nq\x00X\x07\x00\x00\x00echo hiq\x01\x85q\x02Rq\x03.";
+        let loaded = load(data).unwrap();
+
+        assert_eq!(
+            loaded.globals,
+            vec![GlobalRef {
+                module: "posix".to_string(),
+                name: "system".to_string(),
+                position: 2,
+            }]
+        );
+        let Value::Object { callable, args, .. } = loaded.value else {
+            panic!("expected an object");
+        };
+        assert_eq!(callable.as_global(), Some(("posix", "system")));
+        assert_eq!(args, vec![string("echo hi")]);
+    }
+
+    #[test]
+    fn test_stack_global_and_ordered_dict() {
+        // pickle.dumps(collections.OrderedDict(a=1), protocol=4), without framing
+        let data = b"\x80\x04\x8c\x0bcollections\x94\x8c\x0bOrderedDict\x94\x93\x94)R\x94\x8c\x01a\x94K\x01s.";
+        let loaded = load(data).unwrap();
+        assert_eq!(
+            loaded.value,
+            Value::Dict(vec![(string("a"), Value::Int(1))])
+        );
+        assert_eq!(loaded.globals[0].name, "OrderedDict");
+    }
+
+    #[test]
+    fn test_nesting_limit() {
+        let mut data = b"\x80\x02".to_vec();
+        data.extend(std::iter::repeat_n(b']', MAX_DEPTH + 2));
+        data.extend(std::iter::repeat_n(b'a', MAX_DEPTH + 1));
+        data.push(b'.');
+        assert!(matches!(load(&data), Err(PickleError::TooDeep { .. })));
+    }
+
+    #[test]
+    fn test_memo_copies_are_bounded() {
+        // Doubling a list through the memo grows exponentially.
+        let mut data = b"\x80\x02](K\x00K\x00K\x00K\x00e".to_vec();
+        for _ in 0..40 {
+            data.extend(b"q\x00h\x00\x86");
+        }
+        data.push(b'.');
+        assert!(matches!(load(&data), Err(PickleError::TooLarge { .. })));
+    }
+
+    #[test]
+    fn test_truncated_and_unknown() {
+        assert!(matches!(
+            load(b"\x80\x02X\xff\x00\x00\x00ab"),
+            Err(PickleError::UnexpectedEof)
+        ));
+        assert!(matches!(
+            load(b"\x80\x02\xff."),
+            Err(PickleError::UnsupportedOpcode { opcode: 0xff, .. })
+        ));
+        assert!(matches!(
+            load(b"\x80\x02t."),
+            Err(PickleError::Malformed { .. })
+        ));
+    }
+}
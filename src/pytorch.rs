@@ -0,0 +1,833 @@
+use crate::pickle::{self, PickleError, Value};
+use crate::scan;
+use crate::types::{Artifact, CanonicalValue, Finding, Format, ParseLimits, Severity, Tensor};
+use crate::zip::{self, ZipEntry, ZipError, STORED};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io::{Read, Seek, SeekFrom};
+use thiserror::Error;
+
+/// Pickle protocol 2 header followed by `LONG1` of torch's legacy magic
+/// number, which starts checkpoints written before the zip format.
+const LEGACY_MAGIC: &[u8] = b"\x80\x02\x8a\x0a\x6c\xfc\x9c\x46\xf9\x20\x6a\xa8\x50\x19";
+
+#[derive(Error, Debug)]
+pub enum PyTorchParserError {
+    #[error("legacy (pre-1.6) PyTorch checkpoints are not supported; re-save with torch.save")]
+    LegacyFormat,
+    #[error("no data.pkl found in the archive")]
+    MissingDataPickle,
+    #[error("invalid archive: {0}")]
+    Zip(#[from] ZipError),
+    #[error("invalid data.pkl: {0}")]
+    Pickle(#[from] PickleError),
+    #[error("tensor count {count} exceeds max_tensor_count ({max}); needs at least {count}")]
+    TensorCountTooLarge { count: u64, max: u64 },
+    #[error("metadata count {count} exceeds max_metadata_count ({max}); needs at least {count}")]
+    MetadataCountTooLarge { count: u64, max: u64 },
+    #[error(
+        "tensor '{name}' dimensions {dims} exceed max_dimensions ({max}); needs at least {dims}"
+    )]
+    DimensionsTooLarge { name: String, dims: u32, max: u32 },
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+}
+
+/// Parse a PyTorch checkpoint (`torch.save` zip format) without loading it.
+///
+/// `data.pkl` is read with [`pickle::load`], which never imports or calls
+/// anything. Tensors are recovered from `torch._utils._rebuild_tensor_v2`
+/// calls (also when wrapped in `_rebuild_parameter`), named by their path
+/// through dicts, lists and object state, e.g. `model.layers.0.weight`.
+/// Their data offsets point into the stored `data/<key>` entries, so tensor
+/// data is hashed and read straight from the archive. Other scalars along
+/// the way become metadata, as do the archive's `version` and `byteorder`.
+///
+/// Findings record what a loader would trip over:
+/// - `dangerous_global`, `unexpected_global`: the pickle imports something
+///   other than the torch, numpy and collections rebuild functions a
+///   checkpoint needs, rated by [`scan::global_severity`]. `torch.load`
+///   would import it and may call it.
+/// - `missing_storage`, `storage_size_mismatch`, `tensor_out_of_storage`:
+///   a tensor's storage is absent or too small.
+/// - `non_contiguous_tensor`, `compressed_storage`, `unsupported_tensor`:
+///   the tensor's bytes cannot be read in place; it has no data offset.
+/// - `big_endian`: the archive stores big-endian data.
+///
+/// # Example
+///
+/// ```
+/// use weight_inspect::pytorch;
+///
+/// let data = std::fs::read("tests/fixtures/tiny.pt").unwrap();
+/// let mut cursor = std::io::Cursor::new(data);
+/// let artifact = pytorch::parse_pytorch(&mut cursor).unwrap();
+/// assert_eq!(artifact.format, weight_inspect::types::Format::PyTorch);
+/// assert_eq!(artifact.tensors["test.weight.0"].dtype, "f32");
+/// ```
+pub fn parse_pytorch<R: Read + Seek>(reader: &mut R) -> Result<Artifact, PyTorchParserError> {
+    parse_pytorch_with_limits(reader, &ParseLimits::default())
+}
+
+/// Parse a PyTorch checkpoint, enforcing the given safety limits.
+///
+/// `max_header_size` bounds the zip central directory and `data.pkl`.
+pub fn parse_pytorch_with_limits<R: Read + Seek>(
+    reader: &mut R,
+    limits: &ParseLimits,
+) -> Result<Artifact, PyTorchParserError> {
+    let mut magic = [0u8; 14];
+    let n = reader.read(&mut magic)?;
+    if !zip::is_zip(&magic[..n]) {
+        if magic[..n] == *LEGACY_MAGIC {
+            return Err(PyTorchParserError::LegacyFormat);
+        }
+        return Err(ZipError::NotZip.into());
+    }
+    reader.seek(SeekFrom::Start(0))?;
+
+    let entries = zip::read_entries(reader, limits.max_header_size)?;
+    let (prefix, pickle_entry) = entries
+        .iter()
+        .find_map(|entry| Some((archive_prefix(&entry.name)?, entry)))
+        .ok_or(PyTorchParserError::MissingDataPickle)?;
+    let data = zip::read_entry(reader, pickle_entry, limits.max_header_size)?;
+    let loaded = pickle::load(&data)?;
+
+    let storages = entries
+        .iter()
+        .filter_map(|entry| {
+            let key = entry.name.strip_prefix(prefix)?.strip_prefix("data/")?;
+            Some((key, entry))
+        })
+        .collect();
+    let mut walker = Walker {
+        storages,
+        limits,
+        tensors: BTreeMap::new(),
+        metadata: BTreeMap::new(),
+        findings: Vec::new(),
+        big_endian: false,
+        checked_storages: BTreeSet::new(),
+    };
+
+    let mut reported = BTreeSet::new();
+    for global in &loaded.globals {
+        if reported.insert((&global.module, &global.name)) {
+            walker
+                .findings
+                .extend(scan::global_finding("data.pkl", global));
+        }
+    }
+
+    for (key, file) in [("version", "version"), ("byteorder", "byteorder")] {
+        let Some(entry) = entries.iter().find(|e| e.name == format!("{prefix}{file}")) else {
+            continue;
+        };
+        let text = zip::read_entry(reader, entry, 64)?;
+        let text = String::from_utf8_lossy(&text).trim().to_string();
+        let value = match text.parse() {
+            Ok(i) => CanonicalValue::Int(i),
+            Err(_) => CanonicalValue::String(text.clone()),
+        };
+        if key == "byteorder" && text == "big" {
+            walker.big_endian = true;
+            walker.findings.push(Finding::new(
+                Severity::Warning,
+                "big_endian",
+                None,
+                "tensor data is big-endian; values are byte-swapped when decoded".to_string(),
+            ));
+        }
+        walker.metadata.insert(format!("pytorch.{}", key), value);
+    }
+
+    walker.walk(&loaded.value, String::new(), false)?;
+
+    Ok(Artifact {
+        format: Format::PyTorch,
+        gguf_version: None,
+        metadata: walker.metadata,
+        tensors: walker.tensors,
+        graph: None,
+        data_offset: None,
+        findings: walker.findings,
+    })
+}
+
+/// The directory holding `data.pkl`, if `name` is a top-level `data.pkl`.
+/// `torch.save` names it after the file, e.g. `archive/` or `pytorch_model/`.
+fn archive_prefix(name: &str) -> Option<&str> {
+    let prefix = name.strip_suffix("data.pkl")?;
+    match prefix.strip_suffix('/') {
+        Some(dir) if !dir.contains('/') => Some(prefix),
+        None if prefix.is_empty() => Some(prefix),
+        _ => None,
+    }
+}
+
+/// Dtype and element size of a typed storage class such as `FloatStorage`.
+fn storage_dtype(class: &str) -> Option<(&'static str, u64)> {
+    let dtype = match class {
+        "DoubleStorage" => ("f64", 8),
+        "FloatStorage" => ("f32", 4),
+        "HalfStorage" => ("f16", 2),
+        "BFloat16Storage" => ("bf16", 2),
+        "LongStorage" => ("i64", 8),
+        "IntStorage" => ("i32", 4),
+        "ShortStorage" => ("i16", 2),
+        "CharStorage" => ("i8", 1),
+        "ByteStorage" | "UntypedStorage" => ("u8", 1),
+        "BoolStorage" => ("bool", 1),
+        "ComplexFloatStorage" => ("complex64", 8),
+        "ComplexDoubleStorage" => ("complex128", 16),
+        "Float8_e4m3fnStorage" => ("f8_e4m3", 1),
+        "Float8_e5m2Storage" => ("f8_e5m2", 1),
+        _ => return None,
+    };
+    Some(dtype)
+}
+
+/// Keys of `nn.Module` state that hold its parameters, buffers and
+/// submodules; they are left out of tensor names, as in `state_dict()`.
+const MODULE_KEYS: &[&str] = &["_parameters", "_buffers", "_modules"];
+
+/// Arguments of a `_rebuild_tensor_v2` call.
+struct Rebuild<'a> {
+    storage: &'a Value,
+    offset: &'a Value,
+    size: &'a Value,
+    stride: &'a Value,
+}
+
+/// What a value rebuilt by a `torch._utils` function turned out to be.
+enum Rebuilt<'a> {
+    Tensor(Rebuild<'a>),
+    /// A tensor rebuilt some other way, e.g. quantized or sparse.
+    Unsupported(&'a str),
+}
+
+/// Recognize a call that rebuilds a tensor.
+fn rebuilt(value: &Value) -> Option<Rebuilt<'_>> {
+    let Value::Object { callable, args, .. } = value else {
+        return None;
+    };
+    rebuilt_call(callable, args)
+}
+
+fn rebuilt_call<'a>(callable: &'a Value, args: &'a [Value]) -> Option<Rebuilt<'a>> {
+    match callable.as_global()? {
+        ("torch._utils", "_rebuild_tensor_v2" | "_rebuild_tensor") => match args {
+            [storage, offset, size, stride, ..] => Some(Rebuilt::Tensor(Rebuild {
+                storage,
+                offset,
+                size,
+                stride,
+            })),
+            _ => Some(Rebuilt::Unsupported("_rebuild_tensor_v2")),
+        },
+        ("torch._utils", "_rebuild_parameter" | "_rebuild_parameter_with_state") => {
+            rebuilt(args.first()?)
+        }
+        // Tensor subclasses: (func, type, args, state)
+        ("torch._tensor", "_rebuild_from_type_v2") => match args {
+            [func, _, Value::Tuple(inner), ..] => rebuilt_call(func, inner),
+            _ => Some(Rebuilt::Unsupported("_rebuild_from_type_v2")),
+        },
+        ("torch._utils", name) if name.starts_with("_rebuild_") => Some(Rebuilt::Unsupported(name)),
+        _ => None,
+    }
+}
+
+fn is_scalar(value: &Value) -> bool {
+    matches!(
+        value,
+        Value::None | Value::Bool(_) | Value::Int(_) | Value::Float(_) | Value::String(_)
+    )
+}
+
+fn canonical(value: &Value) -> CanonicalValue {
+    match value {
+        Value::Bool(b) => CanonicalValue::Bool(*b),
+        Value::Int(i) => CanonicalValue::Int(*i),
+        Value::Float(f) => CanonicalValue::Float(*f),
+        Value::String(s) => CanonicalValue::String(s.clone()),
+        Value::Tuple(items) | Value::List(items) => {
+            CanonicalValue::Array(items.iter().map(canonical).collect())
+        }
+        _ => CanonicalValue::Null,
+    }
+}
+
+fn ints(value: &Value) -> Option<Vec<u64>> {
+    value
+        .as_seq()?
+        .iter()
+        .map(|v| v.as_int().and_then(|i| u64::try_from(i).ok()))
+        .collect()
+}
+
+/// Collects tensors and metadata from the unpickled object.
+struct Walker<'a> {
+    storages: HashMap<&'a str, &'a ZipEntry>,
+    limits: &'a ParseLimits,
+    tensors: BTreeMap<String, Tensor>,
+    metadata: BTreeMap<String, CanonicalValue>,
+    findings: Vec<Finding>,
+    /// Whether `byteorder` says the storages are big-endian.
+    big_endian: bool,
+    /// Storage keys whose size was already checked.
+    checked_storages: BTreeSet<String>,
+}
+
+impl Walker<'_> {
+    /// Visit `value`, found at `name`. Inside objects (`in_object`) only
+    /// tensors are collected, not their other attributes.
+    fn walk(
+        &mut self,
+        value: &Value,
+        name: String,
+        in_object: bool,
+    ) -> Result<(), PyTorchParserError> {
+        let child = |key: &str| {
+            if name.is_empty() {
+                key.to_string()
+            } else {
+                format!("{}.{}", name, key)
+            }
+        };
+        match rebuilt(value) {
+            Some(Rebuilt::Tensor(rebuild)) => return self.tensor(name, rebuild),
+            Some(Rebuilt::Unsupported(function)) => {
+                self.findings.push(Finding::new(
+                    Severity::Warning,
+                    "unsupported_tensor",
+                    Some(&name),
+                    format!(
+                        "rebuilt by torch._utils.{}; its data cannot be located",
+                        function
+                    ),
+                ));
+                return Ok(());
+            }
+            None => {}
+        }
+        match value {
+            Value::Dict(pairs) => {
+                for (key, item) in pairs {
+                    let key = match key {
+                        Value::String(s) => s.clone(),
+                        Value::Int(i) => i.to_string(),
+                        _ => continue,
+                    };
+                    let name = if in_object && MODULE_KEYS.contains(&key.as_str()) {
+                        name.clone()
+                    } else {
+                        child(&key)
+                    };
+                    self.walk(item, name, in_object)?;
+                }
+            }
+            Value::Tuple(items) | Value::List(items)
+                if in_object || !items.iter().all(is_scalar) =>
+            {
+                for (i, item) in items.iter().enumerate() {
+                    self.walk(item, child(&i.to_string()), in_object)?;
+                }
+            }
+            Value::Object { state, items, .. } => {
+                if let Some(state) = state {
+                    self.walk(state, name.clone(), true)?;
+                }
+                for (key, item) in items {
+                    if let Some(key) = key.as_str() {
+                        self.walk(item, child(key), true)?;
+                    }
+                }
+            }
+            _ if !in_object
+                && !name.is_empty()
+                && (is_scalar(value) || value.as_seq().is_some()) =>
+            {
+                if self.metadata.len() as u64 >= self.limits.max_metadata_count {
+                    return Err(PyTorchParserError::MetadataCountTooLarge {
+                        count: self.metadata.len() as u64 + 1,
+                        max: self.limits.max_metadata_count,
+                    });
+                }
+                self.metadata.insert(name, canonical(value));
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn invalid(&mut self, name: &str, message: &str) {
+        self.findings.push(Finding::new(
+            Severity::Error,
+            "invalid_tensor",
+            Some(name),
+            message.to_string(),
+        ));
+    }
+
+    fn tensor(&mut self, name: String, rebuild: Rebuild) -> Result<(), PyTorchParserError> {
+        // ('storage', storage_type, key, location, numel)
+        let storage = match rebuild.storage {
+            Value::Persistent(id) => id.as_seq(),
+            _ => None,
+        };
+        let Some([tag, class, key, _, numel]) = storage else {
+            self.invalid(
+                &name,
+                "storage is not a ('storage', type, key, location, numel) id",
+            );
+            return Ok(());
+        };
+        let (Some("storage"), Some((_, class)), Some(key), Some(numel)) = (
+            tag.as_str(),
+            class.as_global(),
+            key.as_str(),
+            numel.as_int().and_then(|n| u64::try_from(n).ok()),
+        ) else {
+            self.invalid(
+                &name,
+                "storage is not a ('storage', type, key, location, numel) id",
+            );
+            return Ok(());
+        };
+        let (Some(shape), Some(stride), Some(offset)) = (
+            ints(rebuild.size),
+            ints(rebuild.stride),
+            rebuild.offset.as_int().and_then(|o| u64::try_from(o).ok()),
+        ) else {
+            self.invalid(
+                &name,
+                "size, stride or storage offset is not a valid integer",
+            );
+            return Ok(());
+        };
+        if shape.len() > self.limits.max_dimensions as usize {
+            return Err(PyTorchParserError::DimensionsTooLarge {
+                name,
+                dims: shape.len() as u32,
+                max: self.limits.max_dimensions,
+            });
+        }
+        if shape.len() != stride.len() {
+            self.invalid(&name, "size and stride have different lengths");
+            return Ok(());
+        }
+        if self.tensors.len() as u64 >= self.limits.max_tensor_count {
+            return Err(PyTorchParserError::TensorCountTooLarge {
+                count: self.tensors.len() as u64 + 1,
+                max: self.limits.max_tensor_count,
+            });
+        }
+
+        let entry = self.storages.get(key).copied();
+        let (dtype, elem) = match storage_dtype(class) {
+            Some((dtype, elem)) => (dtype.to_string(), elem),
+            // Assume the storage entry holds exactly `numel` elements.
+            None => {
+                let elem = entry
+                    .filter(|e| numel > 0 && e.size % numel == 0)
+                    .map_or(1, |e| e.size / numel);
+                (format!("unknown_{}", class), elem)
+            }
+        };
+        let count = shape.iter().try_fold(1u64, |acc, &d| acc.checked_mul(d));
+        let byte_length = count.and_then(|c| c.checked_mul(elem));
+        let Some(byte_length) = byte_length else {
+            self.invalid(&name, "tensor size overflows");
+            return Ok(());
+        };
+        let data_offset = match entry {
+            Some(entry) => self.locate(&name, key, entry, numel, elem, offset, &shape, &stride),
+            None => {
+                self.findings.push(Finding::new(
+                    Severity::Error,
+                    "missing_storage",
+                    Some(&name),
+                    format!("storage 'data/{}' is not in the archive", key),
+                ));
+                None
+            }
+        };
+
+        if self.tensors.contains_key(&name) {
+            self.findings.push(Finding::new(
+                Severity::Error,
+                "duplicate_tensor",
+                Some(&name),
+                "name appears more than once; only the first is used".to_string(),
+            ));
+            return Ok(());
+        }
+        self.tensors.insert(
+            name.clone(),
+            Tensor {
+                name,
+                dtype,
+                shape,
+                byte_length,
+                data_offset,
+                digest: None,
+                location: None,
+                nnz: None,
+                big_endian: self.big_endian,
+            },
+        );
+        Ok(())
+    }
+
+    /// Where the tensor's bytes start in the archive, if they can be read
+    /// in place.
+    #[allow(clippy::too_many_arguments)]
+    fn locate(
+        &mut self,
+        name: &str,
+        key: &str,
+        entry: &ZipEntry,
+        numel: u64,
+        elem: u64,
+        offset: u64,
+        shape: &[u64],
+        stride: &[u64],
+    ) -> Option<u64> {
+        if self.checked_storages.insert(key.to_string())
+            && numel.checked_mul(elem) != Some(entry.size)
+        {
+            self.findings.push(Finding::new(
+                Severity::Error,
+                "storage_size_mismatch",
+                Some(name),
+                format!(
+                    "storage 'data/{}' should hold {} elements of {} bytes but has {} bytes",
+                    key, numel, elem, entry.size
+                ),
+            ));
+        }
+
+        // Index of the last element the tensor views.
+        let last = shape
+            .iter()
+            .zip(stride)
+            .try_fold(offset, |acc, (&dim, &step)| {
+                acc.checked_add(dim.saturating_sub(1).checked_mul(step)?)
+            });
+        let empty = shape.contains(&0);
+        if !empty && last.is_none_or(|last| last >= numel) {
+            self.findings.push(Finding::new(
+                Severity::Error,
+                "tensor_out_of_storage",
+                Some(name),
+                format!(
+                    "views past the end of storage 'data/{}' of {} elements",
+                    key, numel
+                ),
+            ));
+            return None;
+        }
+
+        let mut expected = 1u64;
+        for (&dim, &step) in shape.iter().zip(stride).rev() {
+            if dim != 1 && step != expected {
+                self.findings.push(Finding::new(
+                    Severity::Warning,
+                    "non_contiguous_tensor",
+                    Some(name),
+                    format!(
+                        "stride {:?} is not row-major for shape {:?}; its bytes are not in order",
+                        stride, shape
+                    ),
+                ));
+                return None;
+            }
+            expected = expected.saturating_mul(dim);
+        }
+
+        if entry.method != STORED {
+            self.findings.push(Finding::new(
+                Severity::Warning,
+                "compressed_storage",
+                Some(name),
+                format!(
+                    "storage 'data/{}' is compressed (method {}); its bytes cannot be read in place",
+                    key, entry.method
+                ),
+            ));
+            return None;
+        }
+        entry.data_offset.checked_add(offset.checked_mul(elem)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn unicode(out: &mut Vec<u8>, s: &str) {
+        out.push(b'X');
+        out.extend((s.len() as u32).to_le_bytes());
+        out.extend(s.as_bytes());
+    }
+
+    fn int(out: &mut Vec<u8>, value: u64) {
+        out.push(b'J');
+        out.extend((value as i32).to_le_bytes());
+    }
+
+    fn ints(out: &mut Vec<u8>, values: &[u64]) {
+        out.push(b'(');
+        for &v in values {
+            int(out, v);
+        }
+        out.push(b't');
+    }
+
+    struct Spec<'a> {
+        name: &'a str,
+        class: &'a str,
+        key: &'a str,
+        numel: u64,
+        offset: u64,
+        shape: &'a [u64],
+        stride: &'a [u64],
+    }
+
+    /// data.pkl of `OrderedDict(name=tensor, ...)`, as `torch.save` writes it,
+    /// with `extra` spliced in as one more item.
+    fn state_dict(tensors: &[Spec], extra: &[u8]) -> Vec<u8> {
+        let mut out = b"\x80\x02ccollections\nOrderedDict\n)R(".to_vec();
+        for t in tensors {
+            unicode(&mut out, t.name);
+            out.extend(b"ctorch._utils\n_rebuild_tensor_v2\n(");
+            out.push(b'(');
+            unicode(&mut out, "storage");
+            out.extend(format!("ctorch\n{}\n", t.class).as_bytes());
+            unicode(&mut out, t.key);
+            unicode(&mut out, "cpu");
+            int(&mut out, t.numel);
+            out.extend(b"tQ");
+            int(&mut out, t.offset);
+            ints(&mut out, t.shape);
+            ints(&mut out, t.stride);
+            out.extend(b"\x89ccollections\nOrderedDict\n)RtR");
+        }
+        out.extend(extra);
+        out.extend(b"u.");
+        out
+    }
+
+    fn checkpoint(tensors: &[Spec], extra: &[u8], storages: &[(&str, Vec<u8>)]) -> Vec<u8> {
+        checkpoint_with_byteorder(tensors, extra, storages, b"little")
+    }
+
+    fn checkpoint_with_byteorder(
+        tensors: &[Spec],
+        extra: &[u8],
+        storages: &[(&str, Vec<u8>)],
+        byteorder: &[u8],
+    ) -> Vec<u8> {
+        let pickle = state_dict(tensors, extra);
+        let mut files: Vec<(String, &[u8])> = vec![
+            ("archive/data.pkl".to_string(), &pickle),
+            ("archive/byteorder".to_string(), byteorder),
+        ];
+        for (key, data) in storages {
+            files.push((format!("archive/data/{}", key), data));
+        }
+        files.push(("archive/version".to_string(), b"3\n"));
+        let files: Vec<(&str, &[u8])> = files.iter().map(|(n, d)| (n.as_str(), *d)).collect();
+        zip::stored_archive(&files)
+    }
+
+    fn parse(bytes: Vec<u8>) -> (Artifact, Cursor<Vec<u8>>) {
+        let mut cursor = Cursor::new(bytes);
+        let artifact = parse_pytorch(&mut cursor).unwrap();
+        (artifact, cursor)
+    }
+
+    fn read(cursor: &mut Cursor<Vec<u8>>, tensor: &Tensor) -> Vec<u8> {
+        let mut buf = vec![0u8; tensor.byte_length as usize];
+        cursor
+            .seek(SeekFrom::Start(tensor.data_offset.unwrap()))
+            .unwrap();
+        cursor.read_exact(&mut buf).unwrap();
+        buf
+    }
+
+    const WEIGHT: Spec = Spec {
+        name: "fc.weight",
+        class: "FloatStorage",
+        key: "0",
+        numel: 6,
+        offset: 0,
+        shape: &[2, 3],
+        stride: &[3, 1],
+    };
+
+    #[test]
+    fn test_parse_state_dict() {
+        let bias = Spec {
+            name: "fc.bias",
+            class: "HalfStorage",
+            key: "1",
+            numel: 2,
+            offset: 0,
+            shape: &[2],
+            stride: &[1],
+        };
+        let weight_data: Vec<u8> = (0..24).collect();
+        let bytes = checkpoint(
+            &[WEIGHT, bias],
+            &[],
+            &[("0", weight_data.clone()), ("1", vec![9; 4])],
+        );
+
+        let (artifact, mut cursor) = parse(bytes);
+
+        assert!(artifact.findings.is_empty(), "{:?}", artifact.findings);
+        let weight = &artifact.tensors["fc.weight"];
+        assert_eq!(weight.dtype, "f32");
+        assert_eq!(weight.shape, vec![2, 3]);
+        assert_eq!(weight.byte_length, 24);
+        assert_eq!(read(&mut cursor, weight), weight_data);
+        let bias = &artifact.tensors["fc.bias"];
+        assert_eq!(bias.dtype, "f16");
+        assert_eq!(read(&mut cursor, bias), vec![9; 4]);
+        assert_eq!(artifact.metadata["pytorch.version"], CanonicalValue::Int(3));
+        assert_eq!(
+            artifact.metadata["pytorch.byteorder"],
+            CanonicalValue::String("little".to_string())
+        );
+    }
+
+    #[test]
+    fn test_big_endian_values_are_swapped() {
+        let data: Vec<u8> = [1.0f32, -2.0, 0.5, 4.0, 8.0, 3.0]
+            .iter()
+            .flat_map(|v| v.to_be_bytes())
+            .collect();
+        let bytes = checkpoint_with_byteorder(&[WEIGHT], &[], &[("0", data)], b"big");
+
+        let (artifact, mut cursor) = parse(bytes);
+
+        assert_eq!(artifact.findings[0].code, "big_endian");
+        assert!(artifact.tensors["fc.weight"].big_endian);
+        let report = crate::stats::compute_stats(&artifact, &mut cursor).unwrap();
+        let stats = &report.tensors["fc.weight"];
+        assert_eq!(stats.min, Some(-2.0));
+        assert_eq!(stats.max, Some(8.0));
+    }
+
+    #[test]
+    fn test_views_share_storage() {
+        let second_row = Spec {
+            name: "row",
+            offset: 3,
+            shape: &[3],
+            stride: &[1],
+            ..WEIGHT
+        };
+        let data: Vec<u8> = (0..24).collect();
+        let bytes = checkpoint(&[WEIGHT, second_row], &[], &[("0", data.clone())]);
+
+        let (artifact, mut cursor) = parse(bytes);
+
+        assert!(artifact.findings.is_empty(), "{:?}", artifact.findings);
+        assert_eq!(read(&mut cursor, &artifact.tensors["row"]), data[12..]);
+    }
+
+    #[test]
+    fn test_dangerous_global_is_reported_not_called() {
+        let mut extra = Vec::new();
+        unicode(&mut extra, "payload");
+        extra.extend(b"cposix\nsystem\n");
+        unicode(&mut extra, "echo pwned");
+        extra.extend(b"\x85R");
+        let bytes = checkpoint(&[WEIGHT], &extra, &[("0", vec![0; 24])]);
+
+        let (artifact, _) = parse(bytes);
+
+        assert_eq!(artifact.tensors.len(), 1);
+        assert_eq!(artifact.findings.len(), 1);
+        let finding = &artifact.findings[0];
+        assert_eq!(finding.code, "dangerous_global");
+        assert_eq!(finding.severity, Severity::Error);
+        assert_eq!(finding.subject.as_deref(), Some("posix.system"));
+    }
+
+    #[test]
+    fn test_storage_problems() {
+        let transposed = Spec {
+            name: "t",
+            shape: &[3, 2],
+            stride: &[1, 3],
+            ..WEIGHT
+        };
+        let past_end = Spec {
+            name: "past_end",
+            offset: 4,
+            shape: &[3],
+            stride: &[1],
+            ..WEIGHT
+        };
+        let missing = Spec {
+            name: "missing",
+            key: "7",
+            ..WEIGHT
+        };
+        let bytes = checkpoint(&[transposed, past_end, missing], &[], &[("0", vec![0; 20])]);
+
+        let (artifact, _) = parse(bytes);
+        let codes: Vec<_> = artifact
+            .findings
+            .iter()
+            .map(|f| (f.code, f.subject.as_deref().unwrap()))
+            .collect();
+
+        assert_eq!(
+            codes,
+            vec![
+                ("storage_size_mismatch", "t"),
+                ("non_contiguous_tensor", "t"),
+                ("tensor_out_of_storage", "past_end"),
+                ("missing_storage", "missing"),
+            ]
+        );
+        assert!(artifact.tensors.values().all(|t| t.data_offset.is_none()));
+    }
+
+    #[test]
+    fn test_scalars_become_metadata() {
+        let mut extra = Vec::new();
+        unicode(&mut extra, "epoch");
+        extra.extend(b"K\x05");
+        let bytes = checkpoint(&[WEIGHT], &extra, &[("0", vec![0; 24])]);
+
+        let (artifact, _) = parse(bytes);
+
+        assert_eq!(artifact.metadata["epoch"], CanonicalValue::Int(5));
+    }
+
+    #[test]
+    fn test_legacy_and_non_zip() {
+        let mut legacy = LEGACY_MAGIC.to_vec();
+        legacy.extend(b".");
+        assert!(matches!(
+            parse_pytorch(&mut Cursor::new(legacy)),
+            Err(PyTorchParserError::LegacyFormat)
+        ));
+        assert!(matches!(
+            parse_pytorch(&mut Cursor::new(b"GGUF".to_vec())),
+            Err(PyTorchParserError::Zip(ZipError::NotZip))
+        ));
+        let no_pickle = zip::stored_archive(&[("archive/data/0", b"")]);
+        assert!(matches!(
+            parse_pytorch(&mut Cursor::new(no_pickle)),
+            Err(PyTorchParserError::MissingDataPickle)
+        ));
+    }
+}
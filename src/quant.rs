@@ -0,0 +1,547 @@
+use crate::names;
+use crate::types::{Artifact, CanonicalValue, Tensor};
+use crate::values::{self, ValueError, ValueReader};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::{Read, Seek};
+
+/// Quantization error of one tensor or one layer.
+#[derive(Debug, Clone, Serialize)]
+pub struct QuantError {
+    /// GGUF tensor name, or layer name (`blk.N`, or the tensor stem for
+    /// tensors outside the repeating blocks).
+    pub name: String,
+    /// Name of the matching reference tensor (tensor entries only).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reference: Option<String>,
+    /// GGUF dtype (tensor entries only).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dtype: Option<String>,
+    pub element_count: u64,
+    /// Root mean squared error.
+    pub rmse: f64,
+    /// Largest absolute error.
+    pub max_error: f64,
+    /// Signal-to-noise ratio in dB, `10 * log10(sum(ref^2) / sum(err^2))`.
+    /// Infinite when the tensors are identical.
+    pub snr_db: f64,
+}
+
+/// Result of comparing a quantized GGUF file against its reference
+/// checkpoint.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct QuantReport {
+    /// Per-tensor error, worst (lowest SNR) first.
+    pub tensors: Vec<QuantError>,
+    /// Per-layer error, worst (lowest SNR) first.
+    pub layers: Vec<QuantError>,
+    /// GGUF tensors with no matching reference tensor.
+    pub unmatched: Vec<String>,
+    /// Matched tensors that could not be compared, with the reason.
+    pub skipped: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct ErrorAccumulator {
+    count: u64,
+    sum_sq_err: f64,
+    sum_sq_ref: f64,
+    max_err: f64,
+}
+
+impl ErrorAccumulator {
+    fn push(&mut self, reference: f64, value: f64) {
+        let err = value - reference;
+        self.count += 1;
+        self.sum_sq_err += err * err;
+        self.sum_sq_ref += reference * reference;
+        self.max_err = self.max_err.max(err.abs());
+    }
+
+    fn merge(&mut self, other: &ErrorAccumulator) {
+        self.count += other.count;
+        self.sum_sq_err += other.sum_sq_err;
+        self.sum_sq_ref += other.sum_sq_ref;
+        self.max_err = self.max_err.max(other.max_err);
+    }
+
+    fn finish(&self, name: String) -> QuantError {
+        let rmse = if self.count == 0 {
+            0.0
+        } else {
+            (self.sum_sq_err / self.count as f64).sqrt()
+        };
+        let snr_db = if self.sum_sq_err == 0.0 {
+            f64::INFINITY
+        } else {
+            10.0 * (self.sum_sq_ref / self.sum_sq_err).log10()
+        };
+        QuantError {
+            name,
+            reference: None,
+            dtype: None,
+            element_count: self.count,
+            rmse,
+            max_error: self.max_err,
+            snr_db,
+        }
+    }
+}
+
+/// Compare every GGUF tensor with its reference tensor.
+///
+/// Reference tensors are matched by exact name or through the name map chosen
+/// by [`names::detect`]. Shapes must agree after reversing the GGUF
+/// dimension order. For `llama` models, the row permutation that the GGUF
+/// converter applies to `attn_q` and `attn_k` is undone using the head counts
+/// in the GGUF metadata. Quantized GGUF tensors are dequantized with
+/// [`crate::dequant`].
+///
+/// `reference_reader` and `quantized_reader` must be the files the artifacts
+/// were parsed from.
+pub fn quant_report<RR: Read + Seek, RQ: Read + Seek>(
+    reference: &Artifact,
+    quantized: &Artifact,
+    reference_reader: &mut RR,
+    quantized_reader: &mut RQ,
+) -> Result<QuantReport, ValueError> {
+    let map = names::detect(reference, quantized);
+    let mut by_gguf_name: BTreeMap<String, &Tensor> = BTreeMap::new();
+    for (name, tensor) in &reference.tensors {
+        if let Some(mapped) = map.hf_to_gguf(name) {
+            by_gguf_name.entry(mapped).or_insert(tensor);
+        }
+    }
+    for (name, tensor) in &reference.tensors {
+        by_gguf_name.insert(name.clone(), tensor);
+    }
+
+    let mut report = QuantReport::default();
+    let mut layers: BTreeMap<String, ErrorAccumulator> = BTreeMap::new();
+    for (name, tensor) in &quantized.tensors {
+        let Some(&ref_tensor) = by_gguf_name.get(name) else {
+            report.unmatched.push(name.clone());
+            continue;
+        };
+        let reversed: Vec<u64> = tensor.shape.iter().rev().copied().collect();
+        if ref_tensor.shape != reversed && ref_tensor.shape != tensor.shape {
+            report.skipped.insert(
+                name.clone(),
+                format!(
+                    "shape {:?} does not match reference {:?}",
+                    tensor.shape, ref_tensor.shape
+                ),
+            );
+            continue;
+        }
+        let compared = match rope_heads(quantized, name) {
+            Some(heads) => compare_permuted(
+                ref_tensor,
+                tensor,
+                heads,
+                reference_reader,
+                quantized_reader,
+            ),
+            None => compare(ref_tensor, tensor, reference_reader, quantized_reader),
+        };
+        let acc = match compared {
+            Ok(acc) => acc,
+            Err((side, e)) => {
+                let reason = values::skip_reason(&e).ok_or(e)?;
+                report
+                    .skipped
+                    .insert(name.clone(), format!("{} in the {} file", reason, side));
+                continue;
+            }
+        };
+        layers.entry(layer_name(name)).or_default().merge(&acc);
+        let mut error = acc.finish(name.clone());
+        error.reference = Some(ref_tensor.name.clone());
+        error.dtype = Some(tensor.dtype.clone());
+        report.tensors.push(error);
+    }
+
+    report.layers = layers
+        .into_iter()
+        .map(|(name, acc)| acc.finish(name))
+        .collect();
+    report.tensors.sort_by(|a, b| a.snr_db.total_cmp(&b.snr_db));
+    report.layers.sort_by(|a, b| a.snr_db.total_cmp(&b.snr_db));
+    Ok(report)
+}
+
+/// `blk.N` for block tensors, otherwise the tensor name without its last
+/// component (`token_embd.weight` -> `token_embd`).
+fn layer_name(name: &str) -> String {
+    let mut parts = name.splitn(3, '.');
+    match (parts.next(), parts.next()) {
+        (Some("blk"), Some(n)) => format!("blk.{}", n),
+        _ => name
+            .rsplit_once('.')
+            .map_or(name, |(stem, _)| stem)
+            .to_string(),
+    }
+}
+
+/// Number of heads whose rows the llama converter permuted in this tensor.
+fn rope_heads(quantized: &Artifact, name: &str) -> Option<usize> {
+    let arch = match quantized.metadata.get("general.architecture") {
+        Some(CanonicalValue::String(arch)) => arch.as_str(),
+        _ => return None,
+    };
+    if arch != "llama" {
+        return None;
+    }
+    let key = if name.ends_with(".attn_q.weight") || name.ends_with(".attn_q.bias") {
+        "head_count"
+    } else if name.ends_with(".attn_k.weight") || name.ends_with(".attn_k.bias") {
+        "head_count_kv"
+    } else {
+        return None;
+    };
+    let value = quantized
+        .metadata
+        .get(&format!("{}.attention.{}", arch, key))
+        .or_else(|| {
+            quantized
+                .metadata
+                .get(&format!("{}.attention.head_count", arch))
+        })?;
+    match value {
+        CanonicalValue::Uint32(n) | CanonicalValue::Int32(n) | CanonicalValue::Uint64(n) => {
+            usize::try_from(*n).ok().filter(|&n| n > 0)
+        }
+        _ => None,
+    }
+}
+
+/// An error raised while comparing, with the file ("reference" or
+/// "quantized") it was raised on.
+type SideError = (&'static str, ValueError);
+
+fn on_reference(e: ValueError) -> SideError {
+    ("reference", e)
+}
+
+fn on_quantized(e: ValueError) -> SideError {
+    ("quantized", e)
+}
+
+fn compare<RR: Read + Seek, RQ: Read + Seek>(
+    reference: &Tensor,
+    quantized: &Tensor,
+    reference_reader: &mut RR,
+    quantized_reader: &mut RQ,
+) -> Result<ErrorAccumulator, SideError> {
+    const CHUNK: usize = 64 * 1024;
+    let mut values_ref = ValueReader::new(reference, reference_reader).map_err(on_reference)?;
+    let mut values_q = ValueReader::new(quantized, quantized_reader).map_err(on_quantized)?;
+    let (mut chunk_ref, mut chunk_q) = (Vec::new(), Vec::new());
+    let mut acc = ErrorAccumulator::default();
+    loop {
+        let n_ref = values_ref
+            .read_chunk(CHUNK, &mut chunk_ref)
+            .map_err(on_reference)?;
+        let n_q = values_q
+            .read_chunk(CHUNK, &mut chunk_q)
+            .map_err(on_quantized)?;
+        let n = n_ref.min(n_q);
+        if n == 0 {
+            break;
+        }
+        for (&r, &q) in chunk_ref.iter().zip(&chunk_q) {
+            acc.push(r, q);
+        }
+    }
+    Ok(acc)
+}
+
+/// Compare a tensor whose rows were permuted for rotary embeddings.
+///
+/// The converter reshapes each head's rows to `[2, head_dim / 2]` and swaps
+/// the two axes, so GGUF row `h * head_dim + 2 * i + j` holds reference row
+/// `h * head_dim + j * head_dim / 2 + i`. The reference tensor is loaded into
+/// memory to undo this.
+fn compare_permuted<RR: Read + Seek, RQ: Read + Seek>(
+    reference: &Tensor,
+    quantized: &Tensor,
+    heads: usize,
+    reference_reader: &mut RR,
+    quantized_reader: &mut RQ,
+) -> Result<ErrorAccumulator, SideError> {
+    let rows = reference.shape.first().copied().unwrap_or(1) as usize;
+    if !rows.is_multiple_of(2 * heads) {
+        return compare(reference, quantized, reference_reader, quantized_reader);
+    }
+    let head_dim = rows / heads;
+    let half = head_dim / 2;
+
+    let mut values_ref = ValueReader::new(reference, reference_reader).map_err(on_reference)?;
+    let mut ref_data = Vec::new();
+    let mut chunk = Vec::new();
+    while values_ref
+        .read_chunk(64 * 1024, &mut chunk)
+        .map_err(on_reference)?
+        > 0
+    {
+        ref_data.extend_from_slice(&chunk);
+    }
+    let cols = ref_data.len() / rows;
+
+    let mut values_q = ValueReader::new(quantized, quantized_reader).map_err(on_quantized)?;
+    let mut acc = ErrorAccumulator::default();
+    let mut index = 0;
+    while values_q
+        .read_chunk(64 * 1024, &mut chunk)
+        .map_err(on_quantized)?
+        > 0
+    {
+        for &q in &chunk {
+            let (row, col) = (index / cols, index % cols);
+            let (head, within) = (row / head_dim, row % head_dim);
+            let ref_row = head * head_dim + (within % 2) * half + within / 2;
+            if let Some(&r) = ref_data.get(ref_row * cols + col) {
+                acc.push(r, q);
+            }
+            index += 1;
+        }
+    }
+    Ok(acc)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Format;
+    use std::io::Cursor;
+
+    fn artifact(format: Format, tensors: &[(&str, &str, &[u64], Vec<u8>)]) -> (Artifact, Vec<u8>) {
+        let mut artifact = Artifact {
+            format,
+            gguf_version: None,
+            metadata: BTreeMap::new(),
+            tensors: BTreeMap::new(),
+            graph: None,
+            data_offset: Some(0),
+            findings: Vec::new(),
+        };
+        let mut data = Vec::new();
+        for (name, dtype, shape, bytes) in tensors {
+            artifact.tensors.insert(
+                name.to_string(),
+                Tensor {
+                    name: name.to_string(),
+                    dtype: dtype.to_string(),
+                    shape: shape.to_vec(),
+                    byte_length: bytes.len() as u64,
+                    data_offset: Some(data.len() as u64),
+                    digest: None,
+                    location: None,
+                    nnz: None,
+                    big_endian: false,
+                },
+            );
+            data.extend_from_slice(bytes);
+        }
+        (artifact, data)
+    }
+
+    fn f32_bytes(values: &[f32]) -> Vec<u8> {
+        values.iter().flat_map(|v| v.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn test_matches_hf_names_and_ranks_worst_first() {
+        let (reference, ref_data) = artifact(
+            Format::Safetensors,
+            &[
+                (
+                    "model.layers.0.mlp.up_proj.weight",
+                    "f32",
+                    &[1, 4],
+                    f32_bytes(&[1.0, 2.0, 3.0, 4.0]),
+                ),
+                ("model.norm.weight", "f32", &[2], f32_bytes(&[1.0, 1.0])),
+            ],
+        );
+        let (quantized, q_data) = artifact(
+            Format::GGUF,
+            &[
+                (
+                    "blk.0.ffn_up.weight",
+                    "f32",
+                    &[4, 1],
+                    f32_bytes(&[1.0, 2.0, 3.0, 5.0]),
+                ),
+                ("output_norm.weight", "f32", &[2], f32_bytes(&[1.0, 1.0])),
+                ("rope_freqs.weight", "f32", &[1], f32_bytes(&[0.0])),
+            ],
+        );
+
+        let report = quant_report(
+            &reference,
+            &quantized,
+            &mut Cursor::new(ref_data),
+            &mut Cursor::new(q_data),
+        )
+        .unwrap();
+
+        assert_eq!(report.unmatched, vec!["rope_freqs.weight"]);
+        assert_eq!(report.tensors.len(), 2);
+        let worst = &report.tensors[0];
+        assert_eq!(worst.name, "blk.0.ffn_up.weight");
+        assert_eq!(worst.max_error, 1.0);
+        assert_eq!(worst.rmse, 0.5);
+        assert!((worst.snr_db - 10.0 * 30f64.log10()).abs() < 1e-9);
+        assert!(report.tensors[1].snr_db.is_infinite());
+
+        let layer_names: Vec<&str> = report.layers.iter().map(|l| l.name.as_str()).collect();
+        assert_eq!(layer_names, vec!["blk.0", "output_norm"]);
+    }
+
+    #[test]
+    fn test_dequantizes_gguf_side() {
+        let (reference, ref_data) = artifact(
+            Format::Safetensors,
+            &[("w", "f32", &[32], f32_bytes(&[1.0; 32]))],
+        );
+        let mut block = vec![0x00, 0x3c]; // q8_0 scale 1.0
+        block.extend([1u8; 32]);
+        let (quantized, q_data) = artifact(Format::GGUF, &[("w", "q8_0", &[32], block)]);
+
+        let report = quant_report(
+            &reference,
+            &quantized,
+            &mut Cursor::new(ref_data),
+            &mut Cursor::new(q_data),
+        )
+        .unwrap();
+
+        assert_eq!(report.tensors[0].element_count, 32);
+        assert_eq!(report.tensors[0].max_error, 0.0);
+    }
+
+    #[test]
+    fn test_undoes_llama_rope_permutation() {
+        // One head with head_dim 4 and 1 column: rows [0, 1, 2, 3] are stored
+        // by the converter as [0, 2, 1, 3].
+        let (reference, ref_data) = artifact(
+            Format::Safetensors,
+            &[(
+                "model.layers.0.self_attn.q_proj.weight",
+                "f32",
+                &[4, 1],
+                f32_bytes(&[0.0, 1.0, 2.0, 3.0]),
+            )],
+        );
+        let (mut quantized, q_data) = artifact(
+            Format::GGUF,
+            &[(
+                "blk.0.attn_q.weight",
+                "f32",
+                &[1, 4],
+                f32_bytes(&[0.0, 2.0, 1.0, 3.0]),
+            )],
+        );
+        quantized.metadata.insert(
+            "general.architecture".to_string(),
+            CanonicalValue::String("llama".to_string()),
+        );
+        quantized.metadata.insert(
+            "llama.attention.head_count".to_string(),
+            CanonicalValue::Uint32(1),
+        );
+
+        let report = quant_report(
+            &reference,
+            &quantized,
+            &mut Cursor::new(ref_data.clone()),
+            &mut Cursor::new(q_data.clone()),
+        )
+        .unwrap();
+        assert_eq!(report.tensors[0].max_error, 0.0);
+
+        quantized.metadata.remove("general.architecture");
+        let report = quant_report(
+            &reference,
+            &quantized,
+            &mut Cursor::new(ref_data),
+            &mut Cursor::new(q_data),
+        )
+        .unwrap();
+        assert_eq!(report.tensors[0].max_error, 1.0);
+    }
+
+    #[test]
+    fn test_shape_mismatch_is_skipped() {
+        let (reference, ref_data) = artifact(
+            Format::Safetensors,
+            &[("w", "f32", &[2], f32_bytes(&[1.0, 2.0]))],
+        );
+        let (quantized, q_data) = artifact(
+            Format::GGUF,
+            &[("w", "f32", &[3], f32_bytes(&[1.0, 2.0, 3.0]))],
+        );
+
+        let report = quant_report(
+            &reference,
+            &quantized,
+            &mut Cursor::new(ref_data),
+            &mut Cursor::new(q_data),
+        )
+        .unwrap();
+
+        assert!(report.tensors.is_empty());
+        assert!(report.skipped.contains_key("w"));
+    }
+
+    #[test]
+    fn test_missing_offset_is_skipped() {
+        let (reference, ref_data) = artifact(
+            Format::Safetensors,
+            &[("w", "f32", &[2], f32_bytes(&[1.0, 2.0]))],
+        );
+        let (mut quantized, q_data) =
+            artifact(Format::GGUF, &[("w", "f32", &[2], f32_bytes(&[1.0, 2.0]))]);
+        quantized.tensors.get_mut("w").unwrap().data_offset = None;
+
+        let report = quant_report(
+            &reference,
+            &quantized,
+            &mut Cursor::new(ref_data),
+            &mut Cursor::new(q_data),
+        )
+        .unwrap();
+
+        assert!(report.tensors.is_empty());
+        assert_eq!(
+            report.skipped["w"],
+            "no recorded data offset in the quantized file"
+        );
+    }
+
+    #[test]
+    fn test_truncated_tensor_is_skipped() {
+        let (reference, ref_data) = artifact(
+            Format::Safetensors,
+            &[
+                ("a", "f32", &[2], f32_bytes(&[1.0, 2.0])),
+                ("b", "f32", &[2], f32_bytes(&[1.0, 2.0])),
+            ],
+        );
+
+        let report = quant_report(
+            &reference,
+            &reference,
+            &mut Cursor::new(&ref_data[..12]),
+            &mut Cursor::new(&ref_data),
+        )
+        .unwrap();
+
+        assert_eq!(report.tensors.len(), 1);
+        assert_eq!(
+            report.skipped["b"],
+            "data is truncated in the reference file"
+        );
+    }
+}
@@ -1,14 +1,32 @@
-use crate::types::{Artifact, CanonicalValue, Format, Tensor};
-use std::collections::BTreeMap;
+use crate::types::{Artifact, CanonicalValue, Finding, Format, ParseLimits, Severity, Tensor};
+use serde::de::{Deserialize, Deserializer, IgnoredAny, MapAccess, Visitor};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
 use std::io::{Read, Seek};
 use thiserror::Error;
 
+mod sharded;
+
+pub use sharded::{
+    is_sharded, parse_sharded, parse_sharded_with_limits, shard_paths, ShardedError,
+};
+
 #[derive(Error, Debug)]
 pub enum SafetensorsParserError {
     #[error("invalid safetensors JSON header")]
     InvalidHeader,
-    #[error("header size exceeds maximum allowed ({max} bytes): {size} bytes")]
+    #[error(
+        "header size {size} bytes exceeds max_header_size ({max} bytes); needs at least {size}"
+    )]
     HeaderTooLarge { size: usize, max: usize },
+    #[error("tensor count {count} exceeds max_tensor_count ({max}); needs at least {count}")]
+    TensorCountTooLarge { count: u64, max: u64 },
+    #[error("metadata count {count} exceeds max_metadata_count ({max}); needs at least {count}")]
+    MetadataCountTooLarge { count: u64, max: u64 },
+    #[error(
+        "tensor '{name}' dimensions {dims} exceed max_dimensions ({max}); needs at least {dims}"
+    )]
+    DimensionsTooLarge { name: String, dims: u32, max: u32 },
     #[error("invalid tensor length for '{name}': offset {offset} > {end}")]
     InvalidByteLength { name: String, offset: u64, end: u64 },
     #[error("missing required field '{field}' for tensor '{name}'")]
@@ -21,8 +39,6 @@ pub enum SafetensorsParserError {
     JsonError(#[from] serde_json::Error),
 }
 
-const MAX_HEADER_SIZE: usize = 100 * 1024 * 1024; // 100MB
-
 /// Parse a safetensors model file.
 ///
 /// # Example
@@ -38,7 +54,15 @@ const MAX_HEADER_SIZE: usize = 100 * 1024 * 1024; // 100MB
 pub fn parse_safetensors<R: Read + Seek>(
     reader: &mut R,
 ) -> Result<Artifact, SafetensorsParserError> {
-    let header_size = read_header_size(reader)?;
+    parse_safetensors_with_limits(reader, &ParseLimits::default())
+}
+
+/// Parse a safetensors model file, enforcing the given safety limits.
+pub fn parse_safetensors_with_limits<R: Read + Seek>(
+    reader: &mut R,
+    limits: &ParseLimits,
+) -> Result<Artifact, SafetensorsParserError> {
+    let header_size = read_header_size(reader, limits.max_header_size)?;
     let mut header_buf = vec![0u8; header_size];
     reader.read_exact(&mut header_buf)?;
     let header_str =
@@ -51,12 +75,47 @@ pub fn parse_safetensors<R: Read + Seek>(
         .as_object()
         .ok_or(SafetensorsParserError::InvalidHeader)?;
 
+    let tensor_count = obj.keys().filter(|k| *k != "__metadata__").count() as u64;
+    if tensor_count > limits.max_tensor_count {
+        return Err(SafetensorsParserError::TensorCountTooLarge {
+            count: tensor_count,
+            max: limits.max_tensor_count,
+        });
+    }
+
+    let mut findings = Vec::new();
+    let HeaderKeys(keys) =
+        serde_json::from_str(&header_str).map_err(|_| SafetensorsParserError::InvalidHeader)?;
+    let mut seen = BTreeSet::new();
+    let mut duplicates = BTreeSet::new();
+    for key in keys {
+        if !seen.insert(key.clone()) {
+            duplicates.insert(key);
+        }
+    }
+    for key in duplicates {
+        findings.push(Finding::new(
+            Severity::Error,
+            "duplicate_tensor",
+            Some(&key),
+            "header key appears more than once; only one entry is used".to_string(),
+        ));
+    }
+
+    // Data offsets are relative to the byte buffer that follows the header.
+    let data_start = 8 + header_size as u64;
     let mut metadata = BTreeMap::new();
     let mut tensors = BTreeMap::new();
 
     for (key, value) in obj {
         if key == "__metadata__" {
             if let Some(meta_obj) = value.as_object() {
+                if meta_obj.len() as u64 > limits.max_metadata_count {
+                    return Err(SafetensorsParserError::MetadataCountTooLarge {
+                        count: meta_obj.len() as u64,
+                        max: limits.max_metadata_count,
+                    });
+                }
                 for (mk, mv) in meta_obj {
                     let cv = match mv {
                         serde_json::Value::String(s) => CanonicalValue::String(s.clone()),
@@ -88,6 +147,13 @@ pub fn parse_safetensors<R: Read + Seek>(
             };
             let shape: Vec<u64> = match tensor_obj.get("shape").and_then(|v| v.as_array()) {
                 Some(arr) => {
+                    if arr.len() > limits.max_dimensions as usize {
+                        return Err(SafetensorsParserError::DimensionsTooLarge {
+                            name: key.clone(),
+                            dims: arr.len() as u32,
+                            max: limits.max_dimensions,
+                        });
+                    }
                     let mut dims = Vec::with_capacity(arr.len());
                     for (i, v) in arr.iter().enumerate() {
                         let dim =
@@ -149,6 +215,11 @@ pub fn parse_safetensors<R: Read + Seek>(
                     dtype,
                     shape,
                     byte_length,
+                    data_offset: data_start.checked_add(offset),
+                    digest: None,
+                    location: None,
+                    nnz: None,
+                    big_endian: false,
                 },
             );
         }
@@ -159,17 +230,52 @@ pub fn parse_safetensors<R: Read + Seek>(
         gguf_version: None,
         metadata,
         tensors,
+        graph: None,
+        data_offset: Some(data_start),
+        findings,
     })
 }
 
-fn read_header_size<R: Read + Seek>(reader: &mut R) -> Result<usize, SafetensorsParserError> {
+/// Top-level header keys in file order, including duplicates that
+/// `serde_json::Value` would silently merge.
+struct HeaderKeys(Vec<String>);
+
+impl<'de> Deserialize<'de> for HeaderKeys {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct KeysVisitor;
+
+        impl<'de> Visitor<'de> for KeysVisitor {
+            type Value = HeaderKeys;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a JSON object")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<HeaderKeys, A::Error> {
+                let mut keys = Vec::new();
+                while let Some(key) = map.next_key::<String>()? {
+                    map.next_value::<IgnoredAny>()?;
+                    keys.push(key);
+                }
+                Ok(HeaderKeys(keys))
+            }
+        }
+
+        deserializer.deserialize_map(KeysVisitor)
+    }
+}
+
+fn read_header_size<R: Read + Seek>(
+    reader: &mut R,
+    max_header_size: usize,
+) -> Result<usize, SafetensorsParserError> {
     let mut buf = [0u8; 8];
     reader.read_exact(&mut buf)?;
     let size = u64::from_le_bytes(buf) as usize;
-    if size > MAX_HEADER_SIZE {
+    if size > max_header_size {
         return Err(SafetensorsParserError::HeaderTooLarge {
             size,
-            max: MAX_HEADER_SIZE,
+            max: max_header_size,
         });
     }
     Ok(size)
@@ -183,7 +289,7 @@ mod tests {
     fn make_safetensors(header_json: &str) -> Vec<u8> {
         let header_bytes = header_json.as_bytes();
         let header_len = header_bytes.len() as u64;
-        let padded_len = ((header_len as usize) + 7) / 8 * 8;
+        let padded_len = (header_len as usize).div_ceil(8) * 8;
 
         let mut data = Vec::new();
         data.extend_from_slice(&header_len.to_le_bytes());
@@ -313,7 +419,7 @@ mod tests {
     #[test]
     fn test_header_too_large() {
         let mut data = vec![0u8; 8];
-        let too_big = (MAX_HEADER_SIZE + 1) as u64;
+        let too_big = (ParseLimits::default().max_header_size + 1) as u64;
         data[0..8].copy_from_slice(&too_big.to_le_bytes());
         let mut cursor = Cursor::new(data);
 
@@ -325,6 +431,61 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_tensor_count_limit() {
+        let header = r#"{
+            "tensor1": {"dtype": "F32", "shape": [2], "data_offsets": [0, 8]},
+            "tensor2": {"dtype": "F32", "shape": [2], "data_offsets": [8, 16]}
+        }"#;
+        let data = make_safetensors(header);
+        let limits = ParseLimits {
+            max_tensor_count: 1,
+            ..ParseLimits::default()
+        };
+
+        let result = parse_safetensors_with_limits(&mut Cursor::new(data), &limits);
+        assert!(matches!(
+            result.unwrap_err(),
+            SafetensorsParserError::TensorCountTooLarge { count: 2, max: 1 }
+        ));
+    }
+
+    #[test]
+    fn test_dimensions_limit() {
+        let header = r#"{"t":{"dtype":"F32","shape":[1,1,1],"data_offsets":[0,4]}}"#;
+        let data = make_safetensors(header);
+        let limits = ParseLimits {
+            max_dimensions: 2,
+            ..ParseLimits::default()
+        };
+
+        let result = parse_safetensors_with_limits(&mut Cursor::new(data), &limits);
+        assert!(matches!(
+            result.unwrap_err(),
+            SafetensorsParserError::DimensionsTooLarge {
+                dims: 3,
+                max: 2,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_duplicate_tensor_names_are_reported() {
+        let header = r#"{
+            "t": {"dtype": "F32", "shape": [2], "data_offsets": [0, 8]},
+            "t": {"dtype": "F32", "shape": [2], "data_offsets": [8, 16]}
+        }"#;
+        let data = make_safetensors(header);
+
+        let artifact = parse_safetensors(&mut Cursor::new(data)).unwrap();
+
+        assert_eq!(artifact.tensors.len(), 1);
+        assert_eq!(artifact.findings.len(), 1);
+        assert_eq!(artifact.findings[0].code, "duplicate_tensor");
+        assert_eq!(artifact.findings[0].subject.as_deref(), Some("t"));
+    }
+
     #[test]
     fn test_multiple_tensors() {
         let header = r#"{
@@ -0,0 +1,568 @@
+use super::{parse_safetensors_with_limits, HeaderKeys, SafetensorsParserError};
+use crate::types::{Artifact, Finding, Format, ParseLimits, Severity};
+use crate::validate;
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Name of the index written by `transformers` for sharded checkpoints.
+const DEFAULT_INDEX: &str = "model.safetensors.index.json";
+const INDEX_SUFFIX: &str = ".safetensors.index.json";
+
+#[derive(Error, Debug)]
+pub enum ShardedError {
+    #[error("failed to read '{path}': {source}")]
+    Io { path: String, source: io::Error },
+    #[error("invalid shard index '{path}': {reason}")]
+    InvalidIndex { path: String, reason: String },
+    #[error("no .safetensors files found in '{path}'")]
+    NoShards { path: String },
+    #[error("'{path}' contains several shard indexes; pass one of them instead")]
+    AmbiguousIndex { path: String },
+    #[error("failed to parse shard '{path}': {source}")]
+    Shard {
+        path: String,
+        source: SafetensorsParserError,
+    },
+}
+
+fn io_error(path: &Path) -> impl FnOnce(io::Error) -> ShardedError + '_ {
+    move |source| ShardedError::Io {
+        path: path.display().to_string(),
+        source,
+    }
+}
+
+/// Whether `path` names a sharded checkpoint: a directory or a
+/// `*.safetensors.index.json` file.
+pub fn is_sharded(path: &Path) -> bool {
+    path.is_dir()
+        || path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.ends_with(INDEX_SUFFIX))
+}
+
+/// The contents of a `*.safetensors.index.json` file.
+struct ShardIndex {
+    path: PathBuf,
+    /// Tensor name to shard file name.
+    weight_map: BTreeMap<String, String>,
+    /// Tensor names listed more than once in `weight_map`.
+    duplicates: BTreeSet<String>,
+}
+
+#[derive(Deserialize)]
+struct IndexKeys {
+    weight_map: HeaderKeys,
+}
+
+impl ShardIndex {
+    fn read(path: &Path, limits: &ParseLimits) -> Result<Self, ShardedError> {
+        let invalid = |reason: String| ShardedError::InvalidIndex {
+            path: path.display().to_string(),
+            reason,
+        };
+        let size = std::fs::metadata(path).map_err(io_error(path))?.len();
+        if size > limits.max_header_size as u64 {
+            return Err(invalid(format!(
+                "{} bytes exceeds max_header_size ({} bytes)",
+                size, limits.max_header_size
+            )));
+        }
+        let text = std::fs::read_to_string(path).map_err(io_error(path))?;
+        let json: serde_json::Value =
+            serde_json::from_str(&text).map_err(|e| invalid(e.to_string()))?;
+        let entries = json
+            .get("weight_map")
+            .and_then(|v| v.as_object())
+            .ok_or_else(|| invalid("missing 'weight_map' object".to_string()))?;
+        if entries.len() as u64 > limits.max_tensor_count {
+            return Err(invalid(format!(
+                "{} tensors exceeds max_tensor_count ({})",
+                entries.len(),
+                limits.max_tensor_count
+            )));
+        }
+
+        let mut weight_map = BTreeMap::new();
+        for (name, file) in entries {
+            let file = file
+                .as_str()
+                .ok_or_else(|| invalid(format!("shard of '{}' is not a string", name)))?;
+            // Shards live next to the index; refuse anything that points elsewhere.
+            if file.is_empty() || file.contains(['/', '\\']) || file == ".." {
+                return Err(invalid(format!(
+                    "shard '{}' of '{}' is not a plain file name",
+                    file, name
+                )));
+            }
+            weight_map.insert(name.clone(), file.to_string());
+        }
+
+        let IndexKeys {
+            weight_map: HeaderKeys(keys),
+        } = serde_json::from_str(&text).map_err(|e| invalid(e.to_string()))?;
+        let mut seen = BTreeSet::new();
+        let duplicates = keys
+            .into_iter()
+            .filter(|k| !seen.insert(k.clone()))
+            .collect();
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            weight_map,
+            duplicates,
+        })
+    }
+
+    fn dir(&self) -> &Path {
+        self.path.parent().unwrap_or(Path::new(""))
+    }
+
+    /// Shard paths in sorted file name order.
+    fn shard_paths(&self) -> Vec<PathBuf> {
+        let files: BTreeSet<&String> = self.weight_map.values().collect();
+        files
+            .into_iter()
+            .map(|file| self.dir().join(file))
+            .collect()
+    }
+}
+
+/// Find the index of a sharded checkpoint directory, if it has one.
+fn find_index(dir: &Path) -> Result<Option<PathBuf>, ShardedError> {
+    let default = dir.join(DEFAULT_INDEX);
+    if default.is_file() {
+        return Ok(Some(default));
+    }
+    let mut indexes = Vec::new();
+    for entry in std::fs::read_dir(dir).map_err(io_error(dir))? {
+        let path = entry.map_err(io_error(dir))?.path();
+        if path.is_file() && is_sharded(&path) {
+            indexes.push(path);
+        }
+    }
+    match indexes.len() {
+        0 => Ok(None),
+        1 => Ok(indexes.pop()),
+        _ => Err(ShardedError::AmbiguousIndex {
+            path: dir.display().to_string(),
+        }),
+    }
+}
+
+/// Every `*.safetensors` file in `dir`, sorted by name.
+fn safetensors_files(dir: &Path) -> Result<Vec<PathBuf>, ShardedError> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir).map_err(io_error(dir))? {
+        let path = entry.map_err(io_error(dir))?.path();
+        if path.is_file() && path.extension().is_some_and(|e| e == "safetensors") {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Every `*.safetensors` file in `dir`, sorted by name; there must be one.
+fn list_shards(dir: &Path) -> Result<Vec<PathBuf>, ShardedError> {
+    let shards = safetensors_files(dir)?;
+    if shards.is_empty() {
+        return Err(ShardedError::NoShards {
+            path: dir.display().to_string(),
+        });
+    }
+    Ok(shards)
+}
+
+fn resolve(
+    path: &Path,
+    limits: &ParseLimits,
+) -> Result<(Option<ShardIndex>, Vec<PathBuf>), ShardedError> {
+    let index_path = if path.is_dir() {
+        find_index(path)?
+    } else {
+        Some(path.to_path_buf())
+    };
+    match index_path {
+        Some(index_path) => {
+            let index = ShardIndex::read(&index_path, limits)?;
+            let shards = index.shard_paths();
+            if shards.is_empty() {
+                return Err(ShardedError::NoShards {
+                    path: index_path.display().to_string(),
+                });
+            }
+            Ok((Some(index), shards))
+        }
+        None => Ok((None, list_shards(path)?)),
+    }
+}
+
+/// The shard files of a sharded checkpoint, in the order
+/// [`parse_sharded`] concatenates them.
+///
+/// `path` is an index file or a directory. A directory uses its
+/// `model.safetensors.index.json` (or its only `*.safetensors.index.json`),
+/// and otherwise every `*.safetensors` file in it. `limits` apply to the
+/// index as in [`parse_sharded_with_limits`].
+pub fn shard_paths(path: &Path, limits: &ParseLimits) -> Result<Vec<PathBuf>, ShardedError> {
+    resolve(path, limits).map(|(_, shards)| shards)
+}
+
+/// Parse a sharded safetensors checkpoint into a single artifact.
+///
+/// `path` is an index file such as `model.safetensors.index.json` or a
+/// directory (see [`shard_paths`]). Tensors and metadata of every shard are
+/// merged, so the structural hash matches that of the same checkpoint saved
+/// as one file. Tensor data offsets point into the shards laid end to end,
+/// which is what a [`ShardReader`](crate::shards::ShardReader) opened on
+/// [`shard_paths`] reads.
+///
+/// Problems found while merging are recorded in
+/// [`Artifact::findings`]:
+/// - `index_missing_tensor`: listed in the index but in no shard.
+/// - `index_wrong_shard`: stored in another shard than the index says.
+/// - `index_unlisted_tensor`: stored in a shard but not listed in the index.
+/// - `index_unlisted_shard`: a `*.safetensors` file in the given directory
+///   that the index does not name; its tensors are not read.
+/// - `index_duplicate_tensor`: listed more than once in the index.
+/// - `duplicate_tensor`: stored in more than one shard.
+/// - `shard_metadata_conflict`: shards disagree on a metadata value.
+///
+/// Each shard's own parser and layout findings are included too, prefixed
+/// with the shard's file name. Run [`validate::check_tensors`] on the result
+/// for the per-tensor checks; [`validate::validate`] would also check the
+/// layout of the merged offsets, which span several files.
+pub fn parse_sharded(path: &Path) -> Result<Artifact, ShardedError> {
+    parse_sharded_with_limits(path, &ParseLimits::default())
+}
+
+/// Parse a sharded safetensors checkpoint, enforcing the given safety limits
+/// on the index and on every shard.
+pub fn parse_sharded_with_limits(
+    path: &Path,
+    limits: &ParseLimits,
+) -> Result<Artifact, ShardedError> {
+    let (index, shards) = resolve(path, limits)?;
+
+    let mut merged = Artifact {
+        format: Format::Safetensors,
+        gguf_version: None,
+        metadata: BTreeMap::new(),
+        tensors: BTreeMap::new(),
+        graph: None,
+        data_offset: None,
+        findings: Vec::new(),
+    };
+    let mut location: BTreeMap<String, String> = BTreeMap::new();
+    let mut base = 0u64;
+    for shard_path in &shards {
+        let file_name = shard_path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let file = File::open(shard_path).map_err(io_error(shard_path))?;
+        let size = file.metadata().map_err(io_error(shard_path))?.len();
+        let shard =
+            parse_safetensors_with_limits(&mut BufReader::new(file), limits).map_err(|source| {
+                ShardedError::Shard {
+                    path: shard_path.display().to_string(),
+                    source,
+                }
+            })?;
+
+        let layout = validate::check_layout(&shard, size);
+        for mut finding in shard.findings.into_iter().chain(layout) {
+            finding.message = format!("{}: {}", file_name, finding.message);
+            merged.findings.push(finding);
+        }
+        for (key, value) in shard.metadata {
+            match merged.metadata.get(&key) {
+                Some(existing) if *existing != value => merged.findings.push(Finding::new(
+                    Severity::Warning,
+                    "shard_metadata_conflict",
+                    Some(&key),
+                    format!(
+                        "'{}' has {} but an earlier shard has {}; keeping the first",
+                        file_name, value, existing
+                    ),
+                )),
+                Some(_) => {}
+                None => {
+                    merged.metadata.insert(key, value);
+                }
+            }
+        }
+        for (name, mut tensor) in shard.tensors {
+            if let Some(first) = location.get(&name) {
+                merged.findings.push(Finding::new(
+                    Severity::Error,
+                    "duplicate_tensor",
+                    Some(&name),
+                    format!(
+                        "stored in both '{}' and '{}'; only the first is used",
+                        first, file_name
+                    ),
+                ));
+                continue;
+            }
+            tensor.data_offset = tensor.data_offset.and_then(|o| o.checked_add(base));
+            location.insert(name.clone(), file_name.clone());
+            merged.tensors.insert(name, tensor);
+        }
+        base += size;
+    }
+
+    if let Some(index) = index {
+        for name in &index.duplicates {
+            merged.findings.push(Finding::new(
+                Severity::Error,
+                "index_duplicate_tensor",
+                Some(name),
+                "listed more than once in the index".to_string(),
+            ));
+        }
+        for (name, file) in &index.weight_map {
+            match location.get(name) {
+                None => merged.findings.push(Finding::new(
+                    Severity::Error,
+                    "index_missing_tensor",
+                    Some(name),
+                    format!(
+                        "listed in the index for '{}' but not found in any shard",
+                        file
+                    ),
+                )),
+                Some(actual) if actual != file => merged.findings.push(Finding::new(
+                    Severity::Warning,
+                    "index_wrong_shard",
+                    Some(name),
+                    format!(
+                        "listed in the index for '{}' but stored in '{}'",
+                        file, actual
+                    ),
+                )),
+                Some(_) => {}
+            }
+        }
+        for (name, file) in &location {
+            if !index.weight_map.contains_key(name) {
+                merged.findings.push(Finding::new(
+                    Severity::Warning,
+                    "index_unlisted_tensor",
+                    Some(name),
+                    format!("stored in '{}' but not listed in the index", file),
+                ));
+            }
+        }
+        if path.is_dir() {
+            let listed: BTreeSet<&String> = index.weight_map.values().collect();
+            for file in safetensors_files(path)? {
+                let file_name = file
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                if !listed.contains(&file_name) {
+                    merged.findings.push(Finding::new(
+                        Severity::Warning,
+                        "index_unlisted_shard",
+                        Some(&file_name),
+                        "not named in the index; its tensors are not read".to_string(),
+                    ));
+                }
+            }
+        }
+    }
+
+    Ok(merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash;
+    use crate::shards::ShardReader;
+    use std::io::Write;
+
+    fn safetensors_bytes(tensors: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut entries = Vec::new();
+        let mut data = Vec::new();
+        for (name, bytes) in tensors {
+            entries.push(format!(
+                r#""{}":{{"dtype":"U8","shape":[{}],"data_offsets":[{},{}]}}"#,
+                name,
+                bytes.len(),
+                data.len(),
+                data.len() + bytes.len()
+            ));
+            data.extend_from_slice(bytes);
+        }
+        let header = format!(
+            r#"{{"__metadata__":{{"format":"pt"}},{}}}"#,
+            entries.join(",")
+        );
+        let mut out = (header.len() as u64).to_le_bytes().to_vec();
+        out.extend(header.as_bytes());
+        out.extend(data);
+        out
+    }
+
+    fn write(dir: &Path, name: &str, bytes: &[u8]) {
+        File::create(dir.join(name))
+            .unwrap()
+            .write_all(bytes)
+            .unwrap();
+    }
+
+    fn write_index(dir: &Path, weight_map: &str) {
+        let index = format!(
+            r#"{{"metadata":{{"total_size":12}},"weight_map":{{{}}}}}"#,
+            weight_map
+        );
+        write(dir, DEFAULT_INDEX, index.as_bytes());
+    }
+
+    fn sharded_checkpoint() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let first = safetensors_bytes(&[("a", b"aaaa"), ("b", b"bbbb")]);
+        let second = safetensors_bytes(&[("c", b"cccc")]);
+        write(dir.path(), "model-00001-of-00002.safetensors", &first);
+        write(dir.path(), "model-00002-of-00002.safetensors", &second);
+        write_index(
+            dir.path(),
+            r#""a":"model-00001-of-00002.safetensors","b":"model-00001-of-00002.safetensors","c":"model-00002-of-00002.safetensors""#,
+        );
+        dir
+    }
+
+    #[test]
+    fn test_merged_hashes_match_single_file() {
+        let dir = sharded_checkpoint();
+        let single = safetensors_bytes(&[("a", b"aaaa"), ("b", b"bbbb"), ("c", b"cccc")]);
+        let mut cursor = io::Cursor::new(single);
+        let expected = parse_safetensors_with_limits(&mut cursor, &ParseLimits::default()).unwrap();
+
+        for path in [dir.path().to_path_buf(), dir.path().join(DEFAULT_INDEX)] {
+            let merged = parse_sharded(&path).unwrap();
+            assert!(merged.findings.is_empty(), "{:?}", merged.findings);
+            assert_eq!(merged.tensors.len(), 3);
+            assert_eq!(
+                hash::compute_structural_hash(&merged).unwrap(),
+                hash::compute_structural_hash(&expected).unwrap()
+            );
+
+            let mut reader =
+                ShardReader::open(&shard_paths(&path, &ParseLimits::default()).unwrap()).unwrap();
+            assert_eq!(
+                hash::compute_content_hash(&merged, &mut reader).unwrap(),
+                hash::compute_content_hash(&expected, &mut cursor).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_directory_without_index() {
+        let dir = sharded_checkpoint();
+        std::fs::remove_file(dir.path().join(DEFAULT_INDEX)).unwrap();
+
+        let merged = parse_sharded(dir.path()).unwrap();
+
+        assert_eq!(merged.tensors.len(), 3);
+        assert!(merged.findings.is_empty());
+    }
+
+    #[test]
+    fn test_index_mismatches_are_reported() {
+        let dir = sharded_checkpoint();
+        write_index(
+            dir.path(),
+            r#""a":"model-00001-of-00002.safetensors","a":"model-00001-of-00002.safetensors","b":"model-00002-of-00002.safetensors","gone":"model-00002-of-00002.safetensors""#,
+        );
+
+        let merged = parse_sharded(dir.path()).unwrap();
+        let codes: Vec<_> = merged
+            .findings
+            .iter()
+            .map(|f| (f.code, f.subject.as_deref().unwrap()))
+            .collect();
+
+        assert_eq!(
+            codes,
+            vec![
+                ("index_duplicate_tensor", "a"),
+                ("index_wrong_shard", "b"),
+                ("index_missing_tensor", "gone"),
+                ("index_unlisted_tensor", "c"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unlisted_shard_is_reported() {
+        let dir = sharded_checkpoint();
+        write(
+            dir.path(),
+            "model-00005-of-00004.safetensors",
+            &safetensors_bytes(&[("d", b"dddd")]),
+        );
+
+        let merged = parse_sharded(dir.path()).unwrap();
+
+        assert_eq!(merged.tensors.len(), 3);
+        assert_eq!(merged.findings.len(), 1);
+        assert_eq!(merged.findings[0].code, "index_unlisted_shard");
+        assert_eq!(
+            merged.findings[0].subject.as_deref(),
+            Some("model-00005-of-00004.safetensors")
+        );
+
+        let from_index = parse_sharded(&dir.path().join(DEFAULT_INDEX)).unwrap();
+        assert!(from_index.findings.is_empty());
+    }
+
+    #[test]
+    fn test_tensor_in_two_shards() {
+        let dir = tempfile::tempdir().unwrap();
+        write(
+            dir.path(),
+            "a.safetensors",
+            &safetensors_bytes(&[("w", b"1111")]),
+        );
+        write(
+            dir.path(),
+            "b.safetensors",
+            &safetensors_bytes(&[("w", b"2222")]),
+        );
+
+        let merged = parse_sharded(dir.path()).unwrap();
+
+        assert_eq!(merged.findings.len(), 1);
+        assert_eq!(merged.findings[0].code, "duplicate_tensor");
+        assert_eq!(merged.findings[0].severity, Severity::Error);
+    }
+
+    #[test]
+    fn test_index_rejects_paths_outside_directory() {
+        let dir = sharded_checkpoint();
+        write_index(dir.path(), r#""a":"../model.safetensors""#);
+
+        assert!(matches!(
+            parse_sharded(dir.path()),
+            Err(ShardedError::InvalidIndex { .. })
+        ));
+    }
+
+    #[test]
+    fn test_empty_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(matches!(
+            parse_sharded(dir.path()),
+            Err(ShardedError::NoShards { .. })
+        ));
+    }
+}
@@ -0,0 +1,608 @@
+use crate::pickle::{self, Arg, GlobalRef, Instruction, PickleError};
+use crate::types::{Finding, ParseLimits, Severity};
+use crate::zip::{self, ZipError, STORED};
+use serde::Serialize;
+use std::io::{Read, Seek, SeekFrom};
+use thiserror::Error;
+
+/// First byte of a protocol 2+ pickle (`PROTO`).
+const PROTO: u8 = 0x80;
+
+#[derive(Error, Debug)]
+pub enum ScanError {
+    #[error("not a pickle or a zip archive of pickles")]
+    NotPickle,
+    #[error("invalid archive: {0}")]
+    Zip(#[from] ZipError),
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+}
+
+/// A global imported by a pickle stream.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Import {
+    /// `None` for a `STACK_GLOBAL` whose operands could not be determined.
+    pub module: Option<String>,
+    pub name: Option<String>,
+    /// Byte offset of the importing opcode within the stream.
+    pub position: usize,
+    pub severity: Severity,
+}
+
+/// One pickle stream found in a file.
+#[derive(Debug, Clone, Serialize)]
+pub struct Stream {
+    /// The zip entry holding the stream, or `None` for a bare pickle file.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entry: Option<String>,
+    /// Byte offset of the stream within the file or entry.
+    pub offset: u64,
+    /// Length of the stream in bytes, up to its `STOP` or to where
+    /// decoding failed.
+    pub length: u64,
+    pub opcodes: usize,
+    pub imports: Vec<Import>,
+    /// Every decoded opcode, for printing a disassembly.
+    #[serde(skip)]
+    pub instructions: Vec<Instruction>,
+}
+
+/// Result of scanning a file's pickle streams.
+#[derive(Debug, Clone, Serialize)]
+pub struct ScanReport {
+    pub streams: Vec<Stream>,
+    pub findings: Vec<Finding>,
+}
+
+impl ScanReport {
+    /// Number of findings of the given severity.
+    pub fn count(&self, severity: Severity) -> usize {
+        self.findings
+            .iter()
+            .filter(|f| f.severity == severity)
+            .count()
+    }
+}
+
+/// How risky it is for a pickle to import `module.name`.
+///
+/// - [`Severity::Info`]: the rebuild functions, storage types and containers
+///   that PyTorch checkpoints and NumPy arrays are made of.
+/// - [`Severity::Error`]: globals that run commands, evaluate code, import
+///   modules or touch files and sockets when called, e.g. `os.system`,
+///   `builtins.eval`, `subprocess.Popen` or `runpy._run_code`.
+/// - [`Severity::Warning`]: anything else. Unpickling imports the module,
+///   which runs its import-time code, and may call the global.
+///
+/// # Example
+///
+/// ```
+/// use weight_inspect::scan::global_severity;
+/// use weight_inspect::types::Severity;
+///
+/// assert_eq!(global_severity("torch._utils", "_rebuild_tensor_v2"), Severity::Info);
+/// assert_eq!(global_severity("posix", "system"), Severity::Error);
+/// assert_eq!(global_severity("__main__", "MyModel"), Severity::Warning);
+/// ```
+pub fn global_severity(module: &str, name: &str) -> Severity {
+    if allowed_global(module, name) {
+        Severity::Info
+    } else if dangerous_global(module, name) {
+        Severity::Error
+    } else {
+        Severity::Warning
+    }
+}
+
+/// Whether a checkpoint of plain tensors and containers may import a global.
+fn allowed_global(module: &str, name: &str) -> bool {
+    match module {
+        "collections" => name == "OrderedDict",
+        "torch._utils" => name.starts_with("_rebuild_"),
+        "torch._tensor" => name == "_rebuild_from_type_v2",
+        "torch" => {
+            name.ends_with("Storage")
+                || matches!(name, "Size" | "Tensor" | "device")
+                || TORCH_DTYPES.contains(&name)
+        }
+        "torch.storage" => matches!(name, "UntypedStorage" | "TypedStorage"),
+        "torch.nn.parameter" => name == "Parameter",
+        "numpy.core.multiarray" | "numpy._core.multiarray" => {
+            matches!(name, "_reconstruct" | "scalar")
+        }
+        "numpy" => matches!(name, "ndarray" | "dtype"),
+        "_codecs" => name == "encode",
+        "builtins" | "__builtin__" => matches!(name, "set" | "frozenset" | "slice"),
+        _ => false,
+    }
+}
+
+/// `torch` attributes naming dtypes, pickled as globals.
+const TORCH_DTYPES: &[&str] = &[
+    "float64",
+    "float32",
+    "float16",
+    "bfloat16",
+    "int64",
+    "int32",
+    "int16",
+    "int8",
+    "uint8",
+    "bool",
+    "complex64",
+    "complex128",
+    "float8_e4m3fn",
+    "float8_e5m2",
+    "double",
+    "float",
+    "half",
+    "long",
+    "int",
+    "short",
+    "cfloat",
+    "cdouble",
+];
+
+/// Top-level modules whose globals can run commands or code, load native
+/// libraries, or reach the file system and the network.
+const DANGEROUS_MODULES: &[&str] = &[
+    "os",
+    "posix",
+    "nt",
+    "sys",
+    "subprocess",
+    "_posixsubprocess",
+    "pty",
+    "runpy",
+    "importlib",
+    "zipimport",
+    "imp",
+    "code",
+    "codeop",
+    "commands",
+    "popen2",
+    "platform",
+    "shutil",
+    "tempfile",
+    "pathlib",
+    "io",
+    "_io",
+    "fileinput",
+    "socket",
+    "http",
+    "httplib",
+    "urllib",
+    "urllib2",
+    "requests",
+    "ftplib",
+    "smtplib",
+    "telnetlib",
+    "webbrowser",
+    "ctypes",
+    "_ctypes",
+    "cffi",
+    "multiprocessing",
+    "threading",
+    "_thread",
+    "asyncio",
+    "signal",
+    "pickle",
+    "_pickle",
+    "cPickle",
+    "dill",
+    "marshal",
+    "shelve",
+    "pdb",
+    "bdb",
+    "timeit",
+    "profile",
+    "cProfile",
+    "trace",
+    "pip",
+    "ensurepip",
+    "venv",
+    "setuptools",
+    "distutils",
+];
+
+/// Builtins that evaluate code, import modules, open files or reach any
+/// attribute of another object.
+const DANGEROUS_BUILTINS: &[&str] = &[
+    "eval",
+    "exec",
+    "execfile",
+    "compile",
+    "open",
+    "file",
+    "input",
+    "__import__",
+    "getattr",
+    "setattr",
+    "delattr",
+    "globals",
+    "locals",
+    "vars",
+    "breakpoint",
+    "reload",
+];
+
+fn dangerous_global(module: &str, name: &str) -> bool {
+    let root = module.split('.').next().unwrap_or(module);
+    match root {
+        "builtins" | "__builtin__" => DANGEROUS_BUILTINS.contains(&name),
+        "operator" | "_operator" => matches!(name, "attrgetter" | "methodcaller"),
+        _ => DANGEROUS_MODULES.contains(&root),
+    }
+}
+
+/// The finding for a global imported by the pickle at `location`, if it is
+/// not one a checkpoint needs.
+pub(crate) fn global_finding(location: &str, global: &GlobalRef) -> Option<Finding> {
+    let name = format!("{}.{}", global.module, global.name);
+    let severity = global_severity(&global.module, &global.name);
+    let (code, consequence) = match severity {
+        Severity::Info => return None,
+        Severity::Warning => ("unexpected_global", "unpickling imports it and may call it"),
+        Severity::Error => (
+            "dangerous_global",
+            "unpickling can run arbitrary code through it",
+        ),
+    };
+    Some(Finding::new(
+        severity,
+        code,
+        Some(&name),
+        format!(
+            "{} imports '{}' at byte {}; {}",
+            location, name, global.position, consequence
+        ),
+    ))
+}
+
+/// Scan every pickle stream in a file for the globals it imports, without
+/// loading it.
+///
+/// Zip archives (PyTorch checkpoints) have every `.pkl` entry scanned. Any
+/// other file is read as a sequence of pickle streams from its start, as in
+/// legacy `torch.save` files and plain `.pkl` files. Each stream is
+/// disassembled and run with [`pickle::globals`], so every `GLOBAL`,
+/// `INST` and `STACK_GLOBAL` is listed with the module and name it
+/// imports and its [`global_severity`].
+///
+/// Findings:
+/// - `dangerous_global` (error), `unexpected_global` (warning): see
+///   [`global_severity`]. Each global is reported once per stream.
+/// - `unresolved_global` (error): a `STACK_GLOBAL` whose module and name
+///   could not be determined.
+/// - `unscannable_pickle` (error): a `.pkl` entry is compressed, or a
+///   stream runs past `max_header_size`.
+/// - `malformed_pickle` (warning): a stream cannot be decoded or run to its
+///   end; imports up to that point are still listed.
+/// - `no_pickle` (info): a zip archive has no `.pkl` entries.
+///
+/// # Example
+///
+/// ```
+/// use weight_inspect::scan;
+/// use weight_inspect::types::Severity;
+///
+/// // A pickle that calls os.system("ls") when loaded.
+/// let mut cursor = std::io::Cursor::new(b"cposix\nsystem\n(S'ls'\ntR.".to_vec());
+/// let report = scan::scan(&mut cursor).unwrap();
+/// assert_eq!(report.streams[0].imports[0].name.as_deref(), Some("system"));
+/// assert_eq!(report.findings[0].code, "dangerous_global");
+/// assert_eq!(report.count(Severity::Error), 1);
+/// ```
+pub fn scan<R: Read + Seek>(reader: &mut R) -> Result<ScanReport, ScanError> {
+    scan_with_limits(reader, &ParseLimits::default())
+}
+
+/// Scan a file's pickle streams, enforcing the given safety limits.
+///
+/// `max_header_size` bounds the zip central directory and the bytes of
+/// pickle data read per entry or file.
+pub fn scan_with_limits<R: Read + Seek>(
+    reader: &mut R,
+    limits: &ParseLimits,
+) -> Result<ScanReport, ScanError> {
+    let mut report = ScanReport {
+        streams: Vec::new(),
+        findings: Vec::new(),
+    };
+    let mut magic = [0u8; 4];
+    let n = reader.read(&mut magic)?;
+    reader.seek(SeekFrom::Start(0))?;
+
+    if zip::is_zip(&magic[..n]) {
+        let entries = zip::read_entries(reader, limits.max_header_size)?;
+        for entry in entries.iter().filter(|e| e.name.ends_with(".pkl")) {
+            if entry.method != STORED || entry.size > limits.max_header_size as u64 {
+                let reason = if entry.method != STORED {
+                    format!("it is compressed (method {})", entry.method)
+                } else {
+                    format!(
+                        "its {} bytes exceed max_header_size ({} bytes)",
+                        entry.size, limits.max_header_size
+                    )
+                };
+                report.findings.push(Finding::new(
+                    Severity::Error,
+                    "unscannable_pickle",
+                    Some(&entry.name),
+                    format!("'{}' was not scanned: {}", entry.name, reason),
+                ));
+                continue;
+            }
+            let data = zip::read_entry(reader, entry, limits.max_header_size)?;
+            scan_streams(&data, Some(&entry.name), false, &mut report);
+        }
+        if !entries.iter().any(|e| e.name.ends_with(".pkl")) {
+            report.findings.push(Finding::new(
+                Severity::Info,
+                "no_pickle",
+                None,
+                "the archive has no .pkl entries".to_string(),
+            ));
+        }
+        return Ok(report);
+    }
+
+    let file_size = reader.seek(SeekFrom::End(0))?;
+    reader.seek(SeekFrom::Start(0))?;
+    let mut data = Vec::new();
+    reader
+        .take(limits.max_header_size as u64)
+        .read_to_end(&mut data)?;
+    let truncated = file_size > data.len() as u64;
+    if not_pickle(&data, truncated) {
+        return Err(ScanError::NotPickle);
+    }
+    scan_streams(&data, None, truncated, &mut report);
+    Ok(report)
+}
+
+/// Whether `data` does not start with a pickle: its protocol is not one
+/// Python writes, or it cannot be decoded and imports nothing before that.
+fn not_pickle(data: &[u8], truncated: bool) -> bool {
+    let listing = pickle::disassemble(data);
+    let bad_protocol = listing
+        .instructions
+        .first()
+        .is_some_and(|i| i.opcode == PROTO && !matches!(i.arg, Arg::Int(2..=5)));
+    let failed = match listing.error {
+        Some(PickleError::UnexpectedEof) => !truncated,
+        Some(_) => true,
+        None => false,
+    };
+    let imports = listing
+        .instructions
+        .iter()
+        .any(|i| matches!(i.opcode, b'c' | b'i' | 0x93 | 0x82..=0x84));
+    bad_protocol || (failed && !imports)
+}
+
+/// Scan the pickle streams at the start of `data`: the first one, and each
+/// following one that starts with `PROTO`.
+fn scan_streams(data: &[u8], entry: Option<&str>, truncated: bool, report: &mut ScanReport) {
+    let mut offset = 0;
+    loop {
+        let (stream, error) = scan_stream(&data[offset..], offset, entry, report);
+        let location = location(entry, offset);
+        match error {
+            Some(PickleError::UnexpectedEof) if truncated => {
+                report.findings.push(Finding::new(
+                    Severity::Error,
+                    "unscannable_pickle",
+                    entry,
+                    format!(
+                        "{} continues past max_header_size ({} bytes); the rest was not scanned",
+                        location,
+                        data.len()
+                    ),
+                ));
+            }
+            Some(error) => report.findings.push(Finding::new(
+                Severity::Warning,
+                "malformed_pickle",
+                entry,
+                format!("{} cannot be loaded: {}", location, error),
+            )),
+            None => {}
+        }
+        let length = stream.length as usize;
+        let stopped = stream.instructions.last().is_some_and(|i| i.opcode == b'.');
+        report.streams.push(stream);
+        offset += length;
+        if !stopped || data.get(offset) != Some(&PROTO) {
+            return;
+        }
+    }
+}
+
+/// Describe where a stream is, e.g. `'archive/data.pkl'` or
+/// `pickle at byte 120`.
+fn location(entry: Option<&str>, offset: usize) -> String {
+    match (entry, offset) {
+        (Some(entry), 0) => format!("'{}'", entry),
+        (Some(entry), _) => format!("'{}' pickle at byte {}", entry, offset),
+        (None, 0) => "the pickle".to_string(),
+        (None, _) => format!("pickle at byte {}", offset),
+    }
+}
+
+/// Scan one stream, returning it and why it could not be loaded.
+fn scan_stream(
+    data: &[u8],
+    offset: usize,
+    entry: Option<&str>,
+    report: &mut ScanReport,
+) -> (Stream, Option<PickleError>) {
+    let listing = pickle::disassemble(data);
+    let (globals, load_error) = pickle::globals(&data[..listing.length]);
+    let location = location(entry, offset);
+
+    let mut imports = Vec::new();
+    let mut reported = Vec::new();
+    for instruction in &listing.instructions {
+        let global = match (instruction.opcode, &instruction.arg) {
+            (b'c' | b'i', Arg::Global { module, name }) => Some((module.clone(), name.clone())),
+            (0x93 | 0x82..=0x84, _) => globals
+                .iter()
+                .find(|g| g.position == instruction.position)
+                .map(|g| (g.module.clone(), g.name.clone())),
+            _ => continue,
+        };
+        let Some((module, name)) = global else {
+            imports.push(Import {
+                module: None,
+                name: None,
+                position: instruction.position,
+                severity: Severity::Error,
+            });
+            report.findings.push(Finding::new(
+                Severity::Error,
+                "unresolved_global",
+                entry,
+                format!(
+                    "{} has a {} at byte {} whose module and name cannot be determined",
+                    location,
+                    instruction.name(),
+                    instruction.position
+                ),
+            ));
+            continue;
+        };
+        let global = GlobalRef {
+            module,
+            name,
+            position: instruction.position,
+        };
+        if !reported.contains(&(global.module.clone(), global.name.clone())) {
+            reported.push((global.module.clone(), global.name.clone()));
+            report.findings.extend(global_finding(&location, &global));
+        }
+        imports.push(Import {
+            severity: global_severity(&global.module, &global.name),
+            module: Some(global.module),
+            name: Some(global.name),
+            position: global.position,
+        });
+    }
+
+    let stream = Stream {
+        entry: entry.map(str::to_string),
+        offset: offset as u64,
+        length: listing.length as u64,
+        opcodes: listing.instructions.len(),
+        imports,
+        instructions: listing.instructions,
+    };
+    (stream, listing.error.or(load_error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn scan_bytes(data: &[u8]) -> ScanReport {
+        scan(&mut Cursor::new(data.to_vec())).unwrap()
+    }
+
+    fn codes(report: &ScanReport) -> Vec<&str> {
+        report.findings.iter().map(|f| f.code).collect()
+    }
+
+    #[test]
+    fn test_global_severity() {
+        assert_eq!(
+            global_severity("collections", "OrderedDict"),
+            Severity::Info
+        );
+        assert_eq!(global_severity("torch", "FloatStorage"), Severity::Info);
+        assert_eq!(global_severity("builtins", "eval"), Severity::Error);
+        assert_eq!(global_severity("builtins", "set"), Severity::Info);
+        assert_eq!(global_severity("builtins", "print"), Severity::Warning);
+        assert_eq!(global_severity("os.path", "join"), Severity::Error);
+        assert_eq!(global_severity("subprocess", "Popen"), Severity::Error);
+        assert_eq!(global_severity("runpy", "_run_code"), Severity::Error);
+        assert_eq!(global_severity("operator", "attrgetter"), Severity::Error);
+        assert_eq!(global_severity("ossify", "x"), Severity::Warning);
+    }
+
+    #[test]
+    fn test_stack_global_is_resolved() {
+        // pickle.dumps(builtins.eval, protocol=4) with the names memoized
+        let data = b"\x80\x04\x8c\x08builtins\x94\x8c\x04eval\x94\x93\x94.";
+        let report = scan_bytes(data);
+
+        assert_eq!(
+            report.streams[0].imports,
+            vec![Import {
+                module: Some("builtins".to_string()),
+                name: Some("eval".to_string()),
+                position: 20,
+                severity: Severity::Error,
+            }]
+        );
+        assert_eq!(codes(&report), ["dangerous_global"]);
+    }
+
+    #[test]
+    fn test_unresolved_stack_global() {
+        // STACK_GLOBAL on names built by a call cannot be resolved statically.
+        let data = b"\x80\x04c__main__\nf\n)R\x8c\x01x\x93.";
+        let report = scan_bytes(data);
+
+        assert_eq!(report.streams[0].imports.len(), 2);
+        assert_eq!(report.streams[0].imports[1].module, None);
+        assert!(codes(&report).contains(&"unresolved_global"));
+        assert!(codes(&report).contains(&"unexpected_global"));
+    }
+
+    #[test]
+    fn test_multiple_streams_and_malformed() {
+        // Two protocol 2 streams, as in legacy torch.save files, then raw data.
+        let mut data = b"\x80\x02K\x01.\x80\x02cos\nsystem\n.".to_vec();
+        data.extend(b"\x00\x01\x02");
+        let report = scan_bytes(&data);
+        assert_eq!(report.streams.len(), 2);
+        assert_eq!(report.streams[1].offset, 5);
+        assert_eq!(codes(&report), ["dangerous_global"]);
+
+        // Imports before the point of failure are still listed.
+        let report = scan_bytes(b"\x80\x02cnt\nsystem\n0\xff");
+        assert_eq!(codes(&report), ["dangerous_global", "malformed_pickle"]);
+
+        assert!(matches!(
+            scan(&mut Cursor::new(b"GGUF".to_vec())),
+            Err(ScanError::NotPickle)
+        ));
+        let safetensors = std::fs::read("tests/fixtures/tiny.safetensors").unwrap();
+        assert!(matches!(
+            scan(&mut Cursor::new(safetensors)),
+            Err(ScanError::NotPickle)
+        ));
+    }
+
+    #[test]
+    fn test_zip_entries() {
+        let bytes = zip::stored_archive(&[
+            (
+                "archive/data.pkl",
+                b"\x80\x02ctorch._utils\n_rebuild_tensor_v2\n.",
+            ),
+            ("archive/extra.pkl", b"\x80\x02cwebbrowser\nopen\n."),
+            ("archive/data/0", b"\x00\x00\x00\x00"),
+        ]);
+        let report = scan(&mut Cursor::new(bytes)).unwrap();
+
+        assert_eq!(report.streams.len(), 2);
+        assert_eq!(report.streams[0].imports[0].severity, Severity::Info);
+        assert_eq!(report.findings.len(), 1);
+        assert_eq!(
+            report.findings[0].subject.as_deref(),
+            Some("webbrowser.open")
+        );
+    }
+}
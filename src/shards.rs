@@ -0,0 +1,135 @@
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// Reads several files as if they were one, laid end to end.
+///
+/// Used to read tensor data of an artifact merged from several files, such
+/// as [`safetensors::parse_sharded`](crate::safetensors::parse_sharded):
+/// open it with the shard paths and pass it wherever a single file's reader
+/// is expected, e.g. to
+/// [`hash::compute_content_hash`](crate::hash::compute_content_hash).
+///
+/// # Example
+///
+/// ```
+/// use std::io::Read;
+/// use weight_inspect::shards::ShardReader;
+///
+/// let path = "tests/fixtures/tiny.safetensors";
+/// let mut reader = ShardReader::open(&[path, path]).unwrap();
+/// let mut data = Vec::new();
+/// reader.read_to_end(&mut data).unwrap();
+/// assert_eq!(data.len() as u64, 2 * std::fs::metadata(path).unwrap().len());
+/// ```
+pub struct ShardReader {
+    paths: Vec<PathBuf>,
+    /// Start offset of each file, followed by the total length.
+    starts: Vec<u64>,
+    position: u64,
+    /// Index of the open file and the reader's position within it.
+    current: Option<(usize, BufReader<File>, u64)>,
+}
+
+impl ShardReader {
+    /// Open `paths` in order. Only their sizes are read up front.
+    pub fn open<P: AsRef<Path>>(paths: &[P]) -> io::Result<Self> {
+        let mut starts = vec![0];
+        let mut total = 0u64;
+        for path in paths {
+            total += std::fs::metadata(path)?.len();
+            starts.push(total);
+        }
+        Ok(Self {
+            paths: paths.iter().map(|p| p.as_ref().to_path_buf()).collect(),
+            starts,
+            position: 0,
+            current: None,
+        })
+    }
+
+    /// Total length of all files.
+    pub fn len(&self) -> u64 {
+        self.starts[self.starts.len() - 1]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Read for ShardReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position >= self.len() || buf.is_empty() {
+            return Ok(0);
+        }
+        // The last file starting at or before the position.
+        let index = self.starts.partition_point(|&start| start <= self.position) - 1;
+        let local = self.position - self.starts[index];
+        if !matches!(&self.current, Some((open, _, _)) if *open == index) {
+            let file = File::open(&self.paths[index])?;
+            self.current = Some((index, BufReader::new(file), 0));
+        }
+        let (_, reader, offset) = self.current.as_mut().expect("file was just opened");
+        if *offset != local {
+            reader.seek(SeekFrom::Start(local))?;
+            *offset = local;
+        }
+        let available = self.starts[index + 1] - self.position;
+        let len = buf.len().min(available.try_into().unwrap_or(usize::MAX));
+        let n = reader.read(&mut buf[..len])?;
+        if n == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!("'{}' shrank while reading", self.paths[index].display()),
+            ));
+        }
+        *offset += n as u64;
+        self.position += n as u64;
+        Ok(n)
+    }
+}
+
+impl Seek for ShardReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(delta) => self.len().checked_add_signed(delta),
+            SeekFrom::Current(delta) => self.position.checked_add_signed(delta),
+        };
+        self.position = target.ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "seek before start of shards")
+        })?;
+        Ok(self.position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(dir: &Path, name: &str, bytes: &[u8]) {
+        std::fs::write(dir.join(name), bytes).unwrap();
+    }
+
+    #[test]
+    fn test_shard_reader_spans_files() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "1", b"abc");
+        write(dir.path(), "2", b"");
+        write(dir.path(), "3", b"defg");
+        let paths: Vec<_> = ["1", "2", "3"].iter().map(|n| dir.path().join(n)).collect();
+        let mut reader = ShardReader::open(&paths).unwrap();
+        assert_eq!(reader.len(), 7);
+
+        let mut buf = [0u8; 4];
+        reader.seek(SeekFrom::Start(1)).unwrap();
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"bcde");
+
+        reader.seek(SeekFrom::End(-2)).unwrap();
+        let mut rest = Vec::new();
+        reader.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, b"fg");
+    }
+}
@@ -0,0 +1,256 @@
+use crate::types::{Artifact, Tensor};
+use crate::values::{self, ValueError, ValueReader};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::{Read, Seek};
+
+/// Numeric statistics for one tensor, or for a whole model.
+///
+/// `min`, `max`, `mean` and `std` only consider finite values and are `None`
+/// when there are none. `std` is the population standard deviation.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct TensorStats {
+    pub count: u64,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub mean: Option<f64>,
+    pub std: Option<f64>,
+    pub zeros: u64,
+    pub nan_count: u64,
+    pub inf_count: u64,
+}
+
+/// Statistics for every decodable tensor in an artifact.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StatsReport {
+    /// Statistics over all values of all tensors in `tensors`.
+    pub summary: TensorStats,
+    /// Names of tensors containing at least one NaN or infinite value.
+    pub non_finite_tensors: Vec<String>,
+    pub tensors: BTreeMap<String, TensorStats>,
+    /// Tensors that could not be read, with the reason: their dtype cannot
+    /// be decoded, they have no data offset or their data is truncated.
+    pub skipped: BTreeMap<String, String>,
+}
+
+/// Running accumulator using Welford's algorithm, mergeable across tensors.
+#[derive(Debug, Clone, Copy, Default)]
+struct Accumulator {
+    count: u64,
+    finite: u64,
+    mean: f64,
+    m2: f64,
+    min: f64,
+    max: f64,
+    zeros: u64,
+    nan_count: u64,
+    inf_count: u64,
+}
+
+impl Accumulator {
+    fn push(&mut self, value: f64) {
+        self.count += 1;
+        if value.is_nan() {
+            self.nan_count += 1;
+            return;
+        }
+        if value.is_infinite() {
+            self.inf_count += 1;
+            return;
+        }
+        if value == 0.0 {
+            self.zeros += 1;
+        }
+        if self.finite == 0 {
+            self.min = value;
+            self.max = value;
+        } else {
+            self.min = self.min.min(value);
+            self.max = self.max.max(value);
+        }
+        self.finite += 1;
+        let delta = value - self.mean;
+        self.mean += delta / self.finite as f64;
+        self.m2 += delta * (value - self.mean);
+    }
+
+    /// Combine with another accumulator (Chan et al. parallel update).
+    fn merge(&mut self, other: &Accumulator) {
+        if other.finite > 0 {
+            if self.finite == 0 {
+                self.min = other.min;
+                self.max = other.max;
+            } else {
+                self.min = self.min.min(other.min);
+                self.max = self.max.max(other.max);
+            }
+            let total = (self.finite + other.finite) as f64;
+            let delta = other.mean - self.mean;
+            self.mean += delta * other.finite as f64 / total;
+            self.m2 += other.m2 + delta * delta * self.finite as f64 * other.finite as f64 / total;
+            self.finite += other.finite;
+        }
+        self.count += other.count;
+        self.zeros += other.zeros;
+        self.nan_count += other.nan_count;
+        self.inf_count += other.inf_count;
+    }
+
+    fn finish(&self) -> TensorStats {
+        let finite = |value: f64| (self.finite > 0).then_some(value);
+        TensorStats {
+            count: self.count,
+            min: finite(self.min),
+            max: finite(self.max),
+            mean: finite(self.mean),
+            std: finite((self.m2 / self.finite as f64).sqrt()),
+            zeros: self.zeros,
+            nan_count: self.nan_count,
+            inf_count: self.inf_count,
+        }
+    }
+}
+
+/// Read every decodable tensor and compute its statistics.
+///
+/// Tensors that cannot be read (see [`values::skip_reason`]) are listed in
+/// [`StatsReport::skipped`]. `reader` must be the file
+/// the artifact was parsed from.
+///
+/// # Example
+///
+/// ```
+/// use weight_inspect::{safetensors, stats};
+///
+/// let data = std::fs::read("tests/fixtures/tiny.safetensors").unwrap();
+/// let mut cursor = std::io::Cursor::new(data);
+/// let artifact = safetensors::parse_safetensors(&mut cursor).unwrap();
+/// let report = stats::compute_stats(&artifact, &mut cursor).unwrap();
+/// assert_eq!(report.tensors.len(), artifact.tensors.len());
+/// assert_eq!(report.summary.nan_count, 0);
+/// ```
+pub fn compute_stats<R: Read + Seek>(
+    artifact: &Artifact,
+    reader: &mut R,
+) -> Result<StatsReport, ValueError> {
+    let mut report = StatsReport::default();
+    let mut total = Accumulator::default();
+    let mut chunk = Vec::new();
+    for (name, tensor) in &artifact.tensors {
+        let acc = match accumulate(tensor, reader, &mut chunk) {
+            Ok(acc) => acc,
+            Err(e) => {
+                let reason = values::skip_reason(&e).ok_or(e)?;
+                report.skipped.insert(name.clone(), reason);
+                continue;
+            }
+        };
+        if acc.nan_count > 0 || acc.inf_count > 0 {
+            report.non_finite_tensors.push(name.clone());
+        }
+        total.merge(&acc);
+        report.tensors.insert(name.clone(), acc.finish());
+    }
+    report.summary = total.finish();
+    Ok(report)
+}
+
+/// Statistics of one tensor's values.
+fn accumulate<R: Read + Seek>(
+    tensor: &Tensor,
+    reader: &mut R,
+    chunk: &mut Vec<f64>,
+) -> Result<Accumulator, ValueError> {
+    let mut values = ValueReader::new(tensor, reader)?;
+    let mut acc = Accumulator::default();
+    while values.read_chunk(64 * 1024, chunk)? > 0 {
+        for &value in chunk.iter() {
+            acc.push(value);
+        }
+    }
+    Ok(acc)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::values::f32_artifact;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_tensor_stats() {
+        let (artifact, data) = f32_artifact(&[("w", &[2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0])]);
+        let report = compute_stats(&artifact, &mut Cursor::new(data)).unwrap();
+
+        let w = &report.tensors["w"];
+        assert_eq!(w.count, 8);
+        assert_eq!(w.min, Some(2.0));
+        assert_eq!(w.max, Some(9.0));
+        assert_eq!(w.mean, Some(5.0));
+        assert_eq!(w.std, Some(2.0));
+        assert_eq!(w.zeros, 0);
+        assert!(report.non_finite_tensors.is_empty());
+    }
+
+    #[test]
+    fn test_non_finite_values_are_counted_separately() {
+        let (artifact, data) = f32_artifact(&[
+            ("bad", &[f32::NAN, 1.0, f32::INFINITY, 0.0]),
+            ("good", &[3.0]),
+        ]);
+        let report = compute_stats(&artifact, &mut Cursor::new(data)).unwrap();
+
+        let bad = &report.tensors["bad"];
+        assert_eq!(bad.nan_count, 1);
+        assert_eq!(bad.inf_count, 1);
+        assert_eq!(bad.zeros, 1);
+        assert_eq!(bad.mean, Some(0.5));
+        assert_eq!(report.non_finite_tensors, vec!["bad"]);
+    }
+
+    #[test]
+    fn test_summary_merges_tensors() {
+        let (artifact, data) =
+            f32_artifact(&[("a", &[2.0, 4.0, 4.0, 4.0]), ("b", &[5.0, 5.0, 7.0, 9.0])]);
+        let report = compute_stats(&artifact, &mut Cursor::new(data)).unwrap();
+
+        let summary = &report.summary;
+        assert_eq!(summary.count, 8);
+        assert_eq!(summary.min, Some(2.0));
+        assert_eq!(summary.max, Some(9.0));
+        assert_eq!(summary.mean, Some(5.0));
+        assert!((summary.std.unwrap() - 2.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_all_nan_tensor_has_no_range() {
+        let (artifact, data) = f32_artifact(&[("nan", &[f32::NAN, f32::NAN])]);
+        let report = compute_stats(&artifact, &mut Cursor::new(data)).unwrap();
+
+        assert_eq!(report.tensors["nan"].min, None);
+        assert_eq!(report.tensors["nan"].std, None);
+        assert_eq!(report.summary.nan_count, 2);
+    }
+
+    #[test]
+    fn test_unreadable_tensors_are_skipped() {
+        let (mut artifact, data) = f32_artifact(&[("w", &[1.0]), ("x", &[2.0]), ("y", &[3.0])]);
+        artifact.tensors.get_mut("w").unwrap().dtype = "iq2_xxs".to_string();
+        artifact.tensors.get_mut("x").unwrap().data_offset = None;
+        let report = compute_stats(&artifact, &mut Cursor::new(data)).unwrap();
+
+        assert_eq!(report.tensors.keys().collect::<Vec<_>>(), ["y"]);
+        assert_eq!(report.skipped["w"], "dtype 'iq2_xxs' cannot be decoded");
+        assert_eq!(report.skipped["x"], "no recorded data offset");
+    }
+
+    #[test]
+    fn test_truncated_tensor_is_skipped() {
+        let (artifact, data) = f32_artifact(&[("w", &[1.0, 2.0]), ("x", &[3.0, 4.0])]);
+        let report = compute_stats(&artifact, &mut Cursor::new(&data[..12])).unwrap();
+
+        assert_eq!(report.tensors.keys().collect::<Vec<_>>(), ["w"]);
+        assert_eq!(report.skipped["x"], "data is truncated");
+        assert_eq!(report.summary.count, 2);
+    }
+}
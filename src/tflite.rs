@@ -0,0 +1,995 @@
+use crate::types::{Artifact, CanonicalValue, Finding, Format, ParseLimits, Severity, Tensor};
+use std::collections::BTreeMap;
+use std::io::{Read, Seek, SeekFrom};
+use thiserror::Error;
+
+/// Flatbuffer file identifier of TFLite models, at byte 4.
+const FILE_IDENTIFIER: &[u8; 4] = b"TFL3";
+/// Metadata buffers longer than this are summarized by their size.
+const MAX_METADATA_STRING: usize = 1024;
+
+#[derive(Error, Debug)]
+pub enum TFLiteParserError {
+    #[error("invalid file identifier: expected 'TFL3' at byte 4")]
+    InvalidIdentifier,
+    #[error("invalid TFLite model: {0}")]
+    Invalid(String),
+    #[error("tensor count {count} exceeds max_tensor_count ({max}); needs at least {count}")]
+    TensorCountTooLarge { count: u64, max: u64 },
+    #[error("buffer count {count} exceeds max_tensor_count ({max}); needs at least {count}")]
+    BufferCountTooLarge { count: u64, max: u64 },
+    #[error("metadata count {count} exceeds max_metadata_count ({max}); needs at least {count}")]
+    MetadataCountTooLarge { count: u64, max: u64 },
+    #[error(
+        "tensor '{name}' dimensions {dims} exceed max_dimensions ({max}); needs at least {dims}"
+    )]
+    DimensionsTooLarge { name: String, dims: u32, max: u32 },
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+}
+
+/// Whether `magic` (at least 8 bytes) starts a TFLite flatbuffer.
+pub fn is_tflite(magic: &[u8]) -> bool {
+    magic.get(4..8) == Some(FILE_IDENTIFIER)
+}
+
+/// Parse a TensorFlow Lite model (`.tflite` flatbuffer).
+///
+/// Tensors backed by a non-empty buffer (weights and other constants)
+/// become [`Tensor`]s with the buffer's size and file offset; tensors of
+/// subgraphs after the first are prefixed with the subgraph's name, e.g.
+/// `while_body/weights`. Graph information is mapped into metadata:
+/// `version`, `description`, `subgraph_count`, `operator_count`, the sorted
+/// `operator_codes`, the first subgraph's `input_names` and `output_names`
+/// (these three as arrays of strings), and each model metadata entry as
+/// `metadata.<name>`.
+///
+/// Findings:
+/// - `missing_buffer`: a tensor refers to a buffer that does not exist.
+/// - `tensor_out_of_bounds`: a buffer stored after the flatbuffer (models
+///   over 2 GB) extends past the end of the file.
+/// - `invalid_shape`: a tensor has a negative dimension.
+///
+/// # Example
+///
+/// ```
+/// use weight_inspect::tflite;
+///
+/// let data = std::fs::read("tests/fixtures/tiny.tflite").unwrap();
+/// let mut cursor = std::io::Cursor::new(data);
+/// let artifact = tflite::parse_tflite(&mut cursor).unwrap();
+/// assert_eq!(artifact.format, weight_inspect::types::Format::TFLite);
+/// assert_eq!(artifact.tensors["test.weight.0"].shape, vec![64, 128]);
+/// ```
+pub fn parse_tflite<R: Read + Seek>(reader: &mut R) -> Result<Artifact, TFLiteParserError> {
+    parse_tflite_with_limits(reader, &ParseLimits::default())
+}
+
+/// Parse a TensorFlow Lite model, enforcing the given safety limits.
+///
+/// `max_header_size` bounds every string and metadata buffer read.
+pub fn parse_tflite_with_limits<R: Read + Seek>(
+    reader: &mut R,
+    limits: &ParseLimits,
+) -> Result<Artifact, TFLiteParserError> {
+    let size = reader.seek(SeekFrom::End(0))?;
+    let mut fb = Flatbuffer {
+        reader,
+        size,
+        max_string: limits.max_header_size,
+    };
+    if size < 8 || &fb.bytes::<4>(4)? != FILE_IDENTIFIER {
+        return Err(TFLiteParserError::InvalidIdentifier);
+    }
+    let root = fb.u32(0)? as u64;
+    let model = fb.table(root)?;
+
+    let mut metadata = BTreeMap::new();
+    let mut tensors = BTreeMap::new();
+    let mut findings = Vec::new();
+
+    let version = fb.scalar_u32(&model, 0, 0)?;
+    metadata.insert("version".to_string(), CanonicalValue::Int(version as i64));
+    if let Some(description) = fb.string(&model, 3)? {
+        metadata.insert(
+            "description".to_string(),
+            CanonicalValue::String(description),
+        );
+    }
+
+    let mut operator_codes = Vec::new();
+    for code in fb.tables(&model, 1)? {
+        let deprecated = fb.scalar_u8(&code, 0, 0)? as i32;
+        let builtin = fb.scalar_i32(&code, 3, 0)?.max(deprecated);
+        let name = match fb.string(&code, 1)? {
+            Some(custom) if builtin == CUSTOM => custom,
+            _ => builtin_name(builtin),
+        };
+        operator_codes.push(name);
+    }
+
+    // Every tensor has at most one buffer, plus the empty buffer 0.
+    let buffer_count = fb.vector(&model, 4, 4)?.map_or(0, |(_, len)| len);
+    if buffer_count > limits.max_tensor_count.saturating_add(1) {
+        return Err(TFLiteParserError::BufferCountTooLarge {
+            count: buffer_count,
+            max: limits.max_tensor_count,
+        });
+    }
+    let buffers = fb.tables(&model, 4)?;
+    let subgraphs = fb.tables(&model, 2)?;
+    metadata.insert(
+        "subgraph_count".to_string(),
+        CanonicalValue::Int(subgraphs.len() as i64),
+    );
+
+    let mut operator_count = 0;
+    let mut tensor_count = 0u64;
+    for (index, subgraph) in subgraphs.iter().enumerate() {
+        let graph_tensors = fb.tables(subgraph, 0)?;
+        tensor_count += graph_tensors.len() as u64;
+        if tensor_count > limits.max_tensor_count {
+            return Err(TFLiteParserError::TensorCountTooLarge {
+                count: tensor_count,
+                max: limits.max_tensor_count,
+            });
+        }
+        let prefix = match index {
+            0 => String::new(),
+            _ => format!(
+                "{}/",
+                fb.string(subgraph, 4)?
+                    .unwrap_or_else(|| format!("subgraph{}", index))
+            ),
+        };
+
+        let mut names = Vec::with_capacity(graph_tensors.len());
+        for tensor in &graph_tensors {
+            let name = format!("{}{}", prefix, fb.string(tensor, 3)?.unwrap_or_default());
+            names.push(name.clone());
+
+            let dims = fb.ints(tensor, 0)?;
+            if dims.len() > limits.max_dimensions as usize {
+                return Err(TFLiteParserError::DimensionsTooLarge {
+                    name,
+                    dims: dims.len() as u32,
+                    max: limits.max_dimensions,
+                });
+            }
+            let buffer_index = fb.scalar_u32(tensor, 2, 0)? as usize;
+            if buffer_index == 0 {
+                // Buffer 0 is the empty sentinel: an activation or input.
+                continue;
+            }
+            let Some(buffer) = buffers.get(buffer_index) else {
+                findings.push(Finding::new(
+                    Severity::Error,
+                    "missing_buffer",
+                    Some(&name),
+                    format!(
+                        "refers to buffer {} but the model has {}",
+                        buffer_index,
+                        buffers.len()
+                    ),
+                ));
+                continue;
+            };
+            let Some((mut data_offset, byte_length)) = fb.buffer(buffer)? else {
+                continue;
+            };
+            if data_offset.is_some_and(|offset| offset.saturating_add(byte_length) > size) {
+                findings.push(Finding::new(
+                    Severity::Error,
+                    "tensor_out_of_bounds",
+                    Some(&name),
+                    format!(
+                        "buffer {} ends past the end of the file ({} bytes)",
+                        buffer_index, size
+                    ),
+                ));
+                data_offset = None;
+            }
+            if dims.iter().any(|&d| d < 0) {
+                findings.push(Finding::new(
+                    Severity::Error,
+                    "invalid_shape",
+                    Some(&name),
+                    format!("shape {:?} has a negative dimension", dims),
+                ));
+            }
+
+            if tensors.contains_key(&name) {
+                findings.push(Finding::new(
+                    Severity::Error,
+                    "duplicate_tensor",
+                    Some(&name),
+                    "tensor name appears more than once; the last entry is used".to_string(),
+                ));
+            }
+            tensors.insert(
+                name.clone(),
+                Tensor {
+                    name,
+                    dtype: tensor_type(fb.scalar_u8(tensor, 1, 0)?),
+                    shape: dims.iter().map(|&d| d.max(0) as u64).collect(),
+                    byte_length,
+                    data_offset,
+                    digest: None,
+                    location: None,
+                    nnz: None,
+                    big_endian: false,
+                },
+            );
+        }
+
+        let operators = fb.tables(subgraph, 3)?;
+        operator_count += operators.len();
+        if index == 0 {
+            for (key, field) in [("input_names", 1), ("output_names", 2)] {
+                let selected: Vec<CanonicalValue> = fb
+                    .ints(subgraph, field)?
+                    .iter()
+                    .filter_map(|&i| names.get(usize::try_from(i).ok()?))
+                    .map(|name| CanonicalValue::String(name.clone()))
+                    .collect();
+                if !selected.is_empty() {
+                    metadata.insert(key.to_string(), CanonicalValue::Array(selected));
+                }
+            }
+        }
+    }
+
+    metadata.insert(
+        "operator_count".to_string(),
+        CanonicalValue::Int(operator_count as i64),
+    );
+    if !operator_codes.is_empty() {
+        operator_codes.sort();
+        metadata.insert(
+            "operator_codes".to_string(),
+            CanonicalValue::Array(
+                operator_codes
+                    .into_iter()
+                    .map(CanonicalValue::String)
+                    .collect(),
+            ),
+        );
+    }
+
+    let entries = fb.tables(&model, 6)?;
+    if entries.len() as u64 > limits.max_metadata_count {
+        return Err(TFLiteParserError::MetadataCountTooLarge {
+            count: entries.len() as u64,
+            max: limits.max_metadata_count,
+        });
+    }
+    for entry in entries {
+        let name = fb.string(&entry, 0)?.unwrap_or_default();
+        let index = fb.scalar_u32(&entry, 1, 0)? as usize;
+        let value = match buffers.get(index) {
+            Some(buffer) => fb.metadata_value(buffer)?,
+            None => CanonicalValue::Null,
+        };
+        metadata.insert(format!("metadata.{}", name), value);
+    }
+
+    Ok(Artifact {
+        format: Format::TFLite,
+        gguf_version: Some(version as i64),
+        metadata,
+        tensors,
+        graph: None,
+        data_offset: None,
+        findings,
+    })
+}
+
+/// Map a `TensorType` to a dtype name.
+fn tensor_type(code: u8) -> String {
+    let dtype = match code {
+        0 => "f32",
+        1 => "f16",
+        2 => "i32",
+        3 => "u8",
+        4 => "i64",
+        5 => "string",
+        6 => "bool",
+        7 => "i16",
+        8 => "complex64",
+        9 => "i8",
+        10 => "f64",
+        11 => "complex128",
+        12 => "u64",
+        13 => "resource",
+        14 => "variant",
+        15 => "u32",
+        16 => "u16",
+        17 => "i4",
+        18 => "bf16",
+        _ => return format!("unknown_{}", code),
+    };
+    dtype.to_string()
+}
+
+/// `BuiltinOperator.CUSTOM`; such operators are named by their custom code.
+const CUSTOM: i32 = 32;
+
+/// Names of `BuiltinOperator` values, indexed by code.
+const BUILTIN_OPERATORS: &[&str] = &[
+    "ADD",
+    "AVERAGE_POOL_2D",
+    "CONCATENATION",
+    "CONV_2D",
+    "DEPTHWISE_CONV_2D",
+    "DEPTH_TO_SPACE",
+    "DEQUANTIZE",
+    "EMBEDDING_LOOKUP",
+    "FLOOR",
+    "FULLY_CONNECTED",
+    "HASHTABLE_LOOKUP",
+    "L2_NORMALIZATION",
+    "L2_POOL_2D",
+    "LOCAL_RESPONSE_NORMALIZATION",
+    "LOGISTIC",
+    "LSH_PROJECTION",
+    "LSTM",
+    "MAX_POOL_2D",
+    "MUL",
+    "RELU",
+    "RELU_N1_TO_1",
+    "RELU6",
+    "RESHAPE",
+    "RESIZE_BILINEAR",
+    "RNN",
+    "SOFTMAX",
+    "SPACE_TO_DEPTH",
+    "SVDF",
+    "TANH",
+    "CONCAT_EMBEDDINGS",
+    "SKIP_GRAM",
+    "CALL",
+    "CUSTOM",
+    "EMBEDDING_LOOKUP_SPARSE",
+    "PAD",
+    "UNIDIRECTIONAL_SEQUENCE_RNN",
+    "GATHER",
+    "BATCH_TO_SPACE_ND",
+    "SPACE_TO_BATCH_ND",
+    "TRANSPOSE",
+    "MEAN",
+    "SUB",
+    "DIV",
+    "SQUEEZE",
+    "UNIDIRECTIONAL_SEQUENCE_LSTM",
+    "STRIDED_SLICE",
+    "BIDIRECTIONAL_SEQUENCE_RNN",
+    "EXP",
+    "TOPK_V2",
+    "SPLIT",
+    "LOG_SOFTMAX",
+    "DELEGATE",
+    "BIDIRECTIONAL_SEQUENCE_LSTM",
+    "CAST",
+    "PRELU",
+    "MAXIMUM",
+    "ARG_MAX",
+    "MINIMUM",
+    "LESS",
+    "NEG",
+    "PADV2",
+    "GREATER",
+    "GREATER_EQUAL",
+    "LESS_EQUAL",
+    "SELECT",
+    "SLICE",
+    "SIN",
+    "TRANSPOSE_CONV",
+    "SPARSE_TO_DENSE",
+    "TILE",
+    "EXPAND_DIMS",
+    "EQUAL",
+    "NOT_EQUAL",
+    "LOG",
+    "SUM",
+    "SQRT",
+    "RSQRT",
+    "SHAPE",
+    "POW",
+    "ARG_MIN",
+    "FAKE_QUANT",
+    "REDUCE_PROD",
+    "REDUCE_MAX",
+    "PACK",
+    "LOGICAL_OR",
+    "ONE_HOT",
+    "LOGICAL_AND",
+    "LOGICAL_NOT",
+    "UNPACK",
+    "REDUCE_MIN",
+    "FLOOR_DIV",
+    "REDUCE_ANY",
+    "SQUARE",
+    "ZEROS_LIKE",
+    "FILL",
+    "FLOOR_MOD",
+    "RANGE",
+    "RESIZE_NEAREST_NEIGHBOR",
+    "LEAKY_RELU",
+    "SQUARED_DIFFERENCE",
+    "MIRROR_PAD",
+    "ABS",
+    "SPLIT_V",
+    "UNIQUE",
+    "CEIL",
+    "REVERSE_V2",
+    "ADD_N",
+    "GATHER_ND",
+    "COS",
+    "WHERE",
+    "RANK",
+    "ELU",
+    "REVERSE_SEQUENCE",
+    "MATRIX_DIAG",
+    "QUANTIZE",
+    "MATRIX_SET_DIAG",
+    "ROUND",
+    "HARD_SWISH",
+    "IF",
+    "WHILE",
+    "NON_MAX_SUPPRESSION_V4",
+    "NON_MAX_SUPPRESSION_V5",
+    "SCATTER_ND",
+    "SELECT_V2",
+    "DENSIFY",
+    "SEGMENT_SUM",
+    "BATCH_MATMUL",
+    "PLACEHOLDER_FOR_GREATER_OP_CODES",
+    "CUMSUM",
+    "CALL_ONCE",
+    "BROADCAST_TO",
+    "RFFT2D",
+    "CONV_3D",
+    "IMAG",
+    "REAL",
+    "COMPLEX_ABS",
+    "HASHTABLE",
+    "HASHTABLE_FIND",
+    "HASHTABLE_IMPORT",
+    "HASHTABLE_SIZE",
+    "REDUCE_ALL",
+    "CONV_3D_TRANSPOSE",
+    "VAR_HANDLE",
+    "READ_VARIABLE",
+    "ASSIGN_VARIABLE",
+    "BROADCAST_ARGS",
+    "RANDOM_STANDARD_NORMAL",
+    "BUCKETIZE",
+    "RANDOM_UNIFORM",
+    "MULTINOMIAL",
+    "GELU",
+    "DYNAMIC_UPDATE_SLICE",
+    "RELU_0_TO_1",
+    "UNSORTED_SEGMENT_PROD",
+    "UNSORTED_SEGMENT_MAX",
+    "UNSORTED_SEGMENT_SUM",
+    "ATAN2",
+    "UNSORTED_SEGMENT_MIN",
+    "SIGN",
+    "BITCAST",
+    "BITWISE_XOR",
+    "RIGHT_SHIFT",
+];
+
+/// The name of a builtin operator code, or `BUILTIN_<code>` for codes newer
+/// than this table.
+fn builtin_name(code: i32) -> String {
+    usize::try_from(code)
+        .ok()
+        .and_then(|i| BUILTIN_OPERATORS.get(i))
+        .map_or_else(|| format!("BUILTIN_{}", code), |name| name.to_string())
+}
+
+/// A flatbuffer table: its position and its vtable.
+struct Table {
+    pos: u64,
+    vtable: u64,
+    vtable_len: u16,
+}
+
+/// Reads flatbuffer tables straight from a file, so buffer contents are
+/// located without being read.
+struct Flatbuffer<'a, R> {
+    reader: &'a mut R,
+    size: u64,
+    max_string: usize,
+}
+
+fn invalid(reason: String) -> TFLiteParserError {
+    TFLiteParserError::Invalid(reason)
+}
+
+impl<R: Read + Seek> Flatbuffer<'_, R> {
+    fn read(&mut self, pos: u64, len: u64) -> Result<Vec<u8>, TFLiteParserError> {
+        if pos.checked_add(len).is_none_or(|end| end > self.size) {
+            return Err(invalid(format!(
+                "{} bytes at offset {} extend past the end of the file",
+                len, pos
+            )));
+        }
+        self.reader.seek(SeekFrom::Start(pos))?;
+        let mut buf = vec![0u8; len as usize];
+        self.reader.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+
+    fn bytes<const N: usize>(&mut self, pos: u64) -> Result<[u8; N], TFLiteParserError> {
+        Ok(self.read(pos, N as u64)?.try_into().unwrap())
+    }
+
+    fn u16(&mut self, pos: u64) -> Result<u16, TFLiteParserError> {
+        Ok(u16::from_le_bytes(self.bytes(pos)?))
+    }
+
+    fn u32(&mut self, pos: u64) -> Result<u32, TFLiteParserError> {
+        Ok(u32::from_le_bytes(self.bytes(pos)?))
+    }
+
+    fn u64(&mut self, pos: u64) -> Result<u64, TFLiteParserError> {
+        Ok(u64::from_le_bytes(self.bytes(pos)?))
+    }
+
+    fn table(&mut self, pos: u64) -> Result<Table, TFLiteParserError> {
+        let back = i32::from_le_bytes(self.bytes(pos)?) as i64;
+        let vtable = u64::try_from(pos as i64 - back)
+            .map_err(|_| invalid(format!("bad vtable offset for table at {}", pos)))?;
+        let vtable_len = self.u16(vtable)?;
+        Ok(Table {
+            pos,
+            vtable,
+            vtable_len,
+        })
+    }
+
+    /// Position of field `index` of `table`, if it is present.
+    fn field(&mut self, table: &Table, index: u16) -> Result<Option<u64>, TFLiteParserError> {
+        let entry = 4 + 2 * index;
+        if entry + 2 > table.vtable_len {
+            return Ok(None);
+        }
+        match self.u16(table.vtable + entry as u64)? {
+            0 => Ok(None),
+            offset => Ok(Some(table.pos + offset as u64)),
+        }
+    }
+
+    /// Follow the offset stored in field `index` to the object it refers to.
+    fn object(&mut self, table: &Table, index: u16) -> Result<Option<u64>, TFLiteParserError> {
+        match self.field(table, index)? {
+            Some(pos) => Ok(Some(pos + self.u32(pos)? as u64)),
+            None => Ok(None),
+        }
+    }
+
+    /// Position of the first element and length of the vector in field
+    /// `index`, checked to fit in the file.
+    fn vector(
+        &mut self,
+        table: &Table,
+        index: u16,
+        element_size: u64,
+    ) -> Result<Option<(u64, u64)>, TFLiteParserError> {
+        let Some(pos) = self.object(table, index)? else {
+            return Ok(None);
+        };
+        let len = self.u32(pos)? as u64;
+        let start = pos + 4;
+        if start + len * element_size > self.size {
+            return Err(invalid(format!(
+                "vector of {} elements at offset {} extends past the end of the file",
+                len, pos
+            )));
+        }
+        Ok(Some((start, len)))
+    }
+
+    fn scalar_u8(
+        &mut self,
+        table: &Table,
+        index: u16,
+        default: u8,
+    ) -> Result<u8, TFLiteParserError> {
+        match self.field(table, index)? {
+            Some(pos) => Ok(self.bytes::<1>(pos)?[0]),
+            None => Ok(default),
+        }
+    }
+
+    fn scalar_u32(
+        &mut self,
+        table: &Table,
+        index: u16,
+        default: u32,
+    ) -> Result<u32, TFLiteParserError> {
+        match self.field(table, index)? {
+            Some(pos) => self.u32(pos),
+            None => Ok(default),
+        }
+    }
+
+    fn scalar_i32(
+        &mut self,
+        table: &Table,
+        index: u16,
+        default: i32,
+    ) -> Result<i32, TFLiteParserError> {
+        Ok(self.scalar_u32(table, index, default as u32)? as i32)
+    }
+
+    fn string(&mut self, table: &Table, index: u16) -> Result<Option<String>, TFLiteParserError> {
+        let Some((start, len)) = self.vector(table, index, 1)? else {
+            return Ok(None);
+        };
+        if len > self.max_string as u64 {
+            return Err(invalid(format!(
+                "string of {} bytes exceeds max_header_size ({} bytes)",
+                len, self.max_string
+            )));
+        }
+        let bytes = self.read(start, len)?;
+        Ok(Some(String::from_utf8_lossy(&bytes).into_owned()))
+    }
+
+    fn ints(&mut self, table: &Table, index: u16) -> Result<Vec<i32>, TFLiteParserError> {
+        let Some((start, len)) = self.vector(table, index, 4)? else {
+            return Ok(Vec::new());
+        };
+        let bytes = self.read(start, len * 4)?;
+        Ok(bytes
+            .chunks_exact(4)
+            .map(|c| i32::from_le_bytes(c.try_into().unwrap()))
+            .collect())
+    }
+
+    fn tables(&mut self, table: &Table, index: u16) -> Result<Vec<Table>, TFLiteParserError> {
+        let Some((start, len)) = self.vector(table, index, 4)? else {
+            return Ok(Vec::new());
+        };
+        (0..len)
+            .map(|i| {
+                let pos = start + 4 * i;
+                let target = pos + self.u32(pos)? as u64;
+                self.table(target)
+            })
+            .collect()
+    }
+
+    /// Offset and size of a buffer's data, or `None` if it is empty. Large
+    /// models store data after the flatbuffer, at an absolute `offset`.
+    fn buffer(&mut self, buffer: &Table) -> Result<Option<(Option<u64>, u64)>, TFLiteParserError> {
+        if let Some((start, len)) = self.vector(buffer, 0, 1)? {
+            if len > 0 {
+                return Ok(Some((Some(start), len)));
+            }
+        }
+        let offset = match self.field(buffer, 1)? {
+            Some(pos) => self.u64(pos)?,
+            None => 0,
+        };
+        let size = match self.field(buffer, 2)? {
+            Some(pos) => self.u64(pos)?,
+            None => 0,
+        };
+        // An offset of 1 is a placeholder written before data is placed.
+        if offset > 1 && size > 0 {
+            return Ok(Some((Some(offset), size)));
+        }
+        Ok(None)
+    }
+
+    /// A metadata buffer as text, or its size if it is binary or long.
+    fn metadata_value(&mut self, buffer: &Table) -> Result<CanonicalValue, TFLiteParserError> {
+        let Some((Some(offset), len)) = self.buffer(buffer)? else {
+            return Ok(CanonicalValue::String(String::new()));
+        };
+        if len as usize <= MAX_METADATA_STRING
+            && offset.checked_add(len).is_some_and(|end| end <= self.size)
+        {
+            let bytes = self.read(offset, len)?;
+            let end = bytes.iter().rposition(|&b| b != 0).map_or(0, |i| i + 1);
+            if let Ok(text) = std::str::from_utf8(&bytes[..end]) {
+                if !text.chars().any(|c| c.is_control() && c != '\n') {
+                    return Ok(CanonicalValue::String(text.to_string()));
+                }
+            }
+        }
+        Ok(CanonicalValue::String(format!("<{} bytes>", len)))
+    }
+}
+
+/// Build a flatbuffer from nested tables, for tests.
+#[cfg(test)]
+mod build {
+    /// A field value.
+    pub enum Field {
+        U8(u8),
+        U32(u32),
+        I32(i32),
+        U64(u64),
+        Str(String),
+        Ints(Vec<i32>),
+        Bytes(Vec<u8>),
+        Tables(Vec<Vec<(u16, Field)>>),
+    }
+
+    /// A TFLite file whose root table has `fields`.
+    pub fn model(fields: Vec<(u16, Field)>) -> Vec<u8> {
+        let mut buf = vec![0u8; 8];
+        buf[4..8].copy_from_slice(super::FILE_IDENTIFIER);
+        let root = table(&mut buf, &fields);
+        buf[0..4].copy_from_slice(&(root as u32).to_le_bytes());
+        buf
+    }
+
+    fn align(buf: &mut Vec<u8>) {
+        while !buf.len().is_multiple_of(4) {
+            buf.push(0);
+        }
+    }
+
+    fn patch(buf: &mut [u8], at: usize, target: usize) {
+        buf[at..at + 4].copy_from_slice(&((target - at) as u32).to_le_bytes());
+    }
+
+    /// Write a vtable and table, then the objects its fields refer to.
+    /// Returns the table's position.
+    fn table(buf: &mut Vec<u8>, fields: &[(u16, Field)]) -> usize {
+        let slots = fields.iter().map(|(i, _)| *i + 1).max().unwrap_or(0);
+        let sizes: Vec<usize> = fields
+            .iter()
+            .map(|(_, f)| match f {
+                Field::U8(_) => 1,
+                Field::U64(_) => 8,
+                _ => 4,
+            })
+            .collect();
+        let table_len = 4 + sizes.iter().sum::<usize>();
+        align(buf);
+        let vtable = buf.len();
+        buf.extend((4 + 2 * slots).to_le_bytes()[..2].iter());
+        buf.extend((table_len as u16).to_le_bytes());
+        let mut offsets = vec![0u16; slots as usize];
+        let mut at = 4;
+        for ((index, _), size) in fields.iter().zip(&sizes) {
+            offsets[*index as usize] = at as u16;
+            at += size;
+        }
+        for offset in offsets {
+            buf.extend(offset.to_le_bytes());
+        }
+        align(buf);
+        let pos = buf.len();
+        buf.extend(((pos - vtable) as i32).to_le_bytes());
+        let mut refs = Vec::new();
+        for (_, field) in fields {
+            let at = buf.len();
+            match field {
+                Field::U8(v) => buf.push(*v),
+                Field::U32(v) => buf.extend(v.to_le_bytes()),
+                Field::I32(v) => buf.extend(v.to_le_bytes()),
+                Field::U64(v) => buf.extend(v.to_le_bytes()),
+                _ => {
+                    buf.extend([0u8; 4]);
+                    refs.push((at, field));
+                }
+            }
+        }
+        for (at, field) in refs {
+            align(buf);
+            let target = buf.len();
+            match field {
+                Field::Str(s) => {
+                    buf.extend((s.len() as u32).to_le_bytes());
+                    buf.extend(s.as_bytes());
+                    buf.push(0);
+                }
+                Field::Bytes(bytes) => {
+                    buf.extend((bytes.len() as u32).to_le_bytes());
+                    buf.extend(bytes);
+                }
+                Field::Ints(ints) => {
+                    buf.extend((ints.len() as u32).to_le_bytes());
+                    for i in ints {
+                        buf.extend(i.to_le_bytes());
+                    }
+                }
+                Field::Tables(tables) => {
+                    buf.extend((tables.len() as u32).to_le_bytes());
+                    let slots = buf.len();
+                    buf.extend(vec![0u8; 4 * tables.len()]);
+                    for (i, fields) in tables.iter().enumerate() {
+                        let child = table(buf, fields);
+                        patch(buf, slots + 4 * i, child);
+                    }
+                }
+                _ => unreachable!(),
+            }
+            patch(buf, at, target);
+        }
+        pos
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::build::{model, Field};
+    use super::*;
+    use std::io::Cursor;
+
+    fn tensor(name: &str, shape: Vec<i32>, kind: u8, buffer: u32) -> Vec<(u16, Field)> {
+        vec![
+            (0, Field::Ints(shape)),
+            (1, Field::U8(kind)),
+            (2, Field::U32(buffer)),
+            (3, Field::Str(name.to_string())),
+        ]
+    }
+
+    fn buffer(data: &[u8]) -> Vec<(u16, Field)> {
+        vec![(0, Field::Bytes(data.to_vec()))]
+    }
+
+    /// A model with a fully connected layer and a `while` body subgraph.
+    fn sample() -> Vec<u8> {
+        model(vec![
+            (0, Field::U32(3)),
+            (
+                1,
+                Field::Tables(vec![
+                    vec![(0, Field::U8(9))],
+                    vec![(1, Field::Str("MyOp".to_string())), (3, Field::I32(CUSTOM))],
+                ]),
+            ),
+            (
+                2,
+                Field::Tables(vec![
+                    vec![
+                        (
+                            0,
+                            Field::Tables(vec![
+                                tensor("input", vec![1, 4], 0, 0),
+                                tensor("fc.weight", vec![2, 4], 9, 1),
+                                tensor("output", vec![1, 2], 0, 0),
+                            ]),
+                        ),
+                        (1, Field::Ints(vec![0])),
+                        (2, Field::Ints(vec![2])),
+                        (
+                            3,
+                            Field::Tables(vec![vec![
+                                (1, Field::Ints(vec![0, 1])),
+                                (2, Field::Ints(vec![2])),
+                            ]]),
+                        ),
+                    ],
+                    vec![
+                        (0, Field::Tables(vec![tensor("bias", vec![2], 1, 2)])),
+                        (3, Field::Tables(vec![vec![(0, Field::U32(1))]])),
+                        (4, Field::Str("body".to_string())),
+                    ],
+                ]),
+            ),
+            (3, Field::Str("converted".to_string())),
+            (
+                4,
+                Field::Tables(vec![
+                    vec![],
+                    buffer(&[1; 8]),
+                    buffer(&[2; 4]),
+                    buffer(b"1.14.0\0\0\0\0\0\0\0\0\0\0"),
+                ]),
+            ),
+            (
+                6,
+                Field::Tables(vec![vec![
+                    (0, Field::Str("min_runtime_version".to_string())),
+                    (1, Field::U32(3)),
+                ]]),
+            ),
+        ])
+    }
+
+    #[test]
+    fn test_parse_model() {
+        let bytes = sample();
+        let artifact = parse_tflite(&mut Cursor::new(bytes.clone())).unwrap();
+
+        assert_eq!(artifact.gguf_version, Some(3));
+        assert_eq!(artifact.tensors.len(), 2);
+        let weight = &artifact.tensors["fc.weight"];
+        assert_eq!(weight.dtype, "i8");
+        assert_eq!(weight.shape, vec![2, 4]);
+        assert_eq!(weight.byte_length, 8);
+        let offset = weight.data_offset.unwrap() as usize;
+        assert_eq!(&bytes[offset..offset + 8], &[1; 8]);
+        assert_eq!(artifact.tensors["body/bias"].dtype, "f16");
+
+        let meta = |key: &str| artifact.metadata.get(key).cloned();
+        assert_eq!(
+            meta("operator_codes"),
+            Some(CanonicalValue::Array(vec![
+                CanonicalValue::String("FULLY_CONNECTED".to_string()),
+                CanonicalValue::String("MyOp".to_string()),
+            ]))
+        );
+        assert_eq!(meta("operator_count"), Some(CanonicalValue::Int(2)));
+        assert_eq!(meta("subgraph_count"), Some(CanonicalValue::Int(2)));
+        assert_eq!(
+            meta("input_names"),
+            Some(CanonicalValue::Array(vec![CanonicalValue::String(
+                "input".to_string()
+            )]))
+        );
+        assert_eq!(
+            meta("metadata.min_runtime_version"),
+            Some(CanonicalValue::String("1.14.0".to_string()))
+        );
+        assert_eq!(
+            meta("description"),
+            Some(CanonicalValue::String("converted".to_string()))
+        );
+        assert!(artifact.findings.is_empty());
+    }
+
+    #[test]
+    fn test_external_and_missing_buffers() {
+        let bytes = model(vec![
+            (
+                2,
+                Field::Tables(vec![vec![(
+                    0,
+                    Field::Tables(vec![
+                        tensor("big", vec![4], 0, 1),
+                        tensor("lost", vec![4], 0, 7),
+                    ]),
+                )]]),
+            ),
+            (
+                4,
+                Field::Tables(vec![
+                    vec![],
+                    vec![(1, Field::U64(1 << 40)), (2, Field::U64(16))],
+                ]),
+            ),
+        ]);
+        let artifact = parse_tflite(&mut Cursor::new(bytes)).unwrap();
+
+        let codes: Vec<_> = artifact.findings.iter().map(|f| f.code).collect();
+        assert_eq!(codes, ["tensor_out_of_bounds", "missing_buffer"]);
+        assert_eq!(artifact.tensors["big"].byte_length, 16);
+        assert_eq!(artifact.tensors["big"].data_offset, None);
+    }
+
+    #[test]
+    fn test_invalid_files() {
+        assert!(matches!(
+            parse_tflite(&mut Cursor::new(b"GGUF\x03\x00\x00\x00".to_vec())),
+            Err(TFLiteParserError::InvalidIdentifier)
+        ));
+        let mut bytes = sample();
+        bytes.truncate(bytes.len() - 40);
+        assert!(matches!(
+            parse_tflite(&mut Cursor::new(bytes)),
+            Err(TFLiteParserError::Invalid(_))
+        ));
+        let limits = ParseLimits {
+            max_tensor_count: 2,
+            ..Default::default()
+        };
+        assert!(matches!(
+            parse_tflite_with_limits(&mut Cursor::new(sample()), &limits),
+            Err(TFLiteParserError::BufferCountTooLarge { count: 4, max: 2 })
+        ));
+        assert_eq!(builtin_name(3), "CONV_2D");
+        assert_eq!(builtin_name(500), "BUILTIN_500");
+    }
+}
@@ -1,9 +1,11 @@
+use crate::graph::Graph;
 use serde::{Deserialize, Deserializer, Serialize};
 use std::collections::BTreeMap;
 use std::fmt;
 use std::hash::Hash;
 
-/// Represents a parsed model file (GGUF, safetensors, or ONNX).
+/// Represents a parsed model file (GGUF, legacy GGML, safetensors, ONNX,
+/// PyTorch, NumPy or TFLite).
 ///
 /// # Example
 ///
@@ -16,6 +18,9 @@ use std::hash::Hash;
 ///     gguf_version: Some(3),
 ///     metadata: BTreeMap::new(),
 ///     tensors: BTreeMap::new(),
+///     graph: None,
+///     data_offset: None,
+///     findings: Vec::new(),
 /// };
 /// ```
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -25,6 +30,18 @@ pub struct Artifact {
     pub gguf_version: Option<i64>,
     pub metadata: BTreeMap<String, CanonicalValue>,
     pub tensors: BTreeMap<String, Tensor>,
+    /// The computation graph, for formats that store one (ONNX).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub graph: Option<Graph>,
+    /// Absolute file offset where the tensor data section starts.
+    ///
+    /// Layout information only; excluded from the structural hash.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data_offset: Option<u64>,
+    /// Problems noticed while parsing that did not prevent it, such as
+    /// duplicate tensor names. Never serialized or hashed.
+    #[serde(skip)]
+    pub findings: Vec<Finding>,
 }
 
 /// Model file format.
@@ -32,8 +49,12 @@ pub struct Artifact {
 #[serde(rename_all = "lowercase")]
 pub enum Format {
     GGUF,
+    GGML,
     Safetensors,
     Onnx,
+    PyTorch,
+    Numpy,
+    TFLite,
 }
 
 /// Represents a metadata value in a model file.
@@ -47,6 +68,10 @@ pub enum CanonicalValue {
     Float(f64),
     String(String),
     Array(Vec<CanonicalValue>),
+    /// An array with a declared element type (GGUF), named as in the GGUF
+    /// spec (`uint32`, `string`, `array`, ...). The type is kept even when
+    /// the array is empty.
+    TypedArray(String, Vec<CanonicalValue>),
     Uint8(i64),
     Int8(i64),
     Uint16(i64),
@@ -67,6 +92,9 @@ impl PartialEq for CanonicalValue {
             (CanonicalValue::Float(a), CanonicalValue::Float(b)) => a.to_bits() == b.to_bits(),
             (CanonicalValue::String(a), CanonicalValue::String(b)) => a == b,
             (CanonicalValue::Array(a), CanonicalValue::Array(b)) => a == b,
+            (CanonicalValue::TypedArray(ta, a), CanonicalValue::TypedArray(tb, b)) => {
+                ta == tb && a == b
+            }
             (CanonicalValue::Uint8(a), CanonicalValue::Uint8(b)) => a == b,
             (CanonicalValue::Int8(a), CanonicalValue::Int8(b)) => a == b,
             (CanonicalValue::Uint16(a), CanonicalValue::Uint16(b)) => a == b,
@@ -90,6 +118,10 @@ impl Hash for CanonicalValue {
             CanonicalValue::Float(f) => f.to_bits().hash(state),
             CanonicalValue::String(s) => s.hash(state),
             CanonicalValue::Array(arr) => arr.hash(state),
+            CanonicalValue::TypedArray(ty, arr) => {
+                ty.hash(state);
+                arr.hash(state);
+            }
             CanonicalValue::Uint8(i) => i.hash(state),
             CanonicalValue::Int8(i) => i.hash(state),
             CanonicalValue::Uint16(i) => i.hash(state),
@@ -120,6 +152,13 @@ impl CanonicalSerializer {
                     .collect();
                 format!("[{}]", items.join(","))
             }
+            CanonicalValue::TypedArray(ty, arr) => {
+                let items: Vec<String> = arr
+                    .iter()
+                    .map(CanonicalSerializer::serialize_value)
+                    .collect();
+                format!("{}[{}]", ty, items.join(","))
+            }
             CanonicalValue::Uint8(i) => (*i).to_string(),
             CanonicalValue::Int8(i) => (*i).to_string(),
             CanonicalValue::Uint16(i) => (*i).to_string(),
@@ -244,6 +283,7 @@ impl fmt::Display for CanonicalValue {
             CanonicalValue::Float(fl) => write!(f, "{}", fl),
             CanonicalValue::String(s) => write!(f, "{}", s),
             CanonicalValue::Array(arr) => write!(f, "{:?}", arr),
+            CanonicalValue::TypedArray(_, arr) => write!(f, "{:?}", arr),
             CanonicalValue::Uint8(i) => write!(f, "{}", i),
             CanonicalValue::Int8(i) => write!(f, "{}", i),
             CanonicalValue::Uint16(i) => write!(f, "{}", i),
@@ -264,6 +304,138 @@ pub struct Tensor {
     pub dtype: String,
     pub shape: Vec<u64>,
     pub byte_length: u64,
+    /// Absolute file offset of the tensor's data, when the format stores one.
+    ///
+    /// Layout information only; excluded from the structural hash.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data_offset: Option<u64>,
+    /// Hex SHA-256 of the tensor's data bytes, when computed.
+    ///
+    /// Content information; excluded from the structural hash.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub digest: Option<String>,
+    /// Where the tensor's data is stored, when it is outside the model file
+    /// (ONNX external data). Their `data_offset`, if any, points into the
+    /// model file and its data files laid end to end.
+    ///
+    /// Layout information only; excluded from the structural hash.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<DataLocation>,
+    /// Number of stored values of a sparse tensor (ONNX sparse
+    /// initializers). `shape` is then the dense shape and `byte_length` the
+    /// size of the stored values and their indices.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nnz: Option<u64>,
+    /// Whether the data is stored big-endian. Values are byte-swapped when
+    /// decoded.
+    ///
+    /// Layout information only; excluded from the structural hash.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub big_endian: bool,
+}
+
+/// Data of a tensor stored in a separate file.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DataLocation {
+    /// Path of the file, relative to the model file's directory.
+    pub path: String,
+    /// Offset of the data in the file.
+    pub offset: u64,
+    /// Length of the data, when the model declares it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub length: Option<u64>,
+}
+
+/// Severity of a validation finding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Info => write!(f, "info"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A single integrity issue found in a model file.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Finding {
+    /// How serious the issue is.
+    pub severity: Severity,
+    /// Stable machine-readable code, e.g. `tensor_out_of_bounds`.
+    pub code: &'static str,
+    /// The tensor name or metadata key involved, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subject: Option<String>,
+    /// Human-readable description.
+    pub message: String,
+}
+
+impl Finding {
+    pub(crate) fn new(
+        severity: Severity,
+        code: &'static str,
+        subject: Option<&str>,
+        message: String,
+    ) -> Self {
+        Self {
+            severity,
+            code,
+            subject: subject.map(str::to_string),
+            message,
+        }
+    }
+}
+
+/// Safety limits applied while parsing untrusted model files.
+///
+/// Every parser rejects inputs that exceed these limits instead of allocating
+/// unbounded memory. The defaults are sized for real checkpoints, e.g. the
+/// 150k+ entry tokenizer vocabularies of Llama 3 and Qwen.
+///
+/// # Example
+///
+/// ```
+/// use weight_inspect::types::ParseLimits;
+///
+/// let limits = ParseLimits {
+///     max_array_elements: 1_000_000,
+///     ..ParseLimits::default()
+/// };
+/// assert_eq!(limits.max_dimensions, ParseLimits::default().max_dimensions);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseLimits {
+    /// Maximum number of elements in a single metadata array.
+    pub max_array_elements: usize,
+    /// Maximum number of tensors in a file.
+    pub max_tensor_count: u64,
+    /// Maximum number of metadata entries in a file.
+    pub max_metadata_count: u64,
+    /// Maximum number of dimensions of a single tensor.
+    pub max_dimensions: u32,
+    /// Maximum size of a safetensors JSON header, in bytes.
+    pub max_header_size: usize,
+}
+
+impl Default for ParseLimits {
+    fn default() -> Self {
+        Self {
+            max_array_elements: 16 * 1024 * 1024,
+            max_tensor_count: 1_000_000,
+            max_metadata_count: 100_000,
+            max_dimensions: 32,
+            max_header_size: 100 * 1024 * 1024, // 100MB
+        }
+    }
 }
 
 #[cfg(test)]
@@ -297,6 +469,18 @@ mod tests {
         assert_eq!(serialized, "\"\\\"test\\\"\"");
     }
 
+    #[test]
+    fn test_empty_typed_arrays_keep_element_type() {
+        let ints = CanonicalValue::TypedArray("uint32".to_string(), vec![]);
+        let strings = CanonicalValue::TypedArray("string".to_string(), vec![]);
+        assert_ne!(ints, strings);
+        assert_ne!(
+            CanonicalSerializer::serialize_value(&ints),
+            CanonicalSerializer::serialize_value(&strings)
+        );
+        assert_eq!(CanonicalSerializer::serialize_value(&ints), "uint32[]");
+    }
+
     #[test]
     fn test_bool_serialization() {
         let value = CanonicalValue::Bool(true);
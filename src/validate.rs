@@ -0,0 +1,510 @@
+use crate::gguf;
+use crate::graph::{Attribute, Graph, Node};
+use crate::types::{Artifact, Finding, Format, Severity};
+use std::collections::BTreeMap;
+
+/// Run every integrity check on a parsed artifact.
+///
+/// Combines the findings recorded by the parser (e.g. duplicate tensor names)
+/// with [`check_tensors`], [`check_layout`] and [`check_opsets`].
+///
+/// # Example
+///
+/// ```
+/// use weight_inspect::{safetensors, types::Severity, validate};
+///
+/// let data = std::fs::read("tests/fixtures/tiny.safetensors").unwrap();
+/// let file_size = data.len() as u64;
+/// let artifact = safetensors::parse_safetensors(&mut std::io::Cursor::new(data)).unwrap();
+/// let findings = validate::validate(&artifact, file_size);
+/// assert!(findings.iter().all(|f| f.severity != Severity::Error));
+/// ```
+pub fn validate(artifact: &Artifact, file_size: u64) -> Vec<Finding> {
+    let mut findings = artifact.findings.clone();
+    findings.extend(check_tensors(artifact));
+    findings.extend(check_layout(artifact, file_size));
+    findings.extend(check_opsets(artifact));
+    findings
+}
+
+/// Check the operator sets an artifact's graph depends on (ONNX).
+///
+/// Findings:
+/// - `missing_opset`: nodes use a domain that the model (or, inside a
+///   function, the function) does not import. Nodes in `If`, `Loop` and
+///   `Scan` bodies count as nodes of the graph that holds them.
+/// - `function_opset_mismatch`: a function imports a domain at a different
+///   version than the model, so its nodes would run with other semantics.
+pub fn check_opsets(artifact: &Artifact) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let Some(graph) = &artifact.graph else {
+        return findings;
+    };
+    check_domains(graph, &graph.opset_imports, None, &mut findings);
+    for (key, function) in &graph.functions {
+        check_domains(function, &function.opset_imports, Some(key), &mut findings);
+        for (domain, version) in &function.opset_imports {
+            match graph.opset_imports.get(domain) {
+                Some(model_version) if model_version != version => findings.push(Finding::new(
+                    Severity::Error,
+                    "function_opset_mismatch",
+                    Some(key),
+                    format!(
+                        "function imports {} version {} but the model imports version {}",
+                        domain, version, model_version
+                    ),
+                )),
+                _ => {}
+            }
+        }
+    }
+    findings
+}
+
+/// Report the domains used by `graph`'s nodes that `imports` lacks, once per
+/// domain.
+fn check_domains(
+    graph: &Graph,
+    imports: &BTreeMap<String, i64>,
+    function: Option<&str>,
+    findings: &mut Vec<Finding>,
+) {
+    fn visit<'a>(
+        graph: &'a Graph,
+        missing: &mut BTreeMap<&'a str, Vec<&'a Node>>,
+        imports: &BTreeMap<String, i64>,
+    ) {
+        for node in &graph.nodes {
+            if !imports.contains_key(node.opset_domain()) {
+                missing.entry(node.opset_domain()).or_default().push(node);
+            }
+            for attribute in node.attributes.values() {
+                match attribute {
+                    Attribute::Graph(body) => visit(body, missing, imports),
+                    Attribute::Graphs(bodies) => {
+                        bodies.iter().for_each(|body| visit(body, missing, imports))
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    let mut missing = BTreeMap::new();
+    visit(graph, &mut missing, imports);
+    let importer = match function {
+        Some(key) => format!("function '{}'", key),
+        None => "the model".to_string(),
+    };
+    for (domain, nodes) in missing {
+        findings.push(Finding::new(
+            Severity::Error,
+            "missing_opset",
+            Some(nodes[0].key()),
+            format!(
+                "{} node(s), such as {} '{}', use domain {}, which {} does not import",
+                nodes.len(),
+                nodes[0].op_type,
+                nodes[0].key(),
+                domain,
+                importer
+            ),
+        ));
+    }
+}
+
+/// Check each tensor's dtype and declared byte length against its shape.
+pub fn check_tensors(artifact: &Artifact) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for tensor in artifact.tensors.values() {
+        if tensor.dtype.starts_with("unknown_") {
+            findings.push(Finding::new(
+                Severity::Warning,
+                "unknown_dtype",
+                Some(&tensor.name),
+                format!(
+                    "dtype '{}' is not recognized; its size cannot be verified",
+                    tensor.dtype
+                ),
+            ));
+            continue;
+        }
+        // The byte length of a sparse tensor does not follow its dense shape.
+        let Some(size) = element_size(&tensor.dtype).filter(|_| tensor.nnz.is_none()) else {
+            continue;
+        };
+        let expected = tensor
+            .shape
+            .iter()
+            .try_fold(size, |acc, &dim| acc.checked_mul(dim));
+        match expected {
+            Some(expected) if expected == tensor.byte_length => {}
+            Some(expected) => findings.push(Finding::new(
+                Severity::Error,
+                "byte_length_mismatch",
+                Some(&tensor.name),
+                format!(
+                    "shape {:?} of {} needs {} bytes but {} are declared",
+                    tensor.shape, tensor.dtype, expected, tensor.byte_length
+                ),
+            )),
+            None => findings.push(Finding::new(
+                Severity::Error,
+                "shape_overflow",
+                Some(&tensor.name),
+                format!("shape {:?} overflows a 64-bit byte count", tensor.shape),
+            )),
+        }
+    }
+    findings
+}
+
+/// Size in bytes of one element of a non-quantized dtype.
+///
+/// Covers the dtype names produced by every parser. Block-quantized and
+/// sub-byte types return `None`.
+fn element_size(dtype: &str) -> Option<u64> {
+    let size = match dtype {
+        "bool" | "u8" | "i8" | "uint8" | "int8" | "f8_e4m3" | "f8_e5m2" => 1,
+        "u16" | "i16" | "f16" | "bf16" | "uint16" | "int16" | "float16" | "bfloat16" => 2,
+        "u32" | "i32" | "f32" | "uint32" | "int32" | "float32" => 4,
+        "u64" | "i64" | "f64" | "uint64" | "int64" | "float64" | "complex64" => 8,
+        "complex128" => 16,
+        _ => return None,
+    };
+    Some(size)
+}
+
+/// Check tensor data offsets against each other and against the file size.
+///
+/// Detects tensors that extend past the end of the file, overlapping tensors,
+/// unexpected gaps between tensors, offsets that break `general.alignment`
+/// (GGUF) and trailing bytes after the last tensor. Tensors without a
+/// `data_offset` are skipped, as are PyTorch checkpoints and NumPy files,
+/// whose tensors are spread over a zip archive, TFLite models and legacy
+/// GGML files, whose data is interleaved with headers, and ONNX models,
+/// whose inline data is interleaved with protobuf fields; their parsers
+/// check those.
+///
+/// # Example
+///
+/// ```
+/// use weight_inspect::{gguf, validate};
+///
+/// let data = std::fs::read("tests/fixtures/tiny.gguf").unwrap();
+/// let file_size = data.len() as u64;
+/// let artifact = gguf::parse_gguf(&mut std::io::Cursor::new(data)).unwrap();
+/// let findings = validate::check_layout(&artifact, file_size);
+/// // The fixture only contains a header, so its tensor data is missing.
+/// assert!(findings.iter().any(|f| f.code == "tensor_out_of_bounds"));
+/// ```
+pub fn check_layout(artifact: &Artifact, file_size: u64) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    if matches!(
+        artifact.format,
+        Format::GGML | Format::PyTorch | Format::Numpy | Format::TFLite | Format::Onnx
+    ) {
+        return findings;
+    }
+
+    let mut placed: Vec<(u64, &str, u64)> = artifact
+        .tensors
+        .values()
+        .filter_map(|t| t.data_offset.map(|o| (o, t.name.as_str(), t.byte_length)))
+        .collect();
+    if placed.is_empty() {
+        return findings;
+    }
+    placed.sort();
+
+    let data_start = artifact.data_offset.unwrap_or(placed[0].0);
+    let align = match artifact.format {
+        Format::GGUF => gguf::alignment(&artifact.metadata),
+        _ => 1,
+    };
+
+    if data_start > file_size {
+        findings.push(Finding::new(
+            Severity::Error,
+            "data_section_out_of_bounds",
+            None,
+            format!(
+                "tensor data section starts at byte {} but the file is only {} bytes",
+                data_start, file_size
+            ),
+        ));
+    }
+
+    let mut expected = data_start;
+    let mut previous: Option<(&str, u64)> = None;
+    for &(offset, name, byte_length) in &placed {
+        let end = offset.saturating_add(byte_length);
+
+        if offset < data_start {
+            findings.push(Finding::new(
+                Severity::Error,
+                "tensor_before_data_section",
+                Some(name),
+                format!(
+                    "data starts at byte {}, before the data section at byte {}",
+                    offset, data_start
+                ),
+            ));
+        } else if !(offset - data_start).is_multiple_of(align) {
+            findings.push(Finding::new(
+                Severity::Error,
+                "misaligned_offset",
+                Some(name),
+                format!(
+                    "data offset {} is not a multiple of the alignment {}",
+                    offset - data_start,
+                    align
+                ),
+            ));
+        }
+
+        if end > file_size {
+            findings.push(Finding::new(
+                Severity::Error,
+                "tensor_out_of_bounds",
+                Some(name),
+                format!(
+                    "data ends at byte {} but the file is only {} bytes (truncated?)",
+                    end, file_size
+                ),
+            ));
+        }
+
+        match previous {
+            Some((prev_name, prev_end)) if offset < prev_end => {
+                findings.push(Finding::new(
+                    Severity::Error,
+                    "tensor_overlap",
+                    Some(name),
+                    format!(
+                        "data at byte {} overlaps '{}', which ends at byte {}",
+                        offset, prev_name, prev_end
+                    ),
+                ));
+            }
+            _ if offset > expected => {
+                findings.push(Finding::new(
+                    Severity::Warning,
+                    "tensor_gap",
+                    Some(name),
+                    format!(
+                        "{} unused bytes before data at byte {}",
+                        offset - expected,
+                        offset
+                    ),
+                ));
+            }
+            _ => {}
+        }
+
+        let prev_end = previous.map_or(0, |(_, e)| e);
+        if end >= prev_end {
+            previous = Some((name, end));
+        }
+        expected = expected.max(end.div_ceil(align) * align);
+    }
+
+    if file_size > expected {
+        findings.push(Finding::new(
+            Severity::Warning,
+            "trailing_data",
+            None,
+            format!(
+                "{} bytes after the last tensor are not described by the header",
+                file_size - expected
+            ),
+        ));
+    }
+
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{CanonicalValue, Tensor};
+    use std::collections::BTreeMap;
+
+    fn artifact(format: Format, data_offset: u64, tensors: &[(&str, u64, u64)]) -> Artifact {
+        let mut map = BTreeMap::new();
+        for &(name, offset, byte_length) in tensors {
+            map.insert(
+                name.to_string(),
+                Tensor {
+                    name: name.to_string(),
+                    dtype: "u8".to_string(),
+                    shape: vec![byte_length],
+                    byte_length,
+                    data_offset: Some(offset),
+                    digest: None,
+                    location: None,
+                    nnz: None,
+                    big_endian: false,
+                },
+            );
+        }
+        Artifact {
+            format,
+            gguf_version: None,
+            metadata: BTreeMap::new(),
+            tensors: map,
+            graph: None,
+            data_offset: Some(data_offset),
+            findings: Vec::new(),
+        }
+    }
+
+    fn tensor(name: &str, dtype: &str, shape: Vec<u64>, byte_length: u64) -> Tensor {
+        Tensor {
+            name: name.to_string(),
+            dtype: dtype.to_string(),
+            shape,
+            byte_length,
+            data_offset: None,
+            digest: None,
+            location: None,
+            nnz: None,
+            big_endian: false,
+        }
+    }
+
+    fn codes(findings: &[Finding]) -> Vec<&'static str> {
+        findings.iter().map(|f| f.code).collect()
+    }
+
+    #[test]
+    fn test_byte_length_mismatch() {
+        let mut a = artifact(Format::Safetensors, 0, &[]);
+        a.tensors
+            .insert("ok".to_string(), tensor("ok", "f32", vec![2, 3], 24));
+        a.tensors
+            .insert("bad".to_string(), tensor("bad", "bf16", vec![4], 4));
+        let findings = check_tensors(&a);
+        assert_eq!(codes(&findings), vec!["byte_length_mismatch"]);
+        assert_eq!(findings[0].subject.as_deref(), Some("bad"));
+    }
+
+    #[test]
+    fn test_unknown_dtype() {
+        let mut a = artifact(Format::GGUF, 0, &[]);
+        a.tensors
+            .insert("t".to_string(), tensor("t", "unknown_99", vec![4], 0));
+        let findings = check_tensors(&a);
+        assert_eq!(codes(&findings), vec!["unknown_dtype"]);
+        assert_eq!(findings[0].severity, Severity::Warning);
+    }
+
+    #[test]
+    fn test_validate_includes_parser_findings() {
+        let mut a = artifact(Format::Safetensors, 16, &[("a", 16, 8)]);
+        a.findings.push(Finding::new(
+            Severity::Error,
+            "duplicate_tensor",
+            Some("a"),
+            "duplicate".to_string(),
+        ));
+        assert_eq!(codes(&validate(&a, 24)), vec!["duplicate_tensor"]);
+    }
+
+    #[test]
+    fn test_contiguous_layout_is_clean() {
+        let a = artifact(Format::Safetensors, 16, &[("a", 16, 8), ("b", 24, 8)]);
+        assert!(check_layout(&a, 32).is_empty());
+    }
+
+    #[test]
+    fn test_truncated_file() {
+        let a = artifact(Format::Safetensors, 16, &[("a", 16, 8), ("b", 24, 8)]);
+        let findings = check_layout(&a, 28);
+        assert_eq!(codes(&findings), vec!["tensor_out_of_bounds"]);
+        assert_eq!(findings[0].subject.as_deref(), Some("b"));
+        assert_eq!(findings[0].severity, Severity::Error);
+    }
+
+    #[test]
+    fn test_overlap() {
+        let a = artifact(Format::Safetensors, 16, &[("a", 16, 8), ("b", 20, 8)]);
+        assert_eq!(codes(&check_layout(&a, 28)), vec!["tensor_overlap"]);
+    }
+
+    #[test]
+    fn test_gap_and_trailing_data() {
+        let a = artifact(Format::Safetensors, 16, &[("a", 16, 8), ("b", 28, 4)]);
+        assert_eq!(
+            codes(&check_layout(&a, 40)),
+            vec!["tensor_gap", "trailing_data"]
+        );
+    }
+
+    #[test]
+    fn test_leading_gap() {
+        let a = artifact(Format::Safetensors, 16, &[("a", 20, 4)]);
+        assert_eq!(codes(&check_layout(&a, 24)), vec!["tensor_gap"]);
+    }
+
+    #[test]
+    fn test_gguf_alignment_padding_is_not_a_gap() {
+        let a = artifact(Format::GGUF, 32, &[("a", 32, 10), ("b", 64, 10)]);
+        assert!(check_layout(&a, 96).is_empty());
+    }
+
+    #[test]
+    fn test_gguf_misaligned_offset() {
+        let mut a = artifact(Format::GGUF, 64, &[("a", 64, 16), ("b", 80, 16)]);
+        a.metadata
+            .insert("general.alignment".to_string(), CanonicalValue::Uint32(32));
+        assert_eq!(codes(&check_layout(&a, 96)), vec!["misaligned_offset"]);
+    }
+
+    #[test]
+    fn test_data_section_past_eof() {
+        let a = artifact(Format::GGUF, 64, &[("a", 64, 0)]);
+        assert_eq!(
+            codes(&check_layout(&a, 40)),
+            vec!["data_section_out_of_bounds", "tensor_out_of_bounds"]
+        );
+    }
+
+    #[test]
+    fn test_opsets() {
+        let node = |op_type: &str, domain: &str| Node {
+            name: op_type.to_lowercase(),
+            op_type: op_type.to_string(),
+            domain: domain.to_string(),
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            attributes: BTreeMap::new(),
+        };
+        let function = Graph {
+            opset_imports: BTreeMap::from([("ai.onnx".to_string(), 13)]),
+            nodes: vec![node("Relu", "")],
+            ..Default::default()
+        };
+        let graph = Graph {
+            opset_imports: BTreeMap::from([("ai.onnx".to_string(), 17)]),
+            nodes: vec![
+                node("Conv", ""),
+                node("Attention", "com.microsoft"),
+                node("Gelu", "com.microsoft"),
+            ],
+            functions: BTreeMap::from([("custom::Act".to_string(), function)]),
+            ..Default::default()
+        };
+        let mut a = artifact(Format::Onnx, 0, &[]);
+        a.graph = Some(graph);
+
+        let findings = check_opsets(&a);
+        assert_eq!(
+            codes(&findings),
+            vec!["missing_opset", "function_opset_mismatch"]
+        );
+        assert_eq!(findings[0].subject.as_deref(), Some("attention"));
+        assert!(findings[0].message.starts_with("2 node(s)"));
+        assert_eq!(findings[1].subject.as_deref(), Some("custom::Act"));
+    }
+}
@@ -0,0 +1,440 @@
+use crate::dequant::{self, QuantType};
+use crate::types::Tensor;
+use std::io::{self, Read, Seek, SeekFrom};
+use thiserror::Error;
+
+/// Errors raised while decoding tensor values.
+#[derive(Error, Debug)]
+pub enum ValueError {
+    #[error("tensor '{0}' has no recorded data offset")]
+    MissingOffset(String),
+    #[error("tensor '{name}' has dtype '{dtype}', which cannot be decoded")]
+    UnsupportedDtype { name: String, dtype: String },
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+}
+
+/// Element types whose values can be decoded directly.
+///
+/// [`decode`](Self::decode) takes little-endian bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumericType {
+    Bool,
+    U8,
+    I8,
+    U16,
+    I16,
+    U32,
+    I32,
+    U64,
+    I64,
+    F16,
+    BF16,
+    F32,
+    F64,
+}
+
+impl NumericType {
+    /// Map a dtype name produced by any parser to a numeric type.
+    pub fn from_dtype(dtype: &str) -> Option<Self> {
+        let ty = match dtype {
+            "bool" => NumericType::Bool,
+            "u8" | "uint8" => NumericType::U8,
+            "i8" | "int8" => NumericType::I8,
+            "u16" | "uint16" => NumericType::U16,
+            "i16" | "int16" => NumericType::I16,
+            "u32" | "uint32" => NumericType::U32,
+            "i32" | "int32" => NumericType::I32,
+            "u64" | "uint64" => NumericType::U64,
+            "i64" | "int64" => NumericType::I64,
+            "f16" | "float16" => NumericType::F16,
+            "bf16" | "bfloat16" => NumericType::BF16,
+            "f32" | "float32" => NumericType::F32,
+            "f64" | "float64" => NumericType::F64,
+            _ => return None,
+        };
+        Some(ty)
+    }
+
+    /// Size of one element in bytes.
+    pub fn size(self) -> usize {
+        match self {
+            NumericType::Bool | NumericType::U8 | NumericType::I8 => 1,
+            NumericType::U16 | NumericType::I16 | NumericType::F16 | NumericType::BF16 => 2,
+            NumericType::U32 | NumericType::I32 | NumericType::F32 => 4,
+            NumericType::U64 | NumericType::I64 | NumericType::F64 => 8,
+        }
+    }
+
+    /// Decode one element from exactly [`size`](Self::size) bytes.
+    fn decode(self, bytes: &[u8]) -> f64 {
+        match self {
+            NumericType::Bool | NumericType::U8 => bytes[0] as f64,
+            NumericType::I8 => bytes[0] as i8 as f64,
+            NumericType::U16 => u16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            NumericType::I16 => i16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            NumericType::U32 => u32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+            NumericType::I32 => i32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+            NumericType::U64 => u64::from_le_bytes(bytes.try_into().unwrap()) as f64,
+            NumericType::I64 => i64::from_le_bytes(bytes.try_into().unwrap()) as f64,
+            NumericType::F16 => f16_to_f32(u16::from_le_bytes([bytes[0], bytes[1]])) as f64,
+            NumericType::BF16 => bf16_to_f32(u16::from_le_bytes([bytes[0], bytes[1]])) as f64,
+            NumericType::F32 => f32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+            NumericType::F64 => f64::from_le_bytes(bytes.try_into().unwrap()),
+        }
+    }
+}
+
+/// How a tensor's bytes turn into values: one element at a time, or in
+/// quantized blocks.
+#[derive(Debug, Clone, Copy)]
+enum Decoder {
+    /// Single elements, byte-swapped first if `big_endian`.
+    Plain {
+        ty: NumericType,
+        big_endian: bool,
+    },
+    Quant(QuantType),
+}
+
+impl Decoder {
+    fn from_dtype(dtype: &str, big_endian: bool) -> Option<Self> {
+        NumericType::from_dtype(dtype)
+            .map(|ty| Decoder::Plain { ty, big_endian })
+            .or_else(|| QuantType::from_dtype(dtype).map(Decoder::Quant))
+    }
+
+    /// Values and bytes per decoding unit.
+    fn unit(self) -> (usize, usize) {
+        match self {
+            Decoder::Plain { ty, .. } => (1, ty.size()),
+            Decoder::Quant(ty) => (ty.block_size(), ty.block_bytes()),
+        }
+    }
+
+    fn decode(self, bytes: &[u8], out: &mut Vec<f64>) {
+        match self {
+            Decoder::Plain {
+                ty,
+                big_endian: false,
+            } => {
+                out.extend(bytes.chunks_exact(ty.size()).map(|b| ty.decode(b)));
+            }
+            Decoder::Plain {
+                ty,
+                big_endian: true,
+            } => {
+                let mut swapped = [0u8; 8];
+                out.extend(bytes.chunks_exact(ty.size()).map(|b| {
+                    let swapped = &mut swapped[..b.len()];
+                    swapped.copy_from_slice(b);
+                    swapped.reverse();
+                    ty.decode(swapped)
+                }));
+            }
+            Decoder::Quant(ty) => {
+                let mut block = Vec::with_capacity(ty.block_size());
+                for chunk in bytes.chunks_exact(ty.block_bytes()) {
+                    block.clear();
+                    dequant::dequantize_block(ty, chunk, &mut block);
+                    out.extend(block.iter().map(|&v| v as f64));
+                }
+            }
+        }
+    }
+}
+
+/// Whether [`ValueReader`] can decode tensors of this dtype.
+///
+/// Covers the plain [`NumericType`]s and the GGUF block-quantized types in
+/// [`QuantType`].
+pub fn is_decodable(dtype: &str) -> bool {
+    Decoder::from_dtype(dtype, false).is_some()
+}
+
+/// The reason to skip a tensor that failed to decode with `err`, or `None`
+/// if the error is not specific to that tensor.
+///
+/// A tensor is skipped when its dtype cannot be decoded, it has no data
+/// offset, or its data ends early (a truncated file).
+pub fn skip_reason(err: &ValueError) -> Option<String> {
+    match err {
+        ValueError::UnsupportedDtype { dtype, .. } => {
+            Some(format!("dtype '{}' cannot be decoded", dtype))
+        }
+        ValueError::MissingOffset(_) => Some("no recorded data offset".to_string()),
+        ValueError::IoError(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+            Some("data is truncated".to_string())
+        }
+        ValueError::IoError(_) => None,
+    }
+}
+
+/// Convert IEEE 754 half-precision bits to `f32`.
+pub fn f16_to_f32(bits: u16) -> f32 {
+    let sign = if bits & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exp = ((bits >> 10) & 0x1f) as i32;
+    let mant = (bits & 0x3ff) as u32;
+    match exp {
+        0 => sign * mant as f32 * 2f32.powi(-24),
+        0x1f if mant == 0 => sign * f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => {
+            let bits = ((bits as u32 & 0x8000) << 16) | (((exp + 112) as u32) << 23) | (mant << 13);
+            f32::from_bits(bits)
+        }
+    }
+}
+
+/// Convert bfloat16 bits to `f32`.
+pub fn bf16_to_f32(bits: u16) -> f32 {
+    f32::from_bits((bits as u32) << 16)
+}
+
+/// Streams a tensor's values as `f64`, a chunk at a time.
+///
+/// Quantized tensors are dequantized block by block, and
+/// [big-endian](Tensor::big_endian) ones are byte-swapped.
+///
+/// # Example
+///
+/// ```
+/// use weight_inspect::{safetensors, values::ValueReader};
+///
+/// let data = std::fs::read("tests/fixtures/tiny.safetensors").unwrap();
+/// let mut cursor = std::io::Cursor::new(data);
+/// let artifact = safetensors::parse_safetensors(&mut cursor).unwrap();
+/// let tensor = artifact.tensors.values().next().unwrap();
+///
+/// let mut values = ValueReader::new(tensor, &mut cursor).unwrap();
+/// let mut chunk = Vec::new();
+/// let mut count = 0;
+/// while values.read_chunk(1024, &mut chunk).unwrap() > 0 {
+///     count += chunk.len();
+/// }
+/// assert_eq!(count as u64, tensor.shape.iter().product::<u64>());
+/// ```
+pub struct ValueReader<'a, R> {
+    reader: &'a mut R,
+    decoder: Decoder,
+    /// Undecoded units left in the tensor.
+    remaining_units: u64,
+    buf: Vec<u8>,
+    /// Decoded values not yet handed out, from `pending_pos` on.
+    pending: Vec<f64>,
+    pending_pos: usize,
+}
+
+impl<'a, R: Read + Seek> ValueReader<'a, R> {
+    /// Position `reader` at the tensor's data and prepare to decode it.
+    pub fn new(tensor: &Tensor, reader: &'a mut R) -> Result<Self, ValueError> {
+        let decoder = Decoder::from_dtype(&tensor.dtype, tensor.big_endian).ok_or_else(|| {
+            ValueError::UnsupportedDtype {
+                name: tensor.name.clone(),
+                dtype: tensor.dtype.clone(),
+            }
+        })?;
+        let offset = tensor
+            .data_offset
+            .ok_or_else(|| ValueError::MissingOffset(tensor.name.clone()))?;
+        reader.seek(SeekFrom::Start(offset))?;
+        let (_, unit_bytes) = decoder.unit();
+        Ok(Self {
+            reader,
+            decoder,
+            remaining_units: tensor.byte_length / unit_bytes as u64,
+            buf: Vec::new(),
+            pending: Vec::new(),
+            pending_pos: 0,
+        })
+    }
+
+    /// Number of values not yet read.
+    pub fn remaining(&self) -> u64 {
+        let (unit_values, _) = self.decoder.unit();
+        self.remaining_units * unit_values as u64 + (self.pending.len() - self.pending_pos) as u64
+    }
+
+    /// Replace the contents of `out` with up to `max` further values.
+    ///
+    /// Returns the number of values decoded, which is 0 once the tensor is
+    /// exhausted.
+    pub fn read_chunk(&mut self, max: usize, out: &mut Vec<f64>) -> Result<usize, ValueError> {
+        out.clear();
+        while out.len() < max {
+            if self.pending_pos == self.pending.len() {
+                if self.remaining_units == 0 {
+                    break;
+                }
+                self.fill(max - out.len())?;
+            }
+            let take = (max - out.len()).min(self.pending.len() - self.pending_pos);
+            out.extend_from_slice(&self.pending[self.pending_pos..self.pending_pos + take]);
+            self.pending_pos += take;
+        }
+        Ok(out.len())
+    }
+
+    /// Decode enough whole units to produce at least `wanted` values.
+    fn fill(&mut self, wanted: usize) -> Result<(), ValueError> {
+        let (unit_values, unit_bytes) = self.decoder.unit();
+        let units = (wanted.div_ceil(unit_values) as u64).min(self.remaining_units) as usize;
+        self.buf.resize(units * unit_bytes, 0);
+        self.reader.read_exact(&mut self.buf)?;
+        self.pending.clear();
+        self.pending_pos = 0;
+        self.decoder.decode(&self.buf, &mut self.pending);
+        self.remaining_units -= units as u64;
+        Ok(())
+    }
+}
+
+/// An artifact of 1-D f32 tensors laid out back to back, and its data.
+#[cfg(test)]
+pub(crate) fn f32_artifact(values: &[(&str, &[f32])]) -> (crate::types::Artifact, Vec<u8>) {
+    use crate::types::{Artifact, Format};
+
+    let mut artifact = Artifact {
+        format: Format::Safetensors,
+        gguf_version: None,
+        metadata: Default::default(),
+        tensors: Default::default(),
+        graph: None,
+        data_offset: Some(0),
+        findings: Vec::new(),
+    };
+    let mut data = Vec::new();
+    for &(name, vals) in values {
+        artifact.tensors.insert(
+            name.to_string(),
+            Tensor {
+                name: name.to_string(),
+                dtype: "f32".to_string(),
+                shape: vec![vals.len() as u64],
+                byte_length: vals.len() as u64 * 4,
+                data_offset: Some(data.len() as u64),
+                digest: None,
+                location: None,
+                nnz: None,
+                big_endian: false,
+            },
+        );
+        data.extend(vals.iter().flat_map(|v| v.to_le_bytes()));
+    }
+    (artifact, data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn tensor(dtype: &str, byte_length: u64) -> Tensor {
+        Tensor {
+            name: "t".to_string(),
+            dtype: dtype.to_string(),
+            shape: vec![],
+            byte_length,
+            data_offset: Some(0),
+            digest: None,
+            location: None,
+            nnz: None,
+            big_endian: false,
+        }
+    }
+
+    fn read_all(dtype: &str, data: Vec<u8>) -> Vec<f64> {
+        let t = tensor(dtype, data.len() as u64);
+        let mut cursor = Cursor::new(data);
+        let mut reader = ValueReader::new(&t, &mut cursor).unwrap();
+        let mut values = Vec::new();
+        let mut chunk = Vec::new();
+        while reader.read_chunk(3, &mut chunk).unwrap() > 0 {
+            values.extend_from_slice(&chunk);
+        }
+        values
+    }
+
+    #[test]
+    fn test_f16_conversion() {
+        assert_eq!(f16_to_f32(0x3c00), 1.0);
+        assert_eq!(f16_to_f32(0xc000), -2.0);
+        assert_eq!(f16_to_f32(0x7bff), 65504.0);
+        assert_eq!(f16_to_f32(0x0001), 2f32.powi(-24));
+        assert_eq!(f16_to_f32(0x7c00), f32::INFINITY);
+        assert!(f16_to_f32(0x7e00).is_nan());
+    }
+
+    #[test]
+    fn test_bf16_conversion() {
+        assert_eq!(bf16_to_f32(0x3f80), 1.0);
+        assert_eq!(bf16_to_f32(0xc040), -3.0);
+    }
+
+    #[test]
+    fn test_read_f32_in_chunks() {
+        let data: Vec<u8> = [1.5f32, -2.0, 3.25, 0.0, 8.0]
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect();
+        assert_eq!(read_all("f32", data), vec![1.5, -2.0, 3.25, 0.0, 8.0]);
+    }
+
+    #[test]
+    fn test_read_big_endian() {
+        let mut t = tensor("f32", 8);
+        t.big_endian = true;
+        let data: Vec<u8> = [1.5f32, -2.0]
+            .iter()
+            .flat_map(|v| v.to_be_bytes())
+            .collect();
+        let mut cursor = Cursor::new(data);
+        let mut reader = ValueReader::new(&t, &mut cursor).unwrap();
+        let mut chunk = Vec::new();
+        reader.read_chunk(16, &mut chunk).unwrap();
+        assert_eq!(chunk, vec![1.5, -2.0]);
+    }
+
+    #[test]
+    fn test_read_integers() {
+        assert_eq!(read_all("i8", vec![0xff, 0x02]), vec![-1.0, 2.0]);
+        assert_eq!(read_all("uint16", vec![0x00, 0x01]), vec![256.0]);
+        assert_eq!(read_all("i64", (-7i64).to_le_bytes().to_vec()), vec![-7.0]);
+    }
+
+    #[test]
+    fn test_read_quantized_blocks() {
+        // Two q8_0 blocks with scales 1.0 and 2.0; read in chunks of 3 values.
+        let mut data = Vec::new();
+        for scale in [[0x00, 0x3c], [0x00, 0x40]] {
+            data.extend(scale);
+            data.extend((0..32).map(|i| i as u8));
+        }
+        let values = read_all("q8_0", data);
+        assert_eq!(values.len(), 64);
+        assert_eq!(values[31], 31.0);
+        assert_eq!(values[33], 2.0);
+    }
+
+    #[test]
+    fn test_unsupported_dtype() {
+        let t = tensor("iq2_xxs", 66);
+        let mut cursor = Cursor::new(vec![0u8; 66]);
+        assert!(matches!(
+            ValueReader::new(&t, &mut cursor),
+            Err(ValueError::UnsupportedDtype { .. })
+        ));
+    }
+
+    #[test]
+    fn test_truncated_data() {
+        let t = tensor("f32", 8);
+        let mut cursor = Cursor::new(vec![0u8; 6]);
+        let mut reader = ValueReader::new(&t, &mut cursor).unwrap();
+        let mut chunk = Vec::new();
+        assert!(matches!(
+            reader.read_chunk(16, &mut chunk),
+            Err(ValueError::IoError(_))
+        ));
+    }
+}
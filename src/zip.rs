@@ -0,0 +1,369 @@
+use flate2::read::DeflateDecoder;
+use std::io::{self, Read, Seek, SeekFrom};
+use thiserror::Error;
+
+const LOCAL_HEADER_SIG: u32 = 0x0403_4b50;
+const CENTRAL_HEADER_SIG: u32 = 0x0201_4b50;
+const END_SIG: u32 = 0x0605_4b50;
+const ZIP64_END_SIG: u32 = 0x0606_4b50;
+const ZIP64_LOCATOR_SIG: u32 = 0x0706_4b50;
+const ZIP64_EXTRA_ID: u16 = 0x0001;
+/// The end record is 22 bytes followed by a comment of up to 64 KiB.
+const MAX_END_SEARCH: u64 = 22 + u16::MAX as u64;
+
+/// Compression method of an entry stored as is.
+pub const STORED: u16 = 0;
+/// Compression method of a deflate-compressed entry.
+pub const DEFLATED: u16 = 8;
+
+#[derive(Error, Debug)]
+pub enum ZipError {
+    #[error("not a zip archive: no end of central directory record")]
+    NotZip,
+    #[error("invalid zip archive: {0}")]
+    Invalid(String),
+    #[error("central directory of {size} bytes exceeds max_header_size ({max} bytes)")]
+    DirectoryTooLarge { size: u64, max: usize },
+    #[error("entry '{name}' is compressed (method {method}); only stored entries can be read")]
+    Compressed { name: String, method: u16 },
+    #[error("entry '{name}' uses unsupported compression method {method}")]
+    UnsupportedMethod { name: String, method: u16 },
+    #[error("entry '{name}' of {size} bytes exceeds the limit of {max} bytes")]
+    EntryTooLarge { name: String, size: u64, max: usize },
+    #[error("IO error: {0}")]
+    IoError(#[from] io::Error),
+}
+
+/// One file in a zip archive, as listed by its central directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ZipEntry {
+    pub name: String,
+    /// Compression method; [`STORED`] for uncompressed entries.
+    pub method: u16,
+    pub compressed_size: u64,
+    pub size: u64,
+    /// Absolute offset of the entry's (possibly compressed) data.
+    pub data_offset: u64,
+}
+
+/// Whether `magic` starts a zip archive.
+pub fn is_zip(magic: &[u8]) -> bool {
+    magic.starts_with(b"PK\x03\x04")
+}
+
+fn u16_at(buf: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([buf[at], buf[at + 1]])
+}
+
+fn u32_at(buf: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(buf[at..at + 4].try_into().unwrap())
+}
+
+fn u64_at(buf: &[u8], at: usize) -> u64 {
+    u64::from_le_bytes(buf[at..at + 8].try_into().unwrap())
+}
+
+fn invalid(reason: &str) -> ZipError {
+    ZipError::Invalid(reason.to_string())
+}
+
+/// Locate the central directory: its entry count, size and offset.
+fn find_directory<R: Read + Seek>(reader: &mut R) -> Result<(u64, u64, u64), ZipError> {
+    let file_size = reader.seek(SeekFrom::End(0))?;
+    let search = file_size.min(MAX_END_SEARCH);
+    let tail_start = file_size - search;
+    reader.seek(SeekFrom::Start(tail_start))?;
+    let mut tail = vec![0u8; search as usize];
+    reader.read_exact(&mut tail)?;
+
+    let end = (0..tail.len().saturating_sub(21))
+        .rev()
+        .find(|&i| u32_at(&tail, i) == END_SIG)
+        .ok_or(ZipError::NotZip)?;
+    let record = &tail[end..];
+    let mut count = u16_at(record, 10) as u64;
+    let mut size = u32_at(record, 12) as u64;
+    let mut offset = u32_at(record, 16) as u64;
+
+    if count == u16::MAX as u64 || size == u32::MAX as u64 || offset == u32::MAX as u64 {
+        let locator = (tail_start + end as u64)
+            .checked_sub(20)
+            .ok_or_else(|| invalid("missing zip64 end of central directory locator"))?;
+        let mut buf = [0u8; 20];
+        reader.seek(SeekFrom::Start(locator))?;
+        reader.read_exact(&mut buf)?;
+        if u32_at(&buf, 0) != ZIP64_LOCATOR_SIG {
+            return Err(invalid("missing zip64 end of central directory locator"));
+        }
+        let mut record = [0u8; 56];
+        reader.seek(SeekFrom::Start(u64_at(&buf, 8)))?;
+        reader.read_exact(&mut record)?;
+        if u32_at(&record, 0) != ZIP64_END_SIG {
+            return Err(invalid("bad zip64 end of central directory record"));
+        }
+        count = u64_at(&record, 32);
+        size = u64_at(&record, 40);
+        offset = u64_at(&record, 48);
+    }
+    Ok((count, size, offset))
+}
+
+/// Read the 64-bit sizes and offset of an entry from its zip64 extra field.
+/// Only the fields saturated in the central directory are present.
+fn apply_zip64(extra: &[u8], fields: &mut [&mut u64; 3]) -> Result<(), ZipError> {
+    let mut at = 0;
+    while at + 4 <= extra.len() {
+        let id = u16_at(extra, at);
+        let len = u16_at(extra, at + 2) as usize;
+        let body = extra
+            .get(at + 4..at + 4 + len)
+            .ok_or_else(|| invalid("truncated extra field"))?;
+        if id == ZIP64_EXTRA_ID {
+            let mut pos = 0;
+            for field in fields.iter_mut() {
+                if **field == u32::MAX as u64 {
+                    if pos + 8 > body.len() {
+                        return Err(invalid("truncated zip64 extra field"));
+                    }
+                    **field = u64_at(body, pos);
+                    pos += 8;
+                }
+            }
+            return Ok(());
+        }
+        at += 4 + len;
+    }
+    Ok(())
+}
+
+/// List the entries of a zip archive from its central directory.
+///
+/// The central directory may be at most `max_directory_size` bytes. Each
+/// entry's local header is read to find where its data starts, so entry
+/// data can be located without decompressing or copying it.
+pub fn read_entries<R: Read + Seek>(
+    reader: &mut R,
+    max_directory_size: usize,
+) -> Result<Vec<ZipEntry>, ZipError> {
+    let (count, size, offset) = find_directory(reader)?;
+    if size > max_directory_size as u64 {
+        return Err(ZipError::DirectoryTooLarge {
+            size,
+            max: max_directory_size,
+        });
+    }
+    let mut directory = vec![0u8; size as usize];
+    reader.seek(SeekFrom::Start(offset))?;
+    reader.read_exact(&mut directory)?;
+
+    let mut entries = Vec::new();
+    let mut at = 0;
+    for _ in 0..count {
+        let header = directory
+            .get(at..at + 46)
+            .ok_or_else(|| invalid("truncated central directory"))?;
+        if u32_at(header, 0) != CENTRAL_HEADER_SIG {
+            return Err(invalid("bad central directory entry"));
+        }
+        let method = u16_at(header, 10);
+        let mut compressed_size = u32_at(header, 20) as u64;
+        let mut entry_size = u32_at(header, 24) as u64;
+        let name_len = u16_at(header, 28) as usize;
+        let extra_len = u16_at(header, 30) as usize;
+        let comment_len = u16_at(header, 32) as usize;
+        let mut header_offset = u32_at(header, 42) as u64;
+
+        let name_start = at + 46;
+        let extra_start = name_start + name_len;
+        let next = extra_start + extra_len + comment_len;
+        if next > directory.len() {
+            return Err(invalid("truncated central directory"));
+        }
+        let name = String::from_utf8_lossy(&directory[name_start..extra_start]).into_owned();
+        apply_zip64(
+            &directory[extra_start..extra_start + extra_len],
+            &mut [&mut entry_size, &mut compressed_size, &mut header_offset],
+        )?;
+
+        let mut local = [0u8; 30];
+        reader.seek(SeekFrom::Start(header_offset))?;
+        reader.read_exact(&mut local)?;
+        if u32_at(&local, 0) != LOCAL_HEADER_SIG {
+            return Err(ZipError::Invalid(format!(
+                "bad local header for '{}'",
+                name
+            )));
+        }
+        let data_offset =
+            header_offset + 30 + u16_at(&local, 26) as u64 + u16_at(&local, 28) as u64;
+
+        entries.push(ZipEntry {
+            name,
+            method,
+            compressed_size,
+            size: entry_size,
+            data_offset,
+        });
+        at = next;
+    }
+    Ok(entries)
+}
+
+/// Read the contents of a stored (uncompressed) entry of at most `max` bytes.
+pub fn read_entry<R: Read + Seek>(
+    reader: &mut R,
+    entry: &ZipEntry,
+    max: usize,
+) -> Result<Vec<u8>, ZipError> {
+    if entry.method != STORED {
+        return Err(ZipError::Compressed {
+            name: entry.name.clone(),
+            method: entry.method,
+        });
+    }
+    if entry.size > max as u64 {
+        return Err(ZipError::EntryTooLarge {
+            name: entry.name.clone(),
+            size: entry.size,
+            max,
+        });
+    }
+    let mut data = vec![0u8; entry.size as usize];
+    reader.seek(SeekFrom::Start(entry.data_offset))?;
+    reader.read_exact(&mut data)?;
+    Ok(data)
+}
+
+/// Stream the contents of a stored or deflated entry.
+///
+/// Deflated entries are decompressed as they are read, so a prefix such as
+/// a file header can be read without inflating the whole entry.
+pub fn entry_reader<'a, R: Read + Seek>(
+    reader: &'a mut R,
+    entry: &ZipEntry,
+) -> Result<Box<dyn Read + 'a>, ZipError> {
+    reader.seek(SeekFrom::Start(entry.data_offset))?;
+    let data = reader.take(entry.compressed_size);
+    match entry.method {
+        STORED => Ok(Box::new(data)),
+        DEFLATED => Ok(Box::new(DeflateDecoder::new(data))),
+        method => Err(ZipError::UnsupportedMethod {
+            name: entry.name.clone(),
+            method,
+        }),
+    }
+}
+
+/// Build a zip archive of stored entries, for tests.
+#[cfg(test)]
+pub(crate) fn stored_archive(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut directory = Vec::new();
+    for (name, data) in files {
+        let offset = out.len() as u32;
+        let mut fields = Vec::new();
+        fields.extend(20u16.to_le_bytes()); // version needed
+        fields.extend(0u16.to_le_bytes()); // flags
+        fields.extend(STORED.to_le_bytes());
+        fields.extend([0u8; 8]); // time, date, crc-32
+        fields.extend((data.len() as u32).to_le_bytes());
+        fields.extend((data.len() as u32).to_le_bytes());
+        fields.extend((name.len() as u16).to_le_bytes());
+        fields.extend(0u16.to_le_bytes()); // extra length
+
+        out.extend(LOCAL_HEADER_SIG.to_le_bytes());
+        out.extend(&fields);
+        out.extend(name.as_bytes());
+        out.extend(*data);
+
+        directory.extend(CENTRAL_HEADER_SIG.to_le_bytes());
+        directory.extend(20u16.to_le_bytes()); // version made by
+        directory.extend(&fields);
+        directory.extend([0u8; 10]); // comment length, disk, attributes
+        directory.extend(offset.to_le_bytes());
+        directory.extend(name.as_bytes());
+    }
+    let directory_offset = out.len() as u32;
+    out.extend(&directory);
+    out.extend(END_SIG.to_le_bytes());
+    out.extend([0u8; 4]); // disk numbers
+    out.extend((files.len() as u16).to_le_bytes());
+    out.extend((files.len() as u16).to_le_bytes());
+    out.extend((directory.len() as u32).to_le_bytes());
+    out.extend(directory_offset.to_le_bytes());
+    out.extend(0u16.to_le_bytes()); // comment length
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_read_stored_entries() {
+        let bytes = stored_archive(&[("a.txt", b"hello"), ("dir/b.bin", b"\x01\x02")]);
+        let mut cursor = Cursor::new(bytes);
+
+        let entries = read_entries(&mut cursor, 1024).unwrap();
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].name, "dir/b.bin");
+        assert_eq!(entries[1].size, 2);
+        assert_eq!(read_entry(&mut cursor, &entries[0], 16).unwrap(), b"hello");
+        assert_eq!(
+            read_entry(&mut cursor, &entries[1], 16).unwrap(),
+            b"\x01\x02"
+        );
+    }
+
+    #[test]
+    fn test_entry_limits() {
+        let bytes = stored_archive(&[("a.txt", b"hello")]);
+        let mut cursor = Cursor::new(bytes);
+
+        assert!(matches!(
+            read_entries(&mut cursor, 8),
+            Err(ZipError::DirectoryTooLarge { .. })
+        ));
+        let entries = read_entries(&mut cursor, 1024).unwrap();
+        assert!(matches!(
+            read_entry(&mut cursor, &entries[0], 4),
+            Err(ZipError::EntryTooLarge { .. })
+        ));
+    }
+
+    #[test]
+    fn test_deflated_entry() {
+        use flate2::write::DeflateEncoder;
+        use flate2::Compression;
+        use std::io::Write;
+
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&[7u8; 1000]).unwrap();
+        let compressed = encoder.finish().unwrap();
+        let mut cursor = Cursor::new(stored_archive(&[("a.bin", &compressed)]));
+        let mut entry = read_entries(&mut cursor, 1024).unwrap().remove(0);
+        entry.method = DEFLATED;
+
+        assert!(matches!(
+            read_entry(&mut cursor, &entry, 2000),
+            Err(ZipError::Compressed { .. })
+        ));
+        let mut data = Vec::new();
+        entry_reader(&mut cursor, &entry)
+            .unwrap()
+            .read_to_end(&mut data)
+            .unwrap();
+        assert_eq!(data, vec![7u8; 1000]);
+    }
+
+    #[test]
+    fn test_not_a_zip() {
+        let mut cursor = Cursor::new(b"definitely not a zip archive".to_vec());
+        assert!(matches!(
+            read_entries(&mut cursor, 1024),
+            Err(ZipError::NotZip)
+        ));
+    }
+}
@@ -20,9 +20,11 @@ fn run_cli(args: &[&str]) -> std::process::Output {
 
     // Use cargo run from project root
     let mut cmd = Command::new("cargo");
-    cmd.args(["run", "--manifest-path"])
-        .arg(&manifest_path)
-        .arg("--")
+    cmd.args(["run", "--manifest-path"]).arg(&manifest_path);
+    // Run the binary with the same optional formats as this test build
+    #[cfg(feature = "onnx")]
+    cmd.args(["--features", "onnx"]);
+    cmd.arg("--")
         .args(args)
         .current_dir(&project_dir)
         .stdout(Stdio::piped())
@@ -203,3 +205,606 @@ fn test_invalid_format_error() {
     // Should fail with invalid format error
     assert!(!output.status.success());
 }
+
+#[test]
+fn test_parse_limit_flag() {
+    let output = run_cli(&[
+        "inspect",
+        "tests/fixtures/tiny.gguf",
+        "--max-tensor-count",
+        "0",
+    ]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(stderr.contains("max_tensor_count"));
+}
+
+#[test]
+fn test_inspect_reports_truncated_data() {
+    // The fixture contains only a header, so its tensor data is missing.
+    let output = run_cli(&["inspect", "tests/fixtures/tiny.gguf"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success());
+    assert!(stderr.contains("tensor_out_of_bounds"));
+}
+
+#[test]
+fn test_validate_truncated_file_fails() {
+    let output = run_cli(&["validate", "tests/fixtures/tiny.gguf"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!output.status.success());
+    assert!(stdout.contains("INVALID"));
+    assert!(stdout.contains("tensor_out_of_bounds"));
+}
+
+#[test]
+fn test_validate_json_output() {
+    let output = run_cli(&["validate", "tests/fixtures/tiny.safetensors", "--json"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success());
+    assert!(stdout.contains("\"valid\": true"));
+    assert!(stdout.contains("\"findings\":"));
+}
+
+#[test]
+fn test_validate_reports_parse_errors() {
+    let mut temp = NamedTempFile::new().expect("Failed to create temp file");
+    std::io::Write::write_all(&mut temp, b"GGUF\x03\x00\x00\x00").unwrap();
+    let output = run_cli(&["validate", &temp.path().to_string_lossy()]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!output.status.success());
+    assert!(stdout.contains("parse_error"));
+}
+
+#[test]
+fn test_id_content_hash() {
+    let output = run_cli(&[
+        "id",
+        "tests/fixtures/tiny.safetensors",
+        "--content",
+        "--json",
+    ]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success());
+    assert!(stdout.contains("\"content_hash\":"));
+    assert!(stdout.contains("\"merkle_root\":"));
+}
+
+/// A 2x2 column-major f32 `.npy` file, whose data has no usable offset.
+fn fortran_npy() -> NamedTempFile {
+    let mut header = "{'descr': '<f4', 'fortran_order': True, 'shape': (2, 2), }".to_string();
+    while !(10 + header.len() + 1).is_multiple_of(64) {
+        header.push(' ');
+    }
+    header.push('\n');
+    let mut temp = tempfile::Builder::new().suffix(".npy").tempfile().unwrap();
+    std::io::Write::write_all(&mut temp, b"\x93NUMPY\x01\x00").unwrap();
+    std::io::Write::write_all(&mut temp, &(header.len() as u16).to_le_bytes()).unwrap();
+    std::io::Write::write_all(&mut temp, header.as_bytes()).unwrap();
+    std::io::Write::write_all(&mut temp, &[0u8; 16]).unwrap();
+    temp
+}
+
+#[test]
+fn test_id_content_skips_tensors_without_offset() {
+    let npy = fortran_npy();
+    let output = run_cli(&["id", &npy.path().to_string_lossy(), "--content", "--json"]);
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["unhashed_tensors"], serde_json::json!(["arr_0"]));
+
+    let output = run_cli(&["id", &npy.path().to_string_lossy(), "--content"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("unhashed_tensors: 1 (no data offset): arr_0"));
+    assert!(!stdout.contains("Excludes:"));
+}
+
+/// Write a safetensors file with the given `(name, dtype, shape, data)`
+/// tensors, stored back to back in that order.
+fn write_safetensors(path: &std::path::Path, tensors: &[(&str, &str, &[u64], &[u8])]) {
+    let mut entries = Vec::new();
+    let mut data = Vec::new();
+    for (name, dtype, shape, bytes) in tensors {
+        entries.push(format!(
+            r#""{}":{{"dtype":"{}","shape":{:?},"data_offsets":[{},{}]}}"#,
+            name,
+            dtype,
+            shape,
+            data.len(),
+            data.len() + bytes.len()
+        ));
+        data.extend_from_slice(bytes);
+    }
+    let header = format!("{{{}}}", entries.join(","));
+    let mut bytes = (header.len() as u64).to_le_bytes().to_vec();
+    bytes.extend(header.as_bytes());
+    bytes.extend(data);
+    std::fs::write(path, bytes).unwrap();
+}
+
+#[test]
+fn test_diff_content_reports_changed_data() {
+    let a = NamedTempFile::new().expect("Failed to create temp file");
+    let b = NamedTempFile::new().expect("Failed to create temp file");
+    write_safetensors(a.path(), &[("w", "U8", &[4], b"abcd")]);
+    write_safetensors(b.path(), &[("w", "U8", &[4], b"abcX")]);
+    let path_a = a.path().to_string_lossy();
+    let path_b = b.path().to_string_lossy();
+
+    let output = run_cli(&["diff", &path_a, &path_b]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("IDENTICAL"));
+
+    let output = run_cli(&["diff", &path_a, &path_b, "--content", "--json"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success());
+    assert!(stdout.contains("\"digest_new\":"));
+}
+
+#[test]
+fn test_diff_values_reports_metrics() {
+    let a = NamedTempFile::new().expect("Failed to create temp file");
+    let b = NamedTempFile::new().expect("Failed to create temp file");
+    write_safetensors(a.path(), &[("w", "U8", &[4], b"abcd")]);
+    write_safetensors(b.path(), &[("w", "U8", &[4], b"abcX")]);
+    let path_a = a.path().to_string_lossy();
+    let path_b = b.path().to_string_lossy();
+
+    let output = run_cli(&["diff", &path_a, &path_b, "--values", "--json"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success());
+    assert!(stdout.contains("\"value_diffs\":"));
+    assert!(stdout.contains("\"max_abs_diff\": 12.0"));
+}
+
+#[test]
+fn test_diff_values_skips_tensors_without_offset() {
+    let npy = fortran_npy();
+    let path = npy.path().to_string_lossy();
+
+    let output = run_cli(&["diff", &path, &path, "--values", "--json"]);
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        json["values_skipped"]["arr_0"],
+        "no recorded data offset in the first file"
+    );
+}
+
+#[test]
+fn test_diff_values_skips_truncated_tensors() {
+    let output = run_cli(&[
+        "diff",
+        "tests/fixtures/tiny.gguf",
+        "tests/fixtures/tiny.gguf",
+        "--values",
+        "--json",
+    ]);
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        json["values_skipped"]["test.weight.0"],
+        "data is truncated in the first file"
+    );
+}
+
+#[test]
+fn test_inspect_stats_json() {
+    let output = run_cli(&[
+        "inspect",
+        "tests/fixtures/tiny.safetensors",
+        "--stats",
+        "--json",
+    ]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success());
+    assert!(stdout.contains("\"stats\":"));
+    assert!(stdout.contains("\"nan_count\": 0"));
+}
+
+#[test]
+fn test_inspect_stats_skips_truncated_tensors() {
+    let output = run_cli(&["inspect", "tests/fixtures/tiny.gguf", "--stats", "--json"]);
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        json["stats"]["skipped"]["test.weight.0"],
+        "data is truncated"
+    );
+}
+
+#[test]
+fn test_inspect_stats_skips_tensors_without_offset() {
+    let npy = fortran_npy();
+    let output = run_cli(&[
+        "inspect",
+        &npy.path().to_string_lossy(),
+        "--stats",
+        "--json",
+    ]);
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["stats"]["skipped"]["arr_0"], "no recorded data offset");
+}
+
+#[test]
+fn test_inspect_stats_non_ascii_tensor_name() {
+    // 60 bytes but 30 characters: byte 37 falls inside a character.
+    let name = "é".repeat(30);
+    let temp = NamedTempFile::new().expect("Failed to create temp file");
+    write_safetensors(temp.path(), &[(&name, "F32", &[1], &1.0f32.to_le_bytes())]);
+
+    let output = run_cli(&["inspect", &temp.path().to_string_lossy(), "--stats"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains(&name));
+}
+
+/// Write a GGUF v3 file with the given `(key, value type, value bytes)`
+/// metadata and `(name, dims, ggml type, data)` tensors, each tensor's data
+/// aligned to 32 bytes.
+fn write_gguf(
+    path: &std::path::Path,
+    kvs: &[(&str, u32, &[u8])],
+    tensors: &[(&str, &[u64], u32, &[u8])],
+) {
+    let mut bytes = b"GGUF".to_vec();
+    bytes.extend(3u32.to_le_bytes());
+    bytes.extend((tensors.len() as u64).to_le_bytes());
+    bytes.extend((kvs.len() as u64).to_le_bytes());
+    for (key, value_type, value) in kvs {
+        bytes.extend((key.len() as u64).to_le_bytes());
+        bytes.extend(key.as_bytes());
+        bytes.extend(value_type.to_le_bytes());
+        bytes.extend(*value);
+    }
+    let mut data = Vec::new();
+    for (name, dims, ggml_type, tensor_data) in tensors {
+        data.resize(data.len().div_ceil(32) * 32, 0);
+        bytes.extend((name.len() as u64).to_le_bytes());
+        bytes.extend(name.as_bytes());
+        bytes.extend((dims.len() as u32).to_le_bytes());
+        for dim in *dims {
+            bytes.extend(dim.to_le_bytes());
+        }
+        bytes.extend(ggml_type.to_le_bytes());
+        bytes.extend((data.len() as u64).to_le_bytes()); // offset
+        data.extend(*tensor_data);
+    }
+    bytes.resize(bytes.len().div_ceil(32) * 32, 0);
+    bytes.extend(data);
+    std::fs::write(path, bytes).unwrap();
+}
+
+#[test]
+fn test_quant_report_matches_hf_names() {
+    let reference = NamedTempFile::new().expect("Failed to create temp file");
+    let values = 1.5f32.to_le_bytes().repeat(32);
+    write_safetensors(
+        reference.path(),
+        &[("model.layers.0.mlp.up_proj.weight", "F32", &[32], &values)],
+    );
+
+    // One q8_0 block: scale 1.0 and every quant 1.
+    let mut block = vec![0x00, 0x3c];
+    block.extend([1u8; 32]);
+    let quantized = NamedTempFile::new().expect("Failed to create temp file");
+    write_gguf(
+        quantized.path(),
+        &[],
+        &[("blk.0.ffn_up.weight", &[32], 8, &block)],
+    );
+
+    let output = run_cli(&[
+        "quant-report",
+        &reference.path().to_string_lossy(),
+        &quantized.path().to_string_lossy(),
+        "--json",
+    ]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success());
+    assert!(stdout.contains("\"reference\": \"model.layers.0.mlp.up_proj.weight\""));
+    assert!(stdout.contains("\"max_error\": 0.5"));
+    assert!(stdout.contains("\"name\": \"blk.0\""));
+}
+
+#[test]
+fn test_diff_map_matches_hf_and_gguf_names() {
+    let reference = NamedTempFile::new().expect("Failed to create temp file");
+    write_safetensors(
+        reference.path(),
+        &[(
+            "model.layers.0.self_attn.o_proj.weight",
+            "F32",
+            &[2, 4],
+            &[0u8; 32],
+        )],
+    );
+    let converted = NamedTempFile::new().expect("Failed to create temp file");
+    write_gguf(
+        converted.path(),
+        &[],
+        &[("blk.0.attn_output.weight", &[4, 2], 0, &[0u8; 32])],
+    );
+    let path_a = reference.path().to_string_lossy();
+    let path_b = converted.path().to_string_lossy();
+
+    let output = run_cli(&["diff", &path_a, &path_b, "--json"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("blk.0.attn_output.weight"));
+
+    let output = run_cli(&["diff", &path_a, &path_b, "--map", "auto", "--json"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success());
+    assert!(stdout.contains("\"name_map\": \"llama\""));
+    assert!(!stdout.contains("blk.0.attn_output.weight"));
+    assert!(!stdout.contains("o_proj"));
+
+    let output = run_cli(&["diff", &path_a, &path_b, "--map", "bogus"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unknown name map 'bogus'"));
+}
+
+#[test]
+fn test_diff_reports_renamed_tensors() {
+    let write = |names: [&str; 2]| {
+        let temp = NamedTempFile::new().expect("Failed to create temp file");
+        write_safetensors(
+            temp.path(),
+            &[
+                (names[0], "U8", &[4], b"abcd"),
+                (names[1], "U8", &[4], b"efgh"),
+            ],
+        );
+        temp
+    };
+    let a = write(["transformer.wte.weight", "transformer.ln_f.weight"]);
+    let b = write(["model.wte.weight", "model.ln_f.weight"]);
+
+    let output = run_cli(&[
+        "diff",
+        &a.path().to_string_lossy(),
+        &b.path().to_string_lossy(),
+        "--json",
+    ]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success());
+    assert!(stdout.contains("\"tensors_added\": []"));
+    assert!(stdout.contains("\"new_name\": \"model.wte.weight\""));
+    assert!(stdout.contains("\"old_prefix\": \"transformer.\""));
+}
+
+#[test]
+fn test_sharded_checkpoint_matches_single_file() {
+    let dir = tempfile::tempdir().unwrap();
+    write_safetensors(
+        &dir.path().join("model-00001-of-00002.safetensors"),
+        &[("a", "U8", &[4], b"aaaa")],
+    );
+    write_safetensors(
+        &dir.path().join("model-00002-of-00002.safetensors"),
+        &[("b", "U8", &[4], b"bbbb")],
+    );
+    std::fs::write(
+        dir.path().join("model.safetensors.index.json"),
+        r#"{"weight_map":{"a":"model-00001-of-00002.safetensors","b":"model-00002-of-00002.safetensors"}}"#,
+    )
+    .unwrap();
+    let single = dir.path().join("single.safetensors");
+    write_safetensors(
+        &single,
+        &[("a", "U8", &[4], b"aaaa"), ("b", "U8", &[4], b"bbbb")],
+    );
+
+    let id = |path: &std::path::Path| {
+        let output = run_cli(&["id", &path.to_string_lossy(), "--content", "--json"]);
+        assert!(output.status.success());
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        (
+            json["structural_hash"].clone(),
+            json["content_hash"].clone(),
+        )
+    };
+    assert_eq!(id(dir.path()), id(&single));
+    assert_eq!(
+        id(&dir.path().join("model.safetensors.index.json")),
+        id(&single)
+    );
+
+    let output = run_cli(&["validate", &dir.path().to_string_lossy()]);
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_validate_reports_index_mismatch() {
+    let dir = tempfile::tempdir().unwrap();
+    write_safetensors(
+        &dir.path().join("model.safetensors"),
+        &[("a", "U8", &[4], b"aaaa")],
+    );
+    std::fs::write(
+        dir.path().join("model.safetensors.index.json"),
+        r#"{"weight_map":{"a":"model.safetensors","b":"model.safetensors"}}"#,
+    )
+    .unwrap();
+
+    let output = run_cli(&["validate", &dir.path().to_string_lossy()]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!output.status.success());
+    assert!(stdout.contains("index_missing_tensor 'b'"));
+}
+
+#[test]
+fn test_split_gguf_matches_unsplit_model() {
+    let dir = tempfile::tempdir().unwrap();
+    let first = dir.path().join("model-00001-of-00002.gguf");
+    let second = dir.path().join("model-00002-of-00002.gguf");
+    // u16 split.no and split.count, i32 split.tensors.count; one i8 tensor.
+    for (no, path, name) in [(0u16, &first, "a"), (1, &second, "b")] {
+        write_gguf(
+            path,
+            &[
+                ("split.no", 2, &no.to_le_bytes()),
+                ("split.count", 2, &2u16.to_le_bytes()),
+                ("split.tensors.count", 5, &2i32.to_le_bytes()),
+            ],
+            &[(name, &[1], 24, &[no as u8 + 1])],
+        );
+    }
+
+    let unsplit = NamedTempFile::new().expect("Failed to create temp file");
+    write_gguf(
+        unsplit.path(),
+        &[],
+        &[("a", &[1], 24, &[1]), ("b", &[1], 24, &[2])],
+    );
+
+    let id = |path: &std::path::Path| {
+        let output = run_cli(&["id", &path.to_string_lossy(), "--content", "--json"]);
+        assert!(output.status.success());
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        (
+            json["structural_hash"].clone(),
+            json["content_hash"].clone(),
+        )
+    };
+    assert_eq!(id(&second), id(unsplit.path()));
+
+    std::fs::remove_file(&second).unwrap();
+    let output = run_cli(&["validate", &first.to_string_lossy()]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!output.status.success());
+    assert!(stdout.contains("split_missing_shard 'model-00002-of-00002.gguf'"));
+}
+
+#[test]
+fn test_pytorch_checkpoint_matches_safetensors_content() {
+    let content_hash = |path: &str| {
+        let output = run_cli(&["id", path, "--content", "--json"]);
+        assert!(output.status.success());
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        (
+            json["format"].as_str().unwrap().to_string(),
+            json["content_hash"].as_str().unwrap().to_string(),
+        )
+    };
+
+    let (format, pytorch) = content_hash("tests/fixtures/tiny.pt");
+    let (_, safetensors) = content_hash("tests/fixtures/tiny.safetensors");
+    assert_eq!(format, "pytorch");
+    assert_eq!(pytorch, safetensors);
+
+    let output = run_cli(&["validate", "tests/fixtures/tiny.pt"]);
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_npz_matches_safetensors_content() {
+    let output = run_cli(&["id", "tests/fixtures/tiny.npz", "--content", "--json"]);
+    assert!(output.status.success());
+    let npz: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let output = run_cli(&[
+        "id",
+        "tests/fixtures/tiny.safetensors",
+        "--content",
+        "--json",
+    ]);
+    let safetensors: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(npz["format"], "numpy");
+    assert_eq!(npz["content_hash"], safetensors["content_hash"]);
+
+    let output = run_cli(&[
+        "diff",
+        "tests/fixtures/tiny.npz",
+        "tests/fixtures/tiny.safetensors",
+        "--json",
+    ]);
+    let diff: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(diff["tensor_changes"].as_array().unwrap().len(), 0);
+}
+
+#[test]
+fn test_scan_blocks_dangerous_pickle() {
+    let output = run_cli(&["scan", "tests/fixtures/tiny.pt"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success());
+    assert!(stdout.starts_with("SAFE"));
+    assert!(stdout.contains("torch._utils._rebuild_tensor_v2"));
+
+    // A pickle that runs os.system("ls") when loaded.
+    let mut temp = tempfile::Builder::new().suffix(".pkl").tempfile().unwrap();
+    std::io::Write::write_all(&mut temp, b"cposix\nsystem\n(S'ls'\ntR.").unwrap();
+    let output = run_cli(&["scan", &temp.path().to_string_lossy(), "--json"]);
+    assert!(!output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["safe"], false);
+    assert_eq!(json["streams"][0]["imports"][0]["name"], "system");
+    assert_eq!(json["findings"][0]["code"], "dangerous_global");
+}
+
+#[test]
+fn test_tflite_detected_by_identifier() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("model.bin");
+    std::fs::copy("tests/fixtures/tiny.tflite", &path).unwrap();
+    let output = run_cli(&["id", path.to_str().unwrap(), "--content", "--json"]);
+    assert!(output.status.success());
+    let tflite: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(tflite["format"], "tflite");
+
+    let output = run_cli(&[
+        "id",
+        "tests/fixtures/tiny.safetensors",
+        "--content",
+        "--json",
+    ]);
+    let safetensors: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(tflite["content_hash"], safetensors["content_hash"]);
+
+    let output = run_cli(&["inspect", "tests/fixtures/tiny.tflite", "--verbose"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("FULLY_CONNECTED"));
+    assert!(stdout.contains("1.5.0"));
+}
+
+#[test]
+fn test_legacy_ggjt_diffs_against_gguf() {
+    let output = run_cli(&["id", "tests/fixtures/tiny.ggjt"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("wi:ggml:3:"));
+
+    let output = run_cli(&[
+        "diff",
+        "tests/fixtures/tiny.ggjt",
+        "tests/fixtures/tiny.gguf",
+        "--json",
+    ]);
+    let diff: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(diff["tensor_changes"].as_array().unwrap().len(), 0);
+    assert_eq!(diff["tensors_added"].as_array().unwrap().len(), 0);
+    assert!(diff["metadata_removed"]
+        .as_array()
+        .unwrap()
+        .contains(&"ggml.magic".into()));
+}
+
+#[test]
+#[cfg(feature = "onnx")]
+fn test_inspect_json_includes_onnx_graph() {
+    let output = run_cli(&["inspect", "tests/fixtures/mnist.onnx", "--json"]);
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let graph = &json["graph"];
+    assert_eq!(graph["inputs"][0]["name"], "Input3");
+    assert_eq!(
+        graph["inputs"][0]["shape"],
+        serde_json::json!([1, 1, 28, 28])
+    );
+    let conv = graph["nodes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|node| node["op_type"] == "Conv")
+        .unwrap();
+    assert_eq!(
+        conv["attributes"]["kernel_shape"]["ints"],
+        serde_json::json!([5, 5])
+    );
+}